@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::vault::settings::{NoteAction, VaultSettings};
+use crate::vault::{read_json, write_json};
+
+/// In the app's config directory, out of reach of anything a vault brings
+/// with it.
+const TRUST_FILE: &str = "note_actions.json";
+
+/// Output kept from each of stdout and stderr; the rest is read and dropped.
+const MAX_OUTPUT: usize = 64 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How long output is still collected after the command exits, in case
+/// something it started holds on to its stdout or stderr.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteActions {
+    /// Whether the user allows custom actions for the vault; none run until
+    /// they do.
+    pub allowed: bool,
+    pub actions: Vec<ListedAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListedAction {
+    #[serde(flatten)]
+    pub action: NoteAction,
+    /// Whether the user approved the action exactly as it is; one the
+    /// vault declares differently since needs approving again.
+    pub approved: bool,
+    /// Whether the vault's settings declare it; an approved action keeps
+    /// working after they drop it.
+    pub declared: bool,
+}
+
+/// What the user trusts, keyed by canonical vault path.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TrustedActions {
+    vaults: BTreeMap<String, VaultTrust>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct VaultTrust {
+    allow_custom_actions: bool,
+    /// Copies of the actions the user approved, which are what run.
+    approved: Vec<NoteAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteActionOutput {
+    pub name: String,
+    /// `None` when the command was killed, by the timeout or a signal.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether output was cut at 64 KB.
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub duration_ms: u64,
+}
+
+/// The note actions the vault declares and those the user approved for
+/// it, by name.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn list_note_actions(app: AppHandle, vault_path: &str) -> Result<NoteActions, String> {
+    let root = Path::new(vault_path);
+    let declared = VaultSettings::load(root)?.note_actions.actions;
+    let trust = load_trust(&app, root)?;
+    let mut actions: Vec<ListedAction> = declared
+        .into_iter()
+        .map(|action| ListedAction {
+            approved: trust.approved.contains(&action),
+            declared: true,
+            action,
+        })
+        .collect();
+    for action in trust.approved {
+        if !actions.iter().any(|listed| listed.action == action) {
+            actions.push(ListedAction {
+                action,
+                approved: true,
+                declared: false,
+            });
+        }
+    }
+    actions.sort_by(|a, b| a.action.name.cmp(&b.action.name));
+    Ok(NoteActions {
+        allowed: trust.allow_custom_actions,
+        actions,
+    })
+}
+
+/// Allows or stops custom note actions for the vault. Kept in the app's
+/// config, so a vault can't switch them on itself.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn set_note_actions_allowed(
+    app: AppHandle,
+    vault_path: &str,
+    allowed: bool,
+) -> Result<(), String> {
+    update_trust(&app, Path::new(vault_path), |trust| {
+        trust.allow_custom_actions = allowed
+    })
+}
+
+/// Approves the action `action_name` as the vault declares it now, in place
+/// of any approved before under that name. Changing its command in the
+/// vault afterwards takes the approval away again.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn approve_note_action(
+    app: AppHandle,
+    vault_path: &str,
+    action_name: &str,
+) -> Result<NoteAction, String> {
+    let root = Path::new(vault_path);
+    let action = VaultSettings::load(root)?
+        .note_actions
+        .actions
+        .into_iter()
+        .find(|action| action.name == action_name)
+        .ok_or_else(|| format!("No such note action: {}", action_name))?;
+    update_trust(&app, root, |trust| {
+        trust
+            .approved
+            .retain(|approved| approved.name != action.name);
+        trust.approved.push(action.clone());
+    })?;
+    Ok(action)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn revoke_note_action(
+    app: AppHandle,
+    vault_path: &str,
+    action_name: &str,
+) -> Result<(), String> {
+    update_trust(&app, Path::new(vault_path), |trust| {
+        trust
+            .approved
+            .retain(|approved| approved.name != action_name)
+    })
+}
+
+/// Runs the note action `action_name` on the note at `note_path`, absolute
+/// or vault-relative, and waits for it to finish or time out. The program
+/// is started directly with the action's arguments, so a path can't be
+/// read as shell syntax. Only an action the user approved runs, as they
+/// approved it, and only while they allow actions for the vault.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_note_action(
+    app: AppHandle,
+    vault_path: String,
+    action_name: String,
+    note_path: String,
+) -> Result<NoteActionOutput, String> {
+    let root = PathBuf::from(&vault_path);
+    let trust = load_trust(&app, &root)?;
+    if !trust.allow_custom_actions {
+        return Err("Custom note actions are not allowed in this vault".to_string());
+    }
+    let action = trust
+        .approved
+        .into_iter()
+        .find(|action| action.name == action_name)
+        .ok_or_else(|| format!("Note action isn't approved: {}", action_name))?;
+    let (root, note) = note_in_vault(&root, &note_path)?;
+
+    async_runtime::spawn_blocking(move || run(&root, &action, &note))
+        .await
+        .map_err(|e| format!("Note action failed: {}", e))?
+}
+
+/// The real paths of the vault and of the note at `note_path` in it,
+/// compared as the local API does, so neither `..` nor a symlink can lead
+/// out of the vault.
+fn note_in_vault(root: &Path, note_path: &str) -> Result<(PathBuf, PathBuf), String> {
+    let root = fs::canonicalize(root)
+        .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+    let note = fs::canonicalize(root.join(note_path))
+        .map_err(|_| format!("Note does not exist: {}", note_path))?;
+    if !note.starts_with(&root) {
+        return Err(format!("Path is outside the vault: {}", note_path));
+    }
+    if !note.is_file() {
+        return Err(format!("Note does not exist: {}", note_path));
+    }
+    Ok((root, note))
+}
+
+fn trust_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to find config directory: {}", e))?;
+    Ok(dir.join(TRUST_FILE))
+}
+
+/// The vault's key in the trust file: its real path, so a symlink or
+/// `..` can't pass one vault off as another.
+fn vault_key(root: &Path) -> Result<String, String> {
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", root.display()));
+    }
+    let root = fs::canonicalize(root)
+        .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+    Ok(root.to_string_lossy().to_string())
+}
+
+fn load_trust(app: &AppHandle, root: &Path) -> Result<VaultTrust, String> {
+    let key = vault_key(root)?;
+    let mut trusted: TrustedActions = read_json(&trust_path(app)?)?;
+    Ok(trusted.vaults.remove(&key).unwrap_or_default())
+}
+
+fn update_trust(
+    app: &AppHandle,
+    root: &Path,
+    update: impl FnOnce(&mut VaultTrust),
+) -> Result<(), String> {
+    let key = vault_key(root)?;
+    let path = trust_path(app)?;
+    let mut trusted: TrustedActions = read_json(&path)?;
+    update(trusted.vaults.entry(key).or_default());
+    write_json(&path, &trusted)
+}
+
+fn run(root: &Path, action: &NoteAction, note: &Path) -> Result<NoteActionOutput, String> {
+    let (note, vault) = (note.to_string_lossy(), root.to_string_lossy());
+    let args: Vec<String> = split_command(&action.command)?
+        .into_iter()
+        .map(|arg| arg.replace("{{path}}", &note).replace("{{vault}}", &vault))
+        .collect();
+    let Some((program, args)) = args.split_first() else {
+        return Err(format!("Note action {} has no command", action.name));
+    };
+    let working_dir = match &action.working_dir {
+        Some(dir) => root.join(dir),
+        None => root.to_path_buf(),
+    };
+    if !working_dir.is_dir() {
+        return Err(format!(
+            "Working directory does not exist: {}",
+            working_dir.display()
+        ));
+    }
+
+    let started = Instant::now();
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(&working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    let stdout = collect(child.stdout.take());
+    let stderr = collect(child.stderr.take());
+
+    let deadline = started + Duration::from_secs(action.timeout_secs.max(1));
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to wait for {}: {}", program, e))?
+        {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            timed_out = true;
+            let _ = child.kill();
+            break child
+                .wait()
+                .map_err(|e| format!("Failed to wait for {}: {}", program, e))?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let grace = Instant::now() + OUTPUT_GRACE;
+    while !(stdout.1.is_finished() && stderr.1.is_finished()) && Instant::now() < grace {
+        thread::sleep(POLL_INTERVAL);
+    }
+    let (stdout, stdout_truncated) = captured(&stdout.0);
+    let (stderr, stderr_truncated) = captured(&stderr.0);
+    Ok(NoteActionOutput {
+        name: action.name.clone(),
+        exit_code: if timed_out { None } else { status.code() },
+        timed_out,
+        stdout,
+        stderr,
+        stdout_truncated,
+        stderr_truncated,
+        duration_ms,
+    })
+}
+
+/// What a command writes to a pipe, up to `MAX_OUTPUT` bytes, and whether
+/// there was more. Read on its own thread so a full pipe can't stall it.
+type Captured = Arc<Mutex<(Vec<u8>, bool)>>;
+
+fn collect(pipe: Option<impl Read + Send + 'static>) -> (Captured, thread::JoinHandle<()>) {
+    let captured: Captured = Arc::default();
+    let sink = Arc::clone(&captured);
+    let reader = thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut buf = [0u8; 8192];
+        while let Ok(read) = pipe.read(&mut buf) {
+            if read == 0 {
+                break;
+            }
+            let Ok(mut sink) = sink.lock() else {
+                break;
+            };
+            let room = MAX_OUTPUT - sink.0.len();
+            sink.0.extend_from_slice(&buf[..read.min(room)]);
+            sink.1 |= read > room;
+        }
+    });
+    (captured, reader)
+}
+
+fn captured(captured: &Captured) -> (String, bool) {
+    match captured.lock() {
+        Ok(captured) => (String::from_utf8_lossy(&captured.0).to_string(), captured.1),
+        Err(_) => (String::new(), false),
+    }
+}
+
+/// Splits a command line into arguments the way a POSIX shell would, with
+/// `'...'`, `"..."` and `\` quoting, but with nothing else special.
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(format!("Unterminated ' in command: {}", command)),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(format!("Unterminated \" in command: {}", command)),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(format!("Unterminated \" in command: {}", command)),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                if let Some(c) = chars.next() {
+                    current.push(c);
+                }
+            }
+            c => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    #[test]
+    fn notes_outside_the_vault_are_refused() {
+        let outside = TempVault::new("actions-outside", &[("Secret.md", "no\n")]);
+        let root = TempVault::new("actions-vault", &[("Note.md", "yes\n")]);
+        let real = fs::canonicalize(&root).unwrap();
+        assert_eq!(
+            note_in_vault(&root, "Note.md").unwrap(),
+            (real.clone(), real.join("Note.md"))
+        );
+        let escape = format!(
+            "../{}/Secret.md",
+            outside.file_name().unwrap().to_string_lossy()
+        );
+        assert!(note_in_vault(&root, &escape).is_err());
+        assert!(note_in_vault(&root, &outside.join("Secret.md").to_string_lossy()).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&*outside, root.join("linked")).unwrap();
+            assert!(note_in_vault(&root, "linked/Secret.md").is_err());
+        }
+    }
+
+    #[test]
+    fn quotes_group_words_into_one_argument() {
+        assert_eq!(
+            split_command(r#"pandoc '{{path}}' -o "out file.pdf""#).unwrap(),
+            ["pandoc", "{{path}}", "-o", "out file.pdf"]
+        );
+        assert_eq!(split_command("echo a'b c'd").unwrap(), ["echo", "ab cd"]);
+    }
+
+    #[test]
+    fn escapes_follow_the_shell() {
+        assert_eq!(
+            split_command(r#"echo a\ b "say \"hi\" \n" 'no \escape'"#).unwrap(),
+            ["echo", "a b", r#"say "hi" \n"#, r"no \escape"]
+        );
+    }
+
+    #[test]
+    fn empty_quotes_are_an_empty_argument() {
+        assert_eq!(
+            split_command(r#"  tool '' ""   x  "#).unwrap(),
+            ["tool", "", "", "x"]
+        );
+        assert!(split_command("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn unbalanced_quotes_are_refused() {
+        for command in ["echo 'open", r#"echo "open"#, r#"echo "ends in \"#] {
+            assert!(split_command(command).is_err(), "{}", command);
+        }
+    }
+}
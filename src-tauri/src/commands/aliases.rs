@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::files::{edit_note, FileError};
+use crate::markdown::frontmatter;
+use crate::vault::{self, index::VaultIndex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameKind {
+    Title,
+    Alias,
+}
+
+/// Another note already answering to a name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AliasConflict {
+    pub path: String,
+    pub title: String,
+    /// The clashing title or alias, as that note writes it.
+    pub name: String,
+    pub kind: NameKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AliasUpdate {
+    /// The note's aliases after the change.
+    pub aliases: Vec<String>,
+    /// Other notes the alias also resolves to, for the UI to confirm.
+    pub conflicts: Vec<AliasConflict>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AliasEntry {
+    pub alias: String,
+    /// Every note with this alias.
+    pub paths: Vec<String>,
+    /// Notes whose title is the alias.
+    pub titled: Vec<String>,
+    /// Whether the alias resolves to more than one note.
+    pub conflict: bool,
+}
+
+/// Adds an alias to a note's frontmatter. Adding one it already has is a
+/// no-op. The alias is added even when other notes use it as a title or
+/// alias; those are returned as conflicts.
+#[tauri::command]
+pub fn add_note_alias(vault_path: &str, path: &str, alias: &str) -> Result<AliasUpdate, FileError> {
+    let alias = alias.trim();
+    if alias.is_empty() {
+        return Err("Alias can't be empty".to_string().into());
+    }
+    let root = Path::new(vault_path);
+    let note = note_in_vault(root, path)?;
+    let lowered = alias.to_lowercase();
+    let mut aliases = Vec::new();
+    edit_note(note, "add_note_alias", |content| {
+        let (fm, _) = frontmatter::parse_note(content)?;
+        aliases = current_aliases(&fm);
+        if aliases.iter().any(|a| a.to_lowercase() == lowered) {
+            return Ok(content.to_string());
+        }
+        aliases.push(alias.to_string());
+        frontmatter::update(content, |mapping| set_aliases(mapping, &aliases))
+    })?;
+
+    let index = VaultIndex::build(
+        root,
+        &vault::visible_files(root, root, false, vault::is_markdown)?,
+    );
+    let mut conflicts = Vec::new();
+    for other in index.notes.iter().filter(|n| n.path != note) {
+        let path = other.path.to_string_lossy().to_string();
+        if other.title.to_lowercase() == lowered {
+            conflicts.push(AliasConflict {
+                path: path.clone(),
+                title: other.title.clone(),
+                name: other.title.clone(),
+                kind: NameKind::Title,
+            });
+        }
+        if let Some(name) = other.aliases.iter().find(|a| a.to_lowercase() == lowered) {
+            conflicts.push(AliasConflict {
+                path,
+                title: other.title.clone(),
+                name: name.clone(),
+                kind: NameKind::Alias,
+            });
+        }
+    }
+    Ok(AliasUpdate { aliases, conflicts })
+}
+
+/// Removes an alias (matched case-insensitively) from a note's frontmatter,
+/// dropping the field once it is empty.
+#[tauri::command]
+pub fn remove_note_alias(
+    vault_path: &str,
+    path: &str,
+    alias: &str,
+) -> Result<AliasUpdate, FileError> {
+    let note = note_in_vault(Path::new(vault_path), path)?;
+    let lowered = alias.trim().to_lowercase();
+    let mut aliases = Vec::new();
+    edit_note(note, "remove_note_alias", |content| {
+        let (fm, _) = frontmatter::parse_note(content)?;
+        aliases = current_aliases(&fm);
+        let before = aliases.len();
+        aliases.retain(|a| a.to_lowercase() != lowered);
+        if aliases.len() == before {
+            return Ok(content.to_string());
+        }
+        frontmatter::update(content, |mapping| set_aliases(mapping, &aliases))
+    })?;
+    Ok(AliasUpdate {
+        aliases,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Every alias in the vault with the notes it resolves to, grouped
+/// case-insensitively as link resolution does. Private notes are left out.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_all_aliases(vault_path: &str) -> Result<Vec<AliasEntry>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let index = VaultIndex::build(
+        root,
+        &vault::visible_files(root, root, false, vault::is_markdown)?,
+    );
+
+    let mut by_alias: BTreeMap<String, AliasEntry> = BTreeMap::new();
+    for note in &index.notes {
+        for alias in &note.aliases {
+            let entry = by_alias
+                .entry(alias.to_lowercase())
+                .or_insert_with(|| AliasEntry {
+                    alias: alias.clone(),
+                    paths: Vec::new(),
+                    titled: Vec::new(),
+                    conflict: false,
+                });
+            let path = note.path.to_string_lossy().to_string();
+            if !entry.paths.contains(&path) {
+                entry.paths.push(path);
+            }
+        }
+    }
+    for note in &index.notes {
+        if let Some(entry) = by_alias.get_mut(&note.title.to_lowercase()) {
+            entry.titled.push(note.path.to_string_lossy().to_string());
+        }
+    }
+
+    Ok(by_alias
+        .into_values()
+        .map(|mut entry| {
+            let mut notes: Vec<&String> = entry.paths.iter().chain(&entry.titled).collect();
+            notes.sort();
+            notes.dedup();
+            entry.conflict = notes.len() > 1;
+            entry
+        })
+        .collect())
+}
+
+fn note_in_vault<'a>(root: &Path, path: &'a str) -> Result<&'a Path, String> {
+    let note = Path::new(path);
+    if !note.starts_with(root) {
+        return Err(format!("Note is not in the vault: {}", path));
+    }
+    Ok(note)
+}
+
+fn current_aliases(fm: &serde_json::Map<String, serde_json::Value>) -> Vec<String> {
+    frontmatter::string_list(fm.get("aliases").or_else(|| fm.get("alias")))
+}
+
+/// Writes the list back to whichever of `aliases` or `alias` the note
+/// already uses, preferring `aliases`.
+fn set_aliases(mapping: &mut serde_yaml::Mapping, aliases: &[String]) {
+    let key = if !mapping.contains_key("aliases") && mapping.contains_key("alias") {
+        "alias"
+    } else {
+        "aliases"
+    };
+    if aliases.is_empty() {
+        mapping.remove(key);
+    } else {
+        let list = aliases.iter().map(|a| Value::String(a.clone())).collect();
+        mapping.insert(key.into(), Value::Sequence(list));
+    }
+}
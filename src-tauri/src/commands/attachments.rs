@@ -0,0 +1,620 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::files::{delete_file, write_atomic, FileError};
+use super::rename::{rename_paths, UpdateFailure};
+use crate::markdown::links::{self, MarkdownLink};
+use crate::markdown::{blank_code_spans, code_block_lines, LineBuffer};
+use crate::vault::index::normalize_path;
+use crate::vault::journal::Journal;
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{
+    self, encoding, external, link_format::LinkWriter, locks, settings::VaultSettings,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageRepair {
+    pub note_path: String,
+    pub line_number: usize,
+    pub old_target: String,
+    pub new_target: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnresolvedImage {
+    pub note_path: String,
+    pub line_number: usize,
+    pub target: String,
+    /// Same-named files found in the vault; empty when nothing matched.
+    pub candidates: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImageRepairReport {
+    pub repaired: Vec<ImageRepair>,
+    /// Several candidates and no way to tell which is the moved file.
+    pub ambiguous: Vec<UnresolvedImage>,
+    /// No file with the same name exists anywhere in the vault.
+    pub missing: Vec<UnresolvedImage>,
+    pub updated_notes: Vec<String>,
+    /// Locked notes that needed repairs but were left untouched.
+    pub skipped_locked: Vec<String>,
+    /// For `undo_operation`; `None` in a dry run or when nothing changed.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttachmentRename {
+    pub new_path: String,
+    /// Notes whose links or embeds were rewritten.
+    pub updated_notes: Vec<String>,
+    pub updated_links: usize,
+    /// Notes that link to the attachment but couldn't be updated.
+    pub failed: Vec<UpdateFailure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileListStyle {
+    List,
+    Table,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListedFile {
+    /// The path as given.
+    pub source: String,
+    /// What the link points at: the copy in the vault, when one was made.
+    pub path: String,
+    pub copied: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FileListMarkdown {
+    pub markdown: String,
+    pub files: Vec<ListedFile>,
+    pub skipped: Vec<UpdateFailure>,
+}
+
+/// Renames or moves an attachment and rewrites every link to it: wiki
+/// embeds and links by name or path, and markdown links and images,
+/// percent-encoded or not. Markdown files are refused.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn rename_attachment(
+    vault_path: &str,
+    old_path: &str,
+    new_path: &str,
+) -> Result<AttachmentRename, String> {
+    let root = Path::new(vault_path);
+    let (old, new) = (Path::new(old_path), Path::new(new_path));
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    if !old.starts_with(root) || !new.starts_with(root) {
+        return Err(format!(
+            "Path is outside the vault: {}",
+            if old.starts_with(root) {
+                new_path
+            } else {
+                old_path
+            }
+        ));
+    }
+    if !old.is_file() {
+        return Err(format!("Source path does not exist: {}", old_path));
+    }
+    if vault::is_markdown(old) || vault::is_markdown(new) {
+        return Err(format!("Not an attachment: {}", old_path));
+    }
+
+    let mut outcome = rename_paths(root, &[(old.to_path_buf(), new.to_path_buf())], false, None);
+    if let Some(error) = outcome.errors.pop().flatten() {
+        return Err(error);
+    }
+    Ok(AttachmentRename {
+        new_path: new_path.to_string(),
+        updated_notes: outcome.updates.notes,
+        updated_links: outcome.updates.links,
+        failed: outcome.updates.failed,
+    })
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoteDeletion {
+    pub note_path: String,
+    /// Attachments no other note uses: trashed with the note, or in a dry
+    /// run, those that would be.
+    pub orphaned_attachments: Vec<String>,
+    /// Attachments the note used that other notes still reference.
+    pub shared_attachments: Vec<String>,
+    /// Orphaned attachments that couldn't be trashed once the note was.
+    pub failed: Vec<UpdateFailure>,
+    pub deleted: bool,
+}
+
+/// Deletes a note along with the local attachments it links to or embeds
+/// that no other note references, all moved to the system trash. Notes in
+/// private and ignored folders count as references too. With `dry_run`
+/// nothing is deleted and the report lists what would be. A bare
+/// `[[image.png]]` that several files share can't say which one it means,
+/// so none of them is removed on its account.
+#[tauri::command]
+pub fn delete_note_with_attachments(
+    vault_path: &str,
+    path: &str,
+    dry_run: bool,
+) -> Result<NoteDeletion, FileError> {
+    let root = Path::new(vault_path);
+    let note = Path::new(path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path).into());
+    }
+    if !note.starts_with(root) {
+        return Err(format!("Path is outside the vault: {}", path).into());
+    }
+    if !note.is_file() || !vault::is_markdown(note) {
+        return Err(format!("Not a note: {}", path).into());
+    }
+
+    let by_name = attachments_by_lowercase_name(root);
+    let content = fs::read_to_string(note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let own: BTreeSet<PathBuf> = referenced_attachments(root, note, &content, &by_name)
+        .into_iter()
+        .filter_map(|candidates| match candidates.as_slice() {
+            [only] => Some(only.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut shared = BTreeSet::new();
+    if !own.is_empty() {
+        let names: Vec<String> = own
+            .iter()
+            .filter_map(|p| p.file_name())
+            .flat_map(|n| {
+                let name = n.to_string_lossy().to_lowercase();
+                [links::encode_target(&name).to_lowercase(), name]
+            })
+            .collect();
+        // Every note, private and ignored ones included.
+        for other in vault::markdown_files(root) {
+            if other == note {
+                continue;
+            }
+            let Ok(text) = fs::read_to_string(&other) else {
+                continue;
+            };
+            let lowered = text.to_lowercase();
+            if !names.iter().any(|name| lowered.contains(name.as_str())) {
+                continue;
+            }
+            for candidates in referenced_attachments(root, &other, &text, &by_name) {
+                shared.extend(candidates.into_iter().filter(|c| own.contains(c)));
+            }
+        }
+    }
+    let display = |path: &PathBuf| path.to_string_lossy().to_string();
+    let mut report = NoteDeletion {
+        note_path: path.to_string(),
+        orphaned_attachments: own
+            .iter()
+            .filter(|a| !shared.contains(*a))
+            .map(display)
+            .collect(),
+        shared_attachments: shared.iter().map(display).collect(),
+        ..Default::default()
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    // The note goes first: if it can't be trashed, nothing is.
+    delete_file(path, None)?;
+    report.deleted = true;
+    for attachment in &report.orphaned_attachments {
+        if let Err(e) = delete_file(attachment, None) {
+            report.failed.push(UpdateFailure {
+                path: attachment.clone(),
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// The existing local attachments a note links to or embeds, markdown or
+/// wiki, outside code. Each reference gives the files it may mean: one,
+/// or several when a bare `[[name]]` is shared by more than one file.
+/// `by_name` holds the vault's attachments by lowercased file name.
+pub(super) fn referenced_attachments(
+    root: &Path,
+    note: &Path,
+    content: &str,
+    by_name: &HashMap<String, Vec<PathBuf>>,
+) -> Vec<Vec<PathBuf>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut found = Vec::new();
+    for (line, code) in lines.iter().zip(in_code) {
+        if code || !(line.contains("[[") || line.contains("](")) {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        for link in links::wikilinks(&scrubbed) {
+            let target = link.target.trim();
+            if !is_attachment_target(target) {
+                continue;
+            }
+            let candidates: Vec<PathBuf> = if target.contains('/') {
+                let rooted = root.join(target.trim_start_matches('/'));
+                let relative = note.parent().unwrap_or(root).join(target);
+                [rooted, relative]
+                    .into_iter()
+                    .map(|p| normalize_path(&p))
+                    .filter(|p| p.is_file())
+                    .take(1)
+                    .collect()
+            } else {
+                by_name
+                    .get(&target.to_lowercase())
+                    .cloned()
+                    .unwrap_or_default()
+            };
+            if !candidates.is_empty() {
+                found.push(candidates);
+            }
+        }
+        for link in links::markdown_links(&scrubbed) {
+            if links::is_external(&link.target) {
+                continue;
+            }
+            let path_part =
+                &link.target[..link.target.find(['#', '?']).unwrap_or(link.target.len())];
+            if !is_attachment_target(&links::decode_target(path_part)) {
+                continue;
+            }
+            let resolved = normalize_path(&resolve_local_target(root, note, path_part));
+            if resolved.is_file() {
+                found.push(vec![resolved]);
+            }
+        }
+    }
+    found
+}
+
+fn is_attachment_target(target: &str) -> bool {
+    let path = Path::new(target);
+    path.extension().is_some() && !vault::is_markdown(path)
+}
+
+/// Resolves a local link target from a note, relative to the note's folder
+/// or to the vault root for targets starting with `/`.
+pub(crate) fn resolve_local_target(vault_root: &Path, note: &Path, target: &str) -> PathBuf {
+    let decoded = links::decode_target(target);
+    match decoded.strip_prefix('/') {
+        Some(rooted) => vault_root.join(rooted),
+        None => note.parent().unwrap_or(vault_root).join(decoded),
+    }
+}
+
+/// Groups every non-markdown file in the vault by filename.
+fn attachments_by_name(root: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for file in vault::files_where(root, |p| !vault::is_markdown(p)) {
+        if let Some(name) = file.file_name() {
+            by_name
+                .entry(name.to_string_lossy().to_string())
+                .or_default()
+                .push(file);
+        }
+    }
+    by_name
+}
+
+/// `attachments_by_name` with the names lowercased, as
+/// `referenced_attachments` wants them.
+pub(super) fn attachments_by_lowercase_name(root: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for (name, paths) in attachments_by_name(root) {
+        by_name
+            .entry(name.to_lowercase())
+            .or_default()
+            .extend(paths);
+    }
+    by_name
+}
+
+/// Looks for a copy of the original file in the vault trash or GraphNotes'
+/// own state directory so its hash can pick between candidates.
+fn find_original_copy(root: &Path, name: &str) -> Option<PathBuf> {
+    [root.join(".trash"), vault::state_dir(root)]
+        .iter()
+        .flat_map(|dir| vault::files_where(dir, |p| p.file_name().is_some_and(|n| n == name)))
+        .next()
+}
+
+fn pick_candidate(root: &Path, name: &str, candidates: &[PathBuf]) -> Option<PathBuf> {
+    if candidates.len() == 1 {
+        return Some(candidates[0].clone());
+    }
+    let original_hash = vault::hash_file(&find_original_copy(root, name)?).ok()?;
+    let matching: Vec<&PathBuf> = candidates
+        .iter()
+        .filter(|c| vault::hash_file(c).ok().as_deref() == Some(original_hash.as_str()))
+        .collect();
+    match matching.as_slice() {
+        [only] => Some((*only).clone()),
+        _ => None,
+    }
+}
+
+/// Finds attachment links that no longer resolve and points them at the
+/// same-named file elsewhere in the vault, with the path style of the
+/// vault's link format. Ambiguous cases are reported and never guessed.
+/// The repairs can be undone with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn repair_image_links(vault_path: &str, dry_run: bool) -> Result<ImageRepairReport, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+
+    let by_name = attachments_by_name(root);
+    let settings = VaultSettings::load(root)?;
+    let writer = LinkWriter::new(root, &settings.link_format);
+    let mut report = ImageRepairReport::default();
+    let mut journal = Journal::start(root, "repair_image_links");
+
+    for note in vault::markdown_files(root) {
+        let note_display = note.to_string_lossy().to_string();
+        // Held from the read to the write, so a repair never overwrites an
+        // autosave that landed in between.
+        let lock = (!dry_run).then(|| WriteLocks::global().lock(&note, "repair_image_links"));
+        let _lock = match lock.transpose() {
+            Ok(lock) => lock,
+            Err(_) => {
+                report.skipped_locked.push(note_display);
+                continue;
+            }
+        };
+        let content = match fs::read_to_string(&note) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let note_dir = note.parent().unwrap_or(root);
+        let mut buffer = LineBuffer::parse(&content);
+        let in_code = code_block_lines(&buffer.as_strs());
+        let mut changed = false;
+
+        for (idx, line) in buffer.lines.iter_mut().enumerate() {
+            if in_code[idx] {
+                continue;
+            }
+            let broken: Vec<MarkdownLink> = links::markdown_links(line)
+                .into_iter()
+                .filter(|l| {
+                    !links::is_external(&l.target)
+                        && is_attachment_target(&links::decode_target(&l.target))
+                })
+                .filter(|l| !resolve_local_target(root, &note, &l.target).exists())
+                .collect();
+
+            for link in broken.into_iter().rev() {
+                let decoded = links::decode_target(&link.target);
+                let name = match Path::new(&decoded).file_name() {
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => continue,
+                };
+                let candidates = by_name.get(&name).cloned().unwrap_or_default();
+                let unresolved = |candidates: &[PathBuf]| UnresolvedImage {
+                    note_path: note_display.clone(),
+                    line_number: idx + 1,
+                    target: link.target.clone(),
+                    candidates: candidates
+                        .iter()
+                        .map(|c| c.to_string_lossy().to_string())
+                        .collect(),
+                };
+
+                if candidates.is_empty() {
+                    report.missing.push(unresolved(&candidates));
+                    continue;
+                }
+                let Some(found) = pick_candidate(root, &name, &candidates) else {
+                    report.ambiguous.push(unresolved(&candidates));
+                    continue;
+                };
+
+                let new_target = writer.markdown_target(note_dir, &found);
+                line.replace_range(link.target_start..link.target_end, &new_target);
+                changed = true;
+                report.repaired.push(ImageRepair {
+                    note_path: note_display.clone(),
+                    line_number: idx + 1,
+                    old_target: link.target,
+                    new_target,
+                });
+            }
+        }
+
+        if changed && locks::is_locked(Some(root), &note) {
+            report.repaired.retain(|r| r.note_path != note_display);
+            report.skipped_locked.push(note_display);
+            continue;
+        }
+
+        if changed {
+            if !dry_run {
+                journal.write(&note, || write_atomic(&note, buffer.render()))?;
+            }
+            report.updated_notes.push(note_display);
+        }
+    }
+
+    if !dry_run {
+        report.operation_id = journal.finish(&settings.journal)?;
+    }
+    Ok(report)
+}
+
+/// Writes links to a set of files, e.g. ones dropped on a note, as markdown
+/// for the frontend to insert at the cursor: a bulleted list of links, or a
+/// table with each file's name, size, modification date and link. Images
+/// are embedded and other files linked, in the vault's link format. With
+/// `copy_into_vault`, files from outside the vault are first copied to its
+/// attachment folder; otherwise they are linked with `file://` URLs. Files
+/// that can't be read are left out and listed in `skipped`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn format_files_as_markdown(
+    vault_path: &str,
+    note_path: &str,
+    paths: Vec<String>,
+    style: FileListStyle,
+    copy_into_vault: bool,
+) -> Result<FileListMarkdown, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let note = root.join(note_path);
+    let note_dir = note.parent().unwrap_or(root);
+    let settings = VaultSettings::load(root)?;
+
+    let mut report = FileListMarkdown::default();
+    let mut files = Vec::new();
+    for source in paths {
+        let source_path = PathBuf::from(&source);
+        let metadata = match fs::metadata(&source_path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => {
+                report.skipped.push(UpdateFailure {
+                    path: source,
+                    error: "Not a file".to_string(),
+                });
+                continue;
+            }
+            Err(e) => {
+                report.skipped.push(UpdateFailure {
+                    path: source,
+                    error: format!("Failed to read file: {}", e),
+                });
+                continue;
+            }
+        };
+        let imported = if copy_into_vault && !source_path.starts_with(root) {
+            match import_attachment(root, &settings, &note, &source_path) {
+                Ok(path) => Some(path),
+                Err(error) => {
+                    report.skipped.push(UpdateFailure {
+                        path: source,
+                        error,
+                    });
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        files.push((source, imported.unwrap_or(source_path), metadata));
+    }
+
+    // Made after the copies, so names they share with other files are seen.
+    let writer = LinkWriter::new(root, &settings.link_format);
+    let mut rows = Vec::new();
+    for (source, path, metadata) in files {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let image = encoding::mime_type(&path, &[]).starts_with("image/");
+        let link = if path.starts_with(root) {
+            if image {
+                writer.embed(note_dir, &path, Some(&name))
+            } else {
+                writer.link(note_dir, &path, None)
+            }
+        } else {
+            let url = url::Url::from_file_path(&path)
+                .map(String::from)
+                .unwrap_or_else(|_| links::encode_target(&path.to_string_lossy()));
+            let text = name.replace('[', "\\[").replace(']', "\\]");
+            format!("{}[{}]({})", if image { "!" } else { "" }, text, url)
+        };
+        let modified = metadata.modified().ok().map(|t| {
+            chrono::DateTime::<chrono::Local>::from(t)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        });
+        rows.push((
+            name,
+            vault::human_size(metadata.len()),
+            modified.unwrap_or_default(),
+            link,
+        ));
+        report.files.push(ListedFile {
+            copied: path != Path::new(&source),
+            path: path.to_string_lossy().to_string(),
+            source,
+        });
+    }
+
+    let mut markdown = String::new();
+    match style {
+        FileListStyle::List => {
+            for (_, _, _, link) in &rows {
+                markdown.push_str(&format!("- {}\n", link));
+            }
+        }
+        FileListStyle::Table if !rows.is_empty() => {
+            markdown.push_str("| File | Size | Modified | Link |\n| --- | ---: | --- | --- |\n");
+            let cell = |text: &str| text.replace('|', "\\|");
+            for (name, size, modified, link) in &rows {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    cell(name),
+                    size,
+                    modified,
+                    cell(link)
+                ));
+            }
+        }
+        FileListStyle::Table => {}
+    }
+    report.markdown = markdown;
+    Ok(report)
+}
+
+/// Copies `source` into the attachment folder for `note` and returns where
+/// it went. A file already there with the same name and content is reused;
+/// otherwise a name that is taken gets a number.
+pub(crate) fn import_attachment(
+    root: &Path,
+    settings: &VaultSettings,
+    note: &Path,
+    source: &Path,
+) -> Result<PathBuf, String> {
+    let dir = vault::attachment_dir(root, settings, note);
+    if external::is_read_only(&dir) {
+        return Err(FileError::locked(&dir.to_string_lossy()).to_string());
+    }
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let existing = dir.join(&name);
+    if existing.is_file() && vault::hash_file(&existing)? == vault::hash_file(source)? {
+        return Ok(existing);
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, extension),
+        _ => (name.as_str(), ""),
+    };
+    let target = vault::unique_path(&dir, stem, extension);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    fs::copy(source, &target).map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+    Ok(target)
+}
@@ -0,0 +1,272 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tauri::State;
+
+use super::backlinks::load_index;
+use super::caches::CacheKeys;
+use super::files::modified_secs;
+use crate::vault::{self, settings::VaultSettings, write_ledger};
+
+// "Note copy", "Note - Copy", "Note copy 2", "Note (1)": what copying a
+// file usually appends to its name.
+static COPY_SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:\s*-?\s*copy(?:\s+\d+)?|\s*\(\d+\))$").unwrap());
+// "Note 1": a copy too, but only when there is also a plain "Note".
+static NUMBER_SUFFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+\d+$").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditFile {
+    pub path: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// Byte for byte the same.
+    Content,
+    /// Titled alike once copy suffixes like ` 1` or ` (2)` are dropped.
+    Title,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// The content hash, or the normalised title.
+    pub key: String,
+    /// Newest first.
+    pub files: Vec<AuditFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultAudit {
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Notes no other note links to, by wikilink or markdown link.
+    pub orphans: Vec<AuditFile>,
+    /// Files that couldn't be read; the rest of the audit still ran.
+    pub errors: Vec<AuditError>,
+}
+
+/// A file that changed since GraphNotes last wrote it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalChange {
+    pub path: String,
+    /// When GraphNotes last wrote it, in seconds since the Unix epoch.
+    pub written_at: u64,
+    /// Modification time after that write.
+    pub written_modified: Option<u64>,
+    pub written_size: u64,
+    /// Modification time now.
+    pub modified: Option<u64>,
+    pub size: u64,
+    /// `size` minus `written_size`; negative when content was lost.
+    pub size_delta: i64,
+}
+
+/// Files whose content differs from what GraphNotes last saved to them,
+/// i.e. changed by something else such as a sync tool, newest change
+/// first. Only files saved while the vault's `write_ledger` setting was on
+/// are known; with `since`, only files modified since then, in seconds
+/// since the Unix epoch, are checked. Deleted files aren't reported.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn audit_external_changes(
+    vault_path: &str,
+    since: Option<u64>,
+) -> Result<Vec<ExternalChange>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let mut changes = Vec::new();
+    for entry in write_ledger::load(root)?.into_values() {
+        let path = root.join(&entry.path);
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        let modified = modified_secs(&path);
+        if since.is_some_and(|since| modified.is_none_or(|m| m < since)) {
+            continue;
+        }
+        // A sync tool can put back an old version with its old time, so
+        // the content decides.
+        if meta.len() == entry.size && vault::hash_file(&path).is_ok_and(|hash| hash == entry.hash)
+        {
+            continue;
+        }
+        changes.push(ExternalChange {
+            path: path.to_string_lossy().to_string(),
+            written_at: entry.written_at,
+            written_modified: entry.modified,
+            written_size: entry.size,
+            modified,
+            size: meta.len(),
+            size_delta: meta.len() as i64 - entry.size as i64,
+        });
+    }
+    changes.sort_by(|a, b| (Reverse(a.modified), &a.path).cmp(&(Reverse(b.modified), &b.path)));
+    Ok(changes)
+}
+
+/// Finds notes that are copies of each other and notes nothing links to.
+/// Exact duplicates are grouped by content hash; with `by_title`, notes
+/// whose titles only differ by a copy suffix, like `Meeting notes` and
+/// `Meeting notes 1`, are grouped as well. Ignored notes and private
+/// folders are left out.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn vault_audit(
+    keys: State<'_, CacheKeys>,
+    vault_path: String,
+    by_title: Option<bool>,
+) -> Result<VaultAudit, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let mut audit = VaultAudit::default();
+
+    let mut files: Vec<(PathBuf, AuditFile)> = Vec::new();
+    for note in vault::notes(root, &settings) {
+        match fs::metadata(&note) {
+            Ok(meta) => {
+                let entry = audit_file(&note, meta.len());
+                files.push((note, entry));
+            }
+            Err(e) => audit
+                .errors
+                .push(read_error(&note, format!("Failed to read file: {}", e))),
+        }
+    }
+
+    // Only files the same size can have the same content, so the rest
+    // needn't be hashed.
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, (_, file)) in files.iter().enumerate() {
+        by_size.entry(file.size).or_default().push(idx);
+    }
+    let mut by_hash: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for group in by_size.values().filter(|group| group.len() > 1) {
+        for &idx in group {
+            match vault::hash_file(&files[idx].0) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(idx),
+                Err(e) => audit.errors.push(read_error(&files[idx].0, e)),
+            }
+        }
+    }
+    for (hash, group) in by_hash.into_iter().filter(|(_, group)| group.len() > 1) {
+        audit.duplicates.push(duplicate_group(
+            DuplicateKind::Content,
+            hash,
+            &group,
+            &files,
+        ));
+    }
+
+    let (_, index) = load_index(&keys, &vault_path)?;
+    let position: HashMap<&Path, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(idx, (path, _))| (path.as_path(), idx))
+        .collect();
+    for (path, file) in &files {
+        let reported = audit.errors.iter().any(|e| e.path == file.path);
+        if index.position(path).is_none() && !reported {
+            audit
+                .errors
+                .push(read_error(path, "Failed to read file".to_string()));
+        }
+    }
+
+    if by_title.unwrap_or(false) {
+        // Keyed by title without any suffix; each with the title minus only
+        // its copy suffixes, to tell whether the plain title is there.
+        let mut by_base: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+        for note in &index.notes {
+            let Some(&idx) = position.get(note.path.as_path()) else {
+                continue;
+            };
+            let mut title = note
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            while COPY_SUFFIX.is_match(&title) {
+                title = COPY_SUFFIX.replace(&title, "").trim_end().to_string();
+            }
+            let base = NUMBER_SUFFIX.replace(&title, "").to_string();
+            if !base.is_empty() {
+                by_base.entry(base).or_default().push((idx, title));
+            }
+        }
+        for (base, members) in by_base {
+            let has_plain = members.iter().any(|(_, title)| *title == base);
+            let all_same = members.iter().all(|(_, title)| *title == members[0].1);
+            if members.len() > 1 && (has_plain || all_same) {
+                let group: Vec<usize> = members.iter().map(|(idx, _)| *idx).collect();
+                audit
+                    .duplicates
+                    .push(duplicate_group(DuplicateKind::Title, base, &group, &files));
+            }
+        }
+    }
+
+    let backlinks = index.backlink_counts();
+    audit.orphans = index
+        .notes
+        .iter()
+        .zip(backlinks)
+        .filter(|(_, count)| *count == 0)
+        .filter_map(|(note, _)| {
+            position
+                .get(note.path.as_path())
+                .map(|&idx| files[idx].1.clone())
+        })
+        .collect();
+    Ok(audit)
+}
+
+fn audit_file(path: &Path, size: u64) -> AuditFile {
+    AuditFile {
+        path: path.to_string_lossy().to_string(),
+        size,
+        modified: modified_secs(path),
+    }
+}
+
+fn read_error(path: &Path, message: String) -> AuditError {
+    AuditError {
+        path: path.to_string_lossy().to_string(),
+        message,
+    }
+}
+
+fn duplicate_group(
+    kind: DuplicateKind,
+    key: String,
+    group: &[usize],
+    files: &[(PathBuf, AuditFile)],
+) -> DuplicateGroup {
+    let mut members: Vec<AuditFile> = group.iter().map(|&idx| files[idx].1.clone()).collect();
+    members.sort_by(|a, b| (Reverse(a.modified), &a.path).cmp(&(Reverse(b.modified), &b.path)));
+    DuplicateGroup {
+        kind,
+        key,
+        files: members,
+    }
+}
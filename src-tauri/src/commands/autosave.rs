@@ -0,0 +1,541 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::async_runtime;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::files::{write_file, FileError};
+use crate::vault::write_locks::WriteLocks;
+
+const FAILED_EVENT: &str = "autosave://failed";
+/// How soon a queued autosave tries again when another operation kept its
+/// file locked past the wait.
+const BUSY_RETRY: Duration = Duration::from_millis(500);
+
+/// Autosaves waiting for their interval to pass, and what the last write
+/// to each path left on disk. Kept in managed state.
+#[derive(Default)]
+pub struct AutosaveQueue {
+    slots: Mutex<HashMap<PathBuf, Slot>>,
+}
+
+#[derive(Default)]
+struct Slot {
+    last_write: Option<Instant>,
+    /// Saves made to the path so far; each save's content is numbered by it.
+    saves: u64,
+    /// The number of the save last written, so one held up behind a lock
+    /// can't land over a later one.
+    saved: u64,
+    /// Latest content not yet written, with its number and what the editor
+    /// expected on disk; earlier queued content is dropped.
+    pending: Option<(u64, String, Expected)>,
+    /// Whether a background write is already scheduled.
+    scheduled: bool,
+    /// Modification time and size of the file after our last write.
+    written: Option<(SystemTime, u64)>,
+}
+
+/// The modification time and size the editor last read the file with, as
+/// `write_file` takes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Expected {
+    modified: Option<u64>,
+    size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutosaveStatus {
+    Written,
+    Queued,
+}
+
+/// What `AutosaveQueue::save` did with the content.
+#[derive(Debug, PartialEq, Eq)]
+enum Save {
+    Written,
+    Queued,
+    /// Queued, and `write_pending` needs running after the wait.
+    Schedule(Duration),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutosaveFailed {
+    pub path: String,
+    /// A `conflict` when the note changed on disk since the editor read it;
+    /// the queued content was not written.
+    pub error: FileError,
+}
+
+/// Saves `content` to `path` like `write_file`, but at most once every
+/// `min_interval_ms` per path. A call inside the interval is queued and
+/// replaces any content already queued; the latest is written when the
+/// interval is up. So is a save while another operation, such as a bulk
+/// link rewrite, has the file locked: it waits its turn rather than
+/// failing. `expected_modified` and `expected_size` are kept with the
+/// content and checked as `write_file` does, except against our own
+/// autosaves since. Failures of queued writes, conflicts included, are
+/// reported with an `autosave://failed` event instead of writing.
+#[tauri::command]
+pub fn autosave_file(
+    app: AppHandle,
+    queue: State<'_, AutosaveQueue>,
+    path: String,
+    content: String,
+    min_interval_ms: u64,
+    expected_modified: Option<u64>,
+    expected_size: Option<u64>,
+) -> Result<AutosaveStatus, FileError> {
+    let key = PathBuf::from(&path);
+    let expected = Expected {
+        modified: expected_modified,
+        size: expected_size,
+    };
+    match queue.save(
+        &key,
+        content,
+        expected,
+        Duration::from_millis(min_interval_ms),
+    )? {
+        Save::Written => Ok(AutosaveStatus::Written),
+        Save::Queued => Ok(AutosaveStatus::Queued),
+        Save::Schedule(wait) => {
+            schedule(app, key, wait);
+            Ok(AutosaveStatus::Queued)
+        }
+    }
+}
+
+/// Writes every queued autosave now, e.g. when the window loses focus or
+/// closes. Returns how many were written.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn flush_autosaves(queue: State<'_, AutosaveQueue>) -> Result<usize, String> {
+    queue.flush(None)
+}
+
+/// Whether the file at `path` is exactly as an autosave left it, so a
+/// change notification for it is the echo of our own write rather than an
+/// edit made elsewhere.
+#[tauri::command]
+pub fn is_own_write(queue: State<'_, AutosaveQueue>, path: &str) -> bool {
+    queue.is_own_write(Path::new(path))
+}
+
+impl AutosaveQueue {
+    /// Writes `content` now, unless the last write was under `interval`
+    /// ago, a write is already queued or another operation holds the file,
+    /// in which case it is queued.
+    fn save(
+        &self,
+        path: &Path,
+        content: String,
+        expected: Expected,
+        interval: Duration,
+    ) -> Result<Save, FileError> {
+        let (save, content) = {
+            let mut slots = self.lock()?;
+            let slot = slots.entry(path.to_path_buf()).or_default();
+            slot.saves += 1;
+            let wait = slot
+                .last_write
+                .map(|at| interval.saturating_sub(at.elapsed()))
+                .unwrap_or_default();
+            if slot.scheduled || !wait.is_zero() || WriteLocks::global().is_busy(path) {
+                slot.pending = Some((slot.saves, content, expected));
+                if slot.scheduled {
+                    return Ok(Save::Queued);
+                }
+                slot.scheduled = true;
+                return Ok(Save::Schedule(wait));
+            }
+            slot.last_write = Some(Instant::now());
+            slot.pending = None;
+            (slot.saves, content)
+        };
+        match self.write(path, save, content, expected) {
+            Ok(()) => Ok(Save::Written),
+            // Locked after the check above; `write` queued it.
+            Err(FileError::Busy { .. }) => Ok(self.schedule_requeued(path)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the queued content for `path`, if any is left; content
+    /// flushed in the meantime leaves nothing to do. When another
+    /// operation keeps the file locked, the content stays queued.
+    fn write_pending(&self, path: &Path) -> Result<(), FileError> {
+        let (save, content, expected) = {
+            let mut slots = self.lock()?;
+            let Some(slot) = slots.get_mut(path) else {
+                return Ok(());
+            };
+            slot.scheduled = false;
+            let Some(pending) = slot.pending.take() else {
+                return Ok(());
+            };
+            slot.last_write = Some(Instant::now());
+            pending
+        };
+        self.write(path, save, content, expected)
+    }
+
+    /// Writes the content of save number `save`, unless a later save was
+    /// written while this one waited for the file. If another operation
+    /// keeps the file locked, the content goes back in the queue, unless
+    /// newer content is there already. The file still as our last write
+    /// left it meets any expectation: the editor read it before that write.
+    fn write(
+        &self,
+        path: &Path,
+        save: u64,
+        content: String,
+        expected: Expected,
+    ) -> Result<(), FileError> {
+        let result = WriteLocks::global()
+            .lock(path, "autosave")
+            .map_err(FileError::from)
+            .and_then(|_lock| {
+                if self.lock()?.get(path).is_some_and(|slot| slot.saved > save) {
+                    return Ok(());
+                }
+                let expected = if self.is_own_write(path) {
+                    Expected::default()
+                } else {
+                    expected
+                };
+                write_file(
+                    &path.to_string_lossy(),
+                    &content,
+                    None,
+                    expected.modified,
+                    expected.size,
+                )?;
+                self.record_write(path, save);
+                Ok(())
+            });
+        if let Err(FileError::Busy { .. }) = &result {
+            if let Ok(mut slots) = self.slots.lock() {
+                slots
+                    .entry(path.to_path_buf())
+                    .or_default()
+                    .pending
+                    .get_or_insert((save, content, expected));
+            }
+        }
+        result
+    }
+
+    /// After `write` queued content again: schedules a retry unless one is
+    /// scheduled already.
+    fn schedule_requeued(&self, path: &Path) -> Save {
+        let Ok(mut slots) = self.slots.lock() else {
+            return Save::Queued;
+        };
+        let slot = slots.entry(path.to_path_buf()).or_default();
+        if std::mem::replace(&mut slot.scheduled, true) {
+            Save::Queued
+        } else {
+            Save::Schedule(BUSY_RETRY)
+        }
+    }
+
+    /// Writes the queued autosaves now, only those for files under the
+    /// canonical folder `within` if given.
+    pub fn flush(&self, within: Option<&Path>) -> Result<usize, String> {
+        let pending: Vec<(PathBuf, u64, String, Expected)> = {
+            let mut slots = self.lock()?;
+            slots
+                .iter_mut()
+                .filter(|(path, _)| within.is_none_or(|root| is_within(path, root)))
+                .filter_map(|(path, slot)| {
+                    let (save, content, expected) = slot.pending.take()?;
+                    slot.last_write = Some(Instant::now());
+                    Some((path.clone(), save, content, expected))
+                })
+                .collect()
+        };
+
+        let mut failed = Vec::new();
+        for (path, save, content, expected) in &pending {
+            if let Err(e) = self.write(path, *save, content.clone(), *expected) {
+                failed.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        if !failed.is_empty() {
+            return Err(format!(
+                "Failed to save {} of {} notes: {}",
+                failed.len(),
+                pending.len(),
+                failed.join("; ")
+            ));
+        }
+        Ok(pending.len())
+    }
+
+    pub fn is_own_write(&self, path: &Path) -> bool {
+        let Ok(slots) = self.slots.lock() else {
+            return false;
+        };
+        let written = slots.get(path).and_then(|slot| slot.written);
+        written.is_some() && written == stamp(path)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<PathBuf, Slot>>, String> {
+        self.slots
+            .lock()
+            .map_err(|_| "Autosave is unavailable".to_string())
+    }
+
+    fn record_write(&self, path: &Path, save: u64) {
+        if let Ok(mut slots) = self.slots.lock() {
+            let slot = slots.entry(path.to_path_buf()).or_default();
+            slot.saved = slot.saved.max(save);
+            slot.written = stamp(path);
+        }
+    }
+}
+
+fn schedule(app: AppHandle, path: PathBuf, wait: Duration) {
+    async_runtime::spawn(async move {
+        tokio::time::sleep(wait).await;
+        let _ = async_runtime::spawn_blocking(move || write_pending(&app, &path)).await;
+    });
+}
+
+/// Runs when a queued autosave's interval is up, trying again later while
+/// another operation keeps the file locked.
+fn write_pending(app: &AppHandle, path: &Path) {
+    let queue: State<AutosaveQueue> = app.state();
+    match queue.write_pending(path) {
+        Ok(()) => {}
+        Err(FileError::Busy { .. }) => {
+            if let Save::Schedule(wait) = queue.schedule_requeued(path) {
+                schedule(app.clone(), path.to_path_buf(), wait);
+            }
+        }
+        Err(e) => {
+            let _ = app.emit(
+                FAILED_EVENT,
+                AutosaveFailed {
+                    path: path.to_string_lossy().to_string(),
+                    error: e,
+                },
+            );
+        }
+    }
+}
+
+fn is_within(path: &Path, root: &Path) -> bool {
+    path.starts_with(root) || fs::canonicalize(path).is_ok_and(|path| path.starts_with(root))
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::files::read_file;
+    use crate::commands::format::format_note;
+    use crate::test_support::TempVault;
+    use crate::vault::write_locks::LOCK_TIMEOUT;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+
+    const NOTES: usize = 6;
+
+    fn vault(name: &str) -> (TempVault, Vec<PathBuf>) {
+        let root = TempVault::new(&format!("autosave-{}", name), &[]);
+        let notes: Vec<PathBuf> = (0..NOTES)
+            .map(|n| root.join(format!("Note {}.md", n)))
+            .collect();
+        for note in &notes {
+            fs::write(note, "save 0\n* foo\n").unwrap();
+        }
+        (root, notes)
+    }
+
+    /// Saves each note over and over, as typing in several tabs would, and
+    /// runs the background writes the command would schedule. Returns the
+    /// last content saved to each.
+    fn rapid_autosaves(queue: &AutosaveQueue, notes: &[PathBuf], rounds: usize) -> Vec<String> {
+        let mut last = vec![String::new(); notes.len()];
+        thread::scope(|scope| {
+            for round in 1..=rounds {
+                for (note, saved) in notes.iter().zip(&mut last) {
+                    *saved = format!("save {}\n* foo\n", round);
+                    let queued = queue
+                        .save(note, saved.clone(), Expected::default(), Duration::ZERO)
+                        .unwrap();
+                    if let Save::Schedule(wait) = queued {
+                        scope.spawn(move || {
+                            thread::sleep(wait);
+                            while let Err(e) = queue.write_pending(note) {
+                                assert!(matches!(e, FileError::Busy { .. }), "{}", e);
+                                if !matches!(queue.schedule_requeued(note), Save::Schedule(_)) {
+                                    break;
+                                }
+                            }
+                        });
+                    }
+                }
+            }
+        });
+        queue.flush(None).unwrap();
+        last
+    }
+
+    /// The save a note's content came from, by its first line.
+    fn save_number(note: &Path) -> usize {
+        let content = fs::read_to_string(note).unwrap();
+        let first = content.lines().next().unwrap_or_default();
+        first
+            .strip_prefix("save ")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_else(|| panic!("torn note: {:?}", content))
+    }
+
+    #[test]
+    fn formatting_during_rapid_autosaves_loses_no_save() {
+        let (root, notes) = vault("format");
+        let queue = AutosaveQueue::default();
+        let done = AtomicBool::new(false);
+        let last = thread::scope(|scope| {
+            // Rewrites `* foo` to `- foo`, reading each note and writing it
+            // back as the command does when run from the UI.
+            let formatter = scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    for note in &notes {
+                        format_note(&note.to_string_lossy(), None).unwrap();
+                    }
+                }
+            });
+            // A save going back to an earlier one means a write was lost:
+            // something wrote back what it read before a later save landed.
+            let observer = scope.spawn(|| {
+                let mut seen = vec![0; notes.len()];
+                while !done.load(Ordering::Relaxed) {
+                    for (note, seen) in notes.iter().zip(&mut seen) {
+                        let now = save_number(note);
+                        assert!(
+                            now >= *seen,
+                            "{} went back from save {} to {}",
+                            note.display(),
+                            seen,
+                            now
+                        );
+                        *seen = now;
+                    }
+                }
+            });
+            let last = rapid_autosaves(&queue, &notes, 300);
+            done.store(true, Ordering::Relaxed);
+            formatter.join().unwrap();
+            observer.join().unwrap();
+            last
+        });
+        for (note, last) in notes.iter().zip(&last) {
+            let content = fs::read_to_string(note).unwrap();
+            assert!(
+                content == *last || content == last.replace("* foo", "- foo"),
+                "{} has {:?} after {:?} was saved",
+                note.display(),
+                content,
+                last
+            );
+        }
+        let leftovers = fs::read_dir(&root)
+            .unwrap()
+            .flatten()
+            .filter(|e| !notes.contains(&e.path()))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn a_save_to_a_locked_note_is_queued_and_written_once_it_is_free() {
+        let (_root, notes) = vault("queued");
+        let queue = AutosaveQueue::default();
+        let note = &notes[0];
+        thread::scope(|scope| {
+            let (locked, unlock) = (mpsc::channel(), mpsc::channel::<()>());
+            scope.spawn(move || {
+                let _locks = WriteLocks::global()
+                    .lock_all([note], "move_folder", LOCK_TIMEOUT)
+                    .unwrap();
+                locked.0.send(()).unwrap();
+                let _ = unlock.1.recv();
+            });
+            locked.1.recv().unwrap();
+            let saved = queue
+                .save(
+                    note,
+                    "edited\n".to_string(),
+                    Expected::default(),
+                    Duration::ZERO,
+                )
+                .unwrap();
+            assert_eq!(saved, Save::Schedule(Duration::ZERO));
+            let saved = queue
+                .save(
+                    note,
+                    "edited again\n".to_string(),
+                    Expected::default(),
+                    Duration::ZERO,
+                )
+                .unwrap();
+            assert_eq!(saved, Save::Queued);
+            assert_eq!(fs::read_to_string(note).unwrap(), "save 0\n* foo\n");
+            unlock.0.send(()).unwrap();
+        });
+        queue.write_pending(note).unwrap();
+        assert_eq!(fs::read_to_string(note).unwrap(), "edited again\n");
+    }
+
+    #[test]
+    fn a_queued_save_over_an_outside_edit_is_a_conflict() {
+        let (_root, notes) = vault("conflict");
+        let queue = AutosaveQueue::default();
+        let note = &notes[0];
+        let read = read_file(&note.to_string_lossy()).unwrap();
+        let expected = Expected {
+            modified: read.modified,
+            size: Some(read.size),
+        };
+        let interval = Duration::from_secs(60);
+        assert_eq!(
+            queue
+                .save(note, "typed\n".to_string(), expected, interval)
+                .unwrap(),
+            Save::Written
+        );
+        // Our own write since the read doesn't count as a change.
+        assert!(matches!(
+            queue
+                .save(note, "typed more\n".to_string(), expected, interval)
+                .unwrap(),
+            Save::Schedule(_)
+        ));
+        queue.write_pending(note).unwrap();
+        assert_eq!(fs::read_to_string(note).unwrap(), "typed more\n");
+
+        fs::write(note, "edited elsewhere\n").unwrap();
+        queue
+            .save(note, "typed again\n".to_string(), expected, interval)
+            .unwrap();
+        let conflict = queue.write_pending(note).unwrap_err();
+        assert!(
+            matches!(conflict, FileError::Conflict { current_content: Some(ref c), .. }
+                if c == "edited elsewhere\n")
+        );
+        assert_eq!(fs::read_to_string(note).unwrap(), "edited elsewhere\n");
+    }
+}
@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::caches::CacheKeys;
+use crate::markdown::headings::{self, Anchor};
+use crate::vault::index::{LinkKind, NoteLink, VaultIndex};
+use crate::vault::link_index::{self, IndexStatus};
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backlink {
+    pub source_path: String,
+    pub line_number: usize,
+    pub line_content: String,
+    /// The link as written in the source note.
+    pub link_text: String,
+    pub embed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingLink {
+    pub target: String,
+    /// The note the link resolves to, if any.
+    pub target_path: Option<String>,
+    pub line_number: usize,
+    pub link_text: String,
+    pub embed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub source_path: String,
+    pub target: String,
+    pub line_number: usize,
+    pub link_text: String,
+}
+
+/// Links from other notes to `note_path`, in note order. Notes excluded by
+/// the vault's settings neither have nor give backlinks.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_backlinks(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    note_path: &str,
+) -> Result<Vec<Backlink>, String> {
+    let (root, index) = load_index(&keys, vault_path)?;
+    let Some(to) = note_position(&root, &index, note_path)? else {
+        return Ok(Vec::new());
+    };
+    let mut backlinks = Vec::new();
+    for (from, note) in index
+        .notes
+        .iter()
+        .enumerate()
+        .filter(|(from, _)| *from != to)
+    {
+        for link in note
+            .links
+            .iter()
+            .filter(|link| index.resolve(from, link) == Some(to))
+        {
+            backlinks.push(Backlink {
+                source_path: note.path.to_string_lossy().to_string(),
+                line_number: link.line,
+                line_content: link.line_content.clone(),
+                link_text: link.text.clone(),
+                embed: link.embed,
+            });
+        }
+    }
+    Ok(backlinks)
+}
+
+/// Every note link in `note_path`, resolved or not, in line order.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_outgoing_links(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    note_path: &str,
+) -> Result<Vec<OutgoingLink>, String> {
+    let (root, index) = load_index(&keys, vault_path)?;
+    let Some(from) = note_position(&root, &index, note_path)? else {
+        return Ok(Vec::new());
+    };
+    Ok(index.notes[from]
+        .links
+        .iter()
+        .map(|link| OutgoingLink {
+            target: link.target.clone(),
+            target_path: index
+                .resolve(from, link)
+                .map(|to| index.notes[to].path.to_string_lossy().to_string()),
+            line_number: link.line,
+            link_text: link.text.clone(),
+            embed: link.embed,
+        })
+        .collect())
+}
+
+/// Note links that resolve to no note. Wikilinks naming an attachment that
+/// exists, such as `![[diagram.png]]`, aren't broken.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn find_broken_links(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+) -> Result<Vec<BrokenLink>, String> {
+    let (root, index) = load_index(&keys, vault_path)?;
+    let mut attachment_names: Option<HashSet<String>> = None;
+    let mut broken = Vec::new();
+    for (from, note) in index.notes.iter().enumerate() {
+        for link in &note.links {
+            if link.target.trim().is_empty() || index.resolve(from, link).is_some() {
+                continue;
+            }
+            if is_attachment(link) {
+                let names = attachment_names.get_or_insert_with(|| attachment_file_names(&root));
+                if names.contains(&file_name(&link.target)) {
+                    continue;
+                }
+            }
+            broken.push(BrokenLink {
+                source_path: note.path.to_string_lossy().to_string(),
+                target: link.target.clone(),
+                line_number: link.line,
+                link_text: link.text.clone(),
+            });
+        }
+    }
+    Ok(broken)
+}
+
+/// Notes that link to no other note and that no other note links to.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn find_orphan_notes(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+) -> Result<Vec<String>, String> {
+    let (_, index) = load_index(&keys, vault_path)?;
+    let mut linked = vec![false; index.notes.len()];
+    for from in 0..index.notes.len() {
+        for to in index.outgoing(from) {
+            linked[from] = true;
+            linked[to] = true;
+        }
+    }
+    Ok(index
+        .notes
+        .iter()
+        .zip(linked)
+        .filter(|(_, linked)| !linked)
+        .map(|(note, _)| note.path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// The headings and `^block` ids a `#fragment` in a link to `target_path`
+/// (absolute or vault-relative) can name, with the slugs the broken-link
+/// check accepts.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_anchors(vault_path: &str, target_path: &str) -> Result<Vec<Anchor>, String> {
+    let path = note_path_in(Path::new(vault_path), target_path);
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(headings::anchors(&content))
+}
+
+/// How up to date the stored link index is. Stale notes are re-read by the
+/// next link query.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn backlinks_index_status(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+) -> Result<IndexStatus, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    link_index::status(
+        root,
+        &vault::notes(root, &settings),
+        &keys.mode(root, &settings),
+    )
+}
+
+pub(super) fn load_index(
+    keys: &CacheKeys,
+    vault_path: &str,
+) -> Result<(PathBuf, VaultIndex), String> {
+    let root = PathBuf::from(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(&root)?;
+    let index = link_index::refreshed(
+        &root,
+        &vault::notes(&root, &settings),
+        &keys.mode(&root, &settings),
+    )?;
+    Ok((root, index))
+}
+
+/// Position of a note given by absolute or vault-relative path, or `None`
+/// when it exists but isn't indexed, e.g. because it is ignored.
+pub(super) fn note_position(
+    root: &Path,
+    index: &VaultIndex,
+    note_path: &str,
+) -> Result<Option<usize>, String> {
+    let path = note_path_in(root, note_path);
+    if !path.is_file() {
+        return Err(format!("File does not exist: {}", note_path));
+    }
+    Ok(index.position(&path))
+}
+
+fn note_path_in(root: &Path, note_path: &str) -> PathBuf {
+    let path = Path::new(note_path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    }
+}
+
+pub(super) fn is_attachment(link: &NoteLink) -> bool {
+    let name = file_name(&link.target);
+    link.kind == LinkKind::Wiki
+        && Path::new(&name).extension().is_some()
+        && !vault::is_markdown(Path::new(&name))
+}
+
+fn file_name(target: &str) -> String {
+    target.rsplit('/').next().unwrap_or_default().to_lowercase()
+}
+
+fn attachment_file_names(root: &Path) -> HashSet<String> {
+    vault::files_where(root, |p| !vault::is_markdown(p))
+        .iter()
+        .filter_map(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .collect()
+}
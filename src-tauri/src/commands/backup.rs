@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::export::{write_zip, ZipExportReport};
+use crate::vault::{self, settings::VaultSettings};
+
+const DONE_EVENT: &str = "backup://done";
+const FAILED_EVENT: &str = "backup://failed";
+const STATE_FILE: &str = "backup.json";
+/// Shortest wait before a scheduled backup, so opening a vault whose last
+/// backup is overdue doesn't zip it during startup.
+const MIN_DELAY: Duration = Duration::from_secs(60);
+
+/// The running backup schedules, one per vault.
+#[derive(Default)]
+pub struct BackupScheduler {
+    tasks: Mutex<HashMap<PathBuf, JoinHandle<()>>>,
+    /// Held while a backup is written so manual and scheduled runs of the
+    /// same vault can't interleave.
+    running: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupDone {
+    pub vault_path: String,
+    pub backup: ZipExportReport,
+    /// Older backups deleted to stay within `keep_count`.
+    pub pruned: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFailed {
+    pub vault_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BackupState {
+    /// Fingerprint of the vault when the last backup was taken.
+    fingerprint: Option<String>,
+    /// Seconds since the Unix epoch.
+    last_backup: u64,
+}
+
+/// Saves the vault's backup settings and restarts its schedule. An
+/// `interval_hours` of zero stops automatic backups.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn configure_auto_backup(
+    app: AppHandle,
+    vault_path: &str,
+    interval_hours: u32,
+    destination_dir: &str,
+    keep_count: usize,
+) -> Result<(), String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    if destination_dir.trim().is_empty() {
+        return Err("A backup destination is required".to_string());
+    }
+    if keep_count == 0 {
+        return Err("keep_count must be at least 1".to_string());
+    }
+
+    let mut settings = VaultSettings::load(root)?;
+    settings.backup.interval_hours = interval_hours;
+    settings.backup.destination_dir = Some(destination_dir.to_string());
+    settings.backup.keep_count = keep_count;
+    settings.save(root)?;
+    schedule(&app, root)
+}
+
+/// Starts the vault's backup schedule from its settings. The frontend calls
+/// this when a vault is opened.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn start_auto_backup(app: AppHandle, vault_path: &str) -> Result<(), String> {
+    schedule(&app, Path::new(vault_path))
+}
+
+/// Takes a backup immediately, even if nothing changed since the last one.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn run_backup_now(app: AppHandle, vault_path: String) -> Result<BackupDone, String> {
+    run(&app, PathBuf::from(vault_path), true)
+        .await?
+        .ok_or_else(|| "Backup was skipped".to_string())
+}
+
+fn schedule(app: &AppHandle, root: &Path) -> Result<(), String> {
+    let settings = VaultSettings::load(root)?.backup;
+    let scheduler: State<BackupScheduler> = app.state();
+    let mut tasks = scheduler
+        .tasks
+        .lock()
+        .map_err(|_| "Backup scheduler is unavailable".to_string())?;
+    if let Some(task) = tasks.remove(root) {
+        task.abort();
+    }
+    if settings.interval_hours == 0 || settings.destination_dir.is_none() {
+        return Ok(());
+    }
+
+    let interval = Duration::from_secs(u64::from(settings.interval_hours) * 60 * 60);
+    let app = app.clone();
+    let vault_root = root.to_path_buf();
+    let task = async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(next_delay(&vault_root, interval)).await;
+            let _ = run(&app, vault_root.clone(), false).await;
+        }
+    });
+    tasks.insert(root.to_path_buf(), task);
+    Ok(())
+}
+
+/// Time left until the next backup is due, measured from the last one.
+fn next_delay(root: &Path, interval: Duration) -> Duration {
+    let last = load_state(root).map(|s| s.last_backup).unwrap_or(0);
+    let elapsed = Duration::from_secs(vault::now_secs().saturating_sub(last));
+    interval.saturating_sub(elapsed).max(MIN_DELAY)
+}
+
+/// Runs a backup off the async runtime and reports it with an event.
+/// `Ok(None)` means it was skipped because nothing changed.
+async fn run(app: &AppHandle, root: PathBuf, force: bool) -> Result<Option<BackupDone>, String> {
+    let vault_path = root.to_string_lossy().to_string();
+    let handle = app.clone();
+    let result = async_runtime::spawn_blocking(move || {
+        let scheduler: State<BackupScheduler> = handle.state();
+        let _running = scheduler
+            .running
+            .lock()
+            .map_err(|_| "Backup scheduler is unavailable".to_string())?;
+        backup(&root, force)
+    })
+    .await
+    .map_err(|e| format!("Backup task failed: {}", e))
+    .and_then(|result| result);
+
+    match &result {
+        Ok(Some(done)) => {
+            let _ = app.emit(DONE_EVENT, done.clone());
+        }
+        Ok(None) => {}
+        Err(message) => {
+            let _ = app.emit(
+                FAILED_EVENT,
+                BackupFailed {
+                    vault_path,
+                    message: message.clone(),
+                },
+            );
+        }
+    }
+    result
+}
+
+fn backup(root: &Path, force: bool) -> Result<Option<BackupDone>, String> {
+    let settings = VaultSettings::load(root)?.backup;
+    let destination = settings
+        .destination_dir
+        .as_deref()
+        .map(|dir| root.join(dir))
+        .ok_or_else(|| "No backup destination is configured".to_string())?;
+
+    let files = vault::files_where(root, |p| !p.starts_with(&destination));
+    let fingerprint = fingerprint(root, &files);
+    let mut state = load_state(root)?;
+    if !force && state.fingerprint.as_deref() == Some(fingerprint.as_str()) {
+        return Ok(None);
+    }
+
+    let prefix = backup_prefix(root);
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let report = write_zip(
+        root,
+        &files,
+        &destination.join(format!("{}{}.zip", prefix, stamp)),
+        None,
+    )?;
+    let pruned = prune(&destination, &prefix, settings.keep_count);
+
+    state.fingerprint = Some(fingerprint);
+    state.last_backup = vault::now_secs();
+    vault::write_json(&vault::state_dir(root).join(STATE_FILE), &state)?;
+    Ok(Some(BackupDone {
+        vault_path: root.to_string_lossy().to_string(),
+        backup: report,
+        pruned,
+    }))
+}
+
+fn load_state(root: &Path) -> Result<BackupState, String> {
+    vault::read_json(&vault::state_dir(root).join(STATE_FILE))
+}
+
+/// Hashes each file's path, size and modification time, which is enough to
+/// notice edits without reading any contents.
+fn fingerprint(root: &Path, files: &[PathBuf]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let metadata = fs::metadata(file).ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        hasher.update(format!(
+            "{}\0{}\0{}\n",
+            vault::relative_path(root, file),
+            size,
+            modified
+        ));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Backups are named `<vault>-YYYYMMDD-HHMMSS.zip`, so they sort by age.
+fn backup_prefix(root: &Path) -> String {
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}-", vault::safe_file_name(&name))
+}
+
+/// Deletes the oldest of this vault's backups beyond `keep_count`, returning
+/// their paths. Zero keeps everything.
+fn prune(destination: &Path, prefix: &str, keep_count: usize) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(destination) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(".zip"))
+                .is_some_and(is_timestamp)
+        })
+        .collect();
+    if keep_count == 0 || backups.len() <= keep_count {
+        return Vec::new();
+    }
+    backups.sort();
+    let excess = backups.len() - keep_count;
+    backups
+        .drain(..excess)
+        .filter(|path| fs::remove_file(path).is_ok())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn is_timestamp(stamp: &str) -> bool {
+    stamp.len() == 15
+        && stamp.char_indices().all(|(idx, c)| {
+            if idx == 8 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use super::graph_snapshots::snapshot_files;
+use crate::vault::cache_crypto::{CacheKey, CacheMode};
+use crate::vault::{link_index, settings::VaultSettings};
+
+/// Cache keys unlocked this session, by vault. They are never written to
+/// disk and are forgotten when the app quits.
+#[derive(Default)]
+pub struct CacheKeys {
+    keys: Mutex<HashMap<PathBuf, Arc<CacheKey>>>,
+}
+
+impl CacheKeys {
+    /// How the vault's caches may be read and written right now.
+    pub fn mode(&self, vault_path: &Path, settings: &VaultSettings) -> CacheMode {
+        if !settings.encrypt_caches {
+            return CacheMode::Plain;
+        }
+        let keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        match keys.get(&key_for(vault_path)) {
+            Some(key) => CacheMode::Encrypted(key.clone()),
+            None => CacheMode::Locked,
+        }
+    }
+}
+
+/// Unlocks the vault's encrypted caches for this session. The first unlock
+/// sets the passphrase; caches already written in plain text are encrypted
+/// then.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn unlock_vault_caches(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    passphrase: &str,
+) -> Result<(), String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let key = CacheKey::unlock(root, passphrase)?;
+    if VaultSettings::load(root)?.encrypt_caches {
+        for file in std::iter::once(link_index::index_path(root)).chain(snapshot_files(root)) {
+            key.encrypt_file(&file)?;
+        }
+    }
+    keys.keys
+        .lock()
+        .map_err(|_| "Cache keys are unavailable".to_string())?
+        .insert(key_for(root), Arc::new(key));
+    Ok(())
+}
+
+/// Forgets the vault's cache key. Commands then scan notes directly.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn lock_vault_caches(keys: State<'_, CacheKeys>, vault_path: &str) -> Result<(), String> {
+    keys.keys
+        .lock()
+        .map_err(|_| "Cache keys are unavailable".to_string())?
+        .remove(&key_for(Path::new(vault_path)));
+    Ok(())
+}
+
+/// Whether the vault encrypts its caches and they are still locked.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn vault_caches_locked(keys: State<'_, CacheKeys>, vault_path: &str) -> Result<bool, String> {
+    let root = Path::new(vault_path);
+    Ok(keys.mode(root, &VaultSettings::load(root)?).is_locked())
+}
+
+/// The same vault however its path is written.
+fn key_for(vault_path: &Path) -> PathBuf {
+    fs::canonicalize(vault_path).unwrap_or_else(|_| vault_path.to_path_buf())
+}
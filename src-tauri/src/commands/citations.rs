@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::files::{edit_note, FileError};
+use crate::markdown::bibtex::{self, BibEntry};
+use crate::markdown::citations::{self, Citation};
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnresolvedCitation {
+    pub key: String,
+    pub note_path: String,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CitationReport {
+    /// Cited entries, ordered like the generated reference list.
+    pub entries: Vec<BibEntry>,
+    pub unresolved: Vec<UnresolvedCitation>,
+    /// The reference list in markdown, one item per cited entry.
+    pub references: String,
+    /// Problems found while parsing the `.bib` file.
+    pub bib_errors: Vec<String>,
+    /// Whether the note's References section was rewritten.
+    pub updated: bool,
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn parse_citations(path: &str) -> Result<Vec<Citation>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(citations::extract(&content))
+}
+
+/// Resolves citation keys against a BibTeX file, for one note when
+/// `note_path` is given or otherwise the whole vault. `bib_path` may be
+/// relative to the vault.
+///
+/// With `update_references`, the note's "References" section is replaced
+/// (or appended) with the formatted list; this needs `note_path`.
+#[tauri::command]
+pub fn resolve_citations(
+    vault_path: &str,
+    bib_path: &str,
+    note_path: Option<String>,
+    update_references: Option<bool>,
+) -> Result<CitationReport, FileError> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path).into());
+    }
+    let bib_file = root.join(bib_path);
+    let bib_source = fs::read_to_string(&bib_file)
+        .map_err(|e| format!("Failed to read {}: {}", bib_file.display(), e))?;
+    let bibliography = bibtex::parse(&bib_source);
+    // BibTeX keys are case-insensitive.
+    let by_key: HashMap<String, &BibEntry> = bibliography
+        .entries
+        .iter()
+        .map(|entry| (entry.key.to_lowercase(), entry))
+        .collect();
+
+    let update = update_references.unwrap_or(false);
+    let notes = match &note_path {
+        Some(path) => vec![Path::new(path).to_path_buf()],
+        None if update => {
+            return Err("Updating references requires a note path"
+                .to_string()
+                .into())
+        }
+        None => vault::notes(root, &VaultSettings::load(root)?),
+    };
+
+    let mut cited: Vec<&BibEntry> = Vec::new();
+    let mut unresolved = Vec::new();
+    for note in &notes {
+        let Ok(content) = fs::read_to_string(note) else {
+            if note_path.is_some() {
+                return Err(format!("Failed to read file: {}", note.display()).into());
+            }
+            continue;
+        };
+        for citation in citations::extract(&content) {
+            match by_key.get(&citation.key.to_lowercase()) {
+                Some(entry) => {
+                    if !cited.iter().any(|c| c.key == entry.key) {
+                        cited.push(entry);
+                    }
+                }
+                None => unresolved.push(UnresolvedCitation {
+                    key: citation.key,
+                    note_path: note.to_string_lossy().to_string(),
+                    line_number: citation.line_number,
+                }),
+            }
+        }
+    }
+
+    cited.sort_by_cached_key(|entry| {
+        let first_author = entry
+            .authors
+            .first()
+            .map(|p| p.family.to_lowercase())
+            .unwrap_or_default();
+        let year = entry.fields.get("year").cloned().unwrap_or_default();
+        (first_author, year, entry.key.to_lowercase())
+    });
+    let references: String = cited
+        .iter()
+        .map(|entry| format!("- {}\n", bibtex::format_reference(entry)))
+        .collect();
+
+    let mut updated = false;
+    if update {
+        if let Some(path) = &note_path {
+            // Read again under the lock, so an edit since isn't overwritten.
+            edit_note(Path::new(path), "resolve_citations", |content| {
+                let new_content = citations::set_references_section(content, &references);
+                updated = new_content != content;
+                Ok(new_content)
+            })?;
+        }
+    }
+
+    Ok(CitationReport {
+        entries: cited.into_iter().cloned().collect(),
+        unresolved,
+        references,
+        bib_errors: bibliography.errors,
+        updated,
+    })
+}
@@ -0,0 +1,452 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::async_runtime;
+use tauri::{AppHandle, State};
+
+use super::files::write_atomic;
+use super::rename::{rename_paths, LinkUpdates};
+use crate::tasks::{Task, TaskKind, TaskManager};
+use crate::vault;
+use crate::vault::own_writes::OwnWrites;
+
+const ORIGINALS_DIR: &str = "originals";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutput {
+    /// Re-encode in the image's own format.
+    #[default]
+    Keep,
+    Jpeg,
+    /// Lossless WebP.
+    Webp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressOptions {
+    /// Images at least this large are compressed.
+    pub min_bytes: u64,
+    /// Images wider or taller than this are scaled down to fit, and
+    /// compressed whatever their size.
+    pub max_dimension: Option<u32>,
+    pub format: ImageOutput,
+    /// JPEG quality, from 1 to 100.
+    pub quality: u8,
+    /// Keep a copy of each original in `.graphnotes/originals/`.
+    pub keep_originals: bool,
+    /// Vault-relative folder to look in; the whole vault when unset.
+    pub folder: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressStatus {
+    /// Dry run: would be compressed.
+    Planned,
+    Compressed,
+    /// Already as small as re-encoding makes it.
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressedImage {
+    pub path: String,
+    /// Set when the extension changed.
+    pub new_path: Option<String>,
+    pub status: CompressStatus,
+    pub original_bytes: u64,
+    /// Size after compressing; in a dry run, what it would be.
+    pub new_bytes: u64,
+    pub width: u32,
+    pub height: u32,
+    pub new_width: u32,
+    pub new_height: u32,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompressReport {
+    pub images: Vec<CompressedImage>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Bytes freed, or in a dry run that would be.
+    pub bytes_saved: u64,
+    /// Links rewritten for images whose extension changed.
+    pub link_updates: LinkUpdates,
+    /// The run was cancelled; images not reached yet aren't listed.
+    pub cancelled: bool,
+}
+
+/// Compression still to be finished for an image whose extension changes:
+/// the new content waits in `temp` until the image has been renamed and its
+/// links rewritten.
+struct PendingMove {
+    image: usize,
+    old: PathBuf,
+    new: PathBuf,
+    temp: PathBuf,
+}
+
+/// A representation of the image to write instead of the original.
+struct Encoded {
+    bytes: Vec<u8>,
+    extension: &'static str,
+    width: u32,
+    height: u32,
+    note: Option<String>,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        Self {
+            min_bytes: 500 * 1024,
+            max_dimension: Some(2560),
+            format: ImageOutput::Keep,
+            quality: 85,
+            keep_originals: true,
+            folder: None,
+        }
+    }
+}
+
+/// Re-encodes PNG, JPEG and WebP images over `options.min_bytes` or
+/// `options.max_dimension`: scaled down to fit the maximum dimension, PNGs
+/// and WebPs losslessly and JPEGs at `options.quality`, or converted to
+/// `options.format`. Results that aren't smaller are skipped, and images
+/// with transparency stay in a format that keeps it. When the extension
+/// changes the image is renamed and links to it are rewritten. A dry run
+/// encodes in memory to report sizes without writing anything. Runs as a
+/// `compress_attachments` task, so it reports progress and can be
+/// cancelled between images.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn compress_attachments(
+    app: AppHandle,
+    tasks: State<'_, TaskManager>,
+    vault_path: String,
+    options: Option<CompressOptions>,
+    dry_run: bool,
+) -> Result<CompressReport, String> {
+    let root = PathBuf::from(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let options = options.unwrap_or_default();
+    if !(1..=100).contains(&options.quality) {
+        return Err("quality must be between 1 and 100".to_string());
+    }
+    if options.max_dimension == Some(0) {
+        return Err("max_dimension must be at least 1".to_string());
+    }
+    let task = tasks.start(
+        &app,
+        TaskKind::CompressAttachments,
+        format!("Compressing images in {}", vault_path),
+    );
+    async_runtime::spawn_blocking(move || {
+        let result = compress(&root, &options, dry_run, &task);
+        task.finish(result)
+    })
+    .await
+    .map_err(|e| format!("Compression failed: {}", e))?
+}
+
+fn compress(
+    root: &Path,
+    options: &CompressOptions,
+    dry_run: bool,
+    task: &Task,
+) -> Result<CompressReport, String> {
+    let dir = match &options.folder {
+        Some(folder) => root.join(folder),
+        None => root.to_path_buf(),
+    };
+    let images = vault::files_where(&dir, |p| image_format(p).is_some());
+    let mut report = CompressReport::default();
+    let mut pending = Vec::new();
+
+    for (done, path) in images.iter().enumerate() {
+        if task.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+        task.progress(
+            format!("Compressing {}", vault::relative_path(root, path)),
+            Some(done as f64 / images.len().max(1) as f64),
+            None,
+        );
+        let Some(mut image) = candidate(path, options) else {
+            continue;
+        };
+        match encode(path, options) {
+            Ok(encoded) if encoded.bytes.len() as u64 >= image.original_bytes => {
+                image.status = CompressStatus::Skipped;
+                image.new_bytes = image.original_bytes;
+                image.message = Some("Re-encoding wouldn't make it smaller".to_string());
+            }
+            Ok(encoded) => {
+                image.new_bytes = encoded.bytes.len() as u64;
+                image.new_width = encoded.width;
+                image.new_height = encoded.height;
+                image.message = encoded.note.clone();
+                let new = with_extension(path, encoded.extension);
+                if new != *path {
+                    image.new_path = Some(new.to_string_lossy().to_string());
+                }
+                if dry_run {
+                    image.status = CompressStatus::Planned;
+                } else {
+                    match write_compressed(root, path, &new, &encoded.bytes, options.keep_originals)
+                    {
+                        Ok(None) => image.status = CompressStatus::Compressed,
+                        Ok(Some(temp)) => pending.push(PendingMove {
+                            image: report.images.len(),
+                            old: path.clone(),
+                            new,
+                            temp,
+                        }),
+                        Err(e) => fail(&mut image, e),
+                    }
+                }
+            }
+            Err(e) => fail(&mut image, e),
+        }
+        report.images.push(image);
+    }
+
+    if dry_run {
+        let moves: Vec<(PathBuf, PathBuf)> = report
+            .images
+            .iter()
+            .filter_map(|image| {
+                Some((
+                    PathBuf::from(&image.path),
+                    PathBuf::from(image.new_path.as_ref()?),
+                ))
+            })
+            .collect();
+        if !moves.is_empty() {
+            report.link_updates = rename_paths(root, &moves, true, None).updates;
+        }
+    } else if !pending.is_empty() {
+        task.progress("Updating links", None, None);
+        let moves: Vec<(PathBuf, PathBuf)> = pending
+            .iter()
+            .map(|p| (p.old.clone(), p.new.clone()))
+            .collect();
+        let outcome = rename_paths(root, &moves, false, None);
+        for (pending, error) in pending.iter().zip(outcome.errors) {
+            let image = &mut report.images[pending.image];
+            let result = match error {
+                Some(e) => Err(e),
+                None => fs::rename(&pending.temp, &pending.new)
+                    .map(|()| OwnWrites::global().record(&pending.new))
+                    .map_err(|e| {
+                        format!("Renamed, but failed to write the compressed image: {}", e)
+                    }),
+            };
+            match result {
+                Ok(()) => image.status = CompressStatus::Compressed,
+                Err(e) => fail(image, e),
+            }
+            let _ = fs::remove_file(&pending.temp);
+        }
+        report.link_updates = outcome.updates;
+    }
+
+    for image in &report.images {
+        report.bytes_before += image.original_bytes;
+        report.bytes_after += match image.status {
+            CompressStatus::Planned | CompressStatus::Compressed => image.new_bytes,
+            CompressStatus::Skipped | CompressStatus::Failed => image.original_bytes,
+        };
+    }
+    report.bytes_saved = report.bytes_before - report.bytes_after;
+    Ok(report)
+}
+
+/// The image at `path`, when it is over the size or dimension threshold.
+fn candidate(path: &Path, options: &CompressOptions) -> Option<CompressedImage> {
+    let bytes = fs::metadata(path).ok()?.len();
+    let (width, height) = image::image_dimensions(path).ok()?;
+    let too_big = options
+        .max_dimension
+        .is_some_and(|max| width.max(height) > max);
+    if bytes < options.min_bytes && !too_big {
+        return None;
+    }
+    Some(CompressedImage {
+        path: path.to_string_lossy().to_string(),
+        new_path: None,
+        status: CompressStatus::Failed,
+        original_bytes: bytes,
+        new_bytes: bytes,
+        width,
+        height,
+        new_width: width,
+        new_height: height,
+        message: None,
+    })
+}
+
+fn encode(path: &Path, options: &CompressOptions) -> Result<Encoded, String> {
+    let failed = |e: image::ImageError| format!("Failed to read image: {}", e);
+    let source = image_format(path).ok_or_else(|| "Not a supported image".to_string())?;
+    let mut decoder = ImageReader::open(path)
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .into_decoder()
+        .map_err(failed)?;
+    let orientation = decoder.orientation().map_err(failed)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(failed)?;
+    image.apply_orientation(orientation);
+    if let Some(max) = options
+        .max_dimension
+        .filter(|max| image.width().max(image.height()) > *max)
+    {
+        image = image.resize(max, max, FilterType::Lanczos3);
+    }
+
+    let mut note = None;
+    let target = match (options.format, source) {
+        (ImageOutput::Jpeg, format) if format != ImageFormat::Jpeg && is_transparent(&image) => {
+            note = Some("Kept its format, since JPEG can't store transparency".to_string());
+            format
+        }
+        (ImageOutput::Keep, format) => format,
+        (ImageOutput::Jpeg, _) => ImageFormat::Jpeg,
+        (ImageOutput::Webp, _) => ImageFormat::WebP,
+    };
+
+    let mut bytes = Vec::new();
+    let written = match target {
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, options.quality)
+            .encode_image(&image.to_rgb8()),
+        ImageFormat::WebP if image.color().has_alpha() => image
+            .to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+        ImageFormat::WebP => image
+            .to_rgb8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+        _ => {
+            let encoder = PngEncoder::new_with_quality(
+                &mut bytes,
+                CompressionType::Best,
+                PngFilter::Adaptive,
+            );
+            image.write_with_encoder(encoder)
+        }
+    };
+    written.map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(Encoded {
+        bytes,
+        extension: match target {
+            ImageFormat::Jpeg if target == source => source_extension(path),
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::WebP => "webp",
+            _ => "png",
+        },
+        width: image.width(),
+        height: image.height(),
+        note,
+    })
+}
+
+/// Keeps the original if asked, then writes the compressed image. When its
+/// extension changes it is written beside the original under a hidden name
+/// instead, returned so it can be put in place once the image is renamed.
+fn write_compressed(
+    root: &Path,
+    old: &Path,
+    new: &Path,
+    bytes: &[u8],
+    keep_original: bool,
+) -> Result<Option<PathBuf>, String> {
+    if keep_original {
+        let originals = vault::state_dir(root).join(ORIGINALS_DIR);
+        let original = unused_path(&originals.join(vault::relative_path(root, old)));
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        fs::copy(old, &original).map_err(|e| format!("Failed to keep the original: {}", e))?;
+    }
+    if new == old {
+        write_atomic(old, bytes)?;
+        return Ok(None);
+    }
+    let name = new.file_name().unwrap_or_default().to_string_lossy();
+    let temp = old.with_file_name(format!(".{}.compressed", name));
+    write_atomic(&temp, bytes)?;
+    Ok(Some(temp))
+}
+
+fn fail(image: &mut CompressedImage, error: String) {
+    image.status = CompressStatus::Failed;
+    image.new_path = None;
+    image.new_bytes = image.original_bytes;
+    image.new_width = image.width;
+    image.new_height = image.height;
+    image.message = Some(error);
+}
+
+fn image_format(path: &Path) -> Option<ImageFormat> {
+    match source_extension(path) {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+fn source_extension(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "png",
+        Some("jpg") => "jpg",
+        Some("jpeg") => "jpeg",
+        Some("webp") => "webp",
+        _ => "",
+    }
+}
+
+fn is_transparent(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|p| p.0[3] < u8::MAX)
+}
+
+/// `path` with the image extension `extension`, keeping the original one when
+/// it only differs in case or spelling (`.JPG`, `.jpeg`), and avoiding
+/// existing files.
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    if source_extension(path) == extension {
+        return path.to_path_buf();
+    }
+    unused_path(&path.with_extension(extension))
+}
+
+/// `path`, or `name 2.ext`, `name 3.ext`... when it is taken.
+fn unused_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} {}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
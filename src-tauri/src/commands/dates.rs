@@ -0,0 +1,188 @@
+use chrono::{DateTime, Local, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::LazyLock;
+
+use super::files::write_note;
+use crate::markdown::frontmatter;
+use crate::vault::write_locks::{WriteLocks, LOCK_TIMEOUT};
+use crate::vault::{self, settings::VaultSettings};
+
+// `2024-06-12 Standup`, `daily_2024_06_12`, `2024.06.12`.
+static SEPARATED_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\D)(\d{4})[-_.](\d{2})[-_.](\d{2})(?:\D|$)").unwrap());
+// `20240612` and zettel ids such as `202406120930` or `20240612093015`.
+static COMPACT_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\D)(\d{8})(?:\d{4}|\d{6})?(?:\D|$)").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    Frontmatter,
+    Filename,
+    Git,
+    Modified,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InferredDate {
+    pub path: String,
+    /// As `YYYY-MM-DD`, unless taken from the note's own frontmatter.
+    pub created: String,
+    pub source: DateSource,
+    /// Whether the note lacks `created`, so it is (or, in a dry run, would
+    /// be) added.
+    pub backfill: bool,
+    pub written: bool,
+    pub error: Option<String>,
+}
+
+/// Works out when each note was created, trusting in turn its frontmatter
+/// `created` or `date`, a date in its file name, the commit that added it
+/// when the vault is a git repository, and its mtime. Notes without a
+/// `created` field get one, unless `dry_run` is set. Every note is listed
+/// with its date and where it came from.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn infer_note_dates(vault_path: &str, dry_run: bool) -> Result<Vec<InferredDate>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let mut git_added: Option<HashMap<String, i64>> = None;
+    let mut inferred = Vec::new();
+
+    let notes = vault::notes(root, &settings);
+    let _locks = if dry_run {
+        None
+    } else {
+        let locks = WriteLocks::global().lock_all(&notes, "infer_note_dates", LOCK_TIMEOUT);
+        Some(locks.map_err(|busy| busy.to_string())?)
+    };
+    for note in notes {
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let fm = frontmatter::parse_note(&content)
+            .map(|(fm, _)| fm)
+            .unwrap_or_default();
+        let has_created = fm
+            .get("created")
+            .and_then(frontmatter::parse_date)
+            .is_some();
+
+        let from_frontmatter = ["created", "date"]
+            .iter()
+            .filter_map(|key| fm.get(*key))
+            .find(|value| frontmatter::parse_date(value).is_some())
+            .and_then(|value| value.as_str())
+            .map(|date| (date.trim().to_string(), DateSource::Frontmatter));
+        let (created, source) = match from_frontmatter {
+            Some(found) => found,
+            None => {
+                let from_git = || {
+                    let added = git_added.get_or_insert_with(|| git_added_times(root));
+                    let at = *added.get(&vault::relative_path(root, &note))?;
+                    Some((
+                        local_date(DateTime::from_timestamp(at, 0)?),
+                        DateSource::Git,
+                    ))
+                };
+                let from_mtime = || {
+                    let modified: DateTime<Local> =
+                        fs::metadata(&note).ok()?.modified().ok()?.into();
+                    Some((
+                        modified.format("%Y-%m-%d").to_string(),
+                        DateSource::Modified,
+                    ))
+                };
+                let found = filename_date(&note)
+                    .map(|date| (date.format("%Y-%m-%d").to_string(), DateSource::Filename))
+                    .or_else(from_git)
+                    .or_else(from_mtime);
+                match found {
+                    Some(found) => found,
+                    None => continue,
+                }
+            }
+        };
+
+        let mut entry = InferredDate {
+            path: note.to_string_lossy().to_string(),
+            created,
+            source,
+            backfill: !has_created,
+            written: false,
+            error: None,
+        };
+        if entry.backfill && !dry_run {
+            // Appending keeps the rest of the frontmatter exactly as written.
+            let updated = frontmatter::append_field(&content, "created", &entry.created);
+            match write_note(&note, updated) {
+                Ok(()) => entry.written = true,
+                Err(e) => entry.error = Some(e.to_string()),
+            }
+        }
+        inferred.push(entry);
+    }
+    Ok(inferred)
+}
+
+fn filename_date(path: &Path) -> Option<NaiveDate> {
+    let stem = path.file_stem()?.to_string_lossy();
+    if let Some(caps) = SEPARATED_DATE.captures(&stem) {
+        let date = NaiveDate::from_ymd_opt(
+            caps[1].parse().ok()?,
+            caps[2].parse().ok()?,
+            caps[3].parse().ok()?,
+        );
+        if date.is_some() {
+            return date;
+        }
+    }
+    let caps = COMPACT_DATE.captures(&stem)?;
+    NaiveDate::parse_from_str(&caps[1], "%Y%m%d").ok()
+}
+
+fn local_date(at: DateTime<chrono::Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+/// When each file below `root` was first added to git, keyed by path
+/// relative to `root`. Empty when `root` isn't in a repository or git isn't
+/// installed.
+fn git_added_times(root: &Path) -> HashMap<String, i64> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "core.quotepath=off",
+            "log",
+            "--no-renames",
+            "--diff-filter=A",
+        ])
+        .args(["--format=%x00%at", "--name-only", "--relative", "--", "."])
+        .output();
+    let Some(log) = output.ok().and_then(|o| String::from_utf8(o.stdout).ok()) else {
+        return HashMap::new();
+    };
+
+    // Newest commits come first, so later entries overwrite with older times.
+    let mut added = HashMap::new();
+    for commit in log.split('\0').filter(|c| !c.trim().is_empty()) {
+        let mut lines = commit.lines();
+        let Some(at) = lines.next().and_then(|l| l.trim().parse::<i64>().ok()) else {
+            continue;
+        };
+        for file in lines.map(str::trim).filter(|l| !l.is_empty()) {
+            added.insert(file.to_string(), at);
+        }
+    }
+    added
+}
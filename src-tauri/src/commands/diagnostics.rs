@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use tauri::State;
+use tracing::Level;
+
+use super::caches::CacheKeys;
+use crate::diagnostics::{CommandStats, Diagnostics};
+use crate::logging::{self, Logging};
+use crate::vault::link_index::{self, CacheStats};
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub commands: Vec<CommandStats>,
+    pub vault: Option<VaultStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStats {
+    pub notes: usize,
+    pub attachments: usize,
+    pub link_index: CacheStats,
+    /// Notes changed since the link index last read them.
+    pub stale_notes: usize,
+    /// Only with `include_paths`.
+    pub vault_path: Option<String>,
+    /// Vault-relative paths of the stale notes, only with `include_paths`.
+    pub stale_paths: Option<Vec<String>>,
+}
+
+/// Command timings, plus stats for `vault_path` when given. Note and vault
+/// names are left out unless `include_paths` is set, so the report can be
+/// shared as is.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_diagnostics(
+    diagnostics: State<'_, Diagnostics>,
+    keys: State<'_, CacheKeys>,
+    vault_path: Option<String>,
+    include_paths: bool,
+) -> Result<DiagnosticsReport, String> {
+    let vault = vault_path
+        .map(|vault_path| vault_stats(&keys, &vault_path, include_paths))
+        .transpose()?;
+    Ok(DiagnosticsReport {
+        commands: diagnostics.commands(),
+        vault,
+    })
+}
+
+/// Clears the command timings and the link index hit counts.
+#[tauri::command]
+pub fn reset_diagnostics(diagnostics: State<'_, Diagnostics>) {
+    diagnostics.reset();
+    link_index::reset_cache_stats();
+}
+
+/// The last `lines` log lines, oldest first, each a JSON object. With
+/// `level`, only lines at that level or more severe: `warn` gives warnings
+/// and errors.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_recent_logs(
+    logging: State<'_, Logging>,
+    lines: usize,
+    level: Option<String>,
+) -> Result<Vec<String>, String> {
+    let wanted = level.as_deref().map(logging::parse_level).transpose()?;
+    let mut recent = Vec::new();
+    for file in logging.files().iter().rev() {
+        let content = fs::read_to_string(file).map_err(|e| format!("Failed to read log: {}", e))?;
+        for line in content.lines().rev() {
+            if recent.len() == lines {
+                break;
+            }
+            if wanted.is_none_or(|wanted| line_level(line).is_some_and(|level| wanted >= level)) {
+                recent.push(line.to_string());
+            }
+        }
+    }
+    recent.reverse();
+    Ok(recent)
+}
+
+/// Today's log file, or the log directory before anything was logged.
+#[tauri::command]
+pub fn get_log_file_path(logging: State<'_, Logging>) -> String {
+    let path = logging
+        .files()
+        .pop()
+        .unwrap_or_else(|| logging.dir().to_path_buf());
+    path.to_string_lossy().to_string()
+}
+
+/// Changes the log level until the app quits. Returns the new level.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn set_log_level(logging: State<'_, Logging>, level: String) -> Result<String, String> {
+    let level = logging::parse_level(&level)?;
+    logging.set_level(level)?;
+    tracing::info!(level = %level, "log level changed");
+    Ok(logging.level().to_string().to_lowercase())
+}
+
+fn line_level(line: &str) -> Option<Level> {
+    let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+    Level::from_str(entry.get("level")?.as_str()?).ok()
+}
+
+fn vault_stats(
+    keys: &CacheKeys,
+    vault_path: &str,
+    include_paths: bool,
+) -> Result<VaultStats, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let notes = vault::notes(root, &settings);
+    let mode = keys.mode(root, &settings);
+    let status = link_index::status(root, &notes, &mode)?;
+    Ok(VaultStats {
+        notes: notes.len(),
+        attachments: vault::files_where(root, |p| !vault::is_markdown(p)).len(),
+        link_index: link_index::cache_stats(root, &mode)?,
+        stale_notes: status.stale.len(),
+        vault_path: include_paths.then(|| vault_path.to_string()),
+        stale_paths: include_paths.then_some(status.stale),
+    })
+}
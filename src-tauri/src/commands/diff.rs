@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+
+use crate::markdown::frontmatter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffChange {
+    pub kind: ChangeKind,
+    pub text: String,
+    /// 0-based index of the line (or word) in the old and new text.
+    pub old_index: Option<usize>,
+    pub new_index: Option<usize>,
+}
+
+/// A run of changes with surrounding context. Ranges are half-open and in
+/// units of the chosen granularity.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_end: usize,
+    pub new_start: usize,
+    pub new_end: usize,
+    pub changes: Vec<DiffChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteDiff {
+    /// Differences in the YAML frontmatter, kept apart so metadata edits
+    /// don't clutter the body diff.
+    pub frontmatter: Vec<DiffHunk>,
+    pub body: Vec<DiffHunk>,
+    /// Share of unchanged content across frontmatter and body, 0 to 1.
+    pub similarity: f64,
+}
+
+const CONTEXT: usize = 3;
+
+fn diff_hunks<'a>(diff: &TextDiff<'a, 'a, 'a, str>) -> Vec<DiffHunk> {
+    diff.grouped_ops(CONTEXT)
+        .into_iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let first = group.first().unwrap();
+            let last = group.last().unwrap();
+            let changes = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffChange {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => ChangeKind::Equal,
+                        ChangeTag::Delete => ChangeKind::Delete,
+                        ChangeTag::Insert => ChangeKind::Insert,
+                    },
+                    text: change.value().to_string(),
+                    old_index: change.old_index(),
+                    new_index: change.new_index(),
+                })
+                .collect();
+
+            DiffHunk {
+                old_start: first.old_range().start,
+                old_end: last.old_range().end,
+                new_start: first.new_range().start,
+                new_end: last.new_range().end,
+                changes,
+            }
+        })
+        .collect()
+}
+
+fn text_diff<'a>(
+    old: &'a str,
+    new: &'a str,
+    granularity: Granularity,
+) -> TextDiff<'a, 'a, 'a, str> {
+    match granularity {
+        Granularity::Line => TextDiff::from_lines(old, new),
+        Granularity::Word => TextDiff::configure().diff_unicode_words(old, new),
+    }
+}
+
+/// Diffs two markdown documents, frontmatter and body separately.
+pub fn diff_texts(old: &str, new: &str, granularity: Granularity) -> NoteDiff {
+    let old_split = frontmatter::split(old);
+    let new_split = frontmatter::split(new);
+    let old_yaml = old_split.yaml.unwrap_or("");
+    let new_yaml = new_split.yaml.unwrap_or("");
+
+    let frontmatter_diff = text_diff(old_yaml, new_yaml, granularity);
+    let body_diff = text_diff(old_split.body, new_split.body, granularity);
+
+    // Weight each section's ratio by its size so a one-line metadata change
+    // doesn't dominate a long body.
+    let weight = |a: &str, b: &str| (a.len() + b.len()) as f64;
+    let fm_weight = weight(old_yaml, new_yaml);
+    let body_weight = weight(old_split.body, new_split.body);
+    let similarity = if fm_weight + body_weight == 0.0 {
+        1.0
+    } else {
+        (frontmatter_diff.ratio() as f64 * fm_weight + body_diff.ratio() as f64 * body_weight)
+            / (fm_weight + body_weight)
+    };
+
+    NoteDiff {
+        frontmatter: diff_hunks(&frontmatter_diff),
+        body: diff_hunks(&body_diff),
+        similarity,
+    }
+}
+
+/// Compares two notes, e.g. to preview a merge of near-duplicates.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn diff_notes(
+    path_a: &str,
+    path_b: &str,
+    granularity: Option<Granularity>,
+) -> Result<NoteDiff, String> {
+    let a = fs::read_to_string(path_a).map_err(|e| format!("Failed to read {}: {}", path_a, e))?;
+    let b = fs::read_to_string(path_b).map_err(|e| format!("Failed to read {}: {}", path_b, e))?;
+    Ok(diff_texts(&a, &b, granularity.unwrap_or_default()))
+}
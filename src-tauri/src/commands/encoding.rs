@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::files::{write_note, FileError};
+use crate::vault::encoding::{self, readable_word_share, repair_mojibake, TextEncoding};
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, settings::VaultSettings};
+
+/// Lines shown per note by `detect_mojibake`.
+const MAX_EXAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MojibakeLine {
+    pub line_number: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MojibakeNote {
+    pub path: String,
+    /// Double-encoded characters found.
+    pub occurrences: usize,
+    /// 0 to 1: how much of the note's non-ASCII text is mojibake.
+    pub confidence: f64,
+    /// Whether the note also has non-ASCII text that is fine as it is.
+    pub mixed_encoding: bool,
+    /// Why `fix_mojibake` would leave the note alone, if it would.
+    pub needs_review: Option<String>,
+    pub examples: Vec<MojibakeLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MojibakeFix {
+    pub path: String,
+    pub replacements: usize,
+    /// Every line the fix changes.
+    pub lines: Vec<MojibakeLine>,
+    /// Why the note was left alone for a person to look at, if it was.
+    pub needs_review: Option<String>,
+    /// Whether the note was rewritten; never on a dry run.
+    pub applied: bool,
+}
+
+/// Notes with mojibake from UTF-8 that was read as Windows-1252 and saved
+/// again, like `â€™` for `’` or `Ã©` for `é`, most certain first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn detect_mojibake(vault_path: &str) -> Result<Vec<MojibakeNote>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let mut found: Vec<MojibakeNote> = vault::notes(root, &settings)
+        .into_iter()
+        .filter_map(|note| {
+            let (content, text_encoding) = fs::read(&note)
+                .ok()
+                .and_then(|bytes| encoding::decode(&bytes).ok())?;
+            let repair = repair_mojibake(&content);
+            if repair.replacements == 0 && repair.broken == 0 {
+                return None;
+            }
+            let non_ascii = content.chars().filter(|c| !c.is_ascii()).count();
+            let examples = changed_lines(&content, &repair.text)
+                .into_iter()
+                .take(MAX_EXAMPLES)
+                .collect();
+            Some(MojibakeNote {
+                path: note.to_string_lossy().to_string(),
+                occurrences: repair.replacements,
+                confidence: ((repair.repaired_chars as f64 / non_ascii as f64) * 100.0).round()
+                    / 100.0,
+                mixed_encoding: non_ascii > repair.repaired_chars,
+                needs_review: review_reason(&content, &repair, text_encoding),
+                examples,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| b.occurrences.cmp(&a.occurrences))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(found)
+}
+
+/// Repairs the mojibake in the note at `path`, or with `dry_run` only shows
+/// the lines that would change. A note is left alone, with the reason in
+/// `needs_review`, when some of it looks double-encoded but doesn't decode
+/// or when the fix would leave fewer readable words than before.
+#[tauri::command]
+pub fn fix_mojibake(path: &str, dry_run: bool) -> Result<MojibakeFix, FileError> {
+    let file = Path::new(path);
+    // Held from the read to the write; the note is read as bytes, so not
+    // through `edit_note`.
+    let _lock = (!dry_run)
+        .then(|| WriteLocks::global().lock(file, "fix_mojibake"))
+        .transpose()?;
+    let bytes = fs::read(file).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (content, text_encoding) = encoding::decode(&bytes)?;
+    let repair = repair_mojibake(&content);
+    let needs_review = review_reason(&content, &repair, text_encoding);
+    let apply = !dry_run && needs_review.is_none() && repair.replacements > 0;
+    if apply {
+        let bom = if text_encoding == TextEncoding::Utf8Bom {
+            "\u{feff}"
+        } else {
+            ""
+        };
+        write_note(file, format!("{}{}", bom, repair.text))?;
+    }
+    Ok(MojibakeFix {
+        path: path.to_string(),
+        replacements: repair.replacements,
+        lines: changed_lines(&content, &repair.text),
+        needs_review,
+        applied: apply,
+    })
+}
+
+fn review_reason(
+    content: &str,
+    repair: &encoding::MojibakeRepair,
+    text_encoding: TextEncoding,
+) -> Option<String> {
+    if repair.broken > 0 {
+        return Some(format!(
+            "{} sequences look double-encoded but don't decode",
+            repair.broken
+        ));
+    }
+    if matches!(text_encoding, TextEncoding::Utf16Le | TextEncoding::Utf16Be) {
+        return Some("Note is UTF-16; the fix would save it as UTF-8".to_string());
+    }
+    if readable_word_share(&repair.text) < readable_word_share(content) {
+        return Some("The fix would leave fewer readable words".to_string());
+    }
+    None
+}
+
+/// Lines that differ between `before` and `after`. The repair never adds
+/// or removes lines, so they pair up one to one.
+fn changed_lines(before: &str, after: &str) -> Vec<MojibakeLine> {
+    before
+        .lines()
+        .zip(after.lines())
+        .enumerate()
+        .filter(|(_, (b, a))| b != a)
+        .map(|(idx, (b, a))| MojibakeLine {
+            line_number: idx + 1,
+            before: b.to_string(),
+            after: a.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,544 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::async_runtime;
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::attachments::{attachments_by_lowercase_name, referenced_attachments};
+use super::metadata::{query_notes, QuerySpec};
+use crate::markdown::{blank_code_spans, code_block_lines, frontmatter, links, LineBuffer};
+use crate::tasks::{Task, TaskKind, TaskManager};
+use crate::vault::{self, index::VaultIndex};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZipExportReport {
+    pub output_path: String,
+    pub files: usize,
+    /// Uncompressed size of everything written.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryExportOptions {
+    /// Include the attachments the exported notes link to or embed. On by
+    /// default.
+    pub include_attachments: bool,
+    /// Include the notes the exported notes embed, and the notes those
+    /// embed, and so on.
+    pub include_embeds: bool,
+    /// Only notes modified on or after this day, `YYYY-MM-DD`.
+    pub modified_after: Option<String>,
+    /// Only notes modified on or before this day, `YYYY-MM-DD`.
+    pub modified_before: Option<String>,
+}
+
+impl Default for QueryExportOptions {
+    fn default() -> Self {
+        Self {
+            include_attachments: true,
+            include_embeds: false,
+            modified_after: None,
+            modified_before: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportedKind {
+    /// Matched the query.
+    Note,
+    /// Embedded by an exported note.
+    EmbeddedNote,
+    Attachment,
+}
+
+/// A file in the archive, as listed in its `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedFile {
+    /// Vault-relative, as in the archive.
+    pub path: String,
+    pub kind: ExportedKind,
+    /// Why it was included.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryExportManifest {
+    /// Seconds since the Unix epoch.
+    pub exported_at: u64,
+    pub query: QuerySpec,
+    pub options: QueryExportOptions,
+    pub files: Vec<ExportedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryExportReport {
+    pub output_path: String,
+    pub notes: usize,
+    pub embedded_notes: usize,
+    pub attachments: usize,
+    /// Links to notes left out of the export, turned into plain text.
+    pub links_unlinked: usize,
+    /// Uncompressed size of everything written.
+    pub bytes: u64,
+}
+
+/// Zips the notes matching `query`, as `query_notes` finds them, into
+/// `output_zip` with their folder layout, optionally narrowed to a range of
+/// modification dates. The notes' attachments come along, and with
+/// `include_embeds` the notes they embed, transitively. Links to notes
+/// that aren't exported become plain text, so every link in the archive
+/// works. A `manifest.json` lists each file and why it was included. Runs
+/// as a `query_export` task, so it reports progress and can be cancelled.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_query(
+    app: AppHandle,
+    tasks: State<'_, TaskManager>,
+    vault_path: String,
+    query: QuerySpec,
+    output_zip: String,
+    options: Option<QueryExportOptions>,
+) -> Result<QueryExportReport, String> {
+    let root = PathBuf::from(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let options = options.unwrap_or_default();
+    let day = |text: &Option<String>| {
+        text.as_deref()
+            .map(|t| {
+                NaiveDate::parse_from_str(t.trim(), "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date: {}", t))
+            })
+            .transpose()
+    };
+    let after = day(&options.modified_after)?;
+    let before = day(&options.modified_before)?;
+    let task = tasks.start(
+        &app,
+        TaskKind::QueryExport,
+        format!("Exporting notes to {}", output_zip),
+    );
+    async_runtime::spawn_blocking(move || {
+        let range = (after, before);
+        let result = export_matching(&root, query, options, range, Path::new(&output_zip), &task);
+        task.finish(result)
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))?
+}
+
+fn export_matching(
+    root: &Path,
+    query: QuerySpec,
+    options: QueryExportOptions,
+    (after, before): (Option<NaiveDate>, Option<NaiveDate>),
+    output: &Path,
+    task: &Task,
+) -> Result<QueryExportReport, String> {
+    task.progress("Finding notes", None, None);
+    let in_range = |modified: Option<u64>| {
+        let day = modified
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+            .map(|t| t.with_timezone(&chrono::Local).date_naive());
+        after.is_none_or(|after| day.is_some_and(|d| d >= after))
+            && before.is_none_or(|before| day.is_some_and(|d| d <= before))
+    };
+    let matched: Vec<PathBuf> = query_notes(&root.to_string_lossy(), query.clone())?
+        .into_iter()
+        .filter(|result| in_range(result.modified))
+        .map(|result| PathBuf::from(result.path))
+        .collect();
+    let reason = match_reason(&query, &options);
+
+    task.check_cancelled()?;
+    task.progress(format!("Collecting {} notes", matched.len()), None, None);
+    let index = VaultIndex::build(
+        root,
+        &vault::visible_files(root, root, query.include_private, vault::is_markdown)?,
+    );
+    // Notes by position in the index, in the order they were added.
+    let mut notes: Vec<(usize, ExportedKind, String)> = Vec::new();
+    let mut exported: HashSet<usize> = HashSet::new();
+    for path in &matched {
+        if let Some(idx) = index.position(path).filter(|idx| exported.insert(*idx)) {
+            notes.push((idx, ExportedKind::Note, reason.clone()));
+        }
+    }
+    if options.include_embeds {
+        let mut queue: VecDeque<usize> = notes.iter().map(|(idx, _, _)| *idx).collect();
+        while let Some(from) = queue.pop_front() {
+            for link in index.notes[from].links.iter().filter(|link| link.embed) {
+                let Some(to) = index.resolve(from, link).filter(|to| exported.insert(*to)) else {
+                    continue;
+                };
+                let by = vault::relative_path(root, &index.notes[from].path);
+                notes.push((
+                    to,
+                    ExportedKind::EmbeddedNote,
+                    format!("Embedded in {}", by),
+                ));
+                queue.push_back(to);
+            }
+        }
+    }
+
+    let mut entries: Vec<ZipEntry> = Vec::new();
+    let mut files: Vec<ExportedFile> = Vec::new();
+    let mut attachments: BTreeMap<PathBuf, String> = BTreeMap::new();
+    let by_name = if options.include_attachments {
+        attachments_by_lowercase_name(root)
+    } else {
+        Default::default()
+    };
+    let mut links_unlinked = 0;
+    for (done, (idx, kind, reason)) in notes.iter().enumerate() {
+        task.check_cancelled()?;
+        let path = &index.notes[*idx].path;
+        let name = vault::relative_path(root, path);
+        task.progress(
+            format!("Preparing {}", name),
+            Some(done as f64 / notes.len() as f64 / 2.0),
+            None,
+        );
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if options.include_attachments {
+            for candidates in referenced_attachments(root, path, &content, &by_name) {
+                for attachment in candidates {
+                    attachments
+                        .entry(attachment)
+                        .or_insert_with(|| format!("Used by {}", name));
+                }
+            }
+        }
+        let (content, unlinked) = unlink_unexported(&index, *idx, &content, &exported);
+        links_unlinked += unlinked;
+        entries.push(ZipEntry {
+            name: name.clone(),
+            source: ZipSource::Text {
+                content,
+                modified_from: Some(path.clone()),
+            },
+        });
+        files.push(ExportedFile {
+            path: name,
+            kind: *kind,
+            reason: reason.clone(),
+        });
+    }
+    let attachment_count = attachments.len();
+    for (attachment, reason) in attachments {
+        let name = vault::relative_path(root, &attachment);
+        entries.push(ZipEntry {
+            name: name.clone(),
+            source: ZipSource::File(attachment),
+        });
+        files.push(ExportedFile {
+            path: name,
+            kind: ExportedKind::Attachment,
+            reason,
+        });
+    }
+
+    let embedded_notes = files
+        .iter()
+        .filter(|f| f.kind == ExportedKind::EmbeddedNote)
+        .count();
+    let manifest = QueryExportManifest {
+        exported_at: vault::now_secs(),
+        query,
+        options,
+        files,
+    };
+    entries.push(ZipEntry {
+        name: MANIFEST_FILE.to_string(),
+        source: ZipSource::Text {
+            content: serde_json::to_string_pretty(&manifest)
+                .map_err(|e| format!("Failed to write manifest: {}", e))?,
+            modified_from: None,
+        },
+    });
+    let zipped = write_zip_entries(&entries, output, Some(task))?;
+    Ok(QueryExportReport {
+        output_path: zipped.output_path,
+        notes: notes.len() - embedded_notes,
+        embedded_notes,
+        attachments: attachment_count,
+        links_unlinked,
+        bytes: zipped.bytes,
+    })
+}
+
+/// What every note matching the query has in common, for the manifest.
+fn match_reason(query: &QuerySpec, options: &QueryExportOptions) -> String {
+    let mut parts = Vec::new();
+    if let Some(tag) = &query.tag {
+        parts.push(format!("tagged #{}", tag.trim_start_matches('#')));
+    }
+    if let Some(folder) = &query.folder {
+        parts.push(format!("in {}", folder));
+    }
+    let mut fields: Vec<_> = query.fields.iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in fields {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        parts.push(format!("{} is {}", key, value));
+    }
+    if let Some(after) = &options.modified_after {
+        parts.push(format!("modified on or after {}", after));
+    }
+    if let Some(before) = &options.modified_before {
+        parts.push(format!("modified on or before {}", before));
+    }
+    if parts.is_empty() {
+        "Matched the query".to_string()
+    } else {
+        format!("Matched the query: {}", parts.join(", "))
+    }
+}
+
+/// `content` of the note at `from` with its links to notes that aren't
+/// being exported, or that don't exist, turned into their text: the alias
+/// or target of a wikilink, the text of a markdown link. Links to
+/// attachments are left as they are. Returns how many links were changed.
+fn unlink_unexported(
+    index: &VaultIndex,
+    from: usize,
+    content: &str,
+    exported: &HashSet<usize>,
+) -> (String, usize) {
+    let mut buffer = LineBuffer::parse(content);
+    let lines = buffer.as_strs();
+    let body_start = frontmatter::line_count(&lines);
+    let in_code = code_block_lines(&lines);
+    let mut unlinked = 0;
+    let mut rewritten: Vec<(usize, String)> = Vec::new();
+    for (idx, line) in lines.iter().enumerate().skip(body_start) {
+        if in_code[idx] || !(line.contains("[[") || line.contains("](")) {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        let keep = |to: Option<usize>| to.is_some_and(|to| exported.contains(&to));
+        let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+        for link in links::wikilinks(&scrubbed) {
+            let target = link.target.trim();
+            // `[[diagram.png]]` is an attachment; `[[Note]]` and `[[Note.md]]`
+            // are notes.
+            let extension = Path::new(target).extension();
+            let is_note = extension.is_none() || vault::is_markdown(Path::new(target));
+            if target.is_empty() || !is_note || keep(index.resolve_wikilink(from, target)) {
+                continue;
+            }
+            let text = link.alias.clone().unwrap_or_else(|| target.to_string());
+            replacements.push((link.start, link.end, text));
+        }
+        for link in links::markdown_links(&scrubbed) {
+            let path_part =
+                &link.target[..link.target.find(['#', '?']).unwrap_or(link.target.len())];
+            if links::is_external(&link.target)
+                || path_part.is_empty()
+                || !vault::is_markdown(Path::new(&links::decode_target(path_part)))
+                || keep(index.resolve_markdown_link(from, path_part))
+            {
+                continue;
+            }
+            replacements.push((link.start, link.end, link.text.clone()));
+        }
+        if replacements.is_empty() {
+            continue;
+        }
+        replacements.sort_by_key(|(start, _, _)| *start);
+        let mut out = line.to_string();
+        for (start, end, text) in replacements.iter().rev() {
+            out.replace_range(*start..*end, text);
+        }
+        unlinked += replacements.len();
+        rewritten.push((idx, out));
+    }
+    for (idx, line) in rewritten {
+        buffer.lines[idx] = line;
+    }
+    (buffer.render(), unlinked)
+}
+
+/// Zips every non-hidden file in the vault into `output_zip`, keeping the
+/// folder layout. Private folders are left out unless `include_private` is
+/// set, and an output inside the vault is left out of its own archive.
+/// Runs as a `zip_export` task, so it reports progress and can be cancelled.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_vault_zip(
+    app: AppHandle,
+    tasks: State<'_, TaskManager>,
+    vault_path: String,
+    output_zip: String,
+    include_private: Option<bool>,
+) -> Result<ZipExportReport, String> {
+    let root = PathBuf::from(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let task = tasks.start(
+        &app,
+        TaskKind::ZipExport,
+        format!("Exporting {}", vault_path),
+    );
+    async_runtime::spawn_blocking(move || {
+        let output = PathBuf::from(output_zip);
+        let files = vault::visible_files(&root, &root, include_private.unwrap_or(false), |p| {
+            p != output
+        });
+        let result = files.and_then(|files| write_zip(&root, &files, &output, Some(&task)));
+        task.finish(result)
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))?
+}
+
+/// Something to add to a zip under `name`.
+pub(crate) struct ZipEntry {
+    pub name: String,
+    pub source: ZipSource,
+}
+
+pub(crate) enum ZipSource {
+    File(PathBuf),
+    /// Content made for the archive, dated like `modified_from` if given.
+    Text {
+        content: String,
+        modified_from: Option<PathBuf>,
+    },
+}
+
+/// Writes `files` to a zip at `output` under their paths relative to
+/// `root`. The archive is built beside `output` and renamed into place, so
+/// a failed export never leaves a partial zip behind.
+pub(crate) fn write_zip(
+    root: &Path,
+    files: &[PathBuf],
+    output: &Path,
+    task: Option<&Task>,
+) -> Result<ZipExportReport, String> {
+    let entries: Vec<ZipEntry> = files
+        .iter()
+        .map(|file| ZipEntry {
+            name: vault::relative_path(root, file),
+            source: ZipSource::File(file.clone()),
+        })
+        .collect();
+    write_zip_entries(&entries, output, task)
+}
+
+/// `write_zip` for entries that needn't be files on disk.
+pub(crate) fn write_zip_entries(
+    entries: &[ZipEntry],
+    output: &Path,
+    task: Option<&Task>,
+) -> Result<ZipExportReport, String> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let name = output
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", output.display()))?;
+    let tmp_path = output.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+
+    let result = zip_entries(entries, &tmp_path, task).and_then(|bytes| {
+        fs::rename(&tmp_path, output)
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        Ok(bytes)
+    });
+    match result {
+        Ok(bytes) => Ok(ZipExportReport {
+            output_path: output.to_string_lossy().to_string(),
+            files: entries.len(),
+            bytes,
+        }),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn zip_entries(entries: &[ZipEntry], path: &Path, task: Option<&Task>) -> Result<u64, String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+
+    let mut bytes = 0;
+    for (done, entry) in entries.iter().enumerate() {
+        let name = &entry.name;
+        if let Some(task) = task {
+            task.check_cancelled()?;
+            task.progress(
+                format!("Adding {}", name),
+                Some(done as f64 / entries.len() as f64),
+                None,
+            );
+        }
+        let dated_by = match &entry.source {
+            ZipSource::File(file) => Some(file),
+            ZipSource::Text { modified_from, .. } => modified_from.as_ref(),
+        };
+        let options = match dated_by.and_then(|file| modified_time(file)) {
+            Some(time) => options.last_modified_time(time),
+            None => options,
+        };
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        bytes += match &entry.source {
+            ZipSource::File(file) => {
+                let mut source = File::open(file)
+                    .map_err(|e| format!("Failed to open {}: {}", file.display(), e))?;
+                io::copy(&mut source, &mut zip)
+                    .map_err(|e| format!("Failed to add {}: {}", file.display(), e))?
+            }
+            ZipSource::Text { content, .. } => {
+                zip.write_all(content.as_bytes())
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+                content.len() as u64
+            }
+        };
+    }
+    let mut file = zip
+        .finish()
+        .map_err(|e| format!("Failed to finish {}: {}", path.display(), e))?;
+    file.flush()
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(bytes)
+}
+
+fn modified_time(file: &Path) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+
+    let modified: chrono::DateTime<chrono::Local> =
+        fs::metadata(file).ok()?.modified().ok()?.into();
+    zip::DateTime::from_date_and_time(
+        u16::try_from(modified.year()).ok()?,
+        modified.month() as u8,
+        modified.day() as u8,
+        modified.hour() as u8,
+        modified.minute() as u8,
+        modified.second() as u8,
+    )
+    .ok()
+}
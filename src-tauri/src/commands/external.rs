@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::vault::{self, external};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultKind {
+    /// A GraphNotes vault, with its state in `.graphnotes`.
+    Vault,
+    /// A folder opened with `open_external_folder`.
+    External,
+    /// Neither yet.
+    Folder,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultMode {
+    pub path: String,
+    pub kind: VaultKind,
+    pub read_only: bool,
+    /// Whether `[[wikilinks]]` count as links.
+    pub wikilinks: bool,
+    /// Where GraphNotes keeps the vault's state; `None` for a plain folder.
+    pub state_dir: Option<String>,
+    /// What the UI should tell the user about the mode, if anything.
+    pub banner: Option<String>,
+}
+
+/// Opens any folder, such as a docs-as-code git repository, as a vault
+/// without changing it: GraphNotes' state goes to the app's data directory
+/// instead of a `.graphnotes` folder inside it, notes can't be changed
+/// unless `read_only` is turned off, and the graph is built from relative
+/// markdown links, counting `[[wikilinks]]` only with `wikilinks`. Opening
+/// it again changes those choices.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn open_external_folder(
+    path: &str,
+    read_only: Option<bool>,
+    wikilinks: Option<bool>,
+) -> Result<VaultMode, String> {
+    let root = canonical(path)?;
+    if external::get(&root).is_none() && root.join(vault::STATE_DIR).is_dir() {
+        return Err(format!("Folder is already a vault: {}", root.display()));
+    }
+    external::open(&root, read_only.unwrap_or(true), wikilinks.unwrap_or(false))?;
+    Ok(mode(&root))
+}
+
+/// Whether the folder at `path` is a vault, an external folder or neither,
+/// and how it may be used.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_vault_mode(path: &str) -> Result<VaultMode, String> {
+    Ok(mode(&canonical(path)?))
+}
+
+fn canonical(path: &str) -> Result<PathBuf, String> {
+    let root =
+        fs::canonicalize(path).map_err(|e| format!("Folder does not exist: {} ({})", path, e))?;
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", path));
+    }
+    Ok(root)
+}
+
+fn mode(root: &Path) -> VaultMode {
+    let path = root.to_string_lossy().to_string();
+    match external::get(root) {
+        Some(folder) => VaultMode {
+            path,
+            kind: VaultKind::External,
+            read_only: folder.read_only,
+            wikilinks: folder.wikilinks,
+            state_dir: Some(vault::state_dir(root).to_string_lossy().to_string()),
+            banner: Some(if folder.read_only {
+                "External folder, opened read-only. GraphNotes keeps its data outside this folder."
+                    .to_string()
+            } else {
+                "External folder. Edits are saved here; GraphNotes keeps its data outside this \
+                 folder."
+                    .to_string()
+            }),
+        },
+        None => {
+            let state_dir = vault::state_dir(root);
+            let is_vault = state_dir.is_dir();
+            VaultMode {
+                path,
+                kind: if is_vault {
+                    VaultKind::Vault
+                } else {
+                    VaultKind::Folder
+                },
+                read_only: false,
+                wikilinks: true,
+                state_dir: is_vault.then(|| state_dir.to_string_lossy().to_string()),
+                banner: None,
+            }
+        }
+    }
+}
@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::folder_notes;
+use crate::markdown;
+use crate::markdown::normalize::{self, WriteNormalization};
+use crate::vault::encoding::{self, BinaryKind, TextEncoding};
+use crate::vault::health::{self, VaultHealth};
+use crate::vault::own_writes::OwnWrites;
+use crate::vault::settings::{FolderNoteStyle, VaultSettings};
+use crate::vault::write_locks::{Busy, WriteLocks};
+use crate::vault::{self, external, frecency, goals, locks, positions, renames, write_ledger};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -11,6 +23,23 @@ pub struct FileEntry {
     pub extension: Option<String>,
     pub size: Option<u64>,
     pub modified: Option<u64>,
+    /// Whether this folder has a folder note, per the vault's convention.
+    pub has_folder_note: bool,
+    pub folder_note_path: Option<String>,
+    /// A folder's entries, from `read_directory_recursive` only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<FileEntry>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectoryTreeOptions {
+    /// Leave out files and folders whose names start with `.`.
+    pub skip_hidden: bool,
+    /// Only files with these extensions, e.g. `["md", "png"]`.
+    pub extensions: Option<Vec<String>>,
+    /// Levels to list; 1 is the same as `read_directory`. No limit when unset.
+    pub max_depth: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,9 +47,203 @@ pub struct FileContent {
     pub path: String,
     pub content: String,
     pub size: u64,
+    /// Modification time in milliseconds since the epoch; pass it and
+    /// `size` to `write_file` to be told of changes made since.
+    pub modified: Option<u64>,
+    /// How the file was encoded on disk. `write_file` always writes UTF-8.
+    pub encoding: TextEncoding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrittenFile {
+    /// As in `FileContent`; pass these as `expected_modified` and
+    /// `expected_size` next time.
+    pub modified: Option<u64>,
+    pub size: u64,
+}
+
+/// Largest file `read_file_binary` returns unless told otherwise.
+const DEFAULT_BINARY_LIMIT: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BinaryContent {
+    pub path: String,
+    /// Base64 of the file's bytes.
+    pub data: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// Text `preview_file` returns unless told otherwise.
+const DEFAULT_PREVIEW_BYTES: u64 = 256 * 1024;
+/// Archive entries `preview_file` lists.
+const PREVIEW_ENTRIES: usize = 50;
+/// Enough of a file to recognise any format `binary_format` knows.
+const SNIFF_BYTES: usize = 512;
+
+/// What `preview_file` found, tagged with `type`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilePreview {
+    Text {
+        path: String,
+        content: String,
+        encoding: TextEncoding,
+        /// Whether the file goes on past `content`.
+        truncated: bool,
+        size: u64,
+    },
+    Binary {
+        path: String,
+        kind: BinaryKind,
+        /// From the file's first bytes, e.g. `png` or `sqlite`.
+        format: Option<String>,
+        mime_type: String,
+        size: u64,
+        /// The first entries' names, for zip and tar archives.
+        entries: Option<Vec<String>>,
+        entry_count: Option<usize>,
+    },
+}
+
+/// Errors from commands that modify files. Serialized with a `kind` tag so
+/// the UI can react to specific failures; `message` is always present for
+/// display. Each is logged, by kind, as it is sent to the UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(remote = "Self", tag = "kind", rename_all = "snake_case")]
+pub enum FileError {
+    /// The note is locked; the UI can offer to unlock it.
+    Locked {
+        path: String,
+        message: String,
+    },
+    /// The file changed on disk since the caller last read it.
+    Conflict {
+        path: String,
+        /// In milliseconds, as `FileContent::modified`.
+        current_modified: Option<u64>,
+        /// What is on disk now, when it could be read as text.
+        current_content: Option<String>,
+        message: String,
+    },
+    /// Moving to the trash isn't possible here, e.g. no trash on this
+    /// filesystem. Nothing was deleted; the UI can offer to delete
+    /// permanently instead.
+    TrashUnavailable {
+        path: String,
+        message: String,
+    },
+    /// Writing failed while the disk is full, or nearly, or can't be
+    /// written at all; `message` leads with that.
+    Health {
+        path: String,
+        health: Box<VaultHealth>,
+        message: String,
+    },
+    /// Another operation, e.g. a bulk link rewrite, held the file for
+    /// longer than the write would wait; nothing was written.
+    Busy {
+        path: String,
+        operation: String,
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+}
+
+impl FileError {
+    pub fn locked(path: &str) -> Self {
+        let message = if external::is_read_only(Path::new(path)) {
+            format!("Folder is open read-only: {}", path)
+        } else {
+            format!("Note is locked: {}", path)
+        };
+        FileError::Locked {
+            path: path.to_string(),
+            message,
+        }
+    }
+
+    /// A failure to write `path`, as `Health` when its disk has a problem.
+    pub fn write_failed(path: &Path, message: String) -> Self {
+        match health::problem(path) {
+            Some(health) => FileError::Health {
+                path: path.to_string_lossy().to_string(),
+                health: Box::new(health),
+                message,
+            },
+            None => FileError::Io { message },
+        }
+    }
+}
+
+impl From<Busy> for FileError {
+    fn from(busy: Busy) -> Self {
+        FileError::Busy {
+            message: busy.to_string(),
+            path: busy.path,
+            operation: busy.operation,
+        }
+    }
+}
+
+impl From<String> for FileError {
+    fn from(message: String) -> Self {
+        FileError::Io { message }
+    }
+}
+
+impl Serialize for FileError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let error_kind = match self {
+            FileError::Locked { .. } => "locked",
+            FileError::Conflict { .. } => "conflict",
+            FileError::TrashUnavailable { .. } => "trash_unavailable",
+            FileError::Health { .. } => "health",
+            FileError::Busy { .. } => "busy",
+            FileError::Io { .. } => "io",
+        };
+        tracing::warn!(error_kind, error = %self, "file operation failed");
+        FileError::serialize(self, serializer)
+    }
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileError::Locked { message, .. }
+            | FileError::Conflict { message, .. }
+            | FileError::TrashUnavailable { message, .. }
+            | FileError::Health { message, .. }
+            | FileError::Busy { message, .. }
+            | FileError::Io { message } => f.write_str(message),
+        }
+    }
+}
+
+/// Text to operate on: either a file on disk or content held by the editor,
+/// e.g. `{"path": "/vault/note.md"}` or `{"content": "# Hi"}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextSource {
+    Path(String),
+    Content(String),
+}
+
+impl TextSource {
+    pub fn load(self) -> Result<String, String> {
+        match self {
+            TextSource::Path(path) => {
+                fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+            }
+            TextSource::Content(content) => Ok(content),
+        }
+    }
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub fn read_directory(path: &str) -> Result<Vec<FileEntry>, String> {
     let dir_path = Path::new(path);
 
@@ -32,53 +255,161 @@ pub fn read_directory(path: &str) -> Result<Vec<FileEntry>, String> {
         return Err(format!("Path is not a directory: {}", path));
     }
 
-    let mut entries: Vec<FileEntry> = Vec::new();
-
+    let folder_notes = folder_note_style(dir_path);
     match fs::read_dir(dir_path) {
         Ok(dir_entries) => {
-            for entry in dir_entries {
-                if let Ok(entry) = entry {
-                    let file_path = entry.path();
-                    let metadata = entry.metadata().ok();
-
-                    let file_entry = FileEntry {
-                        name: entry.file_name().to_string_lossy().to_string(),
-                        path: file_path.to_string_lossy().to_string(),
-                        is_directory: file_path.is_dir(),
-                        is_file: file_path.is_file(),
-                        extension: file_path
-                            .extension()
-                            .map(|e| e.to_string_lossy().to_string()),
-                        size: metadata.as_ref().map(|m| m.len()),
-                        modified: metadata.and_then(|m| {
-                            m.modified().ok().and_then(|t| {
-                                t.duration_since(std::time::UNIX_EPOCH)
-                                    .ok()
-                                    .map(|d| d.as_secs())
-                            })
-                        }),
-                    };
-
-                    entries.push(file_entry);
+            let mut entries: Vec<FileEntry> = dir_entries
+                .flatten()
+                .map(|entry| file_entry(&entry, folder_notes))
+                .collect();
+            sort_entries(&mut entries);
+            Ok(entries)
+        }
+        Err(e) => Err(format!("Failed to read directory: {}", e)),
+    }
+}
+
+/// `read_directory` for the whole tree below `path` in one call, each
+/// folder's entries in `children`. Folders are always listed; `extensions`
+/// only filters files. A folder reached again through a symlink, or below
+/// `max_depth`, has no `children`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn read_directory_recursive(
+    path: &str,
+    options: Option<DirectoryTreeOptions>,
+) -> Result<Vec<FileEntry>, String> {
+    let dir_path = Path::new(path);
+    if !dir_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    let options = options.unwrap_or_default();
+    let extensions: Option<HashSet<String>> = options.extensions.as_ref().map(|list| {
+        list.iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect()
+    });
+    let walk = TreeWalk {
+        options: &options,
+        extensions: extensions.as_ref(),
+        folder_notes: folder_note_style(dir_path),
+    };
+    let mut visited = HashSet::new();
+    if let Ok(real) = fs::canonicalize(dir_path) {
+        visited.insert(real);
+    }
+    walk.list(dir_path, 1, &mut visited)
+        .map_err(|e| format!("Failed to read directory: {}", e))
+}
+
+struct TreeWalk<'a> {
+    options: &'a DirectoryTreeOptions,
+    extensions: Option<&'a HashSet<String>>,
+    folder_notes: Option<FolderNoteStyle>,
+}
+
+impl TreeWalk<'_> {
+    fn list(
+        &self,
+        dir: &Path,
+        depth: usize,
+        visited: &mut HashSet<PathBuf>,
+    ) -> std::io::Result<Vec<FileEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if self.options.skip_hidden && vault::is_hidden(&name) {
+                continue;
+            }
+            let mut file_entry = file_entry(&entry, self.folder_notes);
+            if file_entry.is_directory {
+                let below = self.options.max_depth.is_none_or(|max| depth < max);
+                // Canonical paths catch symlinks back up the tree.
+                if below && fs::canonicalize(entry.path()).is_ok_and(|real| visited.insert(real)) {
+                    file_entry.children = self.list(&entry.path(), depth + 1, visited).ok();
+                }
+            } else if let Some(extensions) = self.extensions {
+                let extension = file_entry.extension.as_deref().unwrap_or("").to_lowercase();
+                if !extensions.contains(&extension) {
+                    continue;
                 }
             }
+            entries.push(file_entry);
+        }
+        sort_entries(&mut entries);
+        Ok(entries)
+    }
+}
 
-            // Sort: directories first, then alphabetically
-            entries.sort_by(|a, b| {
-                match (a.is_directory, b.is_directory) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                }
-            });
+fn folder_note_style(dir: &Path) -> Option<FolderNoteStyle> {
+    vault::find_root(dir)
+        .and_then(|root| VaultSettings::load(&root).ok())
+        .and_then(|settings| settings.folder_notes)
+}
 
-            Ok(entries)
-        }
-        Err(e) => Err(format!("Failed to read directory: {}", e)),
+fn file_entry(entry: &fs::DirEntry, folder_notes: Option<FolderNoteStyle>) -> FileEntry {
+    let name = entry.file_name().to_string_lossy().to_string();
+    entry_for(&entry.path(), name, entry.metadata().ok(), folder_notes)
+}
+
+/// The entry for one path, as `read_directory` would list it, for commands
+/// that return files found some other way.
+pub(crate) fn path_entry(path: &Path) -> FileEntry {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let folder_notes = path
+        .parent()
+        .filter(|_| path.is_dir())
+        .and_then(folder_note_style);
+    entry_for(path, name, fs::metadata(path).ok(), folder_notes)
+}
+
+fn entry_for(
+    file_path: &Path,
+    name: String,
+    metadata: Option<fs::Metadata>,
+    folder_notes: Option<FolderNoteStyle>,
+) -> FileEntry {
+    let folder_note = folder_notes
+        .filter(|_| file_path.is_dir())
+        .and_then(|style| folder_notes::find(style, file_path));
+
+    FileEntry {
+        name,
+        path: file_path.to_string_lossy().to_string(),
+        is_directory: file_path.is_dir(),
+        is_file: file_path.is_file(),
+        extension: file_path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string()),
+        size: metadata.as_ref().map(|m| m.len()),
+        modified: metadata.and_then(|m| {
+            m.modified().ok().and_then(|t| {
+                t.duration_since(std::time::UNIX_EPOCH)
+                    .ok()
+                    .map(|d| d.as_secs())
+            })
+        }),
+        has_folder_note: folder_note.is_some(),
+        folder_note_path: folder_note.map(|p| p.to_string_lossy().to_string()),
+        children: None,
     }
 }
 
+/// Directories first, then alphabetically ignoring case.
+fn sort_entries(entries: &mut [FileEntry]) {
+    entries.sort_by(|a, b| match (a.is_directory, b.is_directory) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+}
+
 #[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub fn read_file(path: &str) -> Result<FileContent, String> {
     let file_path = Path::new(path);
 
@@ -90,22 +421,185 @@ pub fn read_file(path: &str) -> Result<FileContent, String> {
         return Err(format!("Path is not a file: {}", path));
     }
 
-    match fs::read_to_string(file_path) {
-        Ok(content) => {
-            let metadata = fs::metadata(file_path).ok();
-            Ok(FileContent {
+    let bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (content, encoding) =
+        encoding::decode(&bytes).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(FileContent {
+        path: path.to_string(),
+        content,
+        size: bytes.len() as u64,
+        modified: modified_millis(file_path),
+        encoding,
+    })
+}
+
+/// Reads any file, such as an image or PDF a note embeds, as base64 with a
+/// guessed MIME type. Files over `max_bytes`, 50 MB by default, are refused
+/// without being read.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn read_file_binary(path: &str, max_bytes: Option<u64>) -> Result<BinaryContent, String> {
+    use base64::Engine;
+
+    let file_path = Path::new(path);
+    let metadata = fs::metadata(file_path).map_err(|_| format!("File does not exist: {}", path))?;
+    if !metadata.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+    let limit = max_bytes.unwrap_or(DEFAULT_BINARY_LIMIT);
+    if metadata.len() > limit {
+        return Err(format!(
+            "File is too large to read: {} is {} bytes, over the limit of {} bytes",
+            path,
+            metadata.len(),
+            limit
+        ));
+    }
+    let bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(BinaryContent {
+        path: path.to_string(),
+        mime_type: encoding::mime_type(file_path, &bytes).to_string(),
+        size: bytes.len() as u64,
+        data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+    })
+}
+
+/// Looks at what a file is before opening it, so clicking a large database
+/// or an archive shows a placeholder rather than an error. Text comes back
+/// as its first `max_bytes`, 256 KB by default, decoded as by `read_file`;
+/// anything else as a description without its content, with the names of
+/// the first entries of zip and tar archives.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn preview_file(path: &str, max_bytes: Option<u64>) -> Result<FilePreview, String> {
+    let file_path = Path::new(path);
+    let metadata = fs::metadata(file_path).map_err(|_| format!("File does not exist: {}", path))?;
+    if !metadata.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+    let size = metadata.len();
+    let limit = max_bytes.unwrap_or(DEFAULT_PREVIEW_BYTES);
+    let mut head = Vec::new();
+    File::open(file_path)
+        .and_then(|file| {
+            file.take(limit.max(SNIFF_BYTES as u64))
+                .read_to_end(&mut head)
+        })
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let sniffed = encoding::binary_format(&head);
+    if sniffed.is_none() {
+        let text = &head[..head.len().min(limit as usize)];
+        let truncated = size > text.len() as u64;
+        if let Some((content, encoding)) =
+            decode_prefix(text, truncated).filter(|(c, _)| !c.contains('\0'))
+        {
+            return Ok(FilePreview::Text {
                 path: path.to_string(),
                 content,
-                size: metadata.map(|m| m.len()).unwrap_or(0),
-            })
+                encoding,
+                truncated,
+                size,
+            });
         }
-        Err(e) => Err(format!("Failed to read file: {}", e)),
     }
+
+    let (kind, format) = sniffed.map_or((BinaryKind::Unknown, None), |(kind, format)| {
+        (kind, Some(format))
+    });
+    let listing = match format {
+        Some("zip") => zip_entry_names(file_path),
+        Some("tar") => tar_entry_names(file_path),
+        _ => None,
+    };
+    let (entries, entry_count) =
+        listing.map_or((None, None), |(names, count)| (Some(names), Some(count)));
+    Ok(FilePreview::Binary {
+        path: path.to_string(),
+        kind,
+        format: format.map(str::to_string),
+        mime_type: encoding::mime_type(file_path, &head).to_string(),
+        size,
+        entries,
+        entry_count,
+    })
+}
+
+/// Decodes text that may have been cut off after `bytes`, dropping the
+/// piece of a character the cut left at the end.
+fn decode_prefix(bytes: &[u8], truncated: bool) -> Option<(String, TextEncoding)> {
+    let cuts = if truncated { 0..4 } else { 0..1 };
+    cuts.filter_map(|cut| bytes.len().checked_sub(cut))
+        .find_map(|end| encoding::decode(&bytes[..end]).ok())
 }
 
+/// The first entries' names and how many there are.
+fn zip_entry_names(path: &Path) -> Option<(Vec<String>, usize)> {
+    let archive = zip::ZipArchive::new(File::open(path).ok()?).ok()?;
+    let names = (0..archive.len().min(PREVIEW_ENTRIES))
+        .filter_map(|idx| archive.name_for_index(idx).map(str::to_string))
+        .collect();
+    Some((names, archive.len()))
+}
+
+/// The first entries' names and how many there are, reading only the
+/// headers. Counting stops at the end of the archive or the first header
+/// that doesn't parse.
+fn tar_entry_names(path: &Path) -> Option<(Vec<String>, usize)> {
+    let mut file = File::open(path).ok()?;
+    let mut names = Vec::new();
+    let mut count = 0;
+    let mut header = [0u8; 512];
+    while file.read_exact(&mut header).is_ok() && header.iter().any(|b| *b != 0) {
+        let field = |range: std::ops::Range<usize>| {
+            let raw = &header[range];
+            String::from_utf8_lossy(&raw[..raw.iter().position(|b| *b == 0).unwrap_or(raw.len())])
+                .to_string()
+        };
+        let Ok(length) = u64::from_str_radix(field(124..136).trim(), 8) else {
+            break;
+        };
+        // ustar keeps long names' folders in a prefix field. Extended
+        // headers and long-name records aren't entries of their own.
+        let name = match field(345..500) {
+            prefix if prefix.is_empty() => field(0..100),
+            prefix => format!("{}/{}", prefix, field(0..100)),
+        };
+        if !matches!(header[156], b'x' | b'g' | b'L' | b'K') {
+            if names.len() < PREVIEW_ENTRIES {
+                names.push(name);
+            }
+            count += 1;
+        }
+        file.seek(SeekFrom::Current(length.div_ceil(512) as i64 * 512))
+            .ok()?;
+    }
+    Some((names, count))
+}
+
+/// Saves a file through a temporary file renamed over it, so a crash never
+/// leaves it half written. With `expected_modified` and `expected_size`, as
+/// the caller last saw them, a file changed since fails with a conflict
+/// carrying what is on disk now instead of being overwritten; a file that
+/// doesn't exist yet is just written. Returns the file's new modification
+/// time and size.
 #[tauri::command]
-pub fn write_file(path: &str, content: &str) -> Result<(), String> {
+pub fn write_file(
+    path: &str,
+    content: &str,
+    normalize: Option<WriteNormalization>,
+    expected_modified: Option<u64>,
+    expected_size: Option<u64>,
+) -> Result<WrittenFile, FileError> {
     let file_path = Path::new(path);
+    let vault_root = vault::find_root(file_path);
+
+    if locks::is_locked(vault_root.as_deref(), file_path) {
+        return Err(FileError::locked(path));
+    }
+    // Held from the check to the write, so nothing lands in between.
+    let _lock = WriteLocks::global().lock(file_path, "write_file")?;
+    check_unmodified(file_path, expected_modified, expected_size)?;
 
     // Ensure parent directory exists
     if let Some(parent) = file_path.parent() {
@@ -115,16 +609,47 @@ pub fn write_file(path: &str, content: &str) -> Result<(), String> {
         }
     }
 
-    fs::write(file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+    // Explicit rules win; otherwise fall back to the vault's default, if any
+    let rules = match normalize {
+        Some(rules) => Some(rules),
+        None => match &vault_root {
+            Some(root) => VaultSettings::load(root)?.write_normalization,
+            None => None,
+        },
+    };
+
+    let written = match rules {
+        Some(rules) if vault::is_markdown(file_path) => normalize::normalize(content, &rules),
+        _ => content.to_string(),
+    };
+    write_atomic(file_path, &written)
+        .map_err(|message| FileError::write_failed(file_path, message))?;
+
+    // Goal history and the ledger are bookkeeping; failing to update them
+    // mustn't fail the save.
+    if let Some(root) = &vault_root {
+        if vault::is_markdown(file_path) {
+            let _ = goals::record(root, file_path, markdown::word_count(&written));
+        }
+        record_write(root, file_path);
+    }
+    Ok(WrittenFile {
+        modified: modified_millis(file_path),
+        size: written.len() as u64,
+    })
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub fn create_file(path: &str, content: Option<String>) -> Result<(), String> {
     let file_path = Path::new(path);
 
     if file_path.exists() {
         return Err(format!("File already exists: {}", path));
     }
+    if external::is_read_only(file_path) {
+        return Err(FileError::locked(path).to_string());
+    }
 
     // Ensure parent directory exists
     if let Some(parent) = file_path.parent() {
@@ -135,38 +660,146 @@ pub fn create_file(path: &str, content: Option<String>) -> Result<(), String> {
     }
 
     let file_content = content.unwrap_or_default();
-    fs::write(file_path, file_content).map_err(|e| format!("Failed to create file: {}", e))
+    fs::write(file_path, file_content)
+        .map_err(|e| health::explain(file_path, format!("Failed to create file: {}", e)))
 }
 
+/// Moves a file or folder to the system trash, or deletes it for good with
+/// `permanent`. If there is no trash to move it to, nothing is deleted.
 #[tauri::command]
-pub fn delete_file(path: &str) -> Result<(), String> {
+pub fn delete_file(path: &str, permanent: Option<bool>) -> Result<(), FileError> {
+    delete_with(path, permanent, |path| trash::delete(path))
+}
+
+/// `delete_file` with the trash given by `trash`, which moves a file or
+/// folder, contents and all, into it.
+fn delete_with(
+    path: &str,
+    permanent: Option<bool>,
+    trash: impl FnOnce(&Path) -> Result<(), trash::Error>,
+) -> Result<(), FileError> {
     let file_path = Path::new(path);
 
     if !file_path.exists() {
-        return Err(format!("Path does not exist: {}", path));
+        return Err(format!("Path does not exist: {}", path).into());
     }
 
-    if file_path.is_dir() {
-        fs::remove_dir_all(file_path).map_err(|e| format!("Failed to delete directory: {}", e))
+    let vault_root = vault::find_root(file_path);
+    if let Some(locked) = locks::locked_within(vault_root.as_deref(), file_path).first() {
+        return Err(FileError::locked(locked));
+    }
+
+    if !permanent.unwrap_or(false) {
+        trash(file_path).map_err(|e| trash_error(path, e))?;
+    } else if file_path.is_dir() {
+        fs::remove_dir_all(file_path).map_err(|e| format!("Failed to delete directory: {}", e))?;
+    } else {
+        fs::remove_file(file_path).map_err(|e| format!("Failed to delete file: {}", e))?;
+    }
+    if let Some(root) = vault_root {
+        goals::remove(&root, file_path)?;
+        frecency::remove(&root, file_path)?;
+        positions::remove(&root, file_path)?;
+    }
+    Ok(())
+}
+
+/// Only a failure to find or make a trash at all is `TrashUnavailable`,
+/// since the UI offers to delete for good on it; anything else, such as
+/// missing permission, is an ordinary error. The trash crate tells these
+/// apart only by its descriptions on some platforms.
+fn trash_error(path: &str, error: trash::Error) -> FileError {
+    let unavailable = match &error {
+        trash::Error::Unknown { description } => {
+            let description = description.to_lowercase();
+            NO_TRASH.iter().any(|phrase| description.contains(phrase))
+        }
+        #[cfg(all(
+            unix,
+            not(target_os = "macos"),
+            not(target_os = "ios"),
+            not(target_os = "android")
+        ))]
+        trash::Error::FileSystem { source, .. } => {
+            matches!(
+                source.kind(),
+                std::io::ErrorKind::Unsupported | std::io::ErrorKind::CrossesDevices
+            )
+        }
+        _ => false,
+    };
+    if unavailable {
+        FileError::TrashUnavailable {
+            path: path.to_string(),
+            message: format!("Can't move to trash: {}", error),
+        }
     } else {
-        fs::remove_file(file_path).map_err(|e| format!("Failed to delete file: {}", e))
+        format!("Failed to move to trash: {}", error).into()
     }
 }
 
+/// How the trash crate words finding no trash to use.
+const NO_TRASH: &[&str] = &[
+    "could not find a valid 'home trash'",
+    "'home trash' either does not exist",
+    "neither the xdg_data_home nor the home",
+    "mount points cannot be determined",
+    "nor '/etc/mtab' could be opened",
+];
+
 #[tauri::command]
-pub fn rename_file(old_path: &str, new_path: &str) -> Result<(), String> {
+pub fn rename_file(old_path: &str, new_path: &str) -> Result<(), FileError> {
     let old = Path::new(old_path);
     let new = Path::new(new_path);
 
     if !old.exists() {
-        return Err(format!("Source path does not exist: {}", old_path));
+        return Err(format!("Source path does not exist: {}", old_path).into());
     }
 
     if new.exists() {
-        return Err(format!("Destination path already exists: {}", new_path));
+        return Err(format!("Destination path already exists: {}", new_path).into());
+    }
+
+    let vault_root = vault::find_root(old);
+    if let Some(locked) = locks::locked_within(vault_root.as_deref(), old).first() {
+        return Err(FileError::locked(locked));
     }
 
-    fs::rename(old, new).map_err(|e| format!("Failed to rename: {}", e))
+    fs::rename(old, new).map_err(|e| format!("Failed to rename: {}", e))?;
+    OwnWrites::global().record(new);
+    if let Some(root) = vault_root {
+        goals::rename(&root, old, new)?;
+        frecency::rename(&root, old, new)?;
+        positions::rename(&root, old, new)?;
+        renames::record(&root, old, new)?;
+        if new.is_dir() {
+            rename_folder_note(&root, old, new)?;
+        }
+    }
+    Ok(())
+}
+
+/// After a folder moves from `old` to `new`, renames its same-name folder
+/// note to match, e.g. `Beta/Alpha.md` to `Beta/Beta.md`.
+fn rename_folder_note(root: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    if VaultSettings::load(root)?.folder_notes != Some(FolderNoteStyle::SameName) {
+        return Ok(());
+    }
+    let (Some(old_name), Some(target)) =
+        (old.file_name(), FolderNoteStyle::SameName.note_path(new))
+    else {
+        return Ok(());
+    };
+    let note = new.join(format!("{}.md", old_name.to_string_lossy()));
+    if note == target || !note.is_file() || target.exists() {
+        return Ok(());
+    }
+    fs::rename(&note, &target).map_err(|e| format!("Failed to rename folder note: {}", e))?;
+    OwnWrites::global().record(&target);
+    goals::rename(root, &note, &target)?;
+    frecency::rename(root, &note, &target)?;
+    positions::rename(root, &note, &target)?;
+    renames::record(root, &note, &target)
 }
 
 #[tauri::command]
@@ -175,6 +808,7 @@ pub fn file_exists(path: &str) -> bool {
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub fn create_directory(path: &str) -> Result<(), String> {
     let dir_path = Path::new(path);
 
@@ -184,6 +818,431 @@ pub fn create_directory(path: &str) -> Result<(), String> {
         }
         return Err(format!("Path exists but is not a directory: {}", path));
     }
+    if external::is_read_only(dir_path) {
+        return Err(FileError::locked(path).to_string());
+    }
 
     fs::create_dir_all(dir_path).map_err(|e| format!("Failed to create directory: {}", e))
 }
+
+/// Writes a note on behalf of an editing command (formatting, table edits,
+/// ...), refusing locked notes just like `write_file` does. A command that
+/// read the note first goes through `edit_note` instead, so nothing lands
+/// in between.
+pub(crate) fn write_note(path: &Path, content: impl AsRef<[u8]>) -> Result<(), FileError> {
+    let vault_root = vault::find_root(path);
+    if locks::is_locked(vault_root.as_deref(), path) {
+        return Err(FileError::locked(&path.to_string_lossy()));
+    }
+    let _lock = WriteLocks::global().lock(path, "write_note")?;
+    write_atomic(path, content)?;
+    if let Some(root) = &vault_root {
+        record_write(root, path);
+    }
+    Ok(())
+}
+
+/// Reads a note, hands its content to `edit` and writes back what comes
+/// out, holding the note's lock for `operation` from the read to the write
+/// so an autosave can't land in between and be overwritten. The note is
+/// only rewritten when the content changed. Returns the new content.
+pub(crate) fn edit_note(
+    path: &Path,
+    operation: &str,
+    edit: impl FnOnce(&str) -> Result<String, String>,
+) -> Result<String, FileError> {
+    if locks::is_locked(vault::find_root(path).as_deref(), path) {
+        return Err(FileError::locked(&path.to_string_lossy()));
+    }
+    let _lock = WriteLocks::global().lock(path, operation)?;
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let edited = edit(&content)?;
+    if edited != content {
+        write_note(path, &edited)?;
+    }
+    Ok(edited)
+}
+
+/// Adds a save to the vault's write ledger, if it keeps one.
+fn record_write(root: &Path, path: &Path) {
+    if VaultSettings::load(root).is_ok_and(|settings| settings.write_ledger) {
+        if let Err(e) = write_ledger::record(root, path) {
+            tracing::warn!(path = %path.display(), error = %e, "write not recorded");
+        }
+    }
+}
+
+/// Modification time in seconds since the epoch, as reported in
+/// `FileEntry::modified`.
+pub(crate) fn modified_secs(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Modification time in milliseconds since the epoch, as reported in
+/// `FileContent::modified`. Two saves within a second differ here where
+/// `modified_secs` can't tell them apart.
+pub(crate) fn modified_millis(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Fails with a conflict when the file's modification time, in
+/// milliseconds, or its size no longer match what the caller saw. A change
+/// that keeps the size within the same millisecond still gets through; a
+/// content hash would catch it, at the cost of reading the file on every
+/// save. Without an expectation, anything goes.
+pub(crate) fn check_unmodified(
+    path: &Path,
+    expected_modified: Option<u64>,
+    expected_size: Option<u64>,
+) -> Result<(), FileError> {
+    if expected_modified.is_none() && expected_size.is_none() {
+        return Ok(());
+    }
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(());
+    };
+    let current = modified_millis(path);
+    let moved = expected_modified.is_some_and(|expected| current != Some(expected));
+    if moved || expected_size.is_some_and(|expected| metadata.len() != expected) {
+        let current_content = fs::read(path)
+            .ok()
+            .and_then(|bytes| encoding::decode(&bytes).ok())
+            .map(|(text, _)| text);
+        let path = path.to_string_lossy().to_string();
+        return Err(FileError::Conflict {
+            message: format!("File was modified externally: {}", path),
+            path,
+            current_modified: current,
+            current_content,
+        });
+    }
+    Ok(())
+}
+
+/// Writes `content` to a temporary file beside `path` and renames it over the
+/// target, so a crash mid-write never leaves a truncated file behind. Waits
+/// for any other operation writing the file to finish. The watcher doesn't
+/// report the write as a change.
+pub(crate) fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    let tmp_path = parent.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        uuid::Uuid::new_v4()
+    ));
+
+    let _lock = WriteLocks::global()
+        .lock(path, "save")
+        .map_err(|busy| busy.to_string())?;
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(health::explain(
+            path,
+            format!("Failed to write file: {}", e),
+        ));
+    }
+    OwnWrites::global().record(path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    /// Trashing either works or fails as unavailable, leaving the path in
+    /// place; build machines often have no trash.
+    fn assert_trashed(path: &Path) {
+        match delete_file(&path.to_string_lossy(), None) {
+            Ok(()) => assert!(!path.exists()),
+            Err(FileError::TrashUnavailable { .. }) => assert!(path.exists()),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn permanent_delete_removes_a_file() {
+        let root = TempVault::new(
+            "files-permanent-file",
+            &[("Note.md", "# Note"), ("Other.md", "")],
+        );
+        delete_file(&root.join("Note.md").to_string_lossy(), Some(true)).unwrap();
+        assert!(!root.join("Note.md").exists());
+        assert!(root.join("Other.md").exists());
+    }
+
+    #[test]
+    fn permanent_delete_removes_a_folder_and_its_contents() {
+        let files = [
+            ("Projects/A.md", "a"),
+            ("Projects/Sub/B.md", "b"),
+            ("Keep.md", ""),
+        ];
+        let root = TempVault::new("files-permanent-dir", &files);
+        delete_file(&root.join("Projects").to_string_lossy(), Some(true)).unwrap();
+        assert!(!root.join("Projects").exists());
+        assert!(root.join("Keep.md").exists());
+    }
+
+    /// A trash that moves what it is given into `bin`, keeping its name.
+    fn fake_trash(bin: &Path) -> impl FnOnce(&Path) -> Result<(), trash::Error> + '_ {
+        move |path| {
+            fs::create_dir_all(bin).unwrap();
+            fs::rename(path, bin.join(path.file_name().unwrap())).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn delete_hands_a_file_to_the_trash() {
+        let root = TempVault::new(
+            "files-seam-file",
+            &[("Note.md", "# Note"), ("Other.md", "")],
+        );
+        let bin = root.join("bin");
+        delete_with(
+            &root.join("Note.md").to_string_lossy(),
+            None,
+            fake_trash(&bin),
+        )
+        .unwrap();
+        assert!(!root.join("Note.md").exists());
+        assert_eq!(fs::read_to_string(bin.join("Note.md")).unwrap(), "# Note");
+        assert!(root.join("Other.md").exists());
+    }
+
+    #[test]
+    fn delete_hands_a_folder_and_its_contents_to_the_trash_at_once() {
+        let files = [
+            ("Projects/A.md", "a"),
+            ("Projects/Sub/B.md", "b"),
+            ("Keep.md", ""),
+        ];
+        let root = TempVault::new("files-seam-dir", &files);
+        let bin = root.join("bin");
+        let mut given = Vec::new();
+        delete_with(
+            &root.join("Projects").to_string_lossy(),
+            None,
+            |path: &Path| {
+                given.push(path.to_path_buf());
+                fake_trash(&bin)(path)
+            },
+        )
+        .unwrap();
+        assert_eq!(given, [root.join("Projects")]);
+        assert!(!root.join("Projects").exists());
+        assert_eq!(
+            fs::read_to_string(bin.join("Projects/Sub/B.md")).unwrap(),
+            "b"
+        );
+        assert!(root.join("Keep.md").exists());
+    }
+
+    #[test]
+    fn a_permanent_delete_never_touches_the_trash() {
+        let root = TempVault::new("files-seam-permanent", &[("Note.md", "")]);
+        delete_with(
+            &root.join("Note.md").to_string_lossy(),
+            Some(true),
+            |_: &Path| panic!("trashed"),
+        )
+        .unwrap();
+        assert!(!root.join("Note.md").exists());
+    }
+
+    #[test]
+    #[ignore = "moves a file into the real trash"]
+    fn delete_moves_a_file_to_the_trash() {
+        let root = TempVault::new("files-trash-file", &[("Note.md", "# Note")]);
+        assert_trashed(&root.join("Note.md"));
+    }
+
+    #[test]
+    #[ignore = "moves a folder into the real trash"]
+    fn delete_moves_a_folder_and_its_contents_to_the_trash() {
+        let root = TempVault::new(
+            "files-trash-dir",
+            &[("Projects/A.md", "a"), ("Projects/Sub/B.md", "b")],
+        );
+        assert_trashed(&root.join("Projects"));
+    }
+
+    #[test]
+    fn missing_trash_is_reported_apart_from_other_errors() {
+        let unavailable = trash_error(
+            "/vault/Note.md",
+            trash::Error::Unknown {
+                description:
+                    "Could not find a valid 'home trash' nor valid trashes on other mount points"
+                        .to_string(),
+            },
+        );
+        assert!(
+            matches!(unavailable, FileError::TrashUnavailable { ref path, .. }
+                if path == "/vault/Note.md")
+        );
+        let other = trash_error("/vault/Note.md", trash::Error::TargetedRoot);
+        assert!(matches!(other, FileError::Io { .. }));
+        let unknown = trash_error(
+            "/vault/Note.md",
+            trash::Error::Unknown {
+                description: "aborted".to_string(),
+            },
+        );
+        assert!(matches!(unknown, FileError::Io { .. }));
+    }
+
+    #[test]
+    #[cfg(all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    ))]
+    fn a_permission_error_is_not_a_missing_trash() {
+        let denied = trash_error(
+            "/vault/Note.md",
+            trash::Error::FileSystem {
+                path: PathBuf::from("/vault/.Trash-1000"),
+                source: std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+            },
+        );
+        assert!(matches!(denied, FileError::Io { .. }));
+    }
+
+    #[test]
+    fn deleting_a_missing_path_fails_without_touching_the_trash() {
+        let root = TempVault::new("files-missing", &[]);
+        let result = delete_file(&root.join("Gone.md").to_string_lossy(), None);
+        assert!(matches!(result, Err(FileError::Io { .. })));
+    }
+
+    #[test]
+    fn a_change_within_the_same_second_is_a_conflict() {
+        let root = TempVault::new("files-conflict", &[]);
+        let note = root.join("Note.md");
+        let path = note.to_string_lossy().to_string();
+        let saved = write_file(&path, "first", None, None, None).unwrap();
+        let at = |millis: u64| std::time::UNIX_EPOCH + std::time::Duration::from_millis(millis);
+        let whole_second = saved.modified.unwrap() / 1000 * 1000;
+        let set_modified = |millis: u64| {
+            fs::File::options()
+                .write(true)
+                .open(&note)
+                .unwrap()
+                .set_modified(at(millis))
+        };
+
+        set_modified(whole_second).unwrap();
+        let seen = read_file(&path).unwrap();
+        fs::write(&note, "other").unwrap();
+        set_modified(whole_second + 400).unwrap();
+        let conflict = write_file(&path, "mine", None, seen.modified, Some(seen.size)).unwrap_err();
+        assert!(
+            matches!(conflict, FileError::Conflict { current_modified: Some(m), .. }
+                if m == whole_second + 400)
+        );
+
+        fs::write(&note, "longer other").unwrap();
+        set_modified(whole_second).unwrap();
+        let conflict = write_file(&path, "mine", None, seen.modified, Some(seen.size)).unwrap_err();
+        assert!(
+            matches!(conflict, FileError::Conflict { current_content: Some(ref c), .. }
+                if c == "longer other")
+        );
+
+        let current = read_file(&path).unwrap();
+        let written =
+            write_file(&path, "mine", None, current.modified, Some(current.size)).unwrap();
+        assert_eq!(written.modified, modified_millis(&note));
+        assert_eq!(fs::read_to_string(&note).unwrap(), "mine");
+    }
+
+    #[test]
+    fn saves_from_two_windows_at_once_leave_one_whole_note() {
+        let root = TempVault::new("files-concurrent", &[("Note.md", "start\n")]);
+        let note = root.join("Note.md");
+        let saves: Vec<String> = ["first", "second"]
+            .iter()
+            .map(|word| format!("{}\n", word).repeat(8192))
+            .collect();
+        let writers: Vec<_> = saves
+            .iter()
+            .cloned()
+            .map(|content| {
+                let note = note.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        write_file(&note.to_string_lossy(), &content, None, None, None).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..200 {
+            let read = fs::read_to_string(&note).unwrap();
+            assert!(
+                read == "start\n" || saves.contains(&read),
+                "torn note of {} bytes",
+                read.len()
+            );
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert!(saves.contains(&fs::read_to_string(&note).unwrap()));
+        let leftovers = fs::read_dir(&root)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name() != "Note.md")
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn an_edit_waits_for_a_write_in_progress_and_keeps_it() {
+        let root = TempVault::new("files-edit", &[("Note.md", "start\n")]);
+        let note = root.join("Note.md");
+        let (held, hold) = std::sync::mpsc::channel();
+        let autosave = {
+            let note = note.clone();
+            std::thread::spawn(move || {
+                let _lock = WriteLocks::global().lock(&note, "autosave").unwrap();
+                held.send(()).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                write_atomic(&note, "autosaved\n").unwrap();
+            })
+        };
+        hold.recv().unwrap();
+        let edited = edit_note(&note, "format_note", |content| Ok(content.to_uppercase())).unwrap();
+        autosave.join().unwrap();
+        assert_eq!(edited, "AUTOSAVED\n");
+        assert_eq!(fs::read_to_string(&note).unwrap(), "AUTOSAVED\n");
+    }
+}
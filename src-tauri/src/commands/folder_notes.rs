@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::files::write_atomic;
+use crate::markdown::templates::{self, TemplateContext};
+use crate::vault::settings::{FolderNoteStyle, VaultSettings};
+
+/// The folder note for `folder` (absolute or relative to the vault), if
+/// folder notes are turned on and it exists.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_folder_note(vault_path: &str, folder: &str) -> Result<Option<String>, String> {
+    let root = Path::new(vault_path);
+    let dir = folder_in_vault(root, folder)?;
+    let Some(style) = VaultSettings::load(root)?.folder_notes else {
+        return Ok(None);
+    };
+    Ok(find(style, &dir).map(|path| path.to_string_lossy().to_string()))
+}
+
+/// Creates the folder note for `folder`, filled from `template` (a
+/// vault-relative path) when one is given. Returns the new note's path.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn create_folder_note(
+    vault_path: &str,
+    folder: &str,
+    template: Option<String>,
+) -> Result<String, String> {
+    let root = Path::new(vault_path);
+    let dir = folder_in_vault(root, folder)?;
+    if !dir.is_dir() {
+        return Err(format!("Folder does not exist: {}", folder));
+    }
+    let style = VaultSettings::load(root)?
+        .folder_notes
+        .ok_or_else(|| "Folder notes are turned off for this vault".to_string())?;
+    let path = style
+        .note_path(&dir)
+        .ok_or_else(|| format!("Folder can't have a folder note: {}", folder))?;
+    if path.exists() {
+        return Err(format!("Folder note already exists: {}", path.display()));
+    }
+
+    let template = match &template {
+        Some(rel) => fs::read_to_string(root.join(rel))
+            .map_err(|e| format!("Failed to read template {}: {}", rel, e))?,
+        None => String::new(),
+    };
+    let title = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    write_atomic(
+        &path,
+        templates::render(&template, &TemplateContext::new(&title)),
+    )?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// The folder note of `dir`, if it has one.
+pub(crate) fn find(style: FolderNoteStyle, dir: &Path) -> Option<PathBuf> {
+    style.note_path(dir).filter(|path| path.is_file())
+}
+
+fn folder_in_vault(root: &Path, folder: &str) -> Result<PathBuf, String> {
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", root.display()));
+    }
+    let folder_path = Path::new(folder);
+    if folder_path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(format!("Folder is outside the vault: {}", folder));
+    }
+    if folder_path.starts_with(root) {
+        Ok(folder_path.to_path_buf())
+    } else {
+        Ok(root.join(folder.trim_matches('/')))
+    }
+}
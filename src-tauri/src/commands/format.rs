@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use super::files::{edit_note, FileError, TextSource};
+use crate::markdown::footnotes;
+use crate::markdown::format::{self, FormatOptions};
+use crate::markdown::headings::{self, Direction, NumberingStyle};
+use crate::markdown::lists::{self, SortListOptions};
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn format_markdown(
+    source: TextSource,
+    options: Option<FormatOptions>,
+) -> Result<String, String> {
+    let content = source.load()?;
+    Ok(format::format(&content, &options.unwrap_or_default()))
+}
+
+/// Formats a note in place and returns the formatted content. The file is
+/// only rewritten when formatting changed something.
+#[tauri::command]
+pub fn format_note(path: &str, options: Option<FormatOptions>) -> Result<String, FileError> {
+    let file_path = Path::new(path);
+    edit_note(file_path, "format_note", |content| {
+        Ok(format::format(content, &options.unwrap_or_default()))
+    })
+}
+
+/// Renumbers a note's numeric footnotes by first reference, optionally
+/// moving every definition to the end, and returns the new content.
+#[tauri::command]
+pub fn renumber_footnotes(path: &str, relocate_definitions: bool) -> Result<String, FileError> {
+    let file_path = Path::new(path);
+    edit_note(file_path, "renumber_footnotes", |content| {
+        Ok(footnotes::renumber(content, relocate_definitions))
+    })
+}
+
+/// Numbers headings from `min_level` to `max_level` (`dotted`), or strips
+/// their numbering (`none`), and updates in-note links to them.
+#[tauri::command]
+pub fn number_headings(
+    path: &str,
+    min_level: usize,
+    max_level: usize,
+    style: NumberingStyle,
+) -> Result<String, FileError> {
+    if !(1..=6).contains(&min_level) || !(min_level..=6).contains(&max_level) {
+        return Err(format!("Invalid heading level range: {}-{}", min_level, max_level).into());
+    }
+    let file_path = Path::new(path);
+    edit_note(file_path, "number_headings", |content| {
+        Ok(headings::number(
+            content, file_path, min_level, max_level, style,
+        ))
+    })
+}
+
+/// Sorts the list containing `line_number` (1-based) and returns the new
+/// content.
+#[tauri::command]
+pub fn sort_list(
+    path: &str,
+    line_number: usize,
+    options: Option<SortListOptions>,
+) -> Result<String, FileError> {
+    let file_path = Path::new(path);
+    edit_note(file_path, "sort_list", |content| {
+        lists::sort_list(
+            content,
+            line_number.saturating_sub(1),
+            &options.unwrap_or_default(),
+        )
+    })
+}
+
+/// Moves the section whose heading is on `heading_line` (1-based) above or
+/// below its neighbouring sibling section.
+#[tauri::command]
+pub fn move_section(
+    path: &str,
+    heading_line: usize,
+    direction: Direction,
+) -> Result<String, FileError> {
+    let file_path = Path::new(path);
+    edit_note(file_path, "move_section", |content| {
+        headings::move_section(content, heading_line.saturating_sub(1), direction)
+    })
+}
+
+/// Promotes (negative `delta`) or demotes a heading together with the
+/// headings nested under it.
+#[tauri::command]
+pub fn change_section_level(
+    path: &str,
+    heading_line: usize,
+    delta: i32,
+) -> Result<String, FileError> {
+    let file_path = Path::new(path);
+    edit_note(file_path, "change_section_level", |content| {
+        headings::change_section_level(content, heading_line.saturating_sub(1), delta)
+    })
+}
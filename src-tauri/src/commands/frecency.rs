@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::vault::frecency::{self, Frecency};
+use crate::vault::{self, settings::VaultSettings, NoteFilter};
+
+const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrequentNote {
+    pub path: String,
+    pub opens: u32,
+    pub last_opened: u64,
+    /// Open count with older opens decayed away.
+    pub score: f64,
+}
+
+/// Counts an open of `path` towards its frecency.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn record_file_opened(vault_path: &str, path: &str) -> Result<(), String> {
+    let note = Path::new(path);
+    if !note.is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    frecency::record_open(Path::new(vault_path), note)
+}
+
+/// The most frequently and recently opened notes that still exist, best
+/// first. Notes in private folders are left out.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_frequent_notes(
+    vault_path: &str,
+    limit: Option<usize>,
+) -> Result<Vec<FrequentNote>, String> {
+    let root = Path::new(vault_path);
+    let store = Frecency::load(root)?;
+    let filter = NoteFilter::new(root, &VaultSettings::load(root)?);
+    let now = vault::now_secs();
+    let mut notes: Vec<FrequentNote> = store
+        .notes
+        .iter()
+        .map(|(rel, visits)| FrequentNote {
+            path: root.join(rel).to_string_lossy().to_string(),
+            opens: visits.opens,
+            last_opened: visits.last_opened,
+            score: visits.score_at(now),
+        })
+        .filter(|note| Path::new(&note.path).is_file() && !filter.is_private(Path::new(&note.path)))
+        .collect();
+    notes.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.last_opened.cmp(&a.last_opened))
+    });
+    notes.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(notes)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn frecency_score(vault_path: &str, path: &str) -> Result<f64, String> {
+    let root = Path::new(vault_path);
+    Ok(Frecency::load(root)?.score(root, Path::new(path), vault::now_secs()))
+}
@@ -0,0 +1,332 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::markdown::{self, blank_code_spans, code_block_lines, frontmatter, links};
+use crate::vault::{self, settings::VaultSettings};
+
+/// A use of a glossary term. Line and column are 1-based, column and length
+/// in characters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TermOccurrence {
+    pub note_path: String,
+    pub line_number: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GlossaryTerm {
+    /// The term as written in the glossary note's title or aliases.
+    pub term: String,
+    pub glossary_note: String,
+    pub case_sensitive: bool,
+    pub occurrences: Vec<TermOccurrence>,
+}
+
+/// A defined term found in the note being edited, with its definition for
+/// hover text.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteTerm {
+    pub term: String,
+    pub line_number: usize,
+    pub column: usize,
+    pub length: usize,
+    pub glossary_note: String,
+    /// First paragraph of the glossary note.
+    pub definition: String,
+}
+
+struct Term {
+    text: String,
+    case_sensitive: bool,
+    note: PathBuf,
+}
+
+/// Terms from glossary notes (frontmatter `glossary: true`) and a matcher
+/// for all of them.
+struct Glossary {
+    terms: Vec<Term>,
+    matcher: Option<Regex>,
+}
+
+impl Glossary {
+    fn load(notes: &[PathBuf]) -> Self {
+        let mut terms: Vec<Term> = Vec::new();
+        for note in notes {
+            let Ok(content) = fs::read_to_string(note) else {
+                continue;
+            };
+            let Ok((fm, split)) = frontmatter::parse_note(&content) else {
+                continue;
+            };
+            if !is_glossary(&fm) {
+                continue;
+            }
+            let title = markdown::note_title(note, &fm, split.body);
+            let aliases = frontmatter::string_list(fm.get("aliases").or_else(|| fm.get("alias")));
+            for text in std::iter::once(title).chain(aliases) {
+                let text = text.trim().to_string();
+                // Terms are matched case-sensitively unless written all
+                // lowercase.
+                let case_sensitive = text.chars().any(char::is_uppercase);
+                let duplicate = terms.iter().any(|t| t.text == text);
+                if !text.is_empty() && !duplicate {
+                    terms.push(Term {
+                        text,
+                        case_sensitive,
+                        note: note.clone(),
+                    });
+                }
+            }
+        }
+
+        Self::from_terms(terms)
+    }
+
+    fn from_terms(mut terms: Vec<Term>) -> Self {
+        // Longest first so "machine learning" wins over "machine". Word
+        // boundaries are part of each alternative, so where the longer one
+        // runs into a word, the shorter is tried instead.
+        terms.sort_by(|a, b| {
+            b.text
+                .len()
+                .cmp(&a.text.len())
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        let alternatives: Vec<String> = terms
+            .iter()
+            .map(|t| {
+                let escaped = regex::escape(&t.text);
+                let term = if t.case_sensitive {
+                    format!("({})", escaped)
+                } else {
+                    format!("((?i:{}))", escaped)
+                };
+                format!(
+                    "{}{}{}",
+                    boundary(t.text.chars().next()),
+                    term,
+                    boundary(t.text.chars().next_back())
+                )
+            })
+            .collect();
+        let matcher = (!alternatives.is_empty())
+            .then(|| Regex::new(&alternatives.join("|")).ok())
+            .flatten();
+
+        Self { terms, matcher }
+    }
+
+    /// Whole-word term uses in a note outside frontmatter, code, and links,
+    /// as `(term index, occurrence)`. A glossary note's own terms are
+    /// skipped.
+    fn find(&self, note: &Path, content: &str) -> Vec<(usize, TermOccurrence)> {
+        let Some(matcher) = &self.matcher else {
+            return Vec::new();
+        };
+        let note_path = note.to_string_lossy().to_string();
+        let lines: Vec<&str> = content.lines().collect();
+        let in_code = markdown::protected_lines(&lines);
+        let mut found = Vec::new();
+
+        for (idx, (line, protected)) in lines.iter().zip(in_code).enumerate() {
+            if protected || line.trim().is_empty() {
+                continue;
+            }
+            let scrubbed = links::blank_links(&blank_code_spans(line));
+            for caps in matcher.captures_iter(&scrubbed) {
+                let whole = caps.get(0).unwrap();
+                // Group n+1 holds term n.
+                let Some(term_idx) = (1..caps.len())
+                    .find(|&g| caps.get(g).is_some())
+                    .map(|g| g - 1)
+                else {
+                    continue;
+                };
+                if self.terms[term_idx].note == note {
+                    continue;
+                }
+                found.push((
+                    term_idx,
+                    TermOccurrence {
+                        note_path: note_path.clone(),
+                        line_number: idx + 1,
+                        column: line[..whole.start()].chars().count() + 1,
+                        length: whole.as_str().chars().count(),
+                    },
+                ));
+            }
+        }
+
+        found
+    }
+}
+
+/// Indexes every use of every glossary term across the vault, sorted by
+/// term. Terms nobody uses are still listed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn build_term_index(vault_path: &str) -> Result<Vec<GlossaryTerm>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let notes = vault::notes(root, &settings);
+    let glossary = Glossary::load(&notes);
+
+    let mut occurrences: Vec<Vec<TermOccurrence>> = vec![Vec::new(); glossary.terms.len()];
+    for note in &notes {
+        let Ok(content) = fs::read_to_string(note) else {
+            continue;
+        };
+        for (term_idx, occurrence) in glossary.find(note, &content) {
+            occurrences[term_idx].push(occurrence);
+        }
+    }
+
+    let mut index: Vec<GlossaryTerm> = glossary
+        .terms
+        .into_iter()
+        .zip(occurrences)
+        .map(|(term, occurrences)| GlossaryTerm {
+            term: term.text,
+            glossary_note: term.note.to_string_lossy().to_string(),
+            case_sensitive: term.case_sensitive,
+            occurrences,
+        })
+        .collect();
+    index.sort_by_key(|t| t.term.to_lowercase());
+    Ok(index)
+}
+
+/// Glossary terms used in one note, in document order, with definitions.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_terms_in_note(vault_path: &str, note_path: &str) -> Result<Vec<NoteTerm>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let glossary = Glossary::load(&vault::notes(root, &settings));
+    let note = Path::new(note_path);
+    let content = fs::read_to_string(note).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let mut definitions: HashMap<PathBuf, String> = HashMap::new();
+    let mut terms = Vec::new();
+    for (term_idx, occurrence) in glossary.find(note, &content) {
+        let term = &glossary.terms[term_idx];
+        let definition = definitions
+            .entry(term.note.clone())
+            .or_insert_with(|| {
+                fs::read_to_string(&term.note)
+                    .map(|c| first_paragraph(&c))
+                    .unwrap_or_default()
+            })
+            .clone();
+        terms.push(NoteTerm {
+            term: term.text.clone(),
+            line_number: occurrence.line_number,
+            column: occurrence.column,
+            length: occurrence.length,
+            glossary_note: term.note.to_string_lossy().to_string(),
+            definition,
+        });
+    }
+    Ok(terms)
+}
+
+fn is_glossary(fm: &Map<String, Value>) -> bool {
+    match fm.get("glossary") {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => s.eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+/// Keeps a term from matching inside a word: `\b` beside a term's word
+/// character, `\B` beside anything else, as in `C++`, so neither side of
+/// the match may touch a word character.
+fn boundary(edge: Option<char>) -> &'static str {
+    if edge.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+        r"\b"
+    } else {
+        r"\B"
+    }
+}
+
+/// The first paragraph of prose in a note body, skipping headings and code.
+fn first_paragraph(content: &str) -> String {
+    let body = frontmatter::split(content).body;
+    let lines: Vec<&str> = body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for (line, code) in lines.iter().zip(in_code) {
+        let trimmed = line.trim();
+        let heading = trimmed.starts_with('#') && trimmed.trim_start_matches('#').starts_with(' ');
+        let skip = code || heading || trimmed.is_empty();
+        if skip {
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        paragraph.push(trimmed);
+    }
+
+    paragraph.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary(terms: &[&str]) -> Glossary {
+        let terms = terms
+            .iter()
+            .map(|text| Term {
+                text: text.to_string(),
+                case_sensitive: text.chars().any(char::is_uppercase),
+                note: PathBuf::from("/vault/Glossary.md"),
+            })
+            .collect();
+        Glossary::from_terms(terms)
+    }
+
+    fn found(glossary: &Glossary, line: &str) -> Vec<(String, usize)> {
+        glossary
+            .find(Path::new("/vault/Note.md"), line)
+            .into_iter()
+            .map(|(idx, occurrence)| (glossary.terms[idx].text.clone(), occurrence.column))
+            .collect()
+    }
+
+    #[test]
+    fn a_longer_term_running_into_a_word_falls_back_to_a_shorter_one() {
+        let glossary = glossary(&["machine", "machine learning"]);
+        let line = "machine learnings, machine learning and machines";
+        assert_eq!(
+            found(&glossary, line),
+            [
+                ("machine".to_string(), 1),
+                ("machine learning".to_string(), 20)
+            ]
+        );
+    }
+
+    #[test]
+    fn terms_edged_with_symbols_still_match_whole_words_only() {
+        let glossary = glossary(&["C++", ".NET"]);
+        let line = "C++ and .NET, not C++x, ABC++ or my.NET";
+        assert_eq!(
+            found(&glossary, line),
+            [("C++".to_string(), 1), (".NET".to_string(), 9)]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::markdown;
+use crate::vault::goals::{self, DailyCount};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteGoalProgress {
+    pub target_words: usize,
+    /// Words in the note now, excluding frontmatter and code.
+    pub current_words: usize,
+    pub history: Vec<DailyCount>,
+}
+
+/// Sets the word goal for a note; a target of zero clears it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn set_note_goal(vault_path: &str, path: &str, target_words: usize) -> Result<(), String> {
+    let note = Path::new(path);
+    if !note.is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    goals::set(Path::new(vault_path), note, target_words)
+}
+
+/// Progress towards a note's goal, or `None` when it has none.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_note_goal_progress(
+    vault_path: &str,
+    path: &str,
+) -> Result<Option<NoteGoalProgress>, String> {
+    let Some(goal) = goals::get(Path::new(vault_path), Path::new(path))? else {
+        return Ok(None);
+    };
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(Some(NoteGoalProgress {
+        target_words: goal.target_words,
+        current_words: markdown::word_count(&content),
+        history: goal.history,
+    }))
+}
@@ -0,0 +1,862 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use tauri::State;
+
+use super::caches::CacheKeys;
+use crate::vault::cache_crypto::CacheMode;
+use crate::vault::index::{index_content, VaultIndex};
+use crate::vault::renames::RenameLog;
+use crate::vault::{self, link_index, settings::VaultSettings, NoteFilter};
+
+const SNAPSHOTS_DIR: &str = "graph_snapshots";
+/// Names the live graph in `compare_graph_snapshots`.
+const CURRENT: &str = "current";
+/// How many of the notes whose degree changed most are reported.
+const TOP_MOVERS: usize = 20;
+
+/// The resolved note graph at one point in time, in
+/// `.graphnotes/graph_snapshots/<id>.json`. Edges refer to nodes by
+/// position and count the links between the two notes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct GraphSnapshot {
+    id: String,
+    label: Option<String>,
+    /// Seconds since the Unix epoch.
+    created_at: u64,
+    /// Vault-relative note paths.
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub label: Option<String>,
+    pub created_at: u64,
+    pub nodes: usize,
+    pub edges: usize,
+    pub links: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// Links from `source` to `target`.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedNode {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeChange {
+    pub path: String,
+    /// Distinct notes linked to or from, in each graph.
+    pub before: usize,
+    pub after: usize,
+    pub change: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphComparison {
+    pub from: SnapshotInfo,
+    pub to: SnapshotInfo,
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    /// Notes that were moved or renamed in between, compared as one node.
+    pub moved_nodes: Vec<MovedNode>,
+    pub added_edges: Vec<GraphEdge>,
+    pub removed_edges: Vec<GraphEdge>,
+    /// The notes whose degree changed most, biggest change first.
+    pub degree_changes: Vec<DegreeChange>,
+}
+
+/// Where a graph in the vault's history comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphSource {
+    Snapshot,
+    /// Rebuilt from the notes as they were in a git commit.
+    Git,
+    /// The vault as it is now.
+    Current,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphPoint {
+    pub source: GraphSource,
+    /// The snapshot's id, the commit's hash, or `current`.
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendPoint {
+    #[serde(flatten)]
+    pub graph: GraphPoint,
+    /// Where the note was then; `None` when it didn't exist yet.
+    pub path: Option<String>,
+    /// Distinct notes linking to it.
+    pub in_degree: usize,
+    /// Distinct notes it links to.
+    pub out_degree: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteLinkTrend {
+    pub path: String,
+    /// Oldest first, ending with the vault as it is now.
+    pub points: Vec<TrendPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteGrowth {
+    pub path: String,
+    /// Distinct notes linking to it, then and now.
+    pub before: usize,
+    pub after: usize,
+    pub change: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrowingNotes {
+    /// The graph compared with; later than asked for when no older one
+    /// was kept.
+    pub since: GraphPoint,
+    /// Biggest increase first.
+    pub notes: Vec<NoteGrowth>,
+}
+
+/// Saves the vault's current note graph, then deletes the oldest snapshots
+/// beyond the vault's `graph_snapshots.keep_count`. Fails while the vault's
+/// caches are encrypted and locked.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn save_graph_snapshot(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    label: Option<String>,
+) -> Result<SnapshotInfo, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let mode = keys.mode(root, &settings);
+    if mode.is_locked() {
+        return Err("Vault caches are locked".to_string());
+    }
+    let mut snapshot = current_graph(root, &settings, &mode)?;
+    let dir = snapshots_dir(root);
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut id = stamp.clone();
+    let mut n = 2;
+    while dir.join(format!("{}.json", id)).exists() {
+        id = format!("{}-{}", stamp, n);
+        n += 1;
+    }
+    snapshot.id = id;
+    snapshot.label = label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty());
+    mode.write_json(&dir.join(format!("{}.json", snapshot.id)), &snapshot)?;
+    prune(&dir, settings.graph_snapshots.keep_count);
+    Ok(snapshot.info())
+}
+
+/// The vault's snapshots, oldest first. Unreadable ones, and encrypted ones
+/// while the vault's caches are locked, are left out.
+#[tauri::command]
+pub fn list_graph_snapshots(keys: State<'_, CacheKeys>, vault_path: &str) -> Vec<SnapshotInfo> {
+    let root = Path::new(vault_path);
+    let mode = keys.mode(root, &VaultSettings::load(root).unwrap_or_default());
+    let dir = snapshots_dir(root);
+    snapshot_ids(&dir)
+        .iter()
+        .filter_map(|id| load_snapshot(&dir, id, &mode).ok())
+        .map(|snapshot| snapshot.info())
+        .collect()
+}
+
+/// What changed in the graph between two snapshots, either of which may be
+/// `current` for the vault as it is now. Notes renamed or moved in between,
+/// going by the rename history or else by a file name that disappeared
+/// from one folder and appeared in another, count as moved rather than as
+/// removed and added.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn compare_graph_snapshots(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    from: &str,
+    to: &str,
+) -> Result<GraphComparison, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let mode = keys.mode(root, &settings);
+    let older = resolve_snapshot(root, &settings, &mode, from)?;
+    let newer = resolve_snapshot(root, &settings, &mode, to)?;
+
+    let after: HashSet<&str> = newer.nodes.iter().map(String::as_str).collect();
+    let moves = moves_between(&older, &newer, &RenameLog::load(root)?);
+    let renamed = |path: &str| moves.get(path).cloned().unwrap_or_else(|| path.to_string());
+
+    let old_nodes: Vec<String> = older.nodes.iter().map(|n| renamed(n)).collect();
+    let old_set: HashSet<&str> = old_nodes.iter().map(String::as_str).collect();
+    let added_nodes = newer
+        .nodes
+        .iter()
+        .filter(|n| !old_set.contains(n.as_str()))
+        .cloned()
+        .collect();
+    let removed_nodes = old_nodes
+        .iter()
+        .filter(|n| !after.contains(n.as_str()))
+        .cloned()
+        .collect();
+
+    let old_edges = edges(&old_nodes, &older.edges);
+    let new_edges = edges(&newer.nodes, &newer.edges);
+    let edge_diff =
+        |a: &BTreeMap<(&str, &str), usize>, b: &BTreeMap<(&str, &str), usize>| -> Vec<GraphEdge> {
+            a.iter()
+                .filter(|(key, _)| !b.contains_key(*key))
+                .map(|((source, target), count)| GraphEdge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    count: *count,
+                })
+                .collect()
+        };
+
+    let old_degrees = degrees(&old_edges);
+    let new_degrees = degrees(&new_edges);
+    let mut degree_changes: Vec<DegreeChange> = old_degrees
+        .keys()
+        .chain(new_degrees.keys())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|path| {
+            let before = old_degrees.get(path).copied().unwrap_or(0);
+            let after = new_degrees.get(path).copied().unwrap_or(0);
+            DegreeChange {
+                path: path.to_string(),
+                before,
+                after,
+                change: after as i64 - before as i64,
+            }
+        })
+        .filter(|change| change.change != 0)
+        .collect();
+    degree_changes.sort_by(|a, b| {
+        b.change
+            .abs()
+            .cmp(&a.change.abs())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    degree_changes.truncate(TOP_MOVERS);
+
+    let mut moved_nodes: Vec<MovedNode> = moves
+        .iter()
+        .map(|(from, to)| MovedNode {
+            from: from.clone(),
+            to: to.clone(),
+        })
+        .collect();
+    moved_nodes.sort_by(|a, b| a.from.cmp(&b.from));
+
+    Ok(GraphComparison {
+        from: older.info(),
+        to: newer.info(),
+        added_nodes,
+        removed_nodes,
+        moved_nodes,
+        added_edges: edge_diff(&new_edges, &old_edges),
+        removed_edges: edge_diff(&old_edges, &new_edges),
+        degree_changes,
+    })
+}
+
+/// How many notes linked to and from the note at `note_path`, absolute or
+/// vault-relative, at up to `points` times: in the graph snapshots, spread
+/// evenly when there are more, and now. When there are fewer snapshots and
+/// the vault is in a git repository, commits from before the first
+/// snapshot fill in. A note moved or renamed is followed to where it was,
+/// as `compare_graph_snapshots` does, so its trend doesn't start over.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn note_link_trend(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    note_path: &str,
+    points: usize,
+) -> Result<NoteLinkTrend, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let mode = keys.mode(root, &settings);
+    let current = current_graph(root, &settings, &mode)?;
+    let path = vault::relative_path(root, &root.join(note_path));
+    let Some(position) = current.nodes.iter().position(|node| *node == path) else {
+        return Err(format!("Note does not exist: {}", note_path));
+    };
+
+    let past_points = points.max(1) - 1;
+    let dir = snapshots_dir(root);
+    let mut history: Vec<(GraphSource, GraphSnapshot)> = snapshot_ids(&dir)
+        .iter()
+        .filter_map(|id| load_snapshot(&dir, id, &mode).ok())
+        .map(|snapshot| (GraphSource::Snapshot, snapshot))
+        .collect();
+    if history.len() < past_points {
+        let first = history
+            .first()
+            .map_or(u64::MAX, |(_, snapshot)| snapshot.created_at);
+        let commits: Vec<(String, u64)> = git_commits(root)
+            .into_iter()
+            .filter(|(_, at)| *at < first)
+            .collect();
+        let filter = NoteFilter::new(root, &settings);
+        let rebuilt = evenly(commits, past_points - history.len())
+            .into_iter()
+            .filter_map(|(hash, at)| git_graph(root, &filter, &hash, at).ok())
+            .map(|graph| (GraphSource::Git, graph));
+        history.splice(0..0, rebuilt);
+    }
+
+    let log = RenameLog::load(root)?;
+    let mut series: Vec<TrendPoint> = evenly(history, past_points)
+        .into_iter()
+        .map(|(source, graph)| {
+            let moves = moves_between(&graph, &current, &log);
+            let then = graph
+                .nodes
+                .iter()
+                .position(|node| moves.get(node).unwrap_or(node) == &path);
+            trend_point(source, &graph, then)
+        })
+        .collect();
+    series.push(trend_point(GraphSource::Current, &current, Some(position)));
+    Ok(NoteLinkTrend {
+        path,
+        points: series,
+    })
+}
+
+/// The notes with the most new backlinks since `since`, a snapshot id or a
+/// `YYYY-MM-DD` date. A date is compared with the last snapshot taken
+/// before it or, when there is none and the vault is in a git repository,
+/// the last commit before it, and otherwise with the oldest graph there is.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn top_growing_notes(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    since: &str,
+) -> Result<GrowingNotes, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let mode = keys.mode(root, &settings);
+    let current = current_graph(root, &settings, &mode)?;
+    let dir = snapshots_dir(root);
+    let (source, older) = if is_snapshot_id(since) {
+        (GraphSource::Snapshot, load_snapshot(&dir, since, &mode)?)
+    } else {
+        let date = chrono::NaiveDate::parse_from_str(since.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Not a snapshot id or date: {}", since))?;
+        let at = date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|start| start.and_local_timezone(chrono::Local).earliest())
+            .and_then(|start| u64::try_from(start.timestamp()).ok())
+            .unwrap_or(0);
+        let mut snapshots: Vec<GraphSnapshot> = snapshot_ids(&dir)
+            .iter()
+            .filter_map(|id| load_snapshot(&dir, id, &mode).ok())
+            .collect();
+        match snapshots
+            .iter()
+            .rposition(|snapshot| snapshot.created_at < at)
+        {
+            Some(idx) => (GraphSource::Snapshot, snapshots.swap_remove(idx)),
+            None => {
+                let commits = git_commits(root);
+                let commit = commits
+                    .iter()
+                    .rev()
+                    .find(|(_, committed)| *committed < at)
+                    .or_else(|| {
+                        let first = snapshots
+                            .first()
+                            .map_or(u64::MAX, |snapshot| snapshot.created_at);
+                        commits.first().filter(|(_, committed)| *committed < first)
+                    });
+                match commit {
+                    Some((hash, committed)) => (
+                        GraphSource::Git,
+                        git_graph(root, &NoteFilter::new(root, &settings), hash, *committed)?,
+                    ),
+                    None if !snapshots.is_empty() => {
+                        (GraphSource::Snapshot, snapshots.swap_remove(0))
+                    }
+                    None => {
+                        return Err("No graph snapshots or git history to compare with".to_string())
+                    }
+                }
+            }
+        }
+    };
+
+    let moves = moves_between(&older, &current, &RenameLog::load(root)?);
+    let mut before: HashMap<&str, usize> = HashMap::new();
+    for &(_, to, _) in &older.edges {
+        if let Some(node) = older.nodes.get(to) {
+            *before
+                .entry(moves.get(node).unwrap_or(node).as_str())
+                .or_default() += 1;
+        }
+    }
+    let after = in_degrees(&current);
+    let mut notes: Vec<NoteGrowth> = current
+        .nodes
+        .iter()
+        .zip(after)
+        .map(|(path, after)| {
+            let before = before.get(path.as_str()).copied().unwrap_or(0);
+            NoteGrowth {
+                path: path.clone(),
+                before,
+                after,
+                change: after as i64 - before as i64,
+            }
+        })
+        .filter(|growth| growth.change > 0)
+        .collect();
+    notes.sort_by(|a, b| b.change.cmp(&a.change).then_with(|| a.path.cmp(&b.path)));
+    notes.truncate(TOP_MOVERS);
+    Ok(GrowingNotes {
+        since: GraphPoint {
+            source,
+            id: older.id.clone(),
+            at: older.created_at,
+        },
+        notes,
+    })
+}
+
+impl GraphSnapshot {
+    fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            created_at: self.created_at,
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            links: self.edges.iter().map(|(_, _, count)| count).sum(),
+        }
+    }
+}
+
+fn current_graph(
+    root: &Path,
+    settings: &VaultSettings,
+    mode: &CacheMode,
+) -> Result<GraphSnapshot, String> {
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", root.display()));
+    }
+    let index = link_index::refreshed(root, &vault::notes(root, settings), mode)?;
+    Ok(GraphSnapshot {
+        id: CURRENT.to_string(),
+        label: None,
+        created_at: vault::now_secs(),
+        nodes: index
+            .notes
+            .iter()
+            .map(|note| vault::relative_path(root, &note.path))
+            .collect(),
+        edges: graph_edges(&index),
+    })
+}
+
+fn graph_edges(index: &VaultIndex) -> Vec<(usize, usize, usize)> {
+    let mut edges = Vec::new();
+    for (from, note) in index.notes.iter().enumerate() {
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for to in note
+            .links
+            .iter()
+            .filter_map(|link| index.resolve(from, link))
+        {
+            if to != from {
+                *counts.entry(to).or_default() += 1;
+            }
+        }
+        edges.extend(counts.into_iter().map(|(to, count)| (from, to, count)));
+    }
+    edges
+}
+
+fn resolve_snapshot(
+    root: &Path,
+    settings: &VaultSettings,
+    mode: &CacheMode,
+    id: &str,
+) -> Result<GraphSnapshot, String> {
+    if id == CURRENT {
+        current_graph(root, settings, mode)
+    } else {
+        load_snapshot(&snapshots_dir(root), id, mode)
+    }
+}
+
+fn load_snapshot(dir: &Path, id: &str, mode: &CacheMode) -> Result<GraphSnapshot, String> {
+    let path = dir.join(format!("{}.json", id));
+    if !is_snapshot_id(id) || !path.is_file() {
+        return Err(format!("No graph snapshot {}", id));
+    }
+    if !mode.can_read(&path) {
+        return Err(format!(
+            "Graph snapshot {} is encrypted and the vault's caches are locked",
+            id
+        ));
+    }
+    let mut snapshot: GraphSnapshot = mode.read_json(&path)?;
+    snapshot.id = id.to_string();
+    Ok(snapshot)
+}
+
+fn snapshots_dir(root: &Path) -> PathBuf {
+    vault::state_dir(root).join(SNAPSHOTS_DIR)
+}
+
+pub(super) fn snapshot_files(root: &Path) -> Vec<PathBuf> {
+    let dir = snapshots_dir(root);
+    snapshot_ids(&dir)
+        .iter()
+        .map(|id| dir.join(format!("{}.json", id)))
+        .collect()
+}
+
+/// Snapshot ids in the order they were taken.
+fn snapshot_ids(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .flatten()
+        .filter_map(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .strip_suffix(".json")
+                .map(str::to_string)
+        })
+        .filter(|id| is_snapshot_id(id))
+        .collect();
+    ids.sort_by(|a, b| sequence(a).cmp(&sequence(b)));
+    ids
+}
+
+/// A timestamp, with a `-2`, `-3`... suffix for snapshots taken within the
+/// same second.
+fn is_snapshot_id(id: &str) -> bool {
+    let Some((stamp, n)) = id.get(..15).zip(id.get(15..)) else {
+        return false;
+    };
+    stamp.char_indices().all(|(idx, c)| {
+        if idx == 8 {
+            c == '-'
+        } else {
+            c.is_ascii_digit()
+        }
+    }) && (n.is_empty()
+        || n.strip_prefix('-')
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())))
+}
+
+fn sequence(id: &str) -> (&str, u32) {
+    let (stamp, n) = id.split_at(15);
+    (stamp, n.trim_start_matches('-').parse().unwrap_or(1))
+}
+
+fn prune(dir: &Path, keep_count: usize) {
+    let ids = snapshot_ids(dir);
+    if keep_count == 0 || ids.len() <= keep_count {
+        return;
+    }
+    for id in &ids[..ids.len() - keep_count] {
+        let _ = fs::remove_file(dir.join(format!("{}.json", id)));
+    }
+}
+
+fn trend_point(source: GraphSource, graph: &GraphSnapshot, position: Option<usize>) -> TrendPoint {
+    let count = |pick: fn(&(usize, usize, usize)) -> usize| {
+        position.map_or(0, |idx| {
+            graph.edges.iter().filter(|edge| pick(edge) == idx).count()
+        })
+    };
+    TrendPoint {
+        graph: GraphPoint {
+            source,
+            id: graph.id.clone(),
+            at: graph.created_at,
+        },
+        path: position.and_then(|idx| graph.nodes.get(idx).cloned()),
+        in_degree: count(|&(_, to, _)| to),
+        out_degree: count(|&(from, _, _)| from),
+    }
+}
+
+/// Distinct notes linking to each of `graph`'s notes, by position.
+fn in_degrees(graph: &GraphSnapshot) -> Vec<usize> {
+    let mut counts = vec![0; graph.nodes.len()];
+    for &(_, to, _) in &graph.edges {
+        if let Some(count) = counts.get_mut(to) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// `count` of `items`, spread evenly and always keeping the last.
+fn evenly<T>(items: Vec<T>, count: usize) -> Vec<T> {
+    if items.len() <= count {
+        return items;
+    }
+    let last = items.len() - 1;
+    let picked: HashSet<usize> = match count {
+        0 => HashSet::new(),
+        1 => HashSet::from([last]),
+        _ => (0..count).map(|i| i * last / (count - 1)).collect(),
+    };
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| picked.contains(idx))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Commits that changed something in the vault, oldest first, with their
+/// commit times. Empty when the vault isn't in a git repository or git
+/// isn't installed.
+fn git_commits(root: &Path) -> Vec<(String, u64)> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["log", "--format=%H %ct", "--", "."])
+        .output();
+    let Some(log) = output
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+    else {
+        return Vec::new();
+    };
+    let mut commits: Vec<(String, u64)> = log
+        .lines()
+        .filter_map(|line| {
+            let (hash, at) = line.split_once(' ')?;
+            Some((hash.to_string(), at.trim().parse().ok()?))
+        })
+        .collect();
+    commits.reverse();
+    commits
+}
+
+/// The note graph as it was at `commit`, from the notes the vault's
+/// settings don't leave out.
+fn git_graph(
+    root: &Path,
+    filter: &NoteFilter,
+    commit: &str,
+    at: u64,
+) -> Result<GraphSnapshot, String> {
+    let listed = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-tree", "-r", "-z", "--name-only", commit])
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !listed.status.success() {
+        return Err(format!("Failed to read commit {}", commit));
+    }
+    let paths: Vec<String> = String::from_utf8_lossy(&listed.stdout)
+        .split('\0')
+        .filter(|path| {
+            !path.is_empty() && !path.contains('\n') && vault::is_markdown(Path::new(path))
+        })
+        .filter(|path| {
+            !path.split('/').any(vault::is_hidden) && !filter.is_ignored(&root.join(path))
+        })
+        .map(str::to_string)
+        .collect();
+    let contents = git_blobs(root, commit, &paths)?;
+    let notes = paths
+        .iter()
+        .zip(contents)
+        .filter_map(|(path, content)| Some(index_content(&root.join(path), &content?, Some(at))))
+        .collect();
+    let index = VaultIndex::from_notes(root, notes);
+    Ok(GraphSnapshot {
+        id: commit.to_string(),
+        label: None,
+        created_at: at,
+        nodes: index
+            .notes
+            .iter()
+            .map(|note| vault::relative_path(root, &note.path))
+            .collect(),
+        edges: graph_edges(&index),
+    })
+}
+
+/// The text of each of `paths`, relative to `root`, at `commit`, read with
+/// one `git cat-file`. `None` for files missing there or not UTF-8.
+fn git_blobs(root: &Path, commit: &str, paths: &[String]) -> Result<Vec<Option<String>>, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err("Failed to run git".to_string());
+    };
+    // Written on its own thread so git can't stall on a full stdout while
+    // requests are still being sent.
+    let requests: String = paths
+        .iter()
+        .map(|path| format!("{}:./{}\n", commit, path))
+        .collect();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let mut reader = BufReader::new(stdout);
+    let mut blobs = Vec::with_capacity(paths.len());
+    let mut header = String::new();
+    for _ in paths {
+        header.clear();
+        if reader
+            .read_line(&mut header)
+            .map_err(|e| format!("Failed to read from git: {}", e))?
+            == 0
+        {
+            break;
+        }
+        // `<hash> blob <size>`, or `<name> missing`.
+        let size = match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [_, "blob", size] => size.parse::<usize>().ok(),
+            _ => None,
+        };
+        let Some(size) = size else {
+            blobs.push(None);
+            continue;
+        };
+        let mut content = vec![0; size + 1];
+        reader
+            .read_exact(&mut content)
+            .map_err(|e| format!("Failed to read from git: {}", e))?;
+        content.pop();
+        blobs.push(String::from_utf8(content).ok());
+    }
+    let _ = writer.join();
+    let _ = child.wait();
+    blobs.resize(paths.len(), None);
+    Ok(blobs)
+}
+
+/// Notes of `older` that are at another path in `newer`, going by the
+/// rename history or else by a file name that disappeared from one folder
+/// and appeared in another.
+fn moves_between(
+    older: &GraphSnapshot,
+    newer: &GraphSnapshot,
+    log: &RenameLog,
+) -> HashMap<String, String> {
+    let after: HashSet<&str> = newer.nodes.iter().map(String::as_str).collect();
+    let mut moves: HashMap<String, String> = older
+        .nodes
+        .iter()
+        .filter(|n| !after.contains(n.as_str()))
+        .map(|n| (n.clone(), log.follow(n, older.created_at, newer.created_at)))
+        .filter(|(from, to)| from != to && after.contains(to.as_str()))
+        .collect();
+    let before: HashSet<&str> = older
+        .nodes
+        .iter()
+        .map(String::as_str)
+        .chain(moves.values().map(String::as_str))
+        .collect();
+    let guessed = moved_nodes(
+        older
+            .nodes
+            .iter()
+            .filter(|n| !after.contains(n.as_str()) && !moves.contains_key(*n)),
+        newer.nodes.iter().filter(|n| !before.contains(n.as_str())),
+    );
+    moves.extend(guessed);
+    moves
+}
+
+/// Pairs notes that disappeared with notes that appeared under the same file
+/// name, when the name picks out exactly one of each.
+fn moved_nodes<'a>(
+    removed: impl Iterator<Item = &'a String>,
+    added: impl Iterator<Item = &'a String>,
+) -> HashMap<String, String> {
+    let by_name = |paths: Vec<&'a String>| {
+        let mut names: HashMap<String, Vec<&'a String>> = HashMap::new();
+        for path in paths {
+            names.entry(file_name(path)).or_default().push(path);
+        }
+        names
+    };
+    let removed = by_name(removed.collect());
+    let added = by_name(added.collect());
+    removed
+        .into_iter()
+        .filter_map(
+            |(name, from)| match (from.as_slice(), added.get(&name).map(Vec::as_slice)) {
+                ([from], Some([to])) => Some(((*from).clone(), (*to).clone())),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_lowercase()
+}
+
+fn edges<'a>(
+    nodes: &'a [String],
+    edges: &[(usize, usize, usize)],
+) -> BTreeMap<(&'a str, &'a str), usize> {
+    edges
+        .iter()
+        .filter_map(|&(from, to, count)| {
+            Some(((nodes.get(from)?.as_str(), nodes.get(to)?.as_str()), count))
+        })
+        .collect()
+}
+
+fn degrees<'a>(edges: &BTreeMap<(&'a str, &'a str), usize>) -> HashMap<&'a str, usize> {
+    let mut neighbours: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for &(source, target) in edges.keys() {
+        neighbours.entry(source).or_default().insert(target);
+        neighbours.entry(target).or_default().insert(source);
+    }
+    neighbours
+        .into_iter()
+        .map(|(path, set)| (path, set.len()))
+        .collect()
+}
@@ -0,0 +1,539 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use super::attachments::resolve_local_target;
+use crate::markdown::{blank_code_spans, frontmatter, headings, links, protected_lines};
+use crate::vault::{self, index::VaultIndex, portable, settings::VaultSettings};
+
+const NOTE_SIZE_CAP: u64 = 5 * 1024 * 1024;
+const ATTACHMENT_SIZE_CAP: u64 = 100 * 1024 * 1024;
+
+// Dropbox "(Sam's conflicted copy 2024-01-02)", Syncthing
+// ".sync-conflict-20240102-...", and the "(conflict)" style used by others.
+static SYNC_CONFLICT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)conflicted copy|\.sync-conflict-|[(\[]conflict(?:ed)?[)\]]").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    Unreadable,
+    InvalidUtf8,
+    OversizedFile,
+    BrokenLink,
+    /// A link from a public note to one in a private folder.
+    PrivateLink,
+    DuplicateTitle,
+    InvalidFrontmatter,
+    SyncConflict,
+    EmptyFile,
+    PortableFilename,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: CheckKind,
+    pub severity: Severity,
+    pub path: String,
+    pub line_number: Option<usize>,
+    pub message: String,
+    /// The command that can fix this, when there is one.
+    pub fix_command: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultCheckReport {
+    pub files_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub findings: Vec<Finding>,
+}
+
+fn finding(kind: CheckKind, severity: Severity, path: &Path, message: String) -> Finding {
+    Finding {
+        kind,
+        severity,
+        path: path.to_string_lossy().to_string(),
+        line_number: None,
+        message,
+        fix_command: None,
+    }
+}
+
+/// Runs every integrity check over the vault's files and returns the
+/// findings, most severe first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn check_vault(vault_path: &str) -> Result<VaultCheckReport, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let files = vault::files_where(root, |_| true);
+    let mut findings = Vec::new();
+    let mut readable_notes = Vec::new();
+
+    for file in &files {
+        let is_note = vault::is_markdown(file);
+        findings.extend(check_name(root, file));
+        let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        let cap = if is_note {
+            NOTE_SIZE_CAP
+        } else {
+            ATTACHMENT_SIZE_CAP
+        };
+        if size > cap {
+            findings.push(finding(
+                CheckKind::OversizedFile,
+                Severity::Warning,
+                file,
+                format!(
+                    "File is {} MB, over the {} MB limit",
+                    size / (1024 * 1024),
+                    cap / (1024 * 1024)
+                ),
+            ));
+        }
+        if !is_note {
+            continue;
+        }
+
+        let bytes = match fs::read(file) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                findings.push(finding(
+                    CheckKind::Unreadable,
+                    Severity::Error,
+                    file,
+                    format!("Can't read file: {}", e),
+                ));
+                continue;
+            }
+        };
+        let Ok(content) = String::from_utf8(bytes) else {
+            findings.push(finding(
+                CheckKind::InvalidUtf8,
+                Severity::Error,
+                file,
+                "File is not valid UTF-8".to_string(),
+            ));
+            continue;
+        };
+        if content.trim().is_empty() {
+            findings.push(finding(
+                CheckKind::EmptyFile,
+                Severity::Info,
+                file,
+                "Note is empty".to_string(),
+            ));
+        }
+        if let Err(e) = frontmatter::parse_note(&content) {
+            findings.push(finding(
+                CheckKind::InvalidFrontmatter,
+                Severity::Warning,
+                file,
+                e,
+            ));
+        }
+        readable_notes.push((file.clone(), content));
+    }
+
+    let settings = VaultSettings::load(root)?;
+    let note_files: Vec<_> = readable_notes
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect();
+    let index = VaultIndex::build(root, &note_files);
+    findings.extend(duplicate_titles(&index));
+    let attachment_names: HashSet<String> = files
+        .iter()
+        .filter(|f| !vault::is_markdown(f))
+        .filter_map(|f| f.file_name().map(|n| n.to_string_lossy().to_lowercase()))
+        .collect();
+    let filter = vault::NoteFilter::new(root, &settings);
+    let contents: HashMap<&Path, &str> = readable_notes
+        .iter()
+        .map(|(path, content)| (path.as_path(), content.as_str()))
+        .collect();
+    for (path, _) in &readable_notes {
+        if filter.is_ignored(path) {
+            continue;
+        }
+        if let Some(from) = index.position(path) {
+            findings.extend(broken_links(
+                root,
+                &index,
+                &filter,
+                from,
+                &contents,
+                &attachment_names,
+            ));
+        }
+    }
+
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(VaultCheckReport {
+        files_checked: files.len(),
+        errors: findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count(),
+        warnings: findings
+            .iter()
+            .filter(|f| f.severity == Severity::Warning)
+            .count(),
+        findings,
+    })
+}
+
+/// Sync conflict copies, and names other platforms can't store: Windows'
+/// reserved characters and device names, trailing dots or spaces, and
+/// names that differ only by case from a sibling.
+fn check_name(root: &Path, file: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if SYNC_CONFLICT.is_match(&name) {
+        findings.push(finding(
+            CheckKind::SyncConflict,
+            Severity::Warning,
+            file,
+            "Looks like a sync conflict copy".to_string(),
+        ));
+    }
+
+    let rel = vault::relative_path(root, file);
+    let problem = rel
+        .split('/')
+        .find_map(|component| portable::name_problems(component).into_iter().next());
+    if let Some((_, message)) = problem {
+        findings.push(finding(
+            CheckKind::PortableFilename,
+            Severity::Warning,
+            file,
+            message,
+        ));
+    }
+
+    if let Some(parent) = file.parent() {
+        let lowered = name.to_lowercase();
+        let clash = fs::read_dir(parent).ok().and_then(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .find(|other| *other != name && other.to_lowercase() == lowered)
+        });
+        if let Some(other) = clash {
+            findings.push(finding(
+                CheckKind::PortableFilename,
+                Severity::Warning,
+                file,
+                format!(
+                    "Differs only by case from \"{}\", which clashes on macOS and Windows",
+                    other
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+fn duplicate_titles(index: &VaultIndex) -> Vec<Finding> {
+    let mut by_title: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, note) in index.notes.iter().enumerate() {
+        by_title
+            .entry(note.title.to_lowercase())
+            .or_default()
+            .push(idx);
+    }
+    let mut findings = Vec::new();
+    for group in by_title.values().filter(|group| group.len() > 1) {
+        for &idx in group {
+            let note = &index.notes[idx];
+            findings.push(finding(
+                CheckKind::DuplicateTitle,
+                Severity::Info,
+                &note.path,
+                format!("{} notes are titled \"{}\"", group.len(), note.title),
+            ));
+        }
+    }
+    findings
+}
+
+/// Wikilinks and local markdown links that point at nothing, or into a
+/// private folder, and markdown links whose `#anchor` names no heading or
+/// block in the target note. Missing attachments can be repaired by
+/// `repair_image_links` when the file moved.
+fn broken_links(
+    root: &Path,
+    index: &VaultIndex,
+    filter: &vault::NoteFilter,
+    from: usize,
+    contents: &HashMap<&Path, &str>,
+    attachment_names: &HashSet<String>,
+) -> Vec<Finding> {
+    let note = &index.notes[from].path;
+    let content = contents.get(note.as_path()).copied().unwrap_or_default();
+    let lines: Vec<&str> = content.lines().collect();
+    let protected = protected_lines(&lines);
+    let own_anchors = OnceCell::new();
+    let mut findings = Vec::new();
+
+    for (idx, line) in lines.iter().enumerate() {
+        if protected[idx] || !(line.contains("[[") || line.contains("](")) {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        let mut broken: Vec<(String, Option<&str>)> = Vec::new();
+        let mut private: Vec<String> = Vec::new();
+        let mut missing_anchors: Vec<String> = Vec::new();
+        for link in links::wikilinks(&scrubbed) {
+            if link.target.trim().is_empty() {
+                continue;
+            }
+            if let Some(to) = index.resolve_wikilink(from, &link.target) {
+                if filter.is_private(&index.notes[to].path) {
+                    private.push(link.target);
+                }
+                continue;
+            }
+            let name = link
+                .target
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            let is_attachment =
+                Path::new(&name).extension().is_some() && !vault::is_markdown(Path::new(&name));
+            if !(is_attachment && attachment_names.contains(&name)) {
+                broken.push((link.target, None));
+            }
+        }
+        for link in links::markdown_links(&scrubbed) {
+            if let Some(fragment) = link.target.strip_prefix('#') {
+                if !headings::has_anchor(
+                    own_anchors.get_or_init(|| headings::anchors(content)),
+                    fragment,
+                ) {
+                    missing_anchors.push(link.target);
+                }
+                continue;
+            }
+            if links::is_external(&link.target) || link.target.is_empty() {
+                continue;
+            }
+            let (target, fragment) = link.target.split_once('#').unwrap_or((&link.target, ""));
+            let is_note = vault::is_markdown(Path::new(&links::decode_target(target)));
+            let resolved = if is_note {
+                index
+                    .resolve_markdown_link(from, target)
+                    .map(|to| index.notes[to].path.clone())
+            } else {
+                Some(resolve_local_target(root, note, target)).filter(|path| path.exists())
+            };
+            match resolved {
+                Some(path) if filter.is_private(&path) => private.push(link.target),
+                Some(path) => {
+                    let target_content = contents.get(path.as_path()).copied();
+                    if let Some(target_content) =
+                        target_content.filter(|_| is_note && !fragment.is_empty())
+                    {
+                        if !headings::has_anchor(&headings::anchors(target_content), fragment) {
+                            missing_anchors.push(link.target.clone());
+                        }
+                    }
+                }
+                None if is_note => broken.push((link.target, None)),
+                None => broken.push((link.target, Some("repair_image_links"))),
+            }
+        }
+
+        for (target, fix) in broken {
+            findings.push(Finding {
+                kind: CheckKind::BrokenLink,
+                severity: Severity::Warning,
+                path: note.to_string_lossy().to_string(),
+                line_number: Some(idx + 1),
+                message: format!("Link target not found: {}", target),
+                fix_command: fix.map(str::to_string),
+            });
+        }
+        for target in missing_anchors {
+            findings.push(Finding {
+                kind: CheckKind::BrokenLink,
+                severity: Severity::Warning,
+                path: note.to_string_lossy().to_string(),
+                line_number: Some(idx + 1),
+                message: format!("Heading or block not found: {}", target),
+                fix_command: None,
+            });
+        }
+        for target in private {
+            findings.push(Finding {
+                kind: CheckKind::PrivateLink,
+                severity: Severity::Info,
+                path: note.to_string_lossy().to_string(),
+                line_number: Some(idx + 1),
+                message: format!("Link target is in a private folder: {}", target),
+                fix_command: None,
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    fn link_findings(report: &VaultCheckReport, kind: CheckKind) -> Vec<(String, String)> {
+        report
+            .findings
+            .iter()
+            .filter(|f| f.kind == kind)
+            .map(|f| {
+                (
+                    Path::new(&f.path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    f.message.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn links_into_private_folders_are_private_not_missing() {
+        let root = TempVault::new(
+            "health-private-links",
+            &[
+                (
+                    ".graphnotes/settings.json",
+                    r#"{"private_folders": ["private"]}"#,
+                ),
+                (
+                    "Public.md",
+                    "See [[Secret]], [the diary](private/Diary.md) and [[Nowhere]].\n",
+                ),
+                ("private/Secret.md", "# Secret\n"),
+                ("private/Diary.md", "# Diary\n"),
+            ],
+        );
+        let report = check_vault(&root.to_string_lossy()).unwrap();
+
+        assert_eq!(
+            link_findings(&report, CheckKind::PrivateLink),
+            vec![
+                (
+                    "Public.md".to_string(),
+                    "Link target is in a private folder: Secret".to_string()
+                ),
+                (
+                    "Public.md".to_string(),
+                    "Link target is in a private folder: private/Diary.md".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            link_findings(&report, CheckKind::BrokenLink),
+            vec![(
+                "Public.md".to_string(),
+                "Link target not found: Nowhere".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn links_from_private_notes_are_not_reported() {
+        let root = TempVault::new(
+            "health-private-sources",
+            &[
+                (
+                    ".graphnotes/settings.json",
+                    r#"{"private_folders": ["private"]}"#,
+                ),
+                ("Public.md", "# Public\n"),
+                (
+                    "private/Secret.md",
+                    "Points at [[Nowhere]] and [[Public]].\n",
+                ),
+            ],
+        );
+        let report = check_vault(&root.to_string_lossy()).unwrap();
+
+        assert!(link_findings(&report, CheckKind::BrokenLink).is_empty());
+        assert!(link_findings(&report, CheckKind::PrivateLink).is_empty());
+    }
+
+    #[test]
+    fn markdown_link_anchors_must_match_a_heading_or_block() {
+        let root = TempVault::new(
+            "health-anchors",
+            &[
+                (
+                    "Source.md",
+                    "[ok](Target.md#second-part) [repeat](Target.md#intro-1) \
+                     [block](Target.md#^key)\n\
+                     [bad](Target.md#nowhere) [self](#source) [self bad](#missing)\n\n# Source\n",
+                ),
+                (
+                    "Target.md",
+                    "# Intro\n\n## Second part\n\nA fact. ^key\n\n# Intro\n",
+                ),
+            ],
+        );
+        let report = check_vault(&root.to_string_lossy()).unwrap();
+
+        assert_eq!(
+            link_findings(&report, CheckKind::BrokenLink),
+            vec![
+                (
+                    "Source.md".to_string(),
+                    "Heading or block not found: Target.md#nowhere".to_string()
+                ),
+                (
+                    "Source.md".to_string(),
+                    "Heading or block not found: #missing".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn without_private_folders_such_links_resolve_normally() {
+        let root = TempVault::new(
+            "health-no-private",
+            &[
+                ("Public.md", "See [[Secret]].\n"),
+                ("private/Secret.md", "# Secret\n"),
+            ],
+        );
+        let report = check_vault(&root.to_string_lossy()).unwrap();
+
+        assert!(link_findings(&report, CheckKind::PrivateLink).is_empty());
+        assert!(link_findings(&report, CheckKind::BrokenLink).is_empty());
+    }
+}
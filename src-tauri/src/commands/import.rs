@@ -0,0 +1,697 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use super::files::{write_atomic, write_note};
+use crate::markdown::{frontmatter, heading};
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, link_format::LinkWriter, settings::VaultSettings};
+
+const ATTACHMENTS_DIR: &str = "attachments";
+
+// `dayone-moment://ID` for photos, `dayone-moment:/video/ID` and so on for
+// other media, along with the `![alt](...)` around it when there is one.
+static MOMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?:!\[([^\]]*)\]\()?",
+        r"dayone-moment:/(?:/|(?:video|audio|pdfAttachment)/)([A-Za-z0-9]+)(\))?"
+    ))
+    .unwrap()
+});
+
+// `<!-- highlight:ID -->`, left above each imported highlight so a later
+// import can tell it is already there.
+static HIGHLIGHT_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!--\s*highlight:(\S+?)\s*-->").unwrap());
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameDayEntries {
+    /// One note per day, each entry under a time heading.
+    #[default]
+    Merge,
+    /// One note per entry: `2024-06-12`, `2024-06-12 2`, ...
+    Suffix,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayOneImportOptions {
+    pub same_day: SameDayEntries,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub notes_created: Vec<String>,
+    pub entries_imported: usize,
+    pub attachments_copied: usize,
+    /// Problems that didn't stop the import, such as missing media files.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct DayOneExport {
+    #[serde(default)]
+    entries: Vec<DayOneEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DayOneEntry {
+    uuid: Option<String>,
+    creation_date: String,
+    time_zone: Option<String>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    starred: bool,
+    location: Option<DayOneLocation>,
+    weather: Option<DayOneWeather>,
+    #[serde(default)]
+    photos: Vec<DayOneMedia>,
+    #[serde(default)]
+    videos: Vec<DayOneMedia>,
+    #[serde(default)]
+    audios: Vec<DayOneMedia>,
+    #[serde(default)]
+    pdf_attachments: Vec<DayOneMedia>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DayOneLocation {
+    place_name: Option<String>,
+    locality_name: Option<String>,
+    administrative_area: Option<String>,
+    country: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DayOneWeather {
+    conditions_description: Option<String>,
+    temperature_celsius: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct DayOneMedia {
+    identifier: String,
+    md5: Option<String>,
+    #[serde(rename = "type")]
+    file_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightImportOptions {
+    /// Tags for the frontmatter of newly created book notes.
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighlightImportReport {
+    pub books_created: usize,
+    pub books_updated: usize,
+    pub highlights_added: usize,
+    /// The notes created or updated.
+    pub notes: Vec<String>,
+    /// Books or highlights that were skipped, and why.
+    pub warnings: Vec<String>,
+}
+
+/// A Readwise export: either `{"results": [...]}` as the API returns it, or
+/// just the list of books.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HighlightExport {
+    Results { results: Vec<Book> },
+    Books(Vec<Book>),
+}
+
+#[derive(Deserialize)]
+struct Book {
+    title: Option<String>,
+    readable_title: Option<String>,
+    author: Option<String>,
+    source: Option<String>,
+    source_url: Option<String>,
+    unique_url: Option<String>,
+    #[serde(default)]
+    highlights: Vec<Highlight>,
+}
+
+#[derive(Deserialize)]
+struct Highlight {
+    id: Option<serde_json::Value>,
+    #[serde(default)]
+    text: String,
+    note: Option<String>,
+    location: Option<serde_json::Value>,
+    location_type: Option<String>,
+    highlighted_at: Option<String>,
+    date: Option<String>,
+}
+
+/// An entry ready to write, with its local creation time.
+struct Entry {
+    created: DateTime<FixedOffset>,
+    body: String,
+    tags: Vec<String>,
+    metadata: BTreeMap<&'static str, serde_yaml::Value>,
+}
+
+/// Imports a Day One JSON export, either the unzipped export folder or one
+/// of its journal `.json` files. Media is copied to `attachments/` inside
+/// `destination_folder` and `dayone-moment:` references become local
+/// embeds. Existing notes are never overwritten.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn import_dayone(
+    export_path: &str,
+    destination_folder: &str,
+    options: Option<DayOneImportOptions>,
+) -> Result<ImportReport, String> {
+    let options = options.unwrap_or_default();
+    let export = Path::new(export_path);
+    let (export_dir, journals) = if export.is_dir() {
+        let journals: Vec<PathBuf> = fs::read_dir(export)
+            .map_err(|e| format!("Failed to read {}: {}", export_path, e))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+            })
+            .collect();
+        (export.to_path_buf(), journals)
+    } else if export.is_file() {
+        (
+            export.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            vec![export.to_path_buf()],
+        )
+    } else {
+        return Err(format!("Export does not exist: {}", export_path));
+    };
+    if journals.is_empty() {
+        return Err(format!("No Day One journal JSON found in {}", export_path));
+    }
+
+    let destination = Path::new(destination_folder);
+    let attachments = destination.join(ATTACHMENTS_DIR);
+    let mut report = ImportReport::default();
+    let mut entries = Vec::new();
+    let root = vault::find_root(&attachments).unwrap_or_else(|| destination.to_path_buf());
+    let writer = LinkWriter::new(&root, &VaultSettings::load(&root)?.link_format);
+
+    for journal in journals {
+        let parsed = fs::read_to_string(&journal)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<DayOneExport>(&json).map_err(|e| e.to_string())
+            });
+        let export = match parsed {
+            Ok(export) => export,
+            Err(e) => {
+                report
+                    .warnings
+                    .push(format!("Skipped {}: {}", journal.display(), e));
+                continue;
+            }
+        };
+        for entry in export.entries {
+            match convert_entry(
+                entry,
+                &export_dir,
+                &attachments,
+                destination,
+                &writer,
+                &mut report,
+            ) {
+                Ok(entry) => entries.push(entry),
+                Err(warning) => report.warnings.push(warning),
+            }
+        }
+    }
+    entries.sort_by_key(|entry| entry.created);
+
+    fs::create_dir_all(destination).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let mut days: Vec<Vec<Entry>> = Vec::new();
+    for entry in entries {
+        let same_day = days
+            .last()
+            .and_then(|day| day.first())
+            .is_some_and(|first| first.created.date_naive() == entry.created.date_naive());
+        match options.same_day {
+            SameDayEntries::Merge if same_day => days.last_mut().unwrap().push(entry),
+            _ => days.push(vec![entry]),
+        }
+    }
+    for day in days {
+        let stem = day[0].created.format("%Y-%m-%d").to_string();
+        let path = vault::unique_path(destination, &stem, "md");
+        write_atomic(&path, render_note(&day)?)?;
+        report.entries_imported += day.len();
+        report
+            .notes_created
+            .push(path.to_string_lossy().to_string());
+    }
+
+    Ok(report)
+}
+
+/// Imports reading highlights exported from Readwise as JSON, one note per
+/// book in `destination_folder`, named after its title. Each highlight is a
+/// blockquote with its note beneath. A book's existing note only gets the
+/// highlights it doesn't have yet, so importing the same export twice
+/// changes nothing.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn import_highlights_json(
+    json_path: &str,
+    destination_folder: &str,
+    options: Option<HighlightImportOptions>,
+) -> Result<HighlightImportReport, String> {
+    let options = options.unwrap_or_default();
+    let json = fs::read_to_string(json_path)
+        .map_err(|e| format!("Failed to read {}: {}", json_path, e))?;
+    let books = match serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid highlights export: {}", e))?
+    {
+        HighlightExport::Results { results } => results,
+        HighlightExport::Books(books) => books,
+    };
+    let destination = Path::new(destination_folder);
+    fs::create_dir_all(destination).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let synced = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let mut report = HighlightImportReport::default();
+
+    for book in books {
+        let Some(title) = book
+            .title
+            .as_deref()
+            .or(book.readable_title.as_deref())
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        else {
+            report
+                .warnings
+                .push("Skipped a book without a title".to_string());
+            continue;
+        };
+        let path = destination.join(format!("{}.md", vault::safe_file_name(title)));
+        // Held until the note is written, so highlights are appended to what
+        // is on disk rather than to a copy an autosave has since replaced.
+        let _lock = match WriteLocks::global().lock(&path, "import_highlights_json") {
+            Ok(lock) => lock,
+            Err(busy) => {
+                report.warnings.push(format!("Skipped {}: {}", title, busy));
+                continue;
+            }
+        };
+        let existing = match path.exists() {
+            true => Some(
+                fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+            ),
+            false => None,
+        };
+        let mut seen: Vec<String> = existing
+            .iter()
+            .flat_map(|content| {
+                HIGHLIGHT_MARKER
+                    .captures_iter(content)
+                    .map(|caps| caps[1].to_string())
+            })
+            .collect();
+        let mut added = Vec::new();
+        for highlight in &book.highlights {
+            if highlight.text.trim().is_empty() {
+                continue;
+            }
+            let key = highlight_key(highlight);
+            if seen.contains(&key) {
+                continue;
+            }
+            added.push(render_highlight(&key, highlight));
+            seen.push(key);
+        }
+        if added.is_empty() {
+            continue;
+        }
+
+        let source = [&book.source_url, &book.unique_url, &book.source]
+            .into_iter()
+            .flatten()
+            .find(|s| !s.trim().is_empty());
+        let set_fields = |mapping: &mut serde_yaml::Mapping| {
+            for (key, value) in [("author", &book.author), ("source", &source.cloned())] {
+                if let Some(value) = value.as_ref().filter(|v| !v.trim().is_empty()) {
+                    mapping
+                        .entry(key.into())
+                        .or_insert_with(|| value.trim().into());
+                }
+            }
+            mapping.insert("last_synced".into(), synced.clone().into());
+        };
+        let content = match &existing {
+            Some(content) => match frontmatter::update(content, set_fields) {
+                Ok(mut content) => {
+                    content.truncate(content.trim_end().len());
+                    format!("{}\n\n{}\n", content, added.join("\n\n"))
+                }
+                Err(e) => {
+                    report.warnings.push(format!("Skipped {}: {}", title, e));
+                    continue;
+                }
+            },
+            None => {
+                let mut fm = serde_yaml::Mapping::new();
+                set_fields(&mut fm);
+                if !options.tags.is_empty() {
+                    fm.insert(
+                        "tags".into(),
+                        serde_yaml::to_value(&options.tags).map_err(|e| e.to_string())?,
+                    );
+                }
+                let yaml = serde_yaml::to_string(&fm)
+                    .map_err(|e| format!("Failed to write frontmatter: {}", e))?;
+                format!(
+                    "---\n{}---\n\n# {}\n\n{}\n",
+                    yaml,
+                    title,
+                    added.join("\n\n")
+                )
+            }
+        };
+        if let Err(e) = write_note(&path, content) {
+            report.warnings.push(format!("Skipped {}: {}", title, e));
+            continue;
+        }
+        match existing {
+            Some(_) => report.books_updated += 1,
+            None => report.books_created += 1,
+        }
+        report.highlights_added += added.len();
+        report.notes.push(path.to_string_lossy().to_string());
+    }
+    Ok(report)
+}
+
+/// What identifies a highlight in its note: Readwise's id, or a hash of
+/// the text for exports without one.
+fn highlight_key(highlight: &Highlight) -> String {
+    use sha2::{Digest, Sha256};
+
+    match &highlight.id {
+        Some(serde_json::Value::String(id)) if !id.trim().is_empty() => {
+            id.trim().replace(char::is_whitespace, "-")
+        }
+        Some(serde_json::Value::Number(id)) => id.to_string(),
+        _ => {
+            let text = highlight
+                .text
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let hash = Sha256::digest(text.as_bytes());
+            let hex: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+            format!("sha256-{}", hex)
+        }
+    }
+}
+
+fn render_highlight(key: &str, highlight: &Highlight) -> String {
+    let mut lines = vec![format!("<!-- highlight:{} -->", key)];
+    lines.extend(
+        highlight
+            .text
+            .trim()
+            .lines()
+            .map(|line| format!("> {}", line.trim_end()).trim_end().to_string()),
+    );
+
+    let location = match (&highlight.location, highlight.location_type.as_deref()) {
+        // Readwise numbers highlights without a real position by order.
+        (_, Some("order")) | (None, _) => None,
+        (Some(serde_json::Value::Number(n)), kind) => {
+            Some(format!("{} {}", location_label(kind), n))
+        }
+        (Some(serde_json::Value::String(s)), kind) if !s.trim().is_empty() => {
+            Some(format!("{} {}", location_label(kind), s.trim()))
+        }
+        _ => None,
+    };
+    let date = highlight
+        .highlighted_at
+        .as_deref()
+        .or(highlight.date.as_deref())
+        .and_then(|d| d.get(..10))
+        .filter(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok());
+    let details: Vec<String> = location
+        .into_iter()
+        .chain(date.map(str::to_string))
+        .collect();
+    if !details.is_empty() {
+        lines.push(">".to_string());
+        lines.push(format!("> — {}", details.join(", ")));
+    }
+    if let Some(note) = highlight
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        lines.push(String::new());
+        lines.push(note.to_string());
+    }
+    lines.join("\n")
+}
+
+fn location_label(kind: Option<&str>) -> &'static str {
+    match kind {
+        Some("page") => "Page",
+        Some("time_offset") => "Time",
+        _ => "Location",
+    }
+}
+
+fn convert_entry(
+    entry: DayOneEntry,
+    export_dir: &Path,
+    attachments: &Path,
+    destination: &Path,
+    writer: &LinkWriter,
+    report: &mut ImportReport,
+) -> Result<Entry, String> {
+    let id = entry.uuid.clone().unwrap_or_default();
+    let utc: DateTime<Utc> = entry.creation_date.parse().map_err(|_| {
+        format!(
+            "Skipped entry {} with invalid date {}",
+            id, entry.creation_date
+        )
+    })?;
+    // Name notes by the day the entry was written where it was written.
+    let created = match entry
+        .time_zone
+        .as_deref()
+        .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok())
+    {
+        Some(tz) => utc.with_timezone(&tz).fixed_offset(),
+        None => utc.fixed_offset(),
+    };
+
+    let media: Vec<(&str, &DayOneMedia)> = [
+        ("photos", &entry.photos),
+        ("videos", &entry.videos),
+        ("audios", &entry.audios),
+        ("pdfs", &entry.pdf_attachments),
+    ]
+    .into_iter()
+    .flat_map(|(dir, items)| items.iter().map(move |item| (dir, item)))
+    .collect();
+
+    let mut targets: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for (dir, item) in media {
+        let (Some(md5), Some(file_type)) = (&item.md5, &item.file_type) else {
+            report.warnings.push(format!(
+                "Entry {}: no file recorded for {}",
+                id, item.identifier
+            ));
+            continue;
+        };
+        let name = format!("{}.{}", md5, file_type);
+        let source = export_dir.join(dir).join(&name);
+        let target = attachments.join(&name);
+        if !source.is_file() {
+            report
+                .warnings
+                .push(format!("Entry {}: missing {}/{}", id, dir, name));
+            continue;
+        }
+        if !target.exists() {
+            fs::create_dir_all(attachments)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            if let Err(e) = fs::copy(&source, &target) {
+                report.warnings.push(format!(
+                    "Entry {}: failed to copy {}/{}: {}",
+                    id, dir, name, e
+                ));
+                continue;
+            }
+            report.attachments_copied += 1;
+        }
+        targets.insert(item.identifier.clone(), target);
+    }
+    let body = MOMENT
+        .replace_all(&entry.text, |caps: &regex::Captures| {
+            let Some(target) = targets.get(&caps[2]) else {
+                return caps[0].to_string();
+            };
+            match (caps.get(1), caps.get(3)) {
+                (Some(alt), Some(_)) => writer.embed(destination, target, Some(alt.as_str())),
+                // Anything but a plain image keeps its markdown syntax.
+                (alt, close) => format!(
+                    "{}{}{}",
+                    alt.map(|alt| format!("![{}](", alt.as_str()))
+                        .unwrap_or_default(),
+                    writer.markdown_target(destination, target),
+                    close.map_or("", |m| m.as_str())
+                ),
+            }
+        })
+        .trim()
+        .to_string();
+
+    let mut metadata = BTreeMap::new();
+    if let Some(location) = entry.location.as_ref().and_then(location_value) {
+        metadata.insert("location", location);
+    }
+    if let Some(weather) = entry.weather.as_ref().and_then(weather_value) {
+        metadata.insert("weather", weather);
+    }
+    if entry.starred {
+        metadata.insert("starred", serde_yaml::Value::Bool(true));
+    }
+    if let Some(uuid) = entry.uuid {
+        metadata.insert("dayone_id", serde_yaml::Value::String(uuid));
+    }
+
+    Ok(Entry {
+        created,
+        body,
+        tags: entry.tags,
+        metadata,
+    })
+}
+
+fn location_value(location: &DayOneLocation) -> Option<serde_yaml::Value> {
+    let mut map = serde_yaml::Mapping::new();
+    let name = [
+        &location.place_name,
+        &location.locality_name,
+        &location.administrative_area,
+        &location.country,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|part| !part.trim().is_empty())
+    .fold(Vec::<&str>::new(), |mut parts, part| {
+        if !parts.contains(&part.as_str()) {
+            parts.push(part);
+        }
+        parts
+    })
+    .join(", ");
+    if !name.is_empty() {
+        map.insert("name".into(), name.into());
+    }
+    if let (Some(lat), Some(lon)) = (location.latitude, location.longitude) {
+        map.insert("latitude".into(), lat.into());
+        map.insert("longitude".into(), lon.into());
+    }
+    (!map.is_empty()).then_some(serde_yaml::Value::Mapping(map))
+}
+
+fn weather_value(weather: &DayOneWeather) -> Option<serde_yaml::Value> {
+    let mut map = serde_yaml::Mapping::new();
+    if let Some(conditions) = &weather.conditions_description {
+        map.insert("conditions".into(), conditions.clone().into());
+    }
+    if let Some(temperature) = weather.temperature_celsius {
+        map.insert(
+            "temperature_c".into(),
+            ((temperature * 10.0).round() / 10.0).into(),
+        );
+    }
+    (!map.is_empty()).then_some(serde_yaml::Value::Mapping(map))
+}
+
+/// A day's note. A single entry's metadata goes straight into the
+/// frontmatter; merged entries list theirs in entry order, and the note is
+/// starred if any entry was.
+fn render_note(entries: &[Entry]) -> Result<String, String> {
+    let mut fm = serde_yaml::Mapping::new();
+    fm.insert("date".into(), entries[0].created.to_rfc3339().into());
+    let mut tags: Vec<String> = Vec::new();
+    for tag in entries.iter().flat_map(|e| &e.tags) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    if !tags.is_empty() {
+        fm.insert(
+            "tags".into(),
+            serde_yaml::to_value(&tags).map_err(|e| e.to_string())?,
+        );
+    }
+    if entries.iter().any(|e| e.metadata.contains_key("starred")) {
+        fm.insert("starred".into(), true.into());
+    }
+    for key in ["location", "weather", "dayone_id"] {
+        let values: Vec<serde_yaml::Value> = entries
+            .iter()
+            .filter_map(|e| e.metadata.get(key).cloned())
+            .collect();
+        match values.len() {
+            0 => {}
+            1 if entries.len() == 1 => {
+                fm.insert(key.into(), values.into_iter().next().unwrap());
+            }
+            _ => {
+                fm.insert(key.into(), serde_yaml::Value::Sequence(values));
+            }
+        }
+    }
+    let yaml =
+        serde_yaml::to_string(&fm).map_err(|e| format!("Failed to write frontmatter: {}", e))?;
+
+    let body = if entries.len() == 1 {
+        entries[0].body.clone()
+    } else {
+        entries
+            .iter()
+            .map(|e| {
+                // An entry's own title joins its time heading.
+                let time = e.created.format("%H:%M");
+                let first_line = e.body.lines().next().unwrap_or_default();
+                match heading(first_line) {
+                    Some((1, title)) => {
+                        let rest = e.body[first_line.len()..].trim_start();
+                        format!("## {} {}\n\n{}", time, title, rest)
+                    }
+                    _ => format!("## {}\n\n{}", time, e.body),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    Ok(format!("---\n{}---\n\n{}\n", yaml, body))
+}
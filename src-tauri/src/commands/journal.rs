@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::files::write_note;
+use super::rename::{move_path, UpdateFailure};
+use crate::vault;
+use crate::vault::journal::{self, Change, Operation};
+use crate::vault::write_locks::{WriteLocks, LOCK_TIMEOUT};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationInfo {
+    pub id: String,
+    pub kind: String,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub files_changed: usize,
+    pub renames: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoConflict {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UndoReport {
+    /// Whether the operation was undone. When files changed since, nothing
+    /// is touched and `conflicts` says why.
+    pub undone: bool,
+    pub conflicts: Vec<UndoConflict>,
+    /// Files given back their old content or moved back, as absolute
+    /// paths, once per change undone.
+    pub restored: Vec<String>,
+    /// Steps that failed part way; the operation's record is then kept.
+    pub failed: Vec<UpdateFailure>,
+}
+
+/// Bulk operations that can still be undone, newest first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn list_operations(vault_path: &str) -> Result<Vec<OperationInfo>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    Ok(journal::operations(root).iter().map(info).collect())
+}
+
+/// Puts back what operation `op_id` changed: old content for files it
+/// rewrote and old paths for files it moved, newest change first. Refuses
+/// if any of those files changed since, listing which.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn undo_operation(vault_path: &str, op_id: &str) -> Result<UndoReport, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let operation = journal::load(root, op_id)?;
+    let mut report = UndoReport {
+        conflicts: conflicts(root, &operation),
+        ..Default::default()
+    };
+    if !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    let mut files = Vec::new();
+    for change in &operation.changes {
+        match change {
+            Change::Write { path, .. } => files.push(root.join(path)),
+            Change::Rename { from, to } => {
+                let [from, to] = [from, to].map(|path| root.join(path));
+                for file in vault::files_where(&to, |_| true) {
+                    files.push(from.join(file.strip_prefix(&to).unwrap_or(&file)));
+                    files.push(file);
+                }
+                files.extend([from, to]);
+            }
+        }
+    }
+    let _locks = WriteLocks::global()
+        .lock_all(files, "undo_operation", LOCK_TIMEOUT)
+        .map_err(|busy| busy.to_string())?;
+
+    let dir = journal::operation_dir(root, op_id)?;
+    for change in operation.changes.iter().rev() {
+        let (path, result) = match change {
+            Change::Write { path, original, .. } => {
+                let target = root.join(path);
+                let result = match original {
+                    Some(name) => fs::read(dir.join(name))
+                        .map_err(|e| format!("Failed to read original: {}", e))
+                        .and_then(|content| {
+                            write_note(&target, content).map_err(|e| e.to_string())
+                        }),
+                    None => fs::remove_file(&target)
+                        .map_err(|e| format!("Failed to delete file: {}", e)),
+                };
+                (target, result)
+            }
+            Change::Rename { from, to } => {
+                let from = root.join(from);
+                (from.clone(), move_path(root, &root.join(to), &from))
+            }
+        };
+        let path = path.to_string_lossy().to_string();
+        match result {
+            Ok(()) => report.restored.push(path),
+            Err(error) => report.failed.push(UpdateFailure { path, error }),
+        }
+    }
+    report.undone = true;
+    if report.failed.is_empty() {
+        journal::remove(root, op_id)?;
+    }
+    Ok(report)
+}
+
+fn info(operation: &Operation) -> OperationInfo {
+    let mut files = HashSet::new();
+    let mut renames = 0;
+    for change in &operation.changes {
+        match change {
+            Change::Write { path, .. } => {
+                files.insert(path.as_str());
+            }
+            Change::Rename { .. } => renames += 1,
+        }
+    }
+    OperationInfo {
+        id: operation.id.clone(),
+        kind: operation.kind.clone(),
+        at: operation.at,
+        files_changed: files.len(),
+        renames,
+    }
+}
+
+/// Files the operation touched that are no longer as it left them. Going
+/// from the newest change back, only the last change to each path is
+/// checked, since earlier ones are undone on top of it. A file written
+/// before a rename that moved it is checked where the rename left it.
+fn conflicts(root: &Path, operation: &Operation) -> Vec<UndoConflict> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut later_renames: Vec<(&str, &str)> = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut conflict = |path: &str, reason: &str| {
+        conflicts.push(UndoConflict {
+            path: root.join(path).to_string_lossy().to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    for change in operation.changes.iter().rev() {
+        match change {
+            Change::Write { path, hash, .. } => {
+                let mut current = path.clone();
+                for (from, to) in later_renames.iter().rev() {
+                    if vault::is_within(&current, from) {
+                        current = format!("{}{}", to, &current[from.len()..]);
+                    }
+                }
+                if !seen.insert(current.clone()) {
+                    continue;
+                }
+                match vault::hash_file(&root.join(&current)) {
+                    Ok(hashed) if hashed == *hash => {}
+                    Ok(_) => conflict(&current, "Changed since the operation"),
+                    Err(_) => conflict(&current, "Deleted or unreadable since the operation"),
+                }
+            }
+            Change::Rename { from, to } => {
+                if seen.insert(to.clone()) && !root.join(to).exists() {
+                    conflict(to, "Moved or deleted since the operation");
+                }
+                // After a case-only rename the old path still "exists" on a
+                // case-insensitive filesystem.
+                let case_only = from.to_lowercase() == to.to_lowercase();
+                if seen.insert(from.clone()) && !case_only && root.join(from).exists() {
+                    conflict(from, "Something else is at the old path now");
+                }
+                later_renames.push((from, to));
+            }
+        }
+    }
+    conflicts
+}
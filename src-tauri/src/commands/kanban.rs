@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::Path;
+
+use super::files::{check_unmodified, write_atomic, FileError};
+use crate::markdown::kanban::{self, KanbanBoard};
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, locks};
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn parse_kanban(path: &str) -> Result<KanbanBoard, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(kanban::parse(&content))
+}
+
+/// Moves a card and returns the updated board. With `expected_modified` and
+/// `expected_size`, as `read_file` gave them, the move is refused if the
+/// note changed since the board was read.
+#[tauri::command]
+pub fn move_kanban_card(
+    path: &str,
+    card_line: usize,
+    target_column: &str,
+    position: usize,
+    expected_modified: Option<u64>,
+    expected_size: Option<u64>,
+) -> Result<KanbanBoard, FileError> {
+    let file_path = Path::new(path);
+    if locks::is_locked(vault::find_root(file_path).as_deref(), file_path) {
+        return Err(FileError::locked(path));
+    }
+    let _lock = WriteLocks::global().lock(file_path, "move_kanban_card")?;
+    check_unmodified(file_path, expected_modified, expected_size)?;
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let moved = kanban::move_card(&content, card_line, target_column, position)?;
+
+    if moved != content {
+        write_atomic(file_path, &moved)?;
+    }
+
+    Ok(kanban::parse(&moved))
+}
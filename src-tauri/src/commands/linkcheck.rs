@@ -0,0 +1,356 @@
+use futures::stream::{self, StreamExt};
+use reqwest::{header, Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::markdown::links::{self, HttpUrl};
+use crate::markdown::{blank_code_spans, code_block_lines, frontmatter};
+use crate::vault::{self, settings::VaultSettings};
+
+const RESULT_EVENT: &str = "linkcheck://result";
+const SUMMARY_EVENT: &str = "linkcheck://summary";
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Why a request failed without producing a status code.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkErrorClass {
+    Timeout,
+    Connect,
+    Redirect,
+    InvalidUrl,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkCheckResult {
+    pub url: String,
+    pub note_path: String,
+    pub line_number: usize,
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub error: Option<LinkErrorClass>,
+    /// Where the URL ended up, when it redirected elsewhere.
+    pub redirect: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkCheckSummary {
+    /// Distinct URLs requested.
+    pub checked: usize,
+    pub ok: usize,
+    pub broken: usize,
+    pub redirected: usize,
+    /// Distinct URLs on skipped domains.
+    pub skipped: usize,
+    /// Link occurrences across all notes, including skipped ones.
+    pub occurrences: usize,
+}
+
+struct Outcome {
+    ok: bool,
+    status: Option<u16>,
+    error: Option<LinkErrorClass>,
+    redirect: Option<String>,
+}
+
+/// Checks every http(s) URL in the vault's notes (outside code) and emits a
+/// `linkcheck://result` event per occurrence as each URL finishes, followed
+/// by a `linkcheck://summary` event with the totals, which is also returned.
+///
+/// Each distinct URL is requested once with HEAD, retrying with a one-byte
+/// ranged GET when the server rejects HEAD. URLs on the vault's
+/// `link_check.skip_domains` are never requested.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn check_external_links(
+    app: AppHandle,
+    vault_path: String,
+    folder: Option<String>,
+    concurrency: Option<usize>,
+) -> Result<LinkCheckSummary, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let folder = folder.map(|f| root.join(f.trim_matches('/')));
+
+    let mut by_url: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+    for note in vault::notes(root, &settings) {
+        if folder.as_ref().is_some_and(|f| !note.starts_with(f)) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let note_path = note.to_string_lossy().to_string();
+        for (line_number, found) in note_urls(&content) {
+            by_url
+                .entry(found.url)
+                .or_default()
+                .push((note_path.clone(), line_number));
+        }
+    }
+
+    let mut summary = LinkCheckSummary {
+        occurrences: by_url.values().map(Vec::len).sum(),
+        ..Default::default()
+    };
+    let skip = &settings.link_check.skip_domains;
+    by_url.retain(|url, _| {
+        let skipped = is_skipped(url, skip);
+        if skipped {
+            summary.skipped += 1;
+        }
+        !skipped
+    });
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(settings.link_check.timeout_secs.max(1)))
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .user_agent(concat!("GraphNotes/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut checks = stream::iter(by_url)
+        .map(|(url, occurrences)| {
+            let client = client.clone();
+            async move {
+                let outcome = check_url(&client, &url).await;
+                (url, occurrences, outcome)
+            }
+        })
+        .buffer_unordered(concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1));
+
+    while let Some((url, occurrences, outcome)) = checks.next().await {
+        summary.checked += 1;
+        if !outcome.ok {
+            summary.broken += 1;
+        } else if outcome.redirect.is_some() {
+            summary.redirected += 1;
+        } else {
+            summary.ok += 1;
+        }
+
+        for (note_path, line_number) in occurrences {
+            let result = LinkCheckResult {
+                url: url.clone(),
+                note_path,
+                line_number,
+                ok: outcome.ok,
+                status: outcome.status,
+                error: outcome.error,
+                redirect: outcome.redirect.clone(),
+            };
+            let _ = app.emit(RESULT_EVENT, result);
+        }
+    }
+
+    let _ = app.emit(SUMMARY_EVENT, summary.clone());
+    Ok(summary)
+}
+
+/// URLs outside code blocks and code spans, with 1-based line numbers.
+fn note_urls(content: &str) -> Vec<(usize, HttpUrl)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut urls = Vec::new();
+    for (idx, (line, code)) in lines.iter().zip(in_code).enumerate() {
+        if code || !line.contains("http") {
+            continue;
+        }
+        for url in links::http_urls(&blank_code_spans(line)) {
+            urls.push((idx + 1, url));
+        }
+    }
+    urls
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkReference {
+    pub note_path: String,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalLink {
+    pub url: String,
+    /// Link text from the first markdown link to the URL that has any.
+    pub title: Option<String>,
+    /// Earliest creation date among the referencing notes, in seconds since
+    /// the Unix epoch.
+    pub first_seen: Option<i64>,
+    pub references: Vec<LinkReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DomainLinks {
+    pub domain: String,
+    pub links: Vec<ExternalLink>,
+}
+
+/// Catalogues every http(s) URL in the vault's notes (outside code),
+/// deduplicated and grouped by domain. Domains and URLs are sorted.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_external_links(vault_path: &str) -> Result<Vec<DomainLinks>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+
+    let mut by_url: BTreeMap<String, ExternalLink> = BTreeMap::new();
+    for note in vault::notes(root, &settings) {
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let found = note_urls(&content);
+        if found.is_empty() {
+            continue;
+        }
+        let created = note_created(&note, &content);
+        let note_path = note.to_string_lossy().to_string();
+
+        for (line_number, found) in found {
+            let link = by_url
+                .entry(found.url.clone())
+                .or_insert_with(|| ExternalLink {
+                    url: found.url,
+                    title: None,
+                    first_seen: None,
+                    references: Vec::new(),
+                });
+            if link.title.is_none() {
+                link.title = found.text;
+            }
+            link.first_seen = match (link.first_seen, created) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            link.references.push(LinkReference {
+                note_path: note_path.clone(),
+                line_number,
+            });
+        }
+    }
+
+    let mut by_domain: BTreeMap<String, Vec<ExternalLink>> = BTreeMap::new();
+    for (url, link) in by_url {
+        let domain = Url::parse(&url)
+            .ok()
+            .and_then(|u| {
+                u.host_str()
+                    .map(|h| h.trim_start_matches("www.").to_lowercase())
+            })
+            .unwrap_or_default();
+        by_domain.entry(domain).or_default().push(link);
+    }
+
+    Ok(by_domain
+        .into_iter()
+        .map(|(domain, links)| DomainLinks { domain, links })
+        .collect())
+}
+
+/// When a note was created: its frontmatter `created` (or `date`) field,
+/// then the file's creation time where the platform records one, then its
+/// mtime.
+fn note_created(path: &Path, content: &str) -> Option<i64> {
+    let from_frontmatter = frontmatter::parse_note(content).ok().and_then(|(fm, _)| {
+        ["created", "date"]
+            .iter()
+            .find_map(|key| fm.get(*key).and_then(frontmatter::parse_date))
+    });
+    from_frontmatter.or_else(|| {
+        let metadata = fs::metadata(path).ok()?;
+        let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+        time.duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs() as i64)
+    })
+}
+
+fn is_skipped(url: &str, skip_domains: &[String]) -> bool {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+    skip_domains.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    })
+}
+
+async fn check_url(client: &Client, url: &str) -> Outcome {
+    let Ok(parsed) = Url::parse(url) else {
+        return failed(LinkErrorClass::InvalidUrl);
+    };
+
+    let response = match client.head(parsed.clone()).send().await {
+        Ok(response) if !rejects_head(response.status()) => Ok(response),
+        Err(e) if e.is_timeout() => Err(e),
+        // Some servers refuse or mishandle HEAD; ask for a single byte instead.
+        _ => {
+            client
+                .get(parsed.clone())
+                .header(header::RANGE, "bytes=0-0")
+                .send()
+                .await
+        }
+    };
+
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            let redirect = (response.url() != &parsed).then(|| response.url().to_string());
+            Outcome {
+                ok: status.is_success() || status.is_redirection(),
+                status: Some(status.as_u16()),
+                error: None,
+                redirect,
+            }
+        }
+        Err(e) => failed(classify(&e)),
+    }
+}
+
+fn rejects_head(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_IMPLEMENTED
+            | StatusCode::FORBIDDEN
+            | StatusCode::BAD_REQUEST
+    )
+}
+
+fn classify(error: &reqwest::Error) -> LinkErrorClass {
+    if error.is_timeout() {
+        LinkErrorClass::Timeout
+    } else if error.is_redirect() {
+        LinkErrorClass::Redirect
+    } else if error.is_connect() {
+        LinkErrorClass::Connect
+    } else if error.is_builder() {
+        LinkErrorClass::InvalidUrl
+    } else {
+        LinkErrorClass::Other
+    }
+}
+
+fn failed(error: LinkErrorClass) -> Outcome {
+    Outcome {
+        ok: false,
+        status: None,
+        error: Some(error),
+        redirect: None,
+    }
+}
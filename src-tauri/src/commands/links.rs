@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::backlinks::is_attachment;
+use super::caches::CacheKeys;
+use crate::vault::index::VaultIndex;
+use crate::vault::{self, link_index, settings::VaultSettings};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub path: String,
+    pub title: String,
+    /// Note links written in this note, resolved or not.
+    pub link_count: usize,
+    /// Links from other notes that resolve to this one.
+    pub backlink_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Link,
+    Embed,
+    /// The two notes share rare tags; see `TagEdgeOptions`.
+    Tag,
+}
+
+/// Connects notes that share at least `min_shared` tags, counting only
+/// tags found in fewer than `max_tag_frequency` notes, so that a tag like
+/// `#todo` doesn't connect everything.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TagEdgeOptions {
+    pub min_shared: usize,
+    pub max_tag_frequency: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphLink {
+    pub source: String,
+    /// The note linked to, or the target as written when no note matches.
+    pub target: String,
+    pub resolved: bool,
+    /// The link as written in the source note.
+    pub link_text: String,
+    pub line_number: usize,
+    pub embed: bool,
+    pub kind: EdgeKind,
+    /// The tags a `tag` edge's notes share, lowercased. Empty otherwise.
+    pub shared_tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Unresolved links are included so they can be drawn as ghost nodes.
+    pub edges: Vec<GraphLink>,
+}
+
+/// Every note under `path` and the wikilinks and markdown links between
+/// them, in one call. Files are found as `grep_search` finds them; in a
+/// vault, notes its settings exclude are left out and the stored link
+/// index is used, so only changed notes are read. `[[note#heading]]`
+/// resolves to the note; links to attachments aren't part of the graph.
+/// With `include_tag_edges`, `tag` edges join notes sharing rare tags; they
+/// are one per pair of notes and don't count towards link counts.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn build_link_index(
+    keys: State<'_, CacheKeys>,
+    path: String,
+    include_tag_edges: Option<TagEdgeOptions>,
+) -> Result<LinkGraph, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    let index = if vault::state_dir(&root).is_dir() {
+        let settings = VaultSettings::load(&root)?;
+        link_index::refreshed(
+            &root,
+            &vault::notes(&root, &settings),
+            &keys.mode(&root, &settings),
+        )?
+    } else {
+        match vault::find_root(&root) {
+            Some(vault_root) => {
+                let files = vault::visible_files(&vault_root, &root, false, vault::is_markdown)?;
+                VaultIndex::build(&vault_root, &files)
+            }
+            None => VaultIndex::build(&root, &vault::markdown_files(&root)),
+        }
+    };
+    let mut graph = graph(&index);
+    if let Some(options) = include_tag_edges {
+        graph.edges.extend(tag_edges(&index, options));
+    }
+    Ok(graph)
+}
+
+fn graph(index: &VaultIndex) -> LinkGraph {
+    let path_of = |idx: usize| display(&index.notes[idx].path);
+    let mut backlink_counts = vec![0; index.notes.len()];
+    let mut link_counts = vec![0; index.notes.len()];
+    let mut edges = Vec::new();
+    for (from, note) in index.notes.iter().enumerate() {
+        for link in &note.links {
+            if link.target.trim().is_empty() || is_attachment(link) {
+                continue;
+            }
+            let to = index.resolve(from, link);
+            link_counts[from] += 1;
+            if let Some(to) = to.filter(|&to| to != from) {
+                backlink_counts[to] += 1;
+            }
+            edges.push(GraphLink {
+                source: path_of(from),
+                target: to.map(path_of).unwrap_or_else(|| link.target.clone()),
+                resolved: to.is_some(),
+                link_text: link.text.clone(),
+                line_number: link.line,
+                embed: link.embed,
+                kind: if link.embed {
+                    EdgeKind::Embed
+                } else {
+                    EdgeKind::Link
+                },
+                shared_tags: Vec::new(),
+            });
+        }
+    }
+    let nodes = index
+        .notes
+        .iter()
+        .enumerate()
+        .map(|(idx, note)| GraphNode {
+            path: display(&note.path),
+            title: note.title.clone(),
+            link_count: link_counts[idx],
+            backlink_count: backlink_counts[idx],
+        })
+        .collect();
+    LinkGraph { nodes, edges }
+}
+
+fn tag_edges(index: &VaultIndex, options: TagEdgeOptions) -> Vec<GraphLink> {
+    let mut notes_by_tag: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    for (idx, note) in index.notes.iter().enumerate() {
+        for tag in &note.tags {
+            notes_by_tag
+                .entry(tag.to_lowercase())
+                .or_default()
+                .insert(idx);
+        }
+    }
+    let mut shared: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for (tag, notes) in notes_by_tag {
+        if notes.len() >= options.max_tag_frequency {
+            continue;
+        }
+        let notes: Vec<usize> = notes.into_iter().collect();
+        for (i, &a) in notes.iter().enumerate() {
+            for &b in &notes[i + 1..] {
+                shared.entry((a, b)).or_default().push(tag.clone());
+            }
+        }
+    }
+    shared
+        .into_iter()
+        .filter(|(_, tags)| tags.len() >= options.min_shared.max(1))
+        .map(|((a, b), tags)| GraphLink {
+            source: display(&index.notes[a].path),
+            target: display(&index.notes[b].path),
+            resolved: true,
+            link_text: String::new(),
+            line_number: 0,
+            embed: false,
+            kind: EdgeKind::Tag,
+            shared_tags: tags,
+        })
+        .collect()
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
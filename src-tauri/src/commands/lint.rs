@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::markdown::lint::{self, Diagnostic};
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteDiagnostics {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Resolves which rules run: an explicit list wins, otherwise every rule
+/// not disabled in the vault settings.
+fn enabled_rules(
+    rules: Option<Vec<String>>,
+    settings: &VaultSettings,
+) -> Result<HashSet<String>, String> {
+    match rules {
+        Some(rules) => {
+            if let Some(unknown) = rules.iter().find(|r| !lint::RULES.contains(&r.as_str())) {
+                return Err(format!(
+                    "Unknown lint rule: {} (expected one of {})",
+                    unknown,
+                    lint::RULES.join(", ")
+                ));
+            }
+            Ok(rules.into_iter().collect())
+        }
+        None => Ok(lint::RULES
+            .iter()
+            .filter(|r| !settings.lint.disabled_rules.iter().any(|d| d == *r))
+            .map(|r| r.to_string())
+            .collect()),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn lint_note(path: &str, rules: Option<Vec<String>>) -> Result<Vec<Diagnostic>, String> {
+    let file_path = Path::new(path);
+    let settings = match vault::find_root(file_path) {
+        Some(root) => VaultSettings::load(&root)?,
+        None => VaultSettings::default(),
+    };
+    let enabled = enabled_rules(rules, &settings)?;
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(lint::lint(&content, &enabled))
+}
+
+/// Lints every note in the vault, skipping ignored paths. Only notes with at
+/// least one diagnostic are returned.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn lint_vault(
+    vault_path: &str,
+    rules: Option<Vec<String>>,
+) -> Result<Vec<NoteDiagnostics>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let enabled = enabled_rules(rules, &settings)?;
+
+    let mut results = Vec::new();
+    for file in vault::notes(root, &settings) {
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let diagnostics = lint::lint(&content, &enabled);
+        if !diagnostics.is_empty() {
+            results.push(NoteDiagnostics {
+                path: file.to_string_lossy().to_string(),
+                diagnostics,
+            });
+        }
+    }
+
+    Ok(results)
+}
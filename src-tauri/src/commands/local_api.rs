@@ -0,0 +1,381 @@
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Manager, State};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::files::{modified_secs, write_atomic, write_note};
+use super::metadata::read_note_metadata;
+use super::search::search_file;
+use super::templates::daily_note;
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, settings::VaultSettings, NoteFilter};
+
+const TOKEN_FILE: &str = "local-api-token";
+const DEFAULT_LIMIT: usize = 20;
+const MAX_SEARCH_RESULTS: usize = 500;
+/// Largest request body read.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// The local HTTP API, when it is running.
+#[derive(Default)]
+pub struct LocalApi {
+    running: Mutex<Option<RunningApi>>,
+}
+
+struct RunningApi {
+    vault_path: PathBuf,
+    port: u16,
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalApiOptions {
+    /// The only vault the API can reach.
+    pub vault_path: String,
+    /// A free port is picked when unset or zero.
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalApiStatus {
+    pub running: bool,
+    pub vault_path: Option<String>,
+    pub port: Option<u16>,
+    /// E.g. `http://127.0.0.1:27124`.
+    pub url: Option<String>,
+    /// The file holding the bearer token every request must send.
+    pub token_path: String,
+}
+
+/// Starts a JSON API on 127.0.0.1 for scripts and launchers, limited to one
+/// vault. Requests need an `Authorization: Bearer <token>` header with the
+/// token in `token_path`, made on first start. Paths are vault-relative:
+///
+/// - `GET /search?q=text&limit=N`: case-insensitive text search, or a
+///   regular expression with `regex=true`.
+/// - `GET /note?path=Note.md`: the note's content and metadata.
+/// - `POST /append` with `{"text": "...", "path": "Note.md"}`: appends a
+///   line to the note, or to today's daily note when `path` is left out.
+/// - `POST /daily`: today's daily note, created if need be.
+/// - `GET /recent?limit=N`: the most recently modified notes.
+///
+/// Private folders, hidden folders and locked notes are off limits, as they
+/// are to the app's own commands.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn start_local_api(
+    app: AppHandle,
+    api: State<'_, LocalApi>,
+    options: LocalApiOptions,
+) -> Result<LocalApiStatus, String> {
+    let root = PathBuf::from(&options.vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", options.vault_path));
+    }
+    let root = fs::canonicalize(&root).map_err(|e| format!("Failed to open vault: {}", e))?;
+    let token_path = token_path(&app)?;
+    let token = load_token(&token_path)?;
+
+    let mut running = api
+        .running
+        .lock()
+        .map_err(|_| "Local API is unavailable".to_string())?;
+    if let Some(previous) = running.take() {
+        stop(previous);
+    }
+    let server = Server::http(("127.0.0.1", options.port.unwrap_or(0)))
+        .map_err(|e| format!("Failed to start local API: {}", e))?;
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .ok_or_else(|| "Local API has no port".to_string())?;
+    let server = Arc::new(server);
+    let thread = {
+        let server = server.clone();
+        let root = root.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle(&root, &token, request);
+            }
+        })
+    };
+    *running = Some(RunningApi {
+        vault_path: root,
+        port,
+        server,
+        thread,
+    });
+    Ok(status(running.as_ref(), token_path))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn stop_local_api(app: AppHandle, api: State<'_, LocalApi>) -> Result<LocalApiStatus, String> {
+    let mut running = api
+        .running
+        .lock()
+        .map_err(|_| "Local API is unavailable".to_string())?;
+    if let Some(previous) = running.take() {
+        stop(previous);
+    }
+    Ok(status(None, token_path(&app)?))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_local_api_status(
+    app: AppHandle,
+    api: State<'_, LocalApi>,
+) -> Result<LocalApiStatus, String> {
+    let running = api
+        .running
+        .lock()
+        .map_err(|_| "Local API is unavailable".to_string())?;
+    Ok(status(running.as_ref(), token_path(&app)?))
+}
+
+fn stop(running: RunningApi) {
+    running.server.unblock();
+    let _ = running.thread.join();
+}
+
+fn status(running: Option<&RunningApi>, token_path: PathBuf) -> LocalApiStatus {
+    LocalApiStatus {
+        running: running.is_some(),
+        vault_path: running.map(|r| r.vault_path.to_string_lossy().to_string()),
+        port: running.map(|r| r.port),
+        url: running.map(|r| format!("http://127.0.0.1:{}", r.port)),
+        token_path: token_path.to_string_lossy().to_string(),
+    }
+}
+
+fn token_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to find config directory: {}", e))?;
+    Ok(dir.join(TOKEN_FILE))
+}
+
+/// The API token, made on first use.
+fn load_token(path: &Path) -> Result<String, String> {
+    if let Ok(token) = fs::read_to_string(path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    write_atomic(path, &token)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    Ok(token)
+}
+
+/// An error response: the status code and message.
+type Failure = (u16, String);
+
+fn handle(root: &Path, token: &str, mut request: Request) {
+    let result = if authorized(&request, token) {
+        route(root, &mut request)
+    } else {
+        Err((401, "Missing or wrong bearer token".to_string()))
+    };
+    let (code, body) = match result {
+        Ok(body) => (200, body),
+        Err((code, message)) => (code, json!({ "error": message })),
+    };
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("static header");
+    let response = Response::from_string(body.to_string())
+        .with_status_code(code)
+        .with_header(content_type);
+    let _ = request.respond(response);
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(header) = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+    else {
+        return false;
+    };
+    let Some(given) = header.value.as_str().strip_prefix("Bearer ") else {
+        return false;
+    };
+    // Compared in full every time, so timing says nothing about the token.
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn route(root: &Path, request: &mut Request) -> Result<Value, Failure> {
+    let url = url::Url::parse(&format!("http://127.0.0.1{}", request.url()))
+        .map_err(|e| (400, e.to_string()))?;
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    };
+    let limit = query("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT);
+    let settings = VaultSettings::load(root).map_err(server_error)?;
+
+    match (request.method(), url.path()) {
+        (Method::Get, "/search") => {
+            let text = query("q")
+                .filter(|q| !q.is_empty())
+                .ok_or((400, "Missing q".to_string()))?;
+            let pattern = if query("regex").as_deref() == Some("true") {
+                text
+            } else {
+                regex::escape(&text)
+            };
+            let regex = RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| (400, format!("Invalid search pattern: {}", e)))?;
+            let limit = limit.min(MAX_SEARCH_RESULTS);
+            let files = vault::visible_files(root, root, false, vault::is_markdown)
+                .map_err(server_error)?;
+            let mut matches = Vec::new();
+            for file in files {
+                if matches.len() >= limit {
+                    break;
+                }
+                if let Ok(content) = fs::read_to_string(&file) {
+                    search_file(&file, &content, &regex, false, limit, &mut matches);
+                }
+            }
+            Ok(json!(matches))
+        }
+        (Method::Get, "/note") => {
+            let path = note_path(root, &settings, &query("path").unwrap_or_default())?;
+            let content =
+                fs::read_to_string(&path).map_err(|_| (404, "Note not found".to_string()))?;
+            let metadata = read_note_metadata(path.to_string_lossy().to_string()).ok();
+            let path = vault::relative_path(root, &path);
+            Ok(json!({ "path": path, "content": content, "metadata": metadata }))
+        }
+        (Method::Post, "/append") => {
+            let body = json_body(request)?;
+            let text = body
+                .get("text")
+                .and_then(Value::as_str)
+                .ok_or((400, "Missing text".to_string()))?;
+            let path = match body.get("path").and_then(Value::as_str) {
+                Some(rel) => note_path(root, &settings, rel)?,
+                None => daily_note(root, &settings, None).map_err(server_error)?.0,
+            };
+            // Held from the read to the write, so nothing lands in between.
+            let _lock = WriteLocks::global()
+                .lock(&path, "append")
+                .map_err(|busy| (409, busy.to_string()))?;
+            let mut content = fs::read_to_string(&path).unwrap_or_default();
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(text.trim_end_matches('\n'));
+            content.push('\n');
+            write_note(&path, content).map_err(|e| (409, e.to_string()))?;
+            Ok(json!({ "path": vault::relative_path(root, &path) }))
+        }
+        (Method::Post, "/daily") => {
+            let (path, created) = daily_note(root, &settings, None).map_err(server_error)?;
+            Ok(json!({ "path": vault::relative_path(root, &path), "created": created }))
+        }
+        (Method::Get, "/recent") => {
+            let files = vault::visible_files(root, root, false, vault::is_markdown)
+                .map_err(server_error)?;
+            let mut notes: Vec<(u64, String)> = files
+                .iter()
+                .map(|file| {
+                    (
+                        modified_secs(file).unwrap_or(0),
+                        vault::relative_path(root, file),
+                    )
+                })
+                .collect();
+            notes.sort_by(|a, b| b.cmp(a));
+            notes.truncate(limit);
+            Ok(json!(notes
+                .into_iter()
+                .map(|(modified, path)| json!({ "path": path, "modified": modified }))
+                .collect::<Vec<_>>()))
+        }
+        (_, "/search" | "/note" | "/append" | "/daily" | "/recent") => {
+            Err((405, "Method not allowed".to_string()))
+        }
+        _ => Err((404, "Not found".to_string())),
+    }
+}
+
+/// A note in the vault given by vault-relative path. Absolute paths, `..`,
+/// hidden folders, private folders and files that aren't markdown are all
+/// refused, as are symlinks that lead out of the vault.
+fn note_path(root: &Path, settings: &VaultSettings, rel: &str) -> Result<PathBuf, Failure> {
+    let forbidden = |message: &str| Err((403, format!("{}: {}", message, rel)));
+    let given = Path::new(rel.trim_start_matches('/'));
+    if rel.is_empty() || !vault::is_markdown(given) {
+        return forbidden("Not a markdown note");
+    }
+    let outside = given.components().any(|c| match c {
+        Component::Normal(name) => vault::is_hidden(&name.to_string_lossy()),
+        _ => true,
+    });
+    if outside {
+        return forbidden("Path is outside the vault");
+    }
+    let path = root.join(given);
+    // A note not written yet is checked by the nearest folder that exists,
+    // so one under a symlink out of the vault can't be created; a dangling
+    // symlink doesn't resolve and is refused.
+    let existing = path
+        .ancestors()
+        .find(|ancestor| fs::symlink_metadata(ancestor).is_ok())
+        .unwrap_or(root);
+    if !fs::canonicalize(existing).is_ok_and(|real| real.starts_with(root)) {
+        return forbidden("Path is outside the vault");
+    }
+    if NoteFilter::new(root, settings).is_private(&path) {
+        return forbidden("Note is in a private folder");
+    }
+    Ok(path)
+}
+
+fn json_body(request: &mut Request) -> Result<Value, Failure> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, format!("Failed to read body: {}", e)))?;
+    serde_json::from_str(&body).map_err(|e| (400, format!("Invalid JSON: {}", e)))
+}
+
+fn server_error(message: String) -> Failure {
+    (500, message)
+}
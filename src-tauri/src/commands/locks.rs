@@ -0,0 +1,18 @@
+use std::path::Path;
+
+use crate::vault::locks;
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn set_note_locked(vault_path: &str, path: &str, locked: bool) -> Result<(), String> {
+    let note = Path::new(path);
+    if !note.is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    locks::set_locked(Path::new(vault_path), note, locked)
+}
+
+#[tauri::command]
+pub fn is_note_locked(vault_path: &str, path: &str) -> bool {
+    locks::is_locked(Some(Path::new(vault_path)), Path::new(path))
+}
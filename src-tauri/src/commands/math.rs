@@ -0,0 +1,19 @@
+use std::fs;
+
+use crate::markdown::math::{self, MathProblem, MathRegion};
+
+/// The inline (`$...$`) and display (`$$...$$`) math in a note, in order.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn extract_math(path: &str) -> Result<Vec<MathRegion>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(math::scan(&content).0)
+}
+
+/// Math delimiters in a note that are never closed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn validate_math(path: &str) -> Result<Vec<MathProblem>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(math::scan(&content).1)
+}
@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::files::{edit_note, FileError};
+use crate::markdown::inline_fields::InlineField;
+use crate::markdown::tables::{Alignment, Table};
+use crate::markdown::{
+    self, code_block_end, fence_marker, frontmatter, inline_fields, tables, LineBuffer,
+};
+use crate::vault::{self, link_format::LinkWriter, settings::VaultSettings};
+
+const QUERY_LANGUAGE: &str = "graphnotes-query";
+const QUERY_END: &str = "<!-- graphnotes-query-end -->";
+
+/// Filters for `query_notes`. Any key other than the named ones is treated
+/// as a field that must equal the given value, e.g.
+/// `{"tag": "book", "status": "reading"}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuerySpec {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Also search notes in private folders.
+    #[serde(default)]
+    pub include_private: bool,
+    #[serde(flatten)]
+    pub fields: HashMap<String, Value>,
+}
+
+/// The spec inside a `graphnotes-query` block: a `QuerySpec` plus the
+/// fields to show. Results are a list of links, or a table when `columns`
+/// is given.
+#[derive(Debug, Deserialize)]
+struct QueryBlock {
+    #[serde(default)]
+    columns: Vec<String>,
+    #[serde(flatten)]
+    query: QuerySpec,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub path: String,
+    pub title: String,
+    pub modified: Option<u64>,
+    /// Frontmatter and inline fields merged under normalized (lowercased)
+    /// keys.
+    pub fields: Map<String, Value>,
+}
+
+/// A note's frontmatter, with the common keys pulled out. Empty for a note
+/// without frontmatter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteMetadata {
+    pub title: Option<String>,
+    /// From `tags: [a, b]`, a list, or a comma separated string, without `#`.
+    pub tags: Vec<String>,
+    /// From `aliases`, or `alias`.
+    pub aliases: Vec<String>,
+    /// `created` and `modified` as written, e.g. `2024-06-12`.
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    /// Every other key.
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteMetadataEntry {
+    pub path: String,
+    /// Empty when the note couldn't be read or parsed.
+    pub metadata: NoteMetadata,
+    pub error: Option<String>,
+}
+
+/// Parses a note's frontmatter. Malformed YAML is an error.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn read_note_metadata(path: String) -> Result<NoteMetadata, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    note_metadata(&content)
+}
+
+/// `read_note_metadata` for every note in the vault its settings don't
+/// exclude. A note that can't be read or parsed gets its error rather than
+/// failing the scan.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn read_vault_metadata(vault_path: String) -> Result<Vec<NoteMetadataEntry>, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    Ok(vault::notes(root, &settings)
+        .into_iter()
+        .map(|note| {
+            let result = fs::read_to_string(&note)
+                .map_err(|e| format!("Failed to read file: {}", e))
+                .and_then(|content| note_metadata(&content));
+            let (metadata, error) = match result {
+                Ok(metadata) => (metadata, None),
+                Err(error) => (NoteMetadata::default(), Some(error)),
+            };
+            NoteMetadataEntry {
+                path: note.to_string_lossy().to_string(),
+                metadata,
+                error,
+            }
+        })
+        .collect())
+}
+
+fn note_metadata(content: &str) -> Result<NoteMetadata, String> {
+    let (mut fm, _) = frontmatter::parse_note(content)?;
+    let text = |value: Option<Value>| match value {
+        Some(Value::String(s)) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Some(Value::Null) | None => None,
+        Some(other) => Some(other.to_string()),
+    };
+    let tags = frontmatter::tags(&fm);
+    let aliases = frontmatter::string_list(fm.get("aliases").or_else(|| fm.get("alias")));
+    for key in ["tags", "aliases", "alias"] {
+        fm.remove(key);
+    }
+    Ok(NoteMetadata {
+        title: text(fm.remove("title")),
+        tags,
+        aliases,
+        created: text(fm.remove("created")),
+        modified: text(fm.remove("modified")),
+        extra: fm,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_inline_fields(path: &str) -> Result<Vec<InlineField>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(inline_fields::extract(&content))
+}
+
+/// Runs a metadata query over every note in the vault.
+///
+/// Frontmatter and inline fields are merged into one field set per note.
+/// When the same key appears in both, the frontmatter value wins and the
+/// inline values are ignored; repeated inline fields collect into a list.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn query_notes(vault_path: &str, query: QuerySpec) -> Result<Vec<QueryResult>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+
+    let folder = query.folder.as_ref().map(|f| root.join(f));
+    let mut results = Vec::new();
+
+    for file in vault::visible_files(root, root, query.include_private, vault::is_markdown)? {
+        if let Some(folder) = &folder {
+            if !file.starts_with(folder) {
+                continue;
+            }
+        }
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let (fm, split) = frontmatter::parse_note(&content)
+            .unwrap_or_else(|_| (Map::new(), frontmatter::split(&content)));
+
+        if let Some(tag) = &query.tag {
+            let wanted = tag.trim_start_matches('#').to_lowercase();
+            if !frontmatter::tags(&fm)
+                .iter()
+                .any(|t| t.to_lowercase() == wanted)
+            {
+                continue;
+            }
+        }
+
+        let fields = merged_fields(&fm, &inline_fields::extract(&content));
+        if !query.fields.iter().all(|(key, expected)| {
+            field_matches(fields.get(&inline_fields::normalize_key(key)), expected)
+        }) {
+            continue;
+        }
+
+        results.push(QueryResult {
+            path: file.to_string_lossy().to_string(),
+            title: markdown::note_title(&file, &fm, split.body),
+            modified: modified_secs(&file),
+            fields,
+        });
+
+        if query.limit.is_some_and(|limit| results.len() >= limit) {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Re-runs every `graphnotes-query` block in a note. The spec is JSON, on
+/// the fence line or inside the block:
+///
+/// ````text
+/// ```graphnotes-query
+/// {"tag": "book", "status": "reading", "columns": ["author"]}
+/// ```
+/// ````
+///
+/// Results are written after the block up to a `<!-- graphnotes-query-end -->`
+/// marker, replacing what a previous refresh wrote there. A spec that fails
+/// to parse or run writes its error in place of the results. Returns the
+/// number of blocks refreshed.
+#[tauri::command]
+pub fn refresh_query_blocks(vault_path: &str, note_path: &str) -> Result<usize, FileError> {
+    let note = Path::new(note_path);
+    let mut refreshed = 0;
+    edit_note(note, "refresh_query_blocks", |content| {
+        let mut buffer = LineBuffer::parse(content);
+        let lines = buffer.as_strs();
+
+        let mut out: Vec<String> = Vec::with_capacity(lines.len());
+        let mut i = 0;
+        while i < lines.len() {
+            let Some((_, _, info)) = fence_marker(lines[i]) else {
+                out.push(lines[i].to_string());
+                i += 1;
+                continue;
+            };
+            let end = code_block_end(&lines, i);
+            out.extend(lines[i..end].iter().map(|l| l.to_string()));
+            let closed = end > i + 1
+                && fence_marker(lines[end - 1]).is_some_and(|(_, _, rest)| rest.is_empty());
+            let Some(inline_spec) = query_info(info).filter(|_| closed) else {
+                i = end;
+                continue;
+            };
+
+            let spec = if inline_spec.is_empty() {
+                lines[i + 1..end - 1].join("\n")
+            } else {
+                inline_spec.to_string()
+            };
+            out.extend(query_output(vault_path, note, &spec));
+            out.push(QUERY_END.to_string());
+            refreshed += 1;
+            i = generated_end(&lines, end);
+        }
+
+        if refreshed == 0 {
+            return Ok(content.to_string());
+        }
+        buffer.lines = out;
+        Ok(buffer.render())
+    })?;
+    Ok(refreshed)
+}
+
+/// The spec written on a `graphnotes-query` fence line (possibly empty), or
+/// `None` for any other fence.
+fn query_info(info: &str) -> Option<&str> {
+    let rest = info.strip_prefix(QUERY_LANGUAGE)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// Where the output of the block ending at `from` stops: just past its end
+/// marker, or `from` when it has not been refreshed before.
+fn generated_end(lines: &[&str], from: usize) -> usize {
+    for (offset, line) in lines[from..].iter().enumerate() {
+        if line.trim() == QUERY_END {
+            return from + offset + 1;
+        }
+        if fence_marker(line).is_some_and(|(_, _, info)| query_info(info).is_some()) {
+            break;
+        }
+    }
+    from
+}
+
+fn query_output(vault_path: &str, note: &Path, spec: &str) -> Vec<String> {
+    let results = serde_json::from_str::<QueryBlock>(spec)
+        .map_err(|e| format!("Invalid query: {}", e))
+        .and_then(|block| {
+            let results = query_notes(vault_path, block.query)?;
+            Ok((block.columns, results))
+        });
+    let (columns, results) = match results {
+        Ok((columns, results)) => (columns, results),
+        Err(e) => return vec![format!("> **Query error:** {}", e.replace('\n', " "))],
+    };
+    let results: Vec<QueryResult> = results
+        .into_iter()
+        .filter(|r| Path::new(&r.path) != note)
+        .collect();
+    if results.is_empty() {
+        return vec!["_No matching notes._".to_string()];
+    }
+    let root = Path::new(vault_path);
+    let format = VaultSettings::load(root)
+        .map(|s| s.link_format)
+        .unwrap_or_default();
+    let writer = LinkWriter::new(root, &format);
+    let from_dir = note.parent().unwrap_or(root);
+    let note_link = |r: &QueryResult| writer.link(from_dir, Path::new(&r.path), Some(&r.title));
+
+    if columns.is_empty() {
+        return results
+            .iter()
+            .map(|r| format!("- {}", note_link(r)))
+            .collect();
+    }
+    let mut headers = vec!["Note".to_string()];
+    headers.extend(columns.iter().cloned());
+    let rows = results
+        .iter()
+        .map(|r| {
+            let mut row = vec![note_link(r)];
+            row.extend(
+                columns
+                    .iter()
+                    .map(|c| display_value(r.fields.get(&inline_fields::normalize_key(c)))),
+            );
+            row
+        })
+        .collect();
+    tables::render(&Table {
+        alignments: vec![Alignment::None; headers.len()],
+        headers,
+        rows,
+    })
+}
+
+fn display_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| display_value(Some(item)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(other) => other.to_string(),
+    }
+}
+
+pub fn merged_fields(
+    frontmatter: &Map<String, Value>,
+    inline: &[InlineField],
+) -> Map<String, Value> {
+    let mut fields = Map::new();
+    for (key, value) in frontmatter {
+        fields.insert(inline_fields::normalize_key(key), value.clone());
+    }
+
+    let mut inline_values: Map<String, Value> = Map::new();
+    for field in inline {
+        if fields.contains_key(&field.normalized_key) {
+            continue;
+        }
+        match inline_values.get_mut(&field.normalized_key) {
+            Some(Value::Array(items)) => items.push(field.value.clone()),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, field.value.clone()]);
+            }
+            None => {
+                inline_values.insert(field.normalized_key.clone(), field.value.clone());
+            }
+        }
+    }
+
+    fields.extend(inline_values);
+    fields
+}
+
+fn field_matches(actual: Option<&Value>, expected: &Value) -> bool {
+    match actual {
+        None => expected.is_null(),
+        Some(Value::Array(items)) => items.iter().any(|item| values_equal(item, expected)),
+        Some(value) => values_equal(value, expected),
+    }
+}
+
+fn values_equal(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::String(a), Value::String(b)) => a.eq_ignore_ascii_case(b),
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Number(a), Value::String(b)) | (Value::String(b), Value::Number(a)) => {
+            b.parse::<f64>().ok() == a.as_f64()
+        }
+        _ => actual == expected,
+    }
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
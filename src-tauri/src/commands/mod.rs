@@ -1 +1,55 @@
+pub mod actions;
+pub mod aliases;
+pub mod attachments;
+pub mod audit;
+pub mod autosave;
+pub mod backlinks;
+pub mod backup;
+pub mod caches;
+pub mod citations;
+pub mod compress;
+pub mod dates;
+pub mod diagnostics;
+pub mod diff;
+pub mod encoding;
+pub mod export;
+pub mod external;
 pub mod files;
+pub mod folder_notes;
+pub mod format;
+pub mod frecency;
+pub mod glossary;
+pub mod goals;
+pub mod graph_snapshots;
+pub mod health;
+pub mod import;
+pub mod journal;
+pub mod kanban;
+pub mod linkcheck;
+pub mod links;
+pub mod lint;
+pub mod local_api;
+pub mod locks;
+pub mod math;
+pub mod metadata;
+pub mod monitor;
+pub mod properties;
+pub mod reading;
+pub mod references;
+pub mod reminders;
+pub mod rename;
+pub mod render;
+pub mod review;
+pub mod rollover;
+pub mod schemas;
+pub mod search;
+pub mod settings;
+pub mod spellcheck;
+pub mod tables;
+pub mod tags;
+pub mod tasks;
+pub mod templates;
+pub mod titles;
+pub mod watcher;
+pub mod web;
+pub mod windows;
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::vault::health::{self, HealthCondition, VaultHealth};
+use crate::vault::settings::VaultSettings;
+use crate::vaults::VaultStateRegistry;
+
+const LOW_DISK_EVENT: &str = "health://low_disk";
+const READ_ONLY_EVENT: &str = "health://read_only_fs";
+
+/// The running health checks, one per open vault; a vault's runs while any
+/// window shows it.
+#[derive(Default)]
+pub struct HealthMonitor {
+    tasks: Mutex<HashMap<PathBuf, JoinHandle<()>>>,
+}
+
+/// Free space on the vault's disk and whether its folder can be written,
+/// checked now.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_vault_health(vault_path: &str) -> Result<VaultHealth, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    Ok(health::check(root))
+}
+
+impl HealthMonitor {
+    /// Starts checking the vault at the canonical `root` every few minutes,
+    /// as its `health.check_interval_minutes` says. When its disk runs low
+    /// or stops being writable, `health://low_disk` or
+    /// `health://read_only_fs` is sent to every window showing it, once
+    /// until the problem clears; `get_vault_health` tells when it has.
+    pub fn start(&self, app: &AppHandle, root: &Path) {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return;
+        };
+        if let Some(task) = tasks.remove(root) {
+            task.abort();
+        }
+        let app = app.clone();
+        let vault_root = root.to_path_buf();
+        let task = async_runtime::spawn(async move {
+            let mut reported: Vec<HealthCondition> = Vec::new();
+            loop {
+                let root = vault_root.clone();
+                let checked = async_runtime::spawn_blocking(move || {
+                    let interval = VaultSettings::load(&root)
+                        .unwrap_or_default()
+                        .health
+                        .check_interval_minutes;
+                    (health::check(&root), interval)
+                })
+                .await;
+                let interval = match checked {
+                    Ok((health, interval)) => {
+                        report(&app, &vault_root, &health, &reported);
+                        reported = health.conditions;
+                        interval
+                    }
+                    Err(e) => {
+                        let vault = vault_root.display();
+                        tracing::warn!(%vault, error = %e, "vault health not checked");
+                        VaultSettings::default().health.check_interval_minutes
+                    }
+                };
+                tokio::time::sleep(Duration::from_secs(interval.max(1) * 60)).await;
+            }
+        });
+        tasks.insert(root.to_path_buf(), task);
+    }
+
+    pub fn stop(&self, root: &Path) {
+        if let Some(task) = self
+            .tasks
+            .lock()
+            .ok()
+            .and_then(|mut tasks| tasks.remove(root))
+        {
+            task.abort();
+        }
+    }
+}
+
+/// Sends an event for each of `health`'s conditions not already reported.
+fn report(app: &AppHandle, root: &Path, health: &VaultHealth, reported: &[HealthCondition]) {
+    let windows = app.state::<VaultStateRegistry>().windows(root);
+    for condition in health
+        .conditions
+        .iter()
+        .filter(|condition| !reported.contains(condition))
+    {
+        let event = match condition {
+            HealthCondition::LowDisk => LOW_DISK_EVENT,
+            HealthCondition::ReadOnlyFs => READ_ONLY_EVENT,
+        };
+        tracing::warn!(vault = %root.display(), message = ?health.message, "{}", event);
+        for window in &windows {
+            let _ = app.emit_to(window.as_str(), event, health.clone());
+        }
+    }
+}
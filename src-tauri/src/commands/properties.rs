@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tauri::State;
+
+use super::backlinks::{load_index, note_position};
+use super::caches::CacheKeys;
+use super::files::{edit_note, FileError};
+use crate::markdown::inline_fields::{self, InlineField};
+use crate::markdown::{self, frontmatter};
+use crate::vault::schemas::{FieldSchema, FieldType, Schemas};
+
+/// Computed properties, named like Dataview's so they can't clash with a
+/// note's own fields.
+const WORD_COUNT: &str = "file.words";
+const CREATED: &str = "file.created";
+const MODIFIED: &str = "file.modified";
+const BACKLINKS: &str = "file.backlinks";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertySource {
+    Frontmatter,
+    Inline,
+    Computed,
+    /// Expected by the note type's schema but not in the note; the value is
+    /// the schema's default, if any.
+    SchemaDefault,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    Text,
+    Number,
+    Boolean,
+    Date,
+    Link,
+    List,
+    Object,
+    Empty,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteProperty {
+    /// As written in the note.
+    pub name: String,
+    pub value: Value,
+    pub value_type: PropertyType,
+    pub source: PropertySource,
+    pub editable: bool,
+    /// Where an inline field is, 1-based.
+    pub line_number: Option<usize>,
+    /// What the note type's schema expects of this field, if it names it.
+    pub schema: Option<PropertySchema>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertySchema {
+    pub field_type: FieldType,
+    pub required: bool,
+    /// Allowed values for `enum` fields.
+    pub values: Vec<String>,
+}
+
+/// Everything the properties panel shows for a note, in order: frontmatter
+/// fields as written, schema fields the note is missing, inline fields,
+/// then computed values. An inline field that frontmatter also sets is
+/// left out, as `query_notes` ignores it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_note_properties(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    path: &str,
+) -> Result<Vec<NoteProperty>, String> {
+    let root = Path::new(vault_path);
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut properties = note_properties(root, &content)?;
+
+    let (index_root, index) = load_index(&keys, vault_path)?;
+    let backlinks = match note_position(&index_root, &index, path)? {
+        Some(to) => (0..index.notes.len())
+            .filter(|from| *from != to && index.outgoing(*from).contains(&to))
+            .count(),
+        None => 0,
+    };
+    let metadata = fs::metadata(path).ok();
+    let time = |get: fn(&fs::Metadata) -> std::io::Result<SystemTime>| {
+        metadata.as_ref().and_then(|m| get(m).ok()).map(iso_time)
+    };
+    let modified = time(fs::Metadata::modified);
+    let created = time(fs::Metadata::created).or_else(|| modified.clone());
+    let computed = [
+        (WORD_COUNT, Value::from(markdown::word_count(&content))),
+        (CREATED, created.map(Value::String).unwrap_or_default()),
+        (MODIFIED, modified.map(Value::String).unwrap_or_default()),
+        (BACKLINKS, Value::from(backlinks)),
+    ];
+    for (name, value) in computed {
+        properties.push(NoteProperty {
+            name: name.to_string(),
+            value_type: value_type(&value),
+            value,
+            source: PropertySource::Computed,
+            editable: false,
+            line_number: None,
+            schema: None,
+        });
+    }
+    Ok(properties)
+}
+
+/// Sets one property where it lives: in the frontmatter for frontmatter
+/// and schema fields, or in place for an inline field. A name the note
+/// doesn't have yet is added to the frontmatter. Computed properties can't
+/// be set. Returns the note's properties afterwards.
+#[tauri::command]
+pub fn set_note_property(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    path: &str,
+    name: &str,
+    value: Value,
+) -> Result<Vec<NoteProperty>, FileError> {
+    edit_note(Path::new(path), "set_note_property", |content| {
+        let existing = note_properties(Path::new(vault_path), content)?;
+        let wanted = inline_fields::normalize_key(name);
+        let property = existing
+            .iter()
+            .find(|p| inline_fields::normalize_key(&p.name) == wanted);
+
+        if [WORD_COUNT, CREATED, MODIFIED, BACKLINKS].contains(&wanted.as_str()) {
+            return Err(format!("{} is computed and can't be set", name));
+        }
+        let updated = match property.map(|p| (p.source, p.line_number)) {
+            Some((PropertySource::Inline, Some(line))) => {
+                let inline = |p: &&NoteProperty| {
+                    p.source == PropertySource::Inline
+                        && inline_fields::normalize_key(&p.name) == wanted
+                };
+                if existing.iter().filter(inline).count() > 1 {
+                    return Err(format!(
+                        "{} is set more than once in the note; edit it there",
+                        name
+                    ));
+                }
+                inline_fields::set_value(content, line, name, &inline_text(&value))
+                    .ok_or_else(|| format!("Inline field {} not found", name))?
+            }
+            source => {
+                // Keep the frontmatter key as written, e.g. `Status` for `status`.
+                let key = match (source, property) {
+                    (Some((PropertySource::Frontmatter, _)), Some(p)) => p.name.as_str(),
+                    _ => name,
+                };
+                let value = serde_yaml::to_value(&value)
+                    .map_err(|e| format!("Invalid value for {}: {}", name, e))?;
+                frontmatter::update(content, |mapping| {
+                    mapping.insert(serde_yaml::Value::String(key.to_string()), value);
+                })?
+            }
+        };
+        Ok(updated)
+    })?;
+    Ok(get_note_properties(keys, vault_path, path)?)
+}
+
+/// The note's own properties and the schema's, without computed ones.
+fn note_properties(root: &Path, content: &str) -> Result<Vec<NoteProperty>, String> {
+    let (fm, split) = frontmatter::parse_note(content)?;
+    let schemas = Schemas::load(root)?;
+    let schema = schemas.schema_for(&fm).map(|(_, schema)| schema);
+    let field_schema = |name: &str| {
+        let spec = schema?
+            .fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))?
+            .1;
+        Some(property_schema(spec))
+    };
+
+    let mut properties: Vec<NoteProperty> = Vec::new();
+    // Read again as YAML, since the parsed map's keys are sorted.
+    let mapping = match split.yaml.map(serde_yaml::from_str::<serde_yaml::Value>) {
+        Some(Ok(serde_yaml::Value::Mapping(mapping))) => mapping,
+        _ => serde_yaml::Mapping::new(),
+    };
+    for key in mapping.keys() {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        let value = fm.get(name).cloned().unwrap_or_default();
+        properties.push(NoteProperty {
+            name: name.to_string(),
+            value_type: value_type(&value),
+            value,
+            source: PropertySource::Frontmatter,
+            editable: true,
+            line_number: None,
+            schema: field_schema(name),
+        });
+    }
+
+    let has = |properties: &[NoteProperty], name: &str| {
+        let wanted = inline_fields::normalize_key(name);
+        properties
+            .iter()
+            .any(|p| inline_fields::normalize_key(&p.name) == wanted)
+    };
+    let inline: Vec<NoteProperty> = inline_fields::extract(content)
+        .into_iter()
+        .filter(|field| !has(&properties, &field.key))
+        .map(|field| inline_property(field, field_schema))
+        .collect();
+    for (name, spec) in schema.map(|s| &s.fields).into_iter().flatten() {
+        if has(&properties, name) || has(&inline, name) {
+            continue;
+        }
+        let value = spec.default.clone().unwrap_or_default();
+        properties.push(NoteProperty {
+            name: name.clone(),
+            value_type: value_type(&value),
+            value,
+            source: PropertySource::SchemaDefault,
+            editable: true,
+            line_number: None,
+            schema: Some(property_schema(spec)),
+        });
+    }
+    properties.extend(inline);
+    Ok(properties)
+}
+
+fn inline_property(
+    field: InlineField,
+    field_schema: impl Fn(&str) -> Option<PropertySchema>,
+) -> NoteProperty {
+    let value_type = match field.value_type {
+        inline_fields::FieldType::Number => PropertyType::Number,
+        inline_fields::FieldType::Date => PropertyType::Date,
+        inline_fields::FieldType::Link => PropertyType::Link,
+        inline_fields::FieldType::Text if field.raw_value.is_empty() => PropertyType::Empty,
+        inline_fields::FieldType::Text => PropertyType::Text,
+    };
+    NoteProperty {
+        schema: field_schema(&field.key),
+        name: field.key,
+        value: field.value,
+        value_type,
+        source: PropertySource::Inline,
+        editable: true,
+        line_number: Some(field.line_number),
+    }
+}
+
+fn property_schema(spec: &FieldSchema) -> PropertySchema {
+    PropertySchema {
+        field_type: spec.field_type,
+        required: spec.required,
+        values: spec.values.clone(),
+    }
+}
+
+fn value_type(value: &Value) -> PropertyType {
+    match value {
+        Value::Null => PropertyType::Empty,
+        Value::Bool(_) => PropertyType::Boolean,
+        Value::Number(_) => PropertyType::Number,
+        Value::Array(_) => PropertyType::List,
+        Value::Object(_) => PropertyType::Object,
+        Value::String(s) if s.trim_start().starts_with("[[") && s.trim_end().ends_with("]]") => {
+            PropertyType::Link
+        }
+        Value::String(_) if frontmatter::parse_date(value).is_some() => PropertyType::Date,
+        Value::String(_) => PropertyType::Text,
+    }
+}
+
+/// How a value is written after `key::`.
+fn inline_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(inline_text).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn iso_time(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
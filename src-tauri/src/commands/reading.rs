@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::files::modified_secs;
+use crate::markdown::{self, frontmatter, reading};
+use crate::vault::positions::{self, ReadingAnchor};
+use crate::vault::settings::VaultSettings;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteInfo {
+    pub path: String,
+    pub title: String,
+    pub size: u64,
+    pub modified: Option<u64>,
+    /// Words outside frontmatter and code.
+    pub word_count: usize,
+    /// Words in fenced code blocks.
+    pub code_word_count: usize,
+    /// At the vault's reading speeds, rounded up.
+    pub reading_minutes: u64,
+    /// E.g. `12 min read`.
+    pub reading_time: String,
+    pub reading_position: Option<ReadingPosition>,
+}
+
+/// A saved reading position, as it falls in the note now.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadingPosition {
+    #[serde(flatten)]
+    pub anchor: ReadingAnchor,
+    /// 1-based line to scroll to.
+    pub line: usize,
+    /// Whether the heading it was saved under is gone, so it points at the
+    /// start of the note instead.
+    pub lost: bool,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+}
+
+/// Where to save reading up to: `{"line": 120}`, the first line in view,
+/// or an anchor worked out already, `{"anchor": {"heading": "setup",
+/// "paragraph": 2}}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionInput {
+    Line(usize),
+    Anchor(ReadingAnchor),
+}
+
+/// A note's size, word counts and reading time, and where reading it
+/// stopped. Code is counted at the vault's slower code reading speed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_note_info(vault_path: &str, path: &str) -> Result<NoteInfo, String> {
+    let root = Path::new(vault_path);
+    let note = root.join(path);
+    let content = fs::read_to_string(&note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let speeds = VaultSettings::load(root)?.reading;
+    let word_count = markdown::word_count(&content);
+    let code_word_count = reading::code_word_count(&content);
+    let reading_minutes = reading::reading_minutes(
+        word_count,
+        code_word_count,
+        speeds.words_per_minute,
+        speeds.code_words_per_minute,
+    );
+    let (fm, split) = frontmatter::parse_note(&content)
+        .unwrap_or_else(|_| (Default::default(), frontmatter::split(&content)));
+    Ok(NoteInfo {
+        path: note.to_string_lossy().to_string(),
+        title: markdown::note_title(&note, &fm, split.body),
+        size: content.len() as u64,
+        modified: modified_secs(&note),
+        word_count,
+        code_word_count,
+        reading_minutes,
+        reading_time: format!("{} min read", reading_minutes),
+        reading_position: position_in(root, &note, &content)?,
+    })
+}
+
+/// Saves where reading the note at `path` stopped, as the nearest heading
+/// above and a paragraph offset from it, so it still points at the same
+/// text after edits elsewhere in the note.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn set_reading_position(
+    vault_path: &str,
+    path: &str,
+    position: PositionInput,
+) -> Result<ReadingPosition, String> {
+    let root = Path::new(vault_path);
+    let note = root.join(path);
+    let content = fs::read_to_string(&note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let anchor = match position {
+        PositionInput::Line(line) => {
+            let (heading, paragraph) = reading::anchor_at(&content, line);
+            ReadingAnchor { heading, paragraph }
+        }
+        PositionInput::Anchor(anchor) => anchor,
+    };
+    let saved_at = positions::set(root, &note, anchor.clone())?;
+    Ok(resolve(&content, anchor, saved_at))
+}
+
+/// Where reading the note at `path` stopped, or `None` if it was never
+/// saved. A position under a heading that has since gone points at the
+/// start of the note.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_reading_position(
+    vault_path: &str,
+    path: &str,
+) -> Result<Option<ReadingPosition>, String> {
+    let root = Path::new(vault_path);
+    let note = root.join(path);
+    let content = fs::read_to_string(&note).map_err(|e| format!("Failed to read file: {}", e))?;
+    position_in(root, &note, &content)
+}
+
+fn position_in(root: &Path, note: &Path, content: &str) -> Result<Option<ReadingPosition>, String> {
+    Ok(positions::get(root, note)?.map(|(anchor, saved_at)| resolve(content, anchor, saved_at)))
+}
+
+fn resolve(content: &str, anchor: ReadingAnchor, saved_at: u64) -> ReadingPosition {
+    let line = reading::anchor_line(content, anchor.heading.as_deref(), anchor.paragraph);
+    ReadingPosition {
+        line: line.unwrap_or(1),
+        lost: line.is_none(),
+        anchor: if line.is_some() {
+            anchor
+        } else {
+            ReadingAnchor::default()
+        },
+        saved_at,
+    }
+}
@@ -0,0 +1,103 @@
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::fs;
+use std::path::Path;
+
+use super::render::URI_COMPONENT;
+use crate::markdown::{self, frontmatter, links};
+use crate::vault::{self, link_format::LinkWriter, settings::VaultSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceStyle {
+    /// `[[Note]]`, as short as the vault allows.
+    Wiki,
+    /// `[Title](folder/Note.md)`, relative to the vault root.
+    Markdown,
+    /// `folder/Note.md`.
+    Path,
+    Title,
+    /// `graphnotes://open?vault=...&path=...`.
+    Deeplink,
+}
+
+/// A reference to `note_path` to paste elsewhere, in `style`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn format_note_reference(
+    vault_path: &str,
+    note_path: &str,
+    style: ReferenceStyle,
+) -> Result<String, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let writer = LinkWriter::new(root, &settings.link_format);
+    reference(&writer, root, note_path, style)
+}
+
+/// References to several notes at once, in the order given.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn format_note_references(
+    vault_path: &str,
+    note_paths: Vec<String>,
+    style: ReferenceStyle,
+) -> Result<Vec<String>, String> {
+    let root = Path::new(vault_path);
+    let settings = VaultSettings::load(root)?;
+    let writer = LinkWriter::new(root, &settings.link_format);
+    note_paths
+        .iter()
+        .map(|note| reference(&writer, root, note, style))
+        .collect()
+}
+
+/// A `graphnotes://` link that opens `note` in the vault at `root`.
+pub(crate) fn deep_link(root: &Path, note: &Path) -> String {
+    let vault_name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!(
+        "graphnotes://open?vault={}&path={}",
+        utf8_percent_encode(&vault_name, URI_COMPONENT),
+        utf8_percent_encode(&vault::relative_path(root, note), URI_COMPONENT)
+    )
+}
+
+fn reference(
+    writer: &LinkWriter,
+    root: &Path,
+    note_path: &str,
+    style: ReferenceStyle,
+) -> Result<String, String> {
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", root.display()));
+    }
+    let note = match Path::new(note_path) {
+        path if path.is_absolute() => path.to_path_buf(),
+        path => root.join(path),
+    };
+    if !note.is_file() {
+        return Err(format!("File does not exist: {}", note_path));
+    }
+    Ok(match style {
+        ReferenceStyle::Wiki => format!("[[{}]]", writer.wiki_target(&note)),
+        ReferenceStyle::Markdown => format!(
+            "[{}]({})",
+            title(&note)?.replace('[', "\\[").replace(']', "\\]"),
+            links::encode_target(&vault::relative_path(root, &note))
+        ),
+        ReferenceStyle::Path => vault::relative_path(root, &note),
+        ReferenceStyle::Title => title(&note)?,
+        ReferenceStyle::Deeplink => deep_link(root, &note),
+    })
+}
+
+fn title(note: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (fm, split) = frontmatter::parse_note(&content)
+        .unwrap_or_else(|_| (Map::new(), frontmatter::split(&content)));
+    Ok(markdown::note_title(note, &fm, split.body))
+}
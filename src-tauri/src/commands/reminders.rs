@@ -0,0 +1,287 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::markdown::reminders::task_reminders;
+use crate::vault::{self, reminders::FiredReminders, settings::VaultSettings};
+use crate::vaults::VaultStateRegistry;
+
+const DUE_EVENT: &str = "reminder://due";
+/// Reminders that came due while the app was closed still go off when the
+/// vault opens, if they were due this recently.
+const MISSED_GRACE_SECS: u64 = 24 * 60 * 60;
+/// The longest the scheduler sleeps before looking at the clock again, in
+/// case the computer slept or its clock changed.
+const MAX_SLEEP: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub path: String,
+    /// 1-based.
+    pub line_number: usize,
+    /// The task's text without its reminder.
+    pub text: String,
+    /// Local time as `YYYY-MM-DDTHH:MM:SS`.
+    pub due: String,
+    /// Seconds since the Unix epoch.
+    pub due_at: u64,
+}
+
+/// The running reminder schedules, one per open vault; a vault's runs
+/// while any window shows it.
+#[derive(Default)]
+pub struct ReminderScheduler {
+    tasks: Mutex<HashMap<PathBuf, JoinHandle<()>>>,
+    /// Held while reminders are fired so a schedule that was just started
+    /// over can't fire them a second time.
+    running: Mutex<()>,
+    /// Each note's open reminders as last read, with the modification time
+    /// and size it had then, so only notes changed since are read again.
+    notes: Mutex<HashMap<PathBuf, NoteReminders>>,
+}
+
+struct NoteReminders {
+    stamp: (SystemTime, u64),
+    reminders: Vec<Reminder>,
+}
+
+/// Open tasks' reminders due in the next `within_hours`, 24 by default,
+/// soonest first. Reminders are set with `(remind:: 2024-07-01 09:00)` or
+/// `⏰ 2024-07-01 09:00` on a task line; a bare date means 9:00.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_upcoming_reminders(
+    scheduler: State<'_, ReminderScheduler>,
+    vault_path: &str,
+    within_hours: Option<u64>,
+) -> Result<Vec<Reminder>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let now = vault::now_secs();
+    let until = now + within_hours.unwrap_or(24) * 60 * 60;
+    Ok(scheduler
+        .open_reminders(root)?
+        .into_iter()
+        .filter(|reminder| (now..=until).contains(&reminder.due_at))
+        .collect())
+}
+
+impl ReminderScheduler {
+    /// Starts firing the reminders of the vault at the canonical `root`
+    /// with `reminder://due` events, sent to every window showing it, or
+    /// starts over if it already was.
+    pub fn schedule(&self, app: &AppHandle, root: &Path) {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return;
+        };
+        if let Some(task) = tasks.remove(root) {
+            task.abort();
+        }
+        let app = app.clone();
+        let vault_root = root.to_path_buf();
+        let task = async_runtime::spawn(async move {
+            loop {
+                let (handle, root) = (app.clone(), vault_root.clone());
+                let checked = async_runtime::spawn_blocking(move || {
+                    let scheduler: State<ReminderScheduler> = handle.state();
+                    let _running = scheduler
+                        .running
+                        .lock()
+                        .map_err(|_| "Reminders are unavailable".to_string())?;
+                    fire_due(&handle, &root)
+                })
+                .await
+                .map_err(|e| format!("Reminder task failed: {}", e))
+                .and_then(|result| result);
+                let wait = match checked {
+                    Ok(next) => {
+                        next.map_or(MAX_SLEEP, |secs| Duration::from_secs(secs).min(MAX_SLEEP))
+                    }
+                    Err(e) => {
+                        let vault = vault_root.display();
+                        tracing::warn!(%vault, error = %e, "reminders not checked");
+                        MAX_SLEEP
+                    }
+                };
+                tokio::time::sleep(wait).await;
+            }
+        });
+        tasks.insert(root.to_path_buf(), task);
+    }
+
+    /// Looks at the reminders of the vault again, if they are being fired,
+    /// so an edited reminder goes off at its new time.
+    pub fn refresh(&self, app: &AppHandle, root: &Path) {
+        let scheduled = self
+            .tasks
+            .lock()
+            .is_ok_and(|tasks| tasks.contains_key(root));
+        if scheduled {
+            self.schedule(app, root);
+        }
+    }
+
+    pub fn stop(&self, root: &Path) {
+        if let Some(task) = self
+            .tasks
+            .lock()
+            .ok()
+            .and_then(|mut tasks| tasks.remove(root))
+        {
+            task.abort();
+        }
+        if let Ok(mut notes) = self.notes.lock() {
+            notes.retain(|note, _| !note.starts_with(root));
+        }
+    }
+
+    /// Every open task's reminders in the vault, soonest first. Notes
+    /// unchanged since they were last read aren't read again.
+    fn open_reminders(&self, root: &Path) -> Result<Vec<Reminder>, String> {
+        let settings = VaultSettings::load(root)?;
+        let mut cache = self
+            .notes
+            .lock()
+            .map_err(|_| "Reminders are unavailable".to_string())?;
+        let mut reminders = Vec::new();
+        for note in vault::notes(root, &settings) {
+            let Some(stamp) = stamp(&note) else {
+                continue;
+            };
+            match cache.get(&note).filter(|cached| cached.stamp == stamp) {
+                Some(cached) => reminders.extend(cached.reminders.iter().cloned()),
+                None => {
+                    let found = note_reminders(&note);
+                    reminders.extend(found.iter().cloned());
+                    cache.insert(
+                        note,
+                        NoteReminders {
+                            stamp,
+                            reminders: found,
+                        },
+                    );
+                }
+            }
+        }
+        cache.retain(|note, _| !note.starts_with(root) || note.is_file());
+        reminders.sort_by(|a, b| {
+            (a.due_at, &a.path, a.line_number).cmp(&(b.due_at, &b.path, b.line_number))
+        });
+        Ok(reminders)
+    }
+}
+
+/// Fires the reminders due now that haven't gone off yet and returns how
+/// many seconds until the next one, if any is coming.
+fn fire_due(app: &AppHandle, root: &Path) -> Result<Option<u64>, String> {
+    let reminders = app.state::<ReminderScheduler>().open_reminders(root)?;
+    let now = vault::now_secs();
+    let mut fired = FiredReminders::load(root)?;
+    let mut changed = false;
+    let windows = app.state::<VaultStateRegistry>().windows(root);
+    for reminder in reminders.iter().filter(|reminder| reminder.due_at <= now) {
+        let key = FiredReminders::key(
+            &vault::relative_path(root, Path::new(&reminder.path)),
+            &reminder.text,
+        );
+        if fired.has_fired(&key, reminder.due_at) {
+            continue;
+        }
+        if reminder.due_at + MISSED_GRACE_SECS > now {
+            for window in &windows {
+                let _ = app.emit_to(window.as_str(), DUE_EVENT, reminder.clone());
+            }
+        }
+        fired.record(key, reminder.due_at);
+        changed = true;
+    }
+    if changed {
+        fired.save(root, now)?;
+    }
+    Ok(reminders
+        .iter()
+        .find(|reminder| reminder.due_at > now)
+        .map(|reminder| reminder.due_at - now))
+}
+
+/// A note's open tasks' reminders.
+fn note_reminders(note: &Path) -> Vec<Reminder> {
+    let Ok(content) = fs::read_to_string(note) else {
+        return Vec::new();
+    };
+    if !content.contains("::") && !content.contains('⏰') {
+        return Vec::new();
+    }
+    task_reminders(&content)
+        .into_iter()
+        .filter(|reminder| !reminder.done)
+        .filter_map(|reminder| {
+            Some(Reminder {
+                path: note.to_string_lossy().to_string(),
+                line_number: reminder.line_number,
+                text: reminder.text,
+                due: reminder.due.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                due_at: local_secs(&reminder.due)?,
+            })
+        })
+        .collect()
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Seconds since the Unix epoch of a local time; the earlier one when a
+/// clock change makes it ambiguous, none when it is skipped over.
+fn local_secs(time: &NaiveDateTime) -> Option<u64> {
+    Local
+        .from_local_datetime(time)
+        .earliest()
+        .and_then(|t| u64::try_from(t.timestamp()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    #[test]
+    fn edited_added_and_removed_notes_are_picked_up() {
+        let root = TempVault::new(
+            "reminders-cache",
+            &[
+                ("A.md", "- [ ] Call (remind:: 2030-01-01 09:00)\n"),
+                ("B.md", "- [ ] Write (remind:: 2030-01-02 09:00)\n"),
+            ],
+        );
+        let scheduler = ReminderScheduler::default();
+        let texts = |scheduler: &ReminderScheduler| -> Vec<String> {
+            let reminders = scheduler.open_reminders(&root).unwrap();
+            reminders.into_iter().map(|r| r.text).collect()
+        };
+        assert_eq!(texts(&scheduler), ["Call", "Write"]);
+
+        fs::write(
+            root.join("A.md"),
+            "- [x] Called (remind:: 2030-01-01 09:00)\n",
+        )
+        .unwrap();
+        fs::remove_file(root.join("B.md")).unwrap();
+        fs::write(
+            root.join("C.md"),
+            "- [ ] Post (remind:: 2030-01-03 09:00)\n",
+        )
+        .unwrap();
+        assert_eq!(texts(&scheduler), ["Post"]);
+        assert_eq!(scheduler.notes.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,1342 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::files::write_note;
+use crate::markdown::{blank_code_spans, links, protected_lines};
+use crate::vault::index::{normalize_path, VaultIndex};
+use crate::vault::journal::Journal;
+use crate::vault::own_writes::OwnWrites;
+use crate::vault::portable::{self, PathRule, MAX_RELATIVE_PATH};
+use crate::vault::renames::{self, RenameLog};
+use crate::vault::settings::{FolderNoteStyle, VaultSettings};
+use crate::vault::write_locks::{WriteGuard, WriteLocks, LOCK_TIMEOUT};
+use crate::vault::{self, frecency, goals, locks, positions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameCase {
+    Lower,
+    Upper,
+    Title,
+    Preserve,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilenameStyle {
+    pub case: FilenameCase,
+    /// What goes between words: ` `, `-` or `_`.
+    pub space_char: char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameStatus {
+    /// Would be renamed; only in a dry run.
+    Planned,
+    Renamed,
+    /// Skipped because another note would end up with the same name.
+    Collision,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilenameChange {
+    pub old_path: String,
+    pub new_path: String,
+    pub status: RenameStatus,
+    /// The other notes normalizing to the same name, and any file that
+    /// already has it.
+    pub conflicts_with: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Links rewritten, or in a dry run that would be, after notes were renamed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkUpdates {
+    /// Notes whose links were rewritten.
+    pub notes: Vec<String>,
+    pub links: usize,
+    /// Notes whose links couldn't be updated.
+    pub failed: Vec<UpdateFailure>,
+    /// Locked notes that link to a moved file but were left untouched.
+    pub skipped_locked: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NormalizeReport {
+    pub changes: Vec<FilenameChange>,
+    pub link_updates: LinkUpdates,
+    /// For `undo_operation`; `None` in a dry run or when nothing changed.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathProblem {
+    pub rule: PathRule,
+    pub message: String,
+}
+
+/// A file or folder that can't be synced safely between platforms.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathIssue {
+    pub path: String,
+    pub is_directory: bool,
+    pub problems: Vec<PathProblem>,
+    /// A name that fixes every problem and clashes with nothing in the
+    /// folder, as a path to pass to `apply_path_fixes`.
+    pub suggested_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathFix {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathFixResult {
+    pub old_path: String,
+    pub new_path: String,
+    pub applied: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PathFixReport {
+    pub results: Vec<PathFixResult>,
+    pub link_updates: LinkUpdates,
+    /// For `undo_operation`; `None` when nothing changed.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameNoteReport {
+    pub old_path: String,
+    pub new_path: String,
+    /// Empty when links weren't updated.
+    pub link_updates: LinkUpdates,
+    /// For `undo_operation`.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedFile {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveFolderReport {
+    pub old_folder: String,
+    pub new_folder: String,
+    /// Every file in the folder, with where it ends up.
+    pub moved_files: Vec<MovedFile>,
+    /// Notes whose links were, or in a dry run would be, rewritten: at
+    /// their new paths after a move, at their current ones in a dry run.
+    pub link_updates: LinkUpdates,
+    /// For `undo_operation`; `None` in a dry run or when nothing changed.
+    pub operation_id: Option<String>,
+}
+
+/// Files and folders whose names or paths work on one platform but not
+/// another: characters or device names Windows rejects, trailing dots and
+/// spaces, overlong names and paths, and names that differ only by case
+/// from another in the same folder. Each comes with a suggested rename.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn audit_cross_platform_paths(vault_path: &str) -> Result<Vec<PathIssue>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let mut issues = Vec::new();
+    audit_dir(root, root, &mut issues);
+    Ok(issues)
+}
+
+fn audit_dir(root: &Path, dir: &Path, issues: &mut Vec<PathIssue>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(String, PathBuf, bool)> = entries
+        .flatten()
+        .map(|e| {
+            (
+                e.file_name().to_string_lossy().to_string(),
+                e.path(),
+                e.path().is_dir(),
+            )
+        })
+        .filter(|(name, _, _)| !vault::is_hidden(name))
+        .collect();
+    entries.sort();
+
+    // Names in use in this folder, ignoring case, so suggestions don't
+    // collide with each other or with what is already there.
+    let mut taken: HashSet<String> = entries
+        .iter()
+        .map(|(name, _, _)| name.to_lowercase())
+        .collect();
+    let mut first_with_name: HashMap<String, &str> = HashMap::new();
+    for (name, path, is_dir) in &entries {
+        let mut problems: Vec<PathProblem> = portable::name_problems(name)
+            .into_iter()
+            .map(|(rule, message)| PathProblem { rule, message })
+            .collect();
+        let length = vault::relative_path(root, path).chars().count();
+        if !is_dir && length > MAX_RELATIVE_PATH {
+            problems.push(PathProblem {
+                rule: PathRule::PathTooLong,
+                message: format!(
+                    "Path is {} characters, over the {} that are safe on Windows",
+                    length, MAX_RELATIVE_PATH
+                ),
+            });
+        }
+        match first_with_name.get(&name.to_lowercase()) {
+            Some(first) => problems.push(PathProblem {
+                rule: PathRule::CaseCollision,
+                message: format!("Differs only by case from \"{}\"", first),
+            }),
+            None => {
+                first_with_name.insert(name.to_lowercase(), name);
+            }
+        }
+
+        if !problems.is_empty() {
+            let mut suggestion = portable::safe_name(name);
+            if !is_dir && length > MAX_RELATIVE_PATH {
+                suggestion = portable::shorten(&suggestion, length - MAX_RELATIVE_PATH);
+            }
+            let suggestion = unused_name(&suggestion, &mut taken);
+            issues.push(PathIssue {
+                path: path.to_string_lossy().to_string(),
+                is_directory: *is_dir,
+                problems,
+                suggested_path: path
+                    .with_file_name(suggestion)
+                    .to_string_lossy()
+                    .to_string(),
+            });
+        }
+        if *is_dir {
+            audit_dir(root, path, issues);
+        }
+    }
+}
+
+/// `name`, or `name 2`, `name 3`... for the first not in `taken`, which it
+/// is then added to. The name being replaced is among those taken, so a
+/// name that only clashed by case gets a number.
+fn unused_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = format!("{} {}{}", stem, n, extension);
+        n += 1;
+    }
+    taken.insert(candidate.to_lowercase());
+    candidate
+}
+
+/// Applies renames chosen from `audit_cross_platform_paths`, updating links
+/// to the renamed notes and attachments. Deeper paths are renamed first, so
+/// fixes for a folder and for files inside it can be applied together. The
+/// fixes can be undone with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn apply_path_fixes(vault_path: &str, fixes: Vec<PathFix>) -> Result<PathFixReport, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let mut fixes = fixes;
+    for fix in &fixes {
+        for path in [&fix.old_path, &fix.new_path] {
+            let path = Path::new(path);
+            if !path.starts_with(root)
+                || path
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+            {
+                return Err(format!("Path is outside the vault: {}", path.display()));
+            }
+        }
+    }
+    fixes.sort_by_key(|fix| std::cmp::Reverse(Path::new(&fix.old_path).components().count()));
+
+    let moves: Vec<(PathBuf, PathBuf)> = fixes
+        .iter()
+        .map(|fix| (PathBuf::from(&fix.old_path), PathBuf::from(&fix.new_path)))
+        .collect();
+    let mut journal = Journal::start(root, "apply_path_fixes");
+    let outcome = rename_paths(root, &moves, false, Some(&mut journal));
+    let operation_id = journal.finish(&VaultSettings::load(root)?.journal)?;
+    let results = fixes
+        .into_iter()
+        .zip(outcome.errors)
+        .map(|(fix, error)| PathFixResult {
+            old_path: fix.old_path,
+            new_path: fix.new_path,
+            applied: error.is_none(),
+            error,
+        })
+        .collect();
+    Ok(PathFixReport {
+        results,
+        link_updates: outcome.updates,
+        operation_id,
+    })
+}
+
+/// Renames notes in the vault, or below `folder`, to a consistent style:
+/// words are split on spaces, `-` and `_`, stripped of punctuation like
+/// heading slugs are, recased and joined with `space_char`. Notes that would
+/// end up with the same name as each other or as an existing file, ignoring
+/// case as case-insensitive filesystems do, are all skipped. Links to the
+/// renamed notes are updated. With `dry_run` nothing is touched and the
+/// report shows what would happen; otherwise the renames can be undone
+/// with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn normalize_filenames(
+    vault_path: &str,
+    style: FilenameStyle,
+    folder: Option<String>,
+    dry_run: bool,
+) -> Result<NormalizeReport, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    if !matches!(style.space_char, ' ' | '-' | '_') {
+        return Err(format!(
+            "Unsupported word separator: {:?}",
+            style.space_char
+        ));
+    }
+    let settings = VaultSettings::load(root)?;
+    let folder = folder
+        .map(|f| f.trim_matches('/').to_string())
+        .filter(|f| !f.is_empty());
+    let proposed: Vec<(PathBuf, PathBuf)> = vault::notes(root, &settings)
+        .into_iter()
+        .filter(|note| {
+            folder
+                .as_deref()
+                .is_none_or(|folder| vault::is_within(&vault::relative_path(root, note), folder))
+        })
+        .filter_map(|note| {
+            let name = normalized_name(&note, &style)?;
+            let new = note.with_file_name(name);
+            (new != note).then_some((note, new))
+        })
+        .collect();
+
+    // Claims on each new name, and what is already in each folder, keyed by
+    // lowercased name so case-only differences count as collisions.
+    let key = |path: &Path| {
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    let mut claims: HashMap<(PathBuf, String), Vec<usize>> = HashMap::new();
+    for (idx, (_, new)) in proposed.iter().enumerate() {
+        let dir = new.parent().unwrap_or(root).to_path_buf();
+        claims.entry((dir, key(new))).or_default().push(idx);
+    }
+    let mut existing: HashMap<PathBuf, HashMap<String, PathBuf>> = HashMap::new();
+
+    let mut changes = Vec::new();
+    let mut moves = Vec::new();
+    for (idx, (old, new)) in proposed.iter().enumerate() {
+        let dir = new.parent().unwrap_or(root).to_path_buf();
+        let in_dir = existing.entry(dir.clone()).or_insert_with(|| {
+            fs::read_dir(&dir)
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .map(|e| (e.file_name().to_string_lossy().to_lowercase(), e.path()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        let mut conflicts: Vec<String> = claims[&(dir, key(new))]
+            .iter()
+            .filter(|&&other| other != idx)
+            .map(|&other| proposed[other].0.to_string_lossy().to_string())
+            .collect();
+        if let Some(there) = in_dir.get(&key(new)).filter(|there| *there != old) {
+            conflicts.push(there.to_string_lossy().to_string());
+        }
+        if conflicts.is_empty() {
+            moves.push((old.clone(), new.clone()));
+        }
+        changes.push(FilenameChange {
+            old_path: old.to_string_lossy().to_string(),
+            new_path: new.to_string_lossy().to_string(),
+            status: if conflicts.is_empty() {
+                RenameStatus::Planned
+            } else {
+                RenameStatus::Collision
+            },
+            conflicts_with: conflicts,
+            error: None,
+        });
+    }
+
+    let mut journal = Journal::start(root, "normalize_filenames");
+    let outcome = rename_paths(root, &moves, dry_run, Some(&mut journal));
+    let operation_id = if dry_run {
+        None
+    } else {
+        journal.finish(&settings.journal)?
+    };
+    if !dry_run {
+        let mut errors = outcome.errors.into_iter();
+        for change in changes
+            .iter_mut()
+            .filter(|c| c.status == RenameStatus::Planned)
+        {
+            let Some(error) = errors.next() else { break };
+            change.status = if error.is_some() {
+                RenameStatus::Failed
+            } else {
+                RenameStatus::Renamed
+            };
+            change.error = error;
+        }
+    }
+    Ok(NormalizeReport {
+        changes,
+        link_updates: outcome.updates,
+        operation_id,
+    })
+}
+
+/// Renames a note, and with `update_links` rewrites the links to it across
+/// the vault as `rename_paths` does. Paths are absolute or vault-relative.
+/// The rename fails as a whole if the note can't be moved; notes whose
+/// links couldn't be rewritten are listed in the report. Either way it can
+/// be undone with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn rename_note(
+    vault_path: String,
+    old_path: String,
+    new_path: String,
+    update_links: bool,
+) -> Result<RenameNoteReport, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let [old, new] = [&old_path, &new_path].map(|path| root.join(path));
+    for path in [&old, &new] {
+        if !path.starts_with(root)
+            || path
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            return Err(format!("Path is outside the vault: {}", path.display()));
+        }
+    }
+    if !old.is_file() {
+        return Err(format!("Note does not exist: {}", old_path));
+    }
+
+    let mut journal = Journal::start(root, "rename_note");
+    let link_updates = if update_links {
+        let outcome = rename_paths(
+            root,
+            &[(old.clone(), new.clone())],
+            false,
+            Some(&mut journal),
+        );
+        if let Some(Some(error)) = outcome.errors.into_iter().next() {
+            return Err(error);
+        }
+        outcome.updates
+    } else {
+        move_path(root, &old, &new)?;
+        journal.renamed(&old, &new);
+        LinkUpdates::default()
+    };
+    Ok(RenameNoteReport {
+        old_path: old.to_string_lossy().to_string(),
+        new_path: new.to_string_lossy().to_string(),
+        link_updates,
+        operation_id: journal.finish(&VaultSettings::load(root)?.journal)?,
+    })
+}
+
+/// Moves or renames a folder, keeping everything that points into it
+/// working: links in notes inside and outside it are rewritten first, as
+/// `rename_paths` does, then the folder is moved, with its same-name folder
+/// note if the vault uses them. Goals, frecency and the rename history,
+/// through which saved pins, workspaces and graph layouts find their notes,
+/// follow it. Paths are absolute or vault-relative. Locked notes linking
+/// into the folder are left alone and listed in `skipped_locked`. If a link
+/// can't be rewritten or the move fails, the rewrites already made are
+/// undone and nothing is moved. With `dry_run` nothing is touched and the
+/// report lists every file that would be; otherwise the move can be undone
+/// with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn move_folder(
+    vault_path: String,
+    old_folder: String,
+    new_folder: String,
+    dry_run: bool,
+) -> Result<MoveFolderReport, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let [old, new] = [&old_folder, &new_folder].map(|path| root.join(path.trim_end_matches('/')));
+    for path in [&old, &new] {
+        if path == root
+            || !path.starts_with(root)
+            || path
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            return Err(format!("Path is outside the vault: {}", path.display()));
+        }
+    }
+    if !old.is_dir() {
+        return Err(format!("Folder does not exist: {}", old_folder));
+    }
+    if old == new {
+        return Ok(MoveFolderReport {
+            old_folder: old.to_string_lossy().to_string(),
+            new_folder: new.to_string_lossy().to_string(),
+            moved_files: Vec::new(),
+            link_updates: LinkUpdates::default(),
+            operation_id: None,
+        });
+    }
+    let case_only = old.to_string_lossy().to_lowercase() == new.to_string_lossy().to_lowercase();
+    if new.starts_with(&old) && !case_only {
+        return Err(format!("Can't move a folder into itself: {}", new_folder));
+    }
+    if new.exists() && !case_only {
+        return Err(format!(
+            "Destination path already exists: {}",
+            new.display()
+        ));
+    }
+    if let Some(locked) = locks::locked_within(Some(root), &old).first() {
+        return Err(format!("Note is locked: {}", locked));
+    }
+
+    let settings = VaultSettings::load(root)?;
+    let mut moves = vec![(old.clone(), new.clone())];
+    if let Some(folder_note) = folder_note_move(&settings, &old, &new) {
+        moves.push(folder_note);
+    }
+    let moved_files = vault::files_where(&old, |_| true)
+        .into_iter()
+        .map(|file| MovedFile {
+            new_path: moved_path(&moves, &file)
+                .unwrap_or_else(|| file.clone())
+                .to_string_lossy()
+                .to_string(),
+            old_path: file.to_string_lossy().to_string(),
+        })
+        .collect();
+
+    let index = VaultIndex::build(root, &vault::markdown_files(root));
+    let _locks = if dry_run {
+        None
+    } else {
+        Some(lock_moves(&index, &moves, "move_folder")?)
+    };
+    let mut journal = Journal::start(root, "move_folder");
+    let shared_names = shared_attachment_names(root, &moves);
+    let mut link_updates = rewrite_links(
+        root,
+        &index,
+        &shared_names,
+        &moves,
+        false,
+        dry_run,
+        Some(&mut journal),
+    );
+    let mut report = MoveFolderReport {
+        old_folder: old.to_string_lossy().to_string(),
+        new_folder: new.to_string_lossy().to_string(),
+        moved_files,
+        link_updates: LinkUpdates::default(),
+        operation_id: None,
+    };
+    if dry_run {
+        report.link_updates = link_updates;
+        return Ok(report);
+    }
+
+    let failure = match link_updates.failed.first() {
+        Some(failed) => Some(format!(
+            "Failed to update links in {}: {}",
+            failed.path, failed.error
+        )),
+        None => moves
+            .iter()
+            .find_map(|(from, to)| match move_path(root, from, to) {
+                Ok(()) => {
+                    journal.renamed(from, to);
+                    None
+                }
+                Err(e) => Some(e),
+            }),
+    };
+    let operation_id = journal.finish(&settings.journal)?;
+    if let Some(error) = failure {
+        let Some(id) = operation_id else {
+            return Err(error);
+        };
+        let undone = super::journal::undo_operation(&vault_path, &id)?;
+        if undone.undone && undone.failed.is_empty() {
+            return Err(format!("{}; link updates were rolled back", error));
+        }
+        return Err(format!(
+            "{}; rolling back failed, undo operation {} to retry",
+            error, id
+        ));
+    }
+    for note in link_updates.notes.iter_mut() {
+        if let Some(new) = moved_path(&moves, Path::new(note)) {
+            *note = new.to_string_lossy().to_string();
+        }
+    }
+    report.link_updates = link_updates;
+    report.operation_id = operation_id;
+    Ok(report)
+}
+
+/// Where a note or folder saved as `old_path` (absolute or vault-relative)
+/// is now, following the vault's rename history. Returns the path in the
+/// form it was given, or `None` when it can't be found.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn resolve_moved_path(vault_path: &str, old_path: &str) -> Result<Option<String>, String> {
+    let root = Path::new(vault_path);
+    let given = Path::new(old_path);
+    let rel = vault::relative_path(root, given);
+    let current = RenameLog::load(root)?.follow(&rel, 0, u64::MAX);
+    let found = [current, rel]
+        .into_iter()
+        .find(|rel| root.join(rel).exists());
+    Ok(found.map(|rel| {
+        if given.is_absolute() {
+            root.join(rel).to_string_lossy().to_string()
+        } else {
+            rel
+        }
+    }))
+}
+
+/// Renaming a folder from `old` to `new` also renames its same-name folder
+/// note, e.g. `Beta/Alpha.md` to `Beta/Beta.md`, when the vault uses them
+/// and nothing has the new name yet. The move is from inside `new`, to
+/// follow the folder's.
+fn folder_note_move(
+    settings: &VaultSettings,
+    old: &Path,
+    new: &Path,
+) -> Option<(PathBuf, PathBuf)> {
+    if settings.folder_notes != Some(FolderNoteStyle::SameName) {
+        return None;
+    }
+    let note = FolderNoteStyle::SameName.note_path(old)?;
+    let target = FolderNoteStyle::SameName.note_path(new)?;
+    let unmoved_target = old.join(target.file_name()?);
+    if !note.is_file() || note.file_name() == target.file_name() || unmoved_target.exists() {
+        return None;
+    }
+    Some((new.join(note.file_name()?), target))
+}
+
+/// `note`'s file name in `style`, or `None` when nothing of it is left.
+fn normalized_name(note: &Path, style: &FilenameStyle) -> Option<String> {
+    let stem = note.file_stem()?.to_string_lossy();
+    let extension = note.extension()?.to_string_lossy().to_lowercase();
+    let words: Vec<String> = stem
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .map(|word| match style.case {
+            FilenameCase::Lower => word.to_lowercase(),
+            FilenameCase::Upper => word.to_uppercase(),
+            FilenameCase::Title => {
+                let mut chars = word.chars();
+                let first: String = chars
+                    .next()
+                    .into_iter()
+                    .flat_map(char::to_uppercase)
+                    .collect();
+                first + &chars.as_str().to_lowercase()
+            }
+            FilenameCase::Preserve => word,
+        })
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}.{}",
+        words.join(&style.space_char.to_string()),
+        extension
+    ))
+}
+
+pub(crate) struct RenameOutcome {
+    /// For each move, in order, why it wasn't made.
+    pub errors: Vec<Option<String>>,
+    pub updates: LinkUpdates,
+}
+
+/// Moves notes, attachments or folders, in order, and rewrites the links to
+/// the notes across the vault, keeping each link's form: a bare `[[name]]`
+/// gets the new name, one with a path the new path, and markdown links a
+/// new relative (or `/`-rooted) target. Anchors and aliases are kept, and
+/// wikilinks that resolved by title or alias are left alone. Markdown links
+/// in moved notes are updated for their new folder, and links and embeds
+/// of moved attachments, wiki or markdown, for the attachment's new place.
+/// Files are moved first, so a move that fails leaves its links as they
+/// were. Locked notes keep their links and are listed in `skipped_locked`.
+/// With `dry_run` only the link updates are counted. With a `journal`, the
+/// moves and rewrites are recorded so they can be undone.
+pub(crate) fn rename_paths(
+    root: &Path,
+    moves: &[(PathBuf, PathBuf)],
+    dry_run: bool,
+    mut journal: Option<&mut Journal>,
+) -> RenameOutcome {
+    let index = VaultIndex::build(root, &vault::markdown_files(root));
+    let shared_names = shared_attachment_names(root, moves);
+    let operation = journal.as_deref().map_or("rename_paths", Journal::kind);
+    let _locks = match (!dry_run)
+        .then(|| lock_moves(&index, moves, operation))
+        .transpose()
+    {
+        Ok(locks) => locks,
+        Err(busy) => {
+            return RenameOutcome {
+                errors: moves.iter().map(|_| Some(busy.clone())).collect(),
+                updates: LinkUpdates::default(),
+            }
+        }
+    };
+    let mut errors = Vec::new();
+    let mut done = Vec::new();
+    for (old, new) in moves {
+        let result = if dry_run {
+            Ok(())
+        } else {
+            move_path(root, old, new)
+        };
+        if result.is_ok() {
+            if let Some(journal) = journal.as_deref_mut().filter(|_| !dry_run) {
+                journal.renamed(old, new);
+            }
+            done.push((old.clone(), new.clone()));
+        }
+        errors.push(result.err());
+    }
+
+    if done.is_empty() {
+        return RenameOutcome {
+            errors,
+            updates: LinkUpdates::default(),
+        };
+    }
+    let updates = rewrite_links(
+        root,
+        &index,
+        &shared_names,
+        &done,
+        !dry_run,
+        dry_run,
+        journal,
+    );
+    RenameOutcome { errors, updates }
+}
+
+/// Rewrites the links affected by `moves` across the vault, as indexed in
+/// `index` before them. With `files_moved` the notes are read and written at
+/// their new paths, otherwise at their old ones, so links can be updated
+/// before the files move.
+fn rewrite_links(
+    root: &Path,
+    index: &VaultIndex,
+    shared_names: &HashSet<String>,
+    moves: &[(PathBuf, PathBuf)],
+    files_moved: bool,
+    dry_run: bool,
+    mut journal: Option<&mut Journal>,
+) -> LinkUpdates {
+    let mut updates = LinkUpdates::default();
+    let moved = moved_notes(index, moves);
+    for from in link_sources(index, moves, &moved, files_moved) {
+        let old_path = &index.notes[from].path;
+        let path = if files_moved {
+            moved.get(&from).unwrap_or(old_path)
+        } else {
+            old_path
+        };
+        let result = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|content| {
+                let rewrite = LinkRewrite {
+                    root,
+                    index,
+                    moves,
+                    moved: &moved,
+                    shared_names,
+                    from,
+                };
+                let (updated, count) = rewrite.apply(&content);
+                if count > 0 && locks::is_locked(Some(root), path) {
+                    updates
+                        .skipped_locked
+                        .push(path.to_string_lossy().to_string());
+                    return Ok(0);
+                }
+                if count > 0 && !dry_run {
+                    match journal.as_deref_mut() {
+                        Some(journal) => journal.write(path, || write_note(path, updated)),
+                        None => write_note(path, updated),
+                    }
+                    .map_err(|e| e.to_string())?;
+                }
+                Ok(count)
+            });
+        match result {
+            Ok(0) => {}
+            Ok(count) => {
+                updates.notes.push(path.to_string_lossy().to_string());
+                updates.links += count;
+            }
+            Err(error) => updates.failed.push(UpdateFailure {
+                path: path.to_string_lossy().to_string(),
+                error,
+            }),
+        }
+    }
+    updates
+}
+
+/// The indexed notes `moves` carry somewhere else, by index position, with
+/// where they end up.
+fn moved_notes(index: &VaultIndex, moves: &[(PathBuf, PathBuf)]) -> HashMap<usize, PathBuf> {
+    index
+        .notes
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, note)| Some((idx, moved_path(moves, &note.path)?)))
+        .collect()
+}
+
+/// The notes whose links `rewrite_links` may change. Notes that moved may
+/// have relative links to fix even when nothing resolves to them, and any
+/// note may link to a moved attachment.
+fn link_sources(
+    index: &VaultIndex,
+    moves: &[(PathBuf, PathBuf)],
+    moved: &HashMap<usize, PathBuf>,
+    files_moved: bool,
+) -> BTreeSet<usize> {
+    (0..index.notes.len())
+        .filter(|&from| {
+            moved.contains_key(&from)
+                || index.notes[from].links.iter().any(|link| {
+                    index
+                        .resolve(from, link)
+                        .is_some_and(|to| moved.contains_key(&to))
+                })
+        })
+        .chain(moved_attachment_sources(index, moves, files_moved))
+        .collect()
+}
+
+/// Locks every file `moves` and the link rewrites after them will touch,
+/// where it is and where it ends up, before the first is moved. Called
+/// while nothing has moved yet.
+fn lock_moves(
+    index: &VaultIndex,
+    moves: &[(PathBuf, PathBuf)],
+    operation: &str,
+) -> Result<WriteGuard<'static>, String> {
+    let moved = moved_notes(index, moves);
+    let mut files: Vec<PathBuf> = link_sources(index, moves, &moved, false)
+        .into_iter()
+        .map(|idx| index.notes[idx].path.clone())
+        .collect();
+    for (old, new) in moves {
+        files.extend([old.clone(), new.clone()]);
+        if old.is_dir() {
+            files.extend(vault::files_where(old, |_| true));
+        }
+    }
+    let arrived: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|file| moved_path(moves, file))
+        .collect();
+    files.extend(arrived);
+    WriteLocks::global()
+        .lock_all(files, operation, LOCK_TIMEOUT)
+        .map_err(|busy| busy.to_string())
+}
+
+/// Where `path` ends up after `moves`, applied in order, or `None` if it
+/// stays put. A move of a folder carries everything below it.
+fn moved_path(moves: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+    let mut current = path.to_path_buf();
+    for (old, new) in moves {
+        if let Ok(rest) = current.strip_prefix(old) {
+            current = if rest.as_os_str().is_empty() {
+                new.clone()
+            } else {
+                new.join(rest)
+            };
+        }
+    }
+    (current != path).then_some(current)
+}
+
+/// Lowercased names of the attachments among `moves` that more than one
+/// file in the vault has. `[[name]]` can't say which of them it means.
+fn shared_attachment_names(root: &Path, moves: &[(PathBuf, PathBuf)]) -> HashSet<String> {
+    let names: HashSet<String> = moves
+        .iter()
+        .filter(|(old, _)| old.is_file() && !vault::is_markdown(old))
+        .filter_map(|(old, _)| old.file_name().map(|n| n.to_string_lossy().to_lowercase()))
+        .collect();
+    if names.is_empty() {
+        return HashSet::new();
+    }
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in vault::files_where(root, |p| !vault::is_markdown(p)) {
+        let name = file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        if names.contains(&name) {
+            *counts.entry(name).or_insert(0) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Notes that may link to a moved attachment: any whose text mentions the
+/// attachment's file name, read where it is now. Which links really point
+/// at it is worked out when the note is rewritten.
+fn moved_attachment_sources(
+    index: &VaultIndex,
+    moves: &[(PathBuf, PathBuf)],
+    files_moved: bool,
+) -> Vec<usize> {
+    let names: Vec<String> = moves
+        .iter()
+        .filter(|(old, _)| !vault::is_markdown(old))
+        .filter_map(|(old, _)| old.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect();
+    if names.is_empty() {
+        return Vec::new();
+    }
+    index
+        .notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| {
+            let path = moved_path(moves, &note.path)
+                .filter(|_| files_moved)
+                .unwrap_or_else(|| note.path.clone());
+            fs::read_to_string(path).is_ok_and(|content| {
+                names.iter().any(|name| {
+                    content.contains(name.as_str()) || content.contains(&links::encode_target(name))
+                })
+            })
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Renames one file or folder, refusing locked notes and existing
+/// destinations. A rename that only changes case goes through a temporary
+/// name, since a case-insensitive filesystem would otherwise treat it as a
+/// no-op.
+pub(super) fn move_path(root: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    if !old.exists() {
+        return Err(format!("Source path does not exist: {}", old.display()));
+    }
+    if let Some(locked) = locks::locked_within(Some(root), old).first() {
+        return Err(format!("Note is locked: {}", locked));
+    }
+    let case_only =
+        old != new && old.to_string_lossy().to_lowercase() == new.to_string_lossy().to_lowercase();
+    if new.exists() && !case_only {
+        return Err(format!(
+            "Destination path already exists: {}",
+            new.display()
+        ));
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    if case_only {
+        let name = old.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = old.with_file_name(format!(".{}.renaming", name));
+        fs::rename(old, &tmp).map_err(|e| format!("Failed to rename: {}", e))?;
+        if let Err(e) = fs::rename(&tmp, new) {
+            let _ = fs::rename(&tmp, old);
+            return Err(format!("Failed to rename: {}", e));
+        }
+    } else {
+        fs::rename(old, new).map_err(|e| format!("Failed to rename: {}", e))?;
+    }
+    OwnWrites::global().record(new);
+    goals::rename(root, old, new)?;
+    frecency::rename(root, old, new)?;
+    positions::rename(root, old, new)?;
+    renames::record(root, old, new)
+}
+
+/// Rewrites the links in one note, `from`, for a set of moves.
+struct LinkRewrite<'a> {
+    root: &'a Path,
+    index: &'a VaultIndex,
+    /// The moves made, for attachments.
+    moves: &'a [(PathBuf, PathBuf)],
+    /// Where each moved note went.
+    moved: &'a HashMap<usize, PathBuf>,
+    /// Lowercased names of moved attachments that other files also have.
+    shared_names: &'a HashSet<String>,
+    from: usize,
+}
+
+impl LinkRewrite<'_> {
+    fn apply(&self, content: &str) -> (String, usize) {
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let trimmed: Vec<&str> = lines
+            .iter()
+            .map(|l| l.trim_end_matches(['\n', '\r']))
+            .collect();
+        let protected = protected_lines(&trimmed);
+        let mut out = String::with_capacity(content.len());
+        let mut count = 0;
+        for (idx, line) in lines.iter().enumerate() {
+            let text = trimmed[idx];
+            if protected[idx] || !(text.contains("[[") || text.contains("](")) {
+                out.push_str(line);
+                continue;
+            }
+            let mut edits = self.edits(text);
+            count += edits.len();
+            edits.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+            let mut rewritten = text.to_string();
+            for (start, end, replacement) in edits {
+                rewritten.replace_range(start..end, &replacement);
+            }
+            out.push_str(&rewritten);
+            out.push_str(&line[text.len()..]);
+        }
+        (out, count)
+    }
+
+    /// Byte ranges within `line` to replace, with their replacements.
+    fn edits(&self, line: &str) -> Vec<(usize, usize, String)> {
+        let scrubbed = blank_code_spans(line);
+        let mut edits = Vec::new();
+        for link in links::wikilinks(&scrubbed) {
+            if let Some(target) = self.wiki_target(&link.target) {
+                let mut link = link.clone();
+                link.target = target;
+                // Take the anchor and alias from the line itself, not its
+                // code-blanked copy.
+                let original = links::wikilinks(&line[link.start..link.end]);
+                if let Some(original) = original.first() {
+                    link.anchor = original.anchor.clone();
+                    link.alias = original.alias.clone();
+                }
+                edits.push((link.start, link.end, link.render()));
+            }
+        }
+        for link in links::markdown_links(&scrubbed) {
+            if let Some(target) = self.markdown_target(&link.target) {
+                edits.push((link.target_start, link.target_end, target));
+            }
+        }
+        edits
+    }
+
+    fn wiki_target(&self, target: &str) -> Option<String> {
+        match self.index.resolve_wikilink(self.from, target) {
+            Some(to) => self.note_wiki_target(to, target),
+            None => self.attachment_wiki_target(target),
+        }
+    }
+
+    /// `[[image.png]]` by name, or `[[assets/image.png]]` by path from the
+    /// vault root. A bare name that several files share is left alone.
+    fn attachment_wiki_target(&self, target: &str) -> Option<String> {
+        let target = target.trim();
+        if Path::new(target).extension().is_none() || vault::is_markdown(Path::new(target)) {
+            return None;
+        }
+        if target.contains('/') {
+            let new = moved_path(self.moves, &self.root.join(target.trim_start_matches('/')))?;
+            let rooted = if target.starts_with('/') { "/" } else { "" };
+            return Some(format!(
+                "{}{}",
+                rooted,
+                vault::relative_path(self.root, &new)
+            ));
+        }
+        let lowered = target.to_lowercase();
+        if self.shared_names.contains(&lowered) {
+            return None;
+        }
+        let file_name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string());
+        let (_, new) = self
+            .moves
+            .iter()
+            .find(|(old, _)| file_name(old).is_some_and(|name| name.to_lowercase() == lowered))?;
+        file_name(new).filter(|name| *name != target)
+    }
+
+    fn note_wiki_target(&self, to: usize, target: &str) -> Option<String> {
+        let new = self.moved.get(&to)?;
+        let old = &self.index.notes[to].path;
+
+        let target = target.trim();
+        let (bare, extension) = match target.rsplit_once('.') {
+            Some((bare, ext)) if vault::is_markdown(Path::new(target)) => (bare, Some(ext)),
+            _ => (target, None),
+        };
+        let with_extension = |name: String| match extension {
+            Some(ext) => format!("{}.{}", name, ext),
+            None => name,
+        };
+        let lowered = bare.to_lowercase();
+        if lowered.contains('/') {
+            let old_rel = vault::relative_path(self.root, &old.with_extension(""));
+            if lowered.trim_start_matches('/') != old_rel.to_lowercase() {
+                return None;
+            }
+            let rooted = if bare.starts_with('/') { "/" } else { "" };
+            let new_rel = vault::relative_path(self.root, &new.with_extension(""));
+            Some(with_extension(format!("{}{}", rooted, new_rel)))
+        } else {
+            let old_stem = old.file_stem()?.to_string_lossy().to_lowercase();
+            if lowered != old_stem {
+                return None;
+            }
+            Some(with_extension(
+                new.file_stem()?.to_string_lossy().to_string(),
+            ))
+        }
+    }
+
+    fn markdown_target(&self, target: &str) -> Option<String> {
+        if target.is_empty() || target.starts_with('#') || links::is_external(target) {
+            return None;
+        }
+        let split = target.find(['#', '?']).unwrap_or(target.len());
+        let (path_part, suffix) = target.split_at(split);
+        let decoded = links::decode_target(path_part);
+        let old_source = &self.index.notes[self.from].path;
+        let new_source = self.moved.get(&self.from);
+
+        let (old_target, new_target) = if vault::is_markdown(Path::new(&decoded)) {
+            let to = self.index.resolve_markdown_link(self.from, path_part)?;
+            let old = self.index.notes[to].path.clone();
+            let new = self.moved.get(&to).cloned().unwrap_or_else(|| old.clone());
+            (old, new)
+        } else {
+            let old = match decoded.strip_prefix('/') {
+                Some(rooted) => self.root.join(rooted),
+                None => old_source.parent().unwrap_or(self.root).join(&decoded),
+            };
+            let old = normalize_path(&old);
+            let new = moved_path(self.moves, &old).unwrap_or_else(|| old.clone());
+            (old, new)
+        };
+        if new_target == old_target
+            && new_source.is_none_or(|new| new.parent() == old_source.parent())
+        {
+            return None;
+        }
+
+        let new_source = new_source.unwrap_or(old_source);
+        let written = if path_part.starts_with('/') {
+            format!("/{}", vault::relative_path(self.root, &new_target))
+        } else {
+            vault::relative_link(new_source.parent().unwrap_or(self.root), &new_target)
+        };
+        // Spaces written as they are only happen inside `<...>`; keep them.
+        let written = if path_part.contains(' ') {
+            written
+        } else {
+            links::encode_target(&written)
+        };
+        Some(format!("{}{}", written, suffix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    /// Renames `old` to `new` in `root`, updating links, and returns what
+    /// `rename_paths` reported along with the content of `linking`.
+    fn rename(root: &TempVault, old: &str, new: &str, linking: &str) -> (LinkUpdates, String) {
+        let outcome = rename_paths(root, &[(root.join(old), root.join(new))], false, None);
+        assert_eq!(outcome.errors, [None]);
+        let content = fs::read_to_string(root.join(linking)).unwrap();
+        (outcome.updates, content)
+    }
+
+    #[test]
+    fn a_wikilink_keeps_its_alias_and_heading() {
+        let root = TempVault::new(
+            "rename-wiki",
+            &[
+                ("Old.md", "# Old\n"),
+                ("sub/Deep.md", "# Deep\n\n## Heading\n"),
+                ("Links.md", "[[Old|alias]] and [[sub/Deep#Heading|there]]\n"),
+            ],
+        );
+        rename(&root, "Old.md", "New.md", "Links.md");
+        let (updates, content) = rename(&root, "sub/Deep.md", "sub/Deeper.md", "Links.md");
+        assert_eq!(content, "[[New|alias]] and [[sub/Deeper#Heading|there]]\n");
+        assert_eq!(updates.links, 1);
+    }
+
+    #[test]
+    fn relative_and_encoded_markdown_links_keep_their_form() {
+        let root = TempVault::new(
+            "rename-markdown",
+            &[
+                ("Old Name.md", "# Old\n"),
+                (
+                    "a/Rel.md",
+                    "[r](../Old%20Name.md#top) [s](<../Old Name.md>)\n",
+                ),
+                ("Links.md", "[e](<Old%20Name.md>) [f](Old%20Name.md)\n"),
+            ],
+        );
+        let (updates, content) = rename(&root, "Old Name.md", "New Name.md", "a/Rel.md");
+        assert_eq!(content, "[r](../New%20Name.md#top) [s](<../New Name.md>)\n");
+        assert_eq!(
+            fs::read_to_string(root.join("Links.md")).unwrap(),
+            "[e](<New%20Name.md>) [f](New%20Name.md)\n"
+        );
+        assert_eq!(updates.links, 4);
+    }
+
+    #[test]
+    fn a_case_only_rename_updates_links() {
+        let root = TempVault::new(
+            "rename-case",
+            &[("old name.md", "# Old\n"), ("Links.md", "[[old name]]\n")],
+        );
+        let (_, content) = rename(&root, "old name.md", "Old Name.md", "Links.md");
+        assert_eq!(content, "[[Old Name]]\n");
+        let names: Vec<_> = fs::read_dir(&*root)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(names.contains(&"Old Name.md".to_string()));
+        assert!(!names.contains(&"old name.md".to_string()));
+    }
+
+    #[test]
+    fn a_locked_linking_note_is_reported_and_left_alone() {
+        let root = TempVault::new(
+            "rename-locked-note",
+            &[("Old.md", "# Old\n"), ("Locked.md", "[[Old]]\n")],
+        );
+        locks::set_locked(&root, &root.join("Locked.md"), true).unwrap();
+        let (updates, content) = rename(&root, "Old.md", "New.md", "Locked.md");
+        assert_eq!(content, "[[Old]]\n");
+        assert_eq!(
+            updates.skipped_locked,
+            [root.join("Locked.md").to_string_lossy().to_string()]
+        );
+        assert!(updates.notes.is_empty());
+    }
+
+    #[test]
+    fn an_unreadable_linking_note_is_listed_as_failed() {
+        let root = TempVault::new(
+            "rename-unreadable",
+            &[("Old.md", "# Old\n"), ("Links.md", "[[Old]]\n")],
+        );
+        let index = VaultIndex::build(&root, &vault::markdown_files(&root));
+        // Swapped for a folder after indexing, so reading it fails as a
+        // vanished or unreadable note would.
+        fs::remove_file(root.join("Links.md")).unwrap();
+        fs::create_dir(root.join("Links.md")).unwrap();
+        let moves = [(root.join("Old.md"), root.join("New.md"))];
+        let updates = rewrite_links(&root, &index, &HashSet::new(), &moves, false, false, None);
+        assert_eq!(updates.failed.len(), 1);
+        assert_eq!(
+            updates.failed[0].path,
+            root.join("Links.md").to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn moving_a_folder_skips_locked_notes_linking_into_it() {
+        let root = TempVault::new(
+            "rename-locked",
+            &[
+                ("Old/Target.md", "# Target\n"),
+                ("Open.md", "See [[Old/Target]].\n"),
+                ("Locked.md", "See [[Old/Target]].\n"),
+            ],
+        );
+        locks::set_locked(&root, &root.join("Locked.md"), true).unwrap();
+        let vault_path = root.to_string_lossy().to_string();
+        let move_to_new =
+            |dry_run| move_folder(vault_path.clone(), "Old".into(), "New".into(), dry_run).unwrap();
+
+        let planned = move_to_new(true);
+        let locked = vec![root.join("Locked.md").to_string_lossy().to_string()];
+        assert_eq!(planned.link_updates.skipped_locked, locked);
+        assert_eq!(planned.link_updates.links, 1);
+
+        let moved = move_to_new(false);
+        assert_eq!(moved.link_updates.skipped_locked, locked);
+        assert!(moved.link_updates.failed.is_empty());
+        assert!(root.join("New/Target.md").is_file());
+        let read = |name: &str| fs::read_to_string(root.join(name)).unwrap();
+        assert_eq!(read("Open.md"), "See [[New/Target]].\n");
+        assert_eq!(read("Locked.md"), "See [[Old/Target]].\n");
+    }
+}
@@ -0,0 +1,260 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::attachments::resolve_local_target;
+use crate::markdown::render::{self, escape, Highlighting, Resolver, DEFAULT_THEME};
+use crate::markdown::{self, frontmatter, links, slug};
+use crate::vault::{self, index::VaultIndex};
+
+/// Embeds nested deeper than this, or embedding themselves, are left as
+/// placeholders.
+const MAX_EMBED_DEPTH: usize = 4;
+
+// What `encodeURIComponent`, and so Tauri's `convertFileSrc`, leaves alone.
+pub(crate) const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RenderHtmlOptions {
+    pub highlighting: Highlighting,
+    /// A syntect theme name; `InspiredGitHub` when unset.
+    pub theme: Option<String>,
+    /// Return a complete HTML document with its CSS inlined, for printing
+    /// and export, rather than a fragment.
+    pub standalone: bool,
+    /// The app theme's CSS, added to standalone documents.
+    pub css: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderedNote {
+    pub title: String,
+    pub html: String,
+    /// Stylesheet for class-based highlighting; `None` with inline styles.
+    pub syntax_css: Option<String>,
+}
+
+/// Renders a note to HTML: embeds are inlined, code blocks highlighted and
+/// local images pointed at the webview's asset protocol. The preview pane,
+/// printing and HTML export all use this so their output matches.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn render_note_html(
+    vault_path: &str,
+    note_path: &str,
+    options: Option<RenderHtmlOptions>,
+) -> Result<RenderedNote, String> {
+    let root = Path::new(vault_path);
+    let note = Path::new(note_path);
+    let options = options.unwrap_or_default();
+    let theme = options.theme.as_deref().unwrap_or(DEFAULT_THEME);
+
+    let content = fs::read_to_string(note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (fm, split) = frontmatter::parse_note(&content)?;
+    let title = markdown::note_title(note, &fm, split.body);
+
+    let index = VaultIndex::build(root, &vault::markdown_files(root));
+    let from = index
+        .position(note)
+        .ok_or_else(|| format!("Note is not in the vault: {}", note_path))?;
+    let mut resolver = VaultResolver {
+        root,
+        index: &index,
+        attachments: None,
+        stack: vec![from],
+        highlighting: options.highlighting,
+        theme,
+    };
+    let body = render::to_html(split.body, options.highlighting, theme, &mut resolver)?;
+    let syntax_css = match options.highlighting {
+        Highlighting::Classes => Some(render::syntax_css(theme)?),
+        Highlighting::Inline => None,
+    };
+
+    let html = if options.standalone {
+        format!(
+            concat!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+                "<title>{}</title>\n<style>\n{}\n{}\n</style>\n</head>\n",
+                "<body>\n<article class=\"markdown-body\">\n{}</article>\n</body>\n</html>\n",
+            ),
+            escape(&title),
+            syntax_css.as_deref().unwrap_or_default(),
+            options.css.as_deref().unwrap_or_default(),
+            body
+        )
+    } else {
+        body
+    };
+    Ok(RenderedNote {
+        title,
+        html,
+        syntax_css,
+    })
+}
+
+struct VaultResolver<'a> {
+    root: &'a Path,
+    index: &'a VaultIndex,
+    /// Attachments by lowercased file name, gathered on first use.
+    attachments: Option<HashMap<String, Vec<PathBuf>>>,
+    /// The note being rendered and the notes embedding it.
+    stack: Vec<usize>,
+    highlighting: Highlighting,
+    theme: &'a str,
+}
+
+impl VaultResolver<'_> {
+    fn current(&self) -> usize {
+        self.stack.last().copied().unwrap_or(0)
+    }
+
+    fn resolve(&self, name: &str) -> Option<usize> {
+        if name.is_empty() {
+            Some(self.current())
+        } else {
+            self.index.resolve_wikilink(self.current(), name)
+        }
+    }
+
+    fn href(&self, to: usize, anchor: Option<&str>) -> String {
+        let mut href =
+            links::encode_target(&vault::relative_path(self.root, &self.index.notes[to].path));
+        match anchor {
+            Some(block) if block.starts_with('^') => {
+                href.push('#');
+                href.push_str(block);
+            }
+            Some(heading) => {
+                href.push('#');
+                href.push_str(&slug(heading));
+            }
+            None => {}
+        }
+        href
+    }
+
+    fn attachment(&mut self, name: &str) -> Option<PathBuf> {
+        let rooted = self.root.join(name.trim_start_matches('/'));
+        if name.contains('/') && rooted.is_file() {
+            return Some(rooted);
+        }
+        let root = self.root;
+        let from_dir = self.index.notes[self.current()].path.parent();
+        let by_name = self.attachments.get_or_insert_with(|| {
+            let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+            for file in vault::files_where(root, |p| !vault::is_markdown(p)) {
+                if let Some(file_name) = file.file_name() {
+                    by_name
+                        .entry(file_name.to_string_lossy().to_lowercase())
+                        .or_default()
+                        .push(file);
+                }
+            }
+            by_name
+        });
+        let file_name = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+        by_name
+            .get(&file_name)?
+            .iter()
+            .min_by_key(|path| (path.parent() != from_dir, path.components().count()))
+            .cloned()
+    }
+}
+
+impl Resolver for VaultResolver<'_> {
+    fn link(&mut self, target: &str) -> Option<String> {
+        let (name, anchor) = split_anchor(target);
+        let to = self.resolve(name)?;
+        Some(self.href(to, anchor))
+    }
+
+    fn embed(&mut self, target: &str) -> Option<String> {
+        let (name, anchor) = split_anchor(target);
+        if Path::new(name).extension().is_some() && !vault::is_markdown(Path::new(name)) {
+            return None;
+        }
+        let placeholder = |class: &str| {
+            Some(format!(
+                "<div class=\"embed {}\" data-target=\"{}\">{}</div>",
+                class,
+                escape(target),
+                escape(target)
+            ))
+        };
+        let Some(to) = self.resolve(name) else {
+            return placeholder("is-unresolved");
+        };
+        if (self.stack.contains(&to) && anchor.is_none()) || self.stack.len() > MAX_EMBED_DEPTH {
+            return placeholder("is-collapsed");
+        }
+
+        let Ok(content) = fs::read_to_string(&self.index.notes[to].path) else {
+            return placeholder("is-unresolved");
+        };
+        let body = frontmatter::split(&content).body;
+        let part = match anchor {
+            Some(anchor) => match render::embedded_part(body, anchor) {
+                Some(part) => part,
+                None => return placeholder("is-unresolved"),
+            },
+            None => body.to_string(),
+        };
+
+        self.stack.push(to);
+        let rendered = render::to_html(&part, self.highlighting, self.theme, self);
+        self.stack.pop();
+        let href = self.href(to, anchor);
+        Some(format!(
+            "<div class=\"embed\" data-href=\"{}\">\n{}</div>",
+            escape(&href),
+            rendered.ok()?
+        ))
+    }
+
+    fn image(&mut self, target: &str, wiki: bool) -> String {
+        if links::is_external(target) {
+            return target.to_string();
+        }
+        let path = if wiki {
+            self.attachment(split_anchor(target).0)
+        } else {
+            let note = self.index.notes[self.current()].path.clone();
+            Some(resolve_local_target(self.root, &note, target))
+        };
+        match path {
+            Some(path) => asset_url(&path),
+            None => target.to_string(),
+        }
+    }
+}
+
+fn split_anchor(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('#') {
+        Some((name, anchor)) => (name.trim(), Some(anchor.trim()).filter(|a| !a.is_empty())),
+        None => (target.trim(), None),
+    }
+}
+
+/// The URL the webview loads a local file from, as `convertFileSrc` builds
+/// it.
+fn asset_url(path: &Path) -> String {
+    let encoded = utf8_percent_encode(&path.to_string_lossy(), URI_COMPONENT).to_string();
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::files::{edit_note, FileError};
+use crate::markdown::{self, frontmatter, tags};
+use crate::vault::{self, index::VaultIndex, settings::VaultSettings};
+
+const LAST_REVIEWED: &str = "last_reviewed";
+const REVIEW_INTERVAL: &str = "review_interval";
+/// Longest gap between reviews, in days.
+const MAX_INTERVAL: u32 = 365;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaleNote {
+    pub path: String,
+    pub title: String,
+    /// When the note was last updated, in seconds since the Unix epoch.
+    pub updated: i64,
+    pub days_since_update: i64,
+    pub word_count: usize,
+    pub backlink_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewOutcome {
+    /// Forgotten or in need of work: see it again tomorrow.
+    Again,
+    Good,
+    /// Wait longer than usual before the next review.
+    Easy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub path: String,
+    pub title: String,
+    /// `YYYY-MM-DD`; `None` for a note never reviewed.
+    pub last_reviewed: Option<String>,
+    /// Days between reviews.
+    pub review_interval: Option<u32>,
+    /// `YYYY-MM-DD`.
+    pub due: String,
+    /// Days past due; 0 for notes due today or never reviewed.
+    pub days_overdue: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReviewSchedule {
+    pub path: String,
+    pub last_reviewed: String,
+    pub review_interval: u32,
+    pub next_review: String,
+}
+
+/// Notes not updated in the last `older_than_days` days, oldest first.
+///
+/// The last update comes from the frontmatter `updated` (or `modified`)
+/// field when it parses as a date, otherwise from the file's mtime. Notes
+/// carrying any of `exclude_tags` in frontmatter or inline are left out.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn find_stale_notes(
+    vault_path: &str,
+    older_than_days: u32,
+    folder: Option<String>,
+    exclude_tags: Option<Vec<String>>,
+) -> Result<Vec<StaleNote>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    // Backlinks count from the whole vault even when reporting on one folder.
+    let index = VaultIndex::build(root, &vault::notes(root, &settings));
+    let backlinks = index.backlink_counts();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let cutoff = now - i64::from(older_than_days) * 86_400;
+    let folder = folder.map(|f| root.join(f.trim_matches('/')));
+    let exclude_tags = exclude_tags.unwrap_or_default();
+
+    let mut stale = Vec::new();
+    for (idx, note) in index.notes.iter().enumerate() {
+        if folder.as_ref().is_some_and(|f| !note.path.starts_with(f)) {
+            continue;
+        }
+        if note.tags.iter().any(|tag| {
+            exclude_tags
+                .iter()
+                .any(|excluded| tags::tag_matches(tag, excluded))
+        }) {
+            continue;
+        }
+
+        let updated = ["updated", "modified"]
+            .iter()
+            .find_map(|key| note.frontmatter.get(*key).and_then(frontmatter::parse_date))
+            .or(note.modified.map(|m| m as i64));
+        let Some(updated) = updated else {
+            continue;
+        };
+        if updated >= cutoff {
+            continue;
+        }
+
+        stale.push(StaleNote {
+            path: note.path.to_string_lossy().to_string(),
+            title: note.title.clone(),
+            updated,
+            days_since_update: (now - updated) / 86_400,
+            word_count: note.word_count,
+            backlink_count: backlinks[idx],
+        });
+    }
+
+    stale.sort_by(|a, b| a.updated.cmp(&b.updated).then_with(|| a.path.cmp(&b.path)));
+    Ok(stale)
+}
+
+/// Notes due for review, most overdue first, at most `limit` of them.
+/// Notes opt in with `review: true` in their frontmatter and are due
+/// `review_interval` days after `last_reviewed`; one never reviewed is due
+/// now.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_review_queue(vault_path: &str, limit: Option<usize>) -> Result<Vec<ReviewItem>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let today = Local::now().date_naive();
+    let mut queue: Vec<ReviewItem> = vault::notes(root, &settings)
+        .into_iter()
+        .filter_map(|note| {
+            let content = fs::read_to_string(&note).ok()?;
+            let (fm, split) = frontmatter::parse_note(&content).ok()?;
+            if !opted_in(fm.get("review")) {
+                return None;
+            }
+            let last_reviewed = fm.get(LAST_REVIEWED).and_then(review_date);
+            let review_interval = fm.get(REVIEW_INTERVAL).and_then(interval_days);
+            let due = match last_reviewed {
+                Some(last) => last + chrono::Days::new(review_interval.unwrap_or(1).into()),
+                None => today,
+            };
+            if due > today {
+                return None;
+            }
+            Some(ReviewItem {
+                path: note.to_string_lossy().to_string(),
+                title: markdown::note_title(&note, &fm, split.body),
+                last_reviewed: last_reviewed.map(|d| d.format("%Y-%m-%d").to_string()),
+                review_interval,
+                due: due.format("%Y-%m-%d").to_string(),
+                days_overdue: (today - due).num_days(),
+            })
+        })
+        .collect();
+    queue.sort_by(|a, b| {
+        b.days_overdue
+            .cmp(&a.days_overdue)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    if let Some(limit) = limit {
+        queue.truncate(limit);
+    }
+    Ok(queue)
+}
+
+/// Records a review of the note at `path` today, setting `last_reviewed`
+/// and a new `review_interval` in its frontmatter, roughly as SM-2 would:
+/// `again` starts over at one day, `good` multiplies the interval by 2.5
+/// and `easy` by 3.25, up to a year.
+#[tauri::command]
+pub fn mark_reviewed(
+    vault_path: &str,
+    path: &str,
+    outcome: ReviewOutcome,
+) -> Result<ReviewSchedule, FileError> {
+    let root = Path::new(vault_path);
+    let note = Path::new(path);
+    if !note.starts_with(root) {
+        return Err(format!("Note is not in the vault: {}", path).into());
+    }
+    let today = Local::now().date_naive();
+    let last_reviewed = today.format("%Y-%m-%d").to_string();
+    let mut interval = 0;
+    edit_note(note, "mark_reviewed", |content| {
+        let (fm, _) = frontmatter::parse_note(content)?;
+        interval = next_interval(fm.get(REVIEW_INTERVAL).and_then(interval_days), outcome);
+        frontmatter::update(content, |mapping| {
+            mapping.insert(LAST_REVIEWED.into(), last_reviewed.clone().into());
+            mapping.insert(REVIEW_INTERVAL.into(), interval.into());
+        })
+    })?;
+    Ok(ReviewSchedule {
+        path: path.to_string(),
+        last_reviewed,
+        review_interval: interval,
+        next_review: (today + chrono::Days::new(interval.into()))
+            .format("%Y-%m-%d")
+            .to_string(),
+    })
+}
+
+fn next_interval(current: Option<u32>, outcome: ReviewOutcome) -> u32 {
+    let current = f64::from(current.unwrap_or(0));
+    let next = match outcome {
+        ReviewOutcome::Again => 1.0,
+        ReviewOutcome::Good => (current * 2.5).max(1.0),
+        ReviewOutcome::Easy => (current * 3.25).max(4.0),
+    };
+    (next.round() as u32).clamp(1, MAX_INTERVAL)
+}
+
+fn opted_in(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Bool(on)) => *on,
+        Some(Value::String(s)) => matches!(s.trim().to_lowercase().as_str(), "true" | "yes"),
+        _ => false,
+    }
+}
+
+fn review_date(value: &Value) -> Option<NaiveDate> {
+    let secs = frontmatter::parse_date(value)?;
+    DateTime::from_timestamp(secs, 0).map(|d| d.date_naive())
+}
+
+fn interval_days(value: &Value) -> Option<u32> {
+    let days = match value {
+        Value::Number(n) => n.as_f64()?,
+        Value::String(s) => s.trim().parse().ok()?,
+        _ => return None,
+    };
+    (days >= 1.0).then(|| (days.round() as u32).min(MAX_INTERVAL))
+}
@@ -0,0 +1,270 @@
+use chrono::{Local, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use super::files::{write_note, FileError};
+use super::templates::{daily_note, daily_note_folder, daily_note_format};
+use crate::markdown::headings::outline;
+use crate::markdown::lists::{indent_width, item_marker};
+use crate::markdown::{protected_lines, templates, LineBuffer};
+use crate::vault::journal::Journal;
+use crate::vault::write_locks::{WriteLocks, LOCK_TIMEOUT};
+use crate::vault::{self, settings::VaultSettings};
+
+const STATE_FILE: &str = "rollover.json";
+const DEFAULT_SECTION: &str = "Tasks";
+
+// `- [ ] ` at the start of an open task, up to the box.
+static OPEN_BOX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*(?:[-*+]|\d{1,9}[.)])\s+)\[ \]").unwrap());
+// `- [>] ` marks a task already carried forward.
+static FORWARDED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:[-*+]|\d{1,9}[.)])\s+\[>\]").unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RolloverOptions {
+    /// The heading in today's note the tasks go under, added at the end if
+    /// missing. `Tasks` by default.
+    pub section: Option<String>,
+    /// Delete the tasks from the old note rather than marking them `[>]`.
+    pub remove: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolledTask {
+    /// The task's text, without its marker.
+    pub text: String,
+    /// Where it was in the old note, 1-based.
+    pub line_number: usize,
+    /// The task and its subtasks as copied.
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RolloverReport {
+    pub from_note: Option<String>,
+    pub to_note: Option<String>,
+    /// Whether the day's note was created from the template.
+    pub created_note: bool,
+    pub tasks: Vec<RolledTask>,
+    /// Tasks were already rolled over into this day's note; nothing was
+    /// done.
+    pub already_rolled_over: bool,
+    /// For `undo_operation`; `None` when nothing moved.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RolloverState {
+    /// The last day tasks were rolled over into, `YYYY-MM-DD`.
+    last_date: Option<String>,
+}
+
+/// Carries the unfinished tasks of the most recent earlier daily note that
+/// has any into the daily note for `date` (`YYYY-MM-DD`, today by default),
+/// under `options.section`. Subtasks and other lines nested under a task go
+/// with it. The originals are marked `[>]`, or removed with
+/// `options.remove`. Runs at most once per day: later calls for the same
+/// day report `already_rolled_over`. Can be undone with `undo_operation`.
+#[tauri::command]
+pub fn rollover_tasks(
+    vault_path: &str,
+    options: RolloverOptions,
+    date: Option<String>,
+) -> Result<RolloverReport, FileError> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path).into());
+    }
+    let settings = VaultSettings::load(root)?;
+    let date = match date {
+        Some(text) => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {}", text))?,
+        None => Local::now().date_naive(),
+    };
+    let state_path = vault::state_dir(root).join(STATE_FILE);
+    let mut state: RolloverState = vault::read_json(&state_path)?;
+    let day = date.format("%Y-%m-%d").to_string();
+    if state.last_date.as_deref() == Some(day.as_str()) {
+        return Ok(RolloverReport {
+            already_rolled_over: true,
+            ..Default::default()
+        });
+    }
+
+    let has_open_tasks = |note: &Path| {
+        fs::read_to_string(note)
+            .is_ok_and(|content| !open_tasks(&LineBuffer::parse(&content).as_strs()).is_empty())
+    };
+    let Some(from) = earlier_daily_notes(root, &settings, date)
+        .into_iter()
+        .find(|note| has_open_tasks(note))
+    else {
+        return Ok(RolloverReport::default());
+    };
+    let (to, created_note) = daily_note(root, &settings, Some(date))?;
+
+    // Both notes stay locked from here until they're written, so an
+    // autosave landing in between isn't overwritten.
+    let _locks = WriteLocks::global().lock_all([&from, &to], "rollover_tasks", LOCK_TIMEOUT)?;
+    let content = fs::read_to_string(&from).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut source = LineBuffer::parse(&content);
+    let blocks = open_tasks(&source.as_strs());
+    let tasks: Vec<RolledTask> = blocks
+        .iter()
+        .map(|&(start, end)| {
+            let lines = &source.lines[start..end];
+            let indent = &lines[0][..lines[0].len() - lines[0].trim_start().len()];
+            let marker = item_marker(&lines[0]).map_or(0, |m| m.text_start);
+            RolledTask {
+                text: lines[0][marker..].trim().to_string(),
+                line_number: start + 1,
+                lines: lines
+                    .iter()
+                    .map(|l| l.strip_prefix(indent).unwrap_or(l).to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let today = fs::read_to_string(&to).map_err(|e| format!("Failed to read file: {}", e))?;
+    let section = options
+        .section
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_SECTION);
+    let copied: Vec<String> = tasks
+        .iter()
+        .flat_map(|task| task.lines.iter().cloned())
+        .collect();
+    let updated_today = insert_in_section(&today, section, copied);
+
+    for &(start, end) in blocks.iter().rev() {
+        if options.remove {
+            source.lines.drain(start..end);
+        } else {
+            source.lines[start] = OPEN_BOX
+                .replace(&source.lines[start], "${1}[>]")
+                .to_string();
+        }
+    }
+
+    let mut journal = Journal::start(root, "rollover_tasks");
+    journal.write(&to, || write_note(&to, updated_today))?;
+    journal.write(&from, || write_note(&from, source.render()))?;
+    state.last_date = Some(day);
+    vault::write_json(&state_path, &state)?;
+    Ok(RolloverReport {
+        from_note: Some(from.to_string_lossy().to_string()),
+        to_note: Some(to.to_string_lossy().to_string()),
+        created_note,
+        tasks,
+        already_rolled_over: false,
+        operation_id: journal.finish(&settings.journal)?,
+    })
+}
+
+/// Daily notes dated before `date`, newest first, recognised by their path
+/// in the daily notes folder matching the vault's daily note format.
+fn earlier_daily_notes(root: &Path, settings: &VaultSettings, date: NaiveDate) -> Vec<PathBuf> {
+    let folder = root.join(daily_note_folder(settings));
+    let format = templates::chrono_format(daily_note_format(settings));
+    let mut notes: Vec<(NaiveDate, PathBuf)> = vault::files_where(&folder, vault::is_markdown)
+        .into_iter()
+        .filter_map(|note| {
+            let rel = vault::relative_path(&folder, &note);
+            let name = rel.strip_suffix(".md").unwrap_or(&rel);
+            let day = NaiveDate::parse_from_str(name, &format).ok()?;
+            (day < date).then_some((day, note))
+        })
+        .collect();
+    notes.sort_by_key(|(day, _)| Reverse(*day));
+    notes.into_iter().map(|(_, note)| note).collect()
+}
+
+/// Line ranges of the open tasks in a note, each with the lines nested
+/// under it. An open task inside another open task goes with it; one under
+/// a finished task or a plain item is a task of its own. Tasks under a
+/// `[>]` one were carried forward with it and are skipped.
+fn open_tasks(lines: &[&str]) -> Vec<(usize, usize)> {
+    let protected = protected_lines(lines);
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let open = !protected[i] && item_marker(lines[i]).is_some_and(|m| m.checked == Some(false));
+        if !open && (protected[i] || !FORWARDED.is_match(lines[i])) {
+            i += 1;
+            continue;
+        }
+        let end = nested_end(lines, i);
+        if open {
+            blocks.push((i, end));
+        }
+        i = end;
+    }
+    blocks
+}
+
+/// Just past the last line indented under line `start`. Blank lines count
+/// only when more nested lines follow them.
+fn nested_end(lines: &[&str], start: usize) -> usize {
+    let indent_of = |line: &str| indent_width(&line[..line.len() - line.trim_start().len()]);
+    let indent = indent_of(lines[start]);
+    let mut end = start + 1;
+    let mut i = start + 1;
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        if indent_of(lines[i]) <= indent {
+            break;
+        }
+        i += 1;
+        end = i;
+    }
+    end
+}
+
+/// Adds `new_lines` at the end of the section headed `section`, or in a new
+/// `## section` at the end of the note.
+fn insert_in_section(content: &str, section: &str, new_lines: Vec<String>) -> String {
+    let mut buffer = LineBuffer::parse(content);
+    let headings = outline(&buffer.as_strs());
+    let found = headings
+        .iter()
+        .position(|h| h.text.trim().eq_ignore_ascii_case(section));
+    match found {
+        Some(idx) => {
+            let heading = &headings[idx];
+            let end = headings[idx + 1..]
+                .iter()
+                .find(|h| h.level <= heading.level)
+                .map_or(buffer.lines.len(), |h| h.line);
+            let mut at = end;
+            while at > heading.line + 1 && buffer.lines[at - 1].trim().is_empty() {
+                at -= 1;
+            }
+            if at == end && end < buffer.lines.len() {
+                buffer.lines.insert(at, String::new());
+            }
+            buffer.lines.splice(at..at, new_lines);
+        }
+        None => {
+            if buffer.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                buffer.lines.push(String::new());
+            }
+            buffer.lines.push(format!("## {}", section));
+            buffer.lines.extend(new_lines);
+        }
+    }
+    buffer.set_trailing_newline(true);
+    buffer.render()
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::files::{write_note, FileError};
+use crate::markdown::frontmatter;
+use crate::vault::journal::Journal;
+use crate::vault::schemas::{SchemaViolation, Schemas, ViolationKind};
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaFixReport {
+    pub fixed: Vec<SchemaViolation>,
+    /// Violations that need a person: no default, a wrong type, or a note
+    /// that couldn't be written.
+    pub remaining: Vec<SchemaViolation>,
+    /// For `undo_operation`; `None` when nothing changed.
+    pub operation_id: Option<String>,
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn validate_note(path: &str) -> Result<Vec<SchemaViolation>, String> {
+    let file_path = Path::new(path);
+    let Some(root) = vault::find_root(file_path) else {
+        return Ok(Vec::new());
+    };
+    let schemas = Schemas::load(&root)?;
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(schemas.validate(file_path, &content))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn validate_vault(vault_path: &str) -> Result<Vec<SchemaViolation>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let schemas = Schemas::load(root)?;
+    let mut violations = Vec::new();
+    for file in vault::notes(root, &VaultSettings::load(root)?) {
+        if let Ok(content) = fs::read_to_string(&file) {
+            violations.extend(schemas.validate(&file, &content));
+        }
+    }
+    Ok(violations)
+}
+
+/// Fixes what a validation report can fix on its own: missing fields whose
+/// schema gives a default are added with that default. Everything else is
+/// returned as remaining. The fixes can be undone with `undo_operation`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn fix_schema_violations(
+    vault_path: &str,
+    violations: Vec<SchemaViolation>,
+) -> Result<SchemaFixReport, String> {
+    let root = Path::new(vault_path);
+    let schemas = Schemas::load(root)?;
+    let mut by_note: BTreeMap<String, Vec<SchemaViolation>> = BTreeMap::new();
+    for violation in violations {
+        by_note
+            .entry(violation.path.clone())
+            .or_default()
+            .push(violation);
+    }
+
+    let mut report = SchemaFixReport {
+        fixed: Vec::new(),
+        remaining: Vec::new(),
+        operation_id: None,
+    };
+    let mut journal = Journal::start(root, "fix_schema_violations");
+    for (path, violations) in by_note {
+        // Held from the read to the write, so an autosave in between isn't
+        // overwritten by the fixed copy.
+        let lock = WriteLocks::global().lock(Path::new(&path), "fix_schema_violations");
+        let (Ok(_lock), Ok(content)) = (lock, fs::read_to_string(&path)) else {
+            report.remaining.extend(violations);
+            continue;
+        };
+        let schema = frontmatter::parse_note(&content)
+            .ok()
+            .and_then(|(fm, _)| schemas.schema_for(&fm).map(|(_, schema)| schema));
+        let (fixable, unfixable): (Vec<_>, Vec<_>) = violations.into_iter().partition(|v| {
+            v.kind == ViolationKind::MissingField
+                && schema
+                    .is_some_and(|s| s.fields.get(&v.field).is_some_and(|f| f.default.is_some()))
+        });
+        report.remaining.extend(unfixable);
+        let Some(schema) = schema.filter(|_| !fixable.is_empty()) else {
+            continue;
+        };
+
+        let updated = frontmatter::update(&content, |mapping| {
+            for violation in &fixable {
+                let key = serde_yaml::Value::String(violation.field.clone());
+                let default = schema.fields[&violation.field].default.as_ref();
+                if let Some(value) = default.and_then(|d| serde_yaml::to_value(d).ok()) {
+                    if !mapping.contains_key(&key) {
+                        mapping.insert(key, value);
+                    }
+                }
+            }
+        });
+        let path = Path::new(&path);
+        let written = updated
+            .map_err(FileError::from)
+            .and_then(|updated| journal.write(path, || write_note(path, updated)));
+        match written {
+            Ok(()) => report.fixed.extend(fixable),
+            Err(_) => report.remaining.extend(fixable),
+        }
+    }
+
+    report.operation_id = journal.finish(&VaultSettings::load(root)?.journal)?;
+    Ok(report)
+}
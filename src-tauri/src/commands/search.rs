@@ -0,0 +1,518 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::async_runtime;
+use tauri::{AppHandle, State};
+
+use super::backlinks::load_index;
+use super::caches::CacheKeys;
+use crate::markdown::{self, frontmatter, headings};
+use crate::tasks::{Task, TaskKind, TaskManager};
+use crate::vault::{self, settings::VaultSettings};
+
+const DEFAULT_MAX_RESULTS: usize = 500;
+const DEFAULT_MARKERS: [&str; 3] = ["TODO", "FIXME", "@review"];
+const DEFAULT_FUZZY_RESULTS: usize = 50;
+
+// Fuzzy match scoring, after fzf: every matched character scores, more so
+// at the start of a word, and gaps between matches cost.
+const SCORE_MATCH: i64 = 16;
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+const BONUS_PREFIX: i64 = 10;
+const BONUS_PATH_SEPARATOR: i64 = 9;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GrepMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line_content: String,
+    /// Byte offsets of the match within `line_content`.
+    pub match_start: usize,
+    pub match_end: usize,
+    /// 1-based column of the match in UTF-16 code units, as JavaScript
+    /// strings index the line; `end_column` is just past it.
+    pub column: usize,
+    pub end_column: usize,
+    /// Headings enclosing the match, outermost first. Only filled in when
+    /// requested; empty for matches before the first heading.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_path: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarkerMatch {
+    pub path: String,
+    pub line_number: usize,
+    /// The rest of the line after the marker.
+    pub description: String,
+    /// 1 to 3, from a `!`, `!!` or `!!!` straight after the marker.
+    pub priority: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarkerGroup {
+    pub marker: String,
+    pub count: usize,
+    pub matches: Vec<MarkerMatch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FuzzyField {
+    FileName,
+    Title,
+    Alias,
+    Path,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub relative_path: String,
+    pub title: String,
+    /// What the query matched best, and that text.
+    pub field: FuzzyField,
+    pub matched_text: String,
+    /// Positions in `matched_text` of the matched characters, counted in
+    /// characters.
+    pub indices: Vec<usize>,
+    /// Higher is better; 0 for an empty query.
+    pub score: i64,
+    pub modified: Option<u64>,
+}
+
+/// Finds notes for a quick switcher: `query` is matched as a subsequence,
+/// ignoring case and spaces, against each note's file name, its
+/// frontmatter `title` and aliases where they differ from it, and its
+/// vault-relative path. Matches at the start of words score higher, as in
+/// fzf. Results are best first, then most recently modified; an empty query
+/// gives the most recently modified notes. Skips hidden folders, ignored
+/// notes and private folders.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn fuzzy_find_files(
+    keys: State<'_, CacheKeys>,
+    vault_path: String,
+    query: String,
+    max_results: Option<usize>,
+) -> Result<Vec<FuzzyMatch>, String> {
+    let (root, index) = load_index(&keys, &vault_path)?;
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut found: Vec<FuzzyMatch> = index
+        .notes
+        .iter()
+        .filter_map(|note| {
+            let relative_path = vault::relative_path(&root, &note.path);
+            let name = note
+                .path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let mut fields = vec![(FuzzyField::FileName, name.clone())];
+            let differs =
+                |text: &String| !text.trim().is_empty() && !text.eq_ignore_ascii_case(&name);
+            if note.frontmatter.get("title").is_some() && differs(&note.title) {
+                fields.push((FuzzyField::Title, note.title.clone()));
+            }
+            fields.extend(
+                note.aliases
+                    .iter()
+                    .filter(|a| differs(a))
+                    .map(|a| (FuzzyField::Alias, a.clone())),
+            );
+            fields.push((FuzzyField::Path, relative_path.clone()));
+
+            // Ties go to the field listed first, so a name beats the path
+            // that ends in it.
+            let (field, matched_text, score, indices) = if query.is_empty() {
+                (FuzzyField::FileName, name, 0, Vec::new())
+            } else {
+                fields
+                    .into_iter()
+                    .filter_map(|(field, text)| {
+                        let (score, indices) = fuzzy_score(&query, &text)?;
+                        Some((field, text, score, indices))
+                    })
+                    .fold(
+                        None,
+                        |best: Option<(FuzzyField, String, i64, Vec<usize>)>, candidate| match best
+                        {
+                            Some(best) if best.2 >= candidate.2 => Some(best),
+                            _ => Some(candidate),
+                        },
+                    )?
+            };
+            Some(FuzzyMatch {
+                path: note.path.to_string_lossy().to_string(),
+                relative_path,
+                title: note.title.clone(),
+                field,
+                matched_text,
+                indices,
+                score,
+                modified: note.modified,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        (Reverse(a.score), Reverse(a.modified), &a.relative_path).cmp(&(
+            Reverse(b.score),
+            Reverse(b.modified),
+            &b.relative_path,
+        ))
+    });
+    found.truncate(max_results.unwrap_or(DEFAULT_FUZZY_RESULTS));
+    Ok(found)
+}
+
+/// The best way to match `query`, lowercase, as a subsequence of `text`,
+/// with the character positions used, or `None` if it isn't one.
+fn fuzzy_score(query: &[char], text: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let (m, n) = (query.len(), chars.len());
+    if m == 0 || m > n {
+        return None;
+    }
+    let bonus: Vec<i64> = (0..n)
+        .map(|j| {
+            let Some(prev) = j.checked_sub(1).map(|p| chars[p]) else {
+                return BONUS_PREFIX;
+            };
+            let cur = chars[j];
+            if prev == '/' || prev == '\\' {
+                BONUS_PATH_SEPARATOR
+            } else if prev.is_whitespace() || matches!(prev, '-' | '_' | '.' | '(' | '[') {
+                BONUS_BOUNDARY
+            } else if (prev.is_lowercase() && cur.is_uppercase())
+                || (!prev.is_numeric() && cur.is_numeric())
+            {
+                BONUS_CAMEL
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    // best[i][j]: the best score with query[i] matched at text[j], and
+    // from[i][j] where query[i - 1] was matched for it.
+    let mut best = vec![vec![None::<i64>; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+    for i in 0..m {
+        // The best earlier match of query[i - 1] to jump a gap from,
+        // already charged for the gap up to j.
+        let mut gap: Option<(i64, usize)> = None;
+        for j in i..n {
+            if i > 0 && j >= 2 {
+                if let Some(score) = best[i - 1][j - 2] {
+                    let opened = score - GAP_START;
+                    gap = match gap {
+                        Some((carried, k)) if carried - GAP_EXTENSION >= opened => {
+                            Some((carried - GAP_EXTENSION, k))
+                        }
+                        _ => Some((opened, j - 2)),
+                    };
+                } else if let Some((carried, k)) = gap {
+                    gap = Some((carried - GAP_EXTENSION, k));
+                }
+            }
+            if lower[j] != query[i] {
+                continue;
+            }
+            if i == 0 {
+                best[i][j] = Some(SCORE_MATCH + bonus[j] * FIRST_CHAR_MULTIPLIER);
+                continue;
+            }
+            let adjacent = best[i - 1][j - 1]
+                .map(|s| (s + SCORE_MATCH + bonus[j].max(BONUS_CONSECUTIVE), j - 1));
+            let jumped = gap.map(|(s, k)| (s + SCORE_MATCH + bonus[j], k));
+            let chosen = match (adjacent, jumped) {
+                (Some(a), Some(g)) => Some(if a.0 >= g.0 { a } else { g }),
+                (a, g) => a.or(g),
+            };
+            if let Some((score, k)) = chosen {
+                best[i][j] = Some(score);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, score) = (0..n)
+        .filter_map(|j| best[m - 1][j].map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, Reverse(j)))?;
+    let mut indices = vec![0; m];
+    for i in (0..m).rev() {
+        indices[i] = j;
+        j = from[i][j];
+    }
+    Some((score, indices))
+}
+
+/// Searches every markdown file under `path` for `pattern`, a regular
+/// expression, reporting each match, several per line if need be.
+/// `case_sensitive` defaults to true; `whole_word` only matches the pattern
+/// between word boundaries.
+///
+/// Notes in the vault's private folders are skipped unless `include_private`
+/// is set. Runs as a `grep` task: each file's matches are streamed in the `data` of
+/// a `task://progress` event as soon as it has been searched, and the search
+/// can be cancelled.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+#[allow(clippy::too_many_arguments)]
+pub async fn grep_search(
+    app: AppHandle,
+    tasks: State<'_, TaskManager>,
+    path: String,
+    pattern: String,
+    max_results: Option<usize>,
+    include_heading_path: Option<bool>,
+    include_private: Option<bool>,
+    case_sensitive: Option<bool>,
+    whole_word: Option<bool>,
+) -> Result<Vec<GrepMatch>, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    let wrapped = if whole_word.unwrap_or(false) {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern.clone()
+    };
+    let regex = RegexBuilder::new(&wrapped)
+        .case_insensitive(!case_sensitive.unwrap_or(true))
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let with_headings = include_heading_path.unwrap_or(false);
+    let include_private = include_private.unwrap_or(false);
+
+    let task = tasks.start(&app, TaskKind::Grep, format!("Searching for {}", pattern));
+    async_runtime::spawn_blocking(move || {
+        let files = match vault::find_root(&root) {
+            Some(vault_root) => {
+                vault::visible_files(&vault_root, &root, include_private, vault::is_markdown)
+            }
+            None => Ok(vault::markdown_files(&root)),
+        };
+        let result =
+            files.and_then(|files| grep(&files, &regex, max_results, with_headings, &task));
+        task.finish(result)
+    })
+    .await
+    .map_err(|e| format!("Search failed: {}", e))?
+}
+
+fn grep(
+    files: &[PathBuf],
+    regex: &Regex,
+    max_results: usize,
+    with_headings: bool,
+    task: &Task,
+) -> Result<Vec<GrepMatch>, String> {
+    let mut matches = Vec::new();
+    for (done, file) in files.iter().enumerate() {
+        task.check_cancelled()?;
+        let Ok(content) = fs::read_to_string(file) else {
+            continue;
+        };
+        let found_before = matches.len();
+        search_file(
+            file,
+            &content,
+            regex,
+            with_headings,
+            max_results,
+            &mut matches,
+        );
+
+        let fraction = Some((done + 1) as f64 / files.len() as f64);
+        let message = format!(
+            "{} matches in {} of {} files",
+            matches.len(),
+            done + 1,
+            files.len()
+        );
+        let found = &matches[found_before..];
+        let data = (!found.is_empty())
+            .then(|| serde_json::to_value(found).ok())
+            .flatten();
+        task.progress(message, fraction, data);
+        if matches.len() >= max_results {
+            break;
+        }
+    }
+    Ok(matches)
+}
+
+pub(super) fn search_file(
+    file: &Path,
+    content: &str,
+    regex: &Regex,
+    with_headings: bool,
+    max_results: usize,
+    matches: &mut Vec<GrepMatch>,
+) {
+    let lines: Vec<&str> = content.lines().collect();
+    let outline = if with_headings {
+        headings::outline(&lines)
+    } else {
+        Vec::new()
+    };
+    let mut next_heading = outline.iter().peekable();
+    let mut stack: Vec<&headings::Heading> = Vec::new();
+
+    for (idx, line) in lines.iter().enumerate() {
+        if let Some(heading) = next_heading.next_if(|h| h.line == idx) {
+            stack.retain(|h| h.level < heading.level);
+            stack.push(heading);
+        }
+        // Empty matches only count when nothing else on the line matches,
+        // so `^$` still finds blank lines without `x*` flooding the results.
+        let mut found: Vec<_> = regex.find_iter(line).filter(|m| !m.is_empty()).collect();
+        if found.is_empty() {
+            found.extend(regex.find(line));
+        }
+        for found in found {
+            let column = line[..found.start()].encode_utf16().count() + 1;
+            matches.push(GrepMatch {
+                path: file.to_string_lossy().to_string(),
+                line_number: idx + 1,
+                line_content: line.to_string(),
+                match_start: found.start(),
+                match_end: found.end(),
+                column,
+                end_column: column + found.as_str().encode_utf16().count(),
+                heading_path: with_headings.then(|| stack.iter().map(|h| h.text.clone()).collect()),
+            });
+            if matches.len() >= max_results {
+                return;
+            }
+        }
+    }
+}
+
+/// Finds `TODO:`-style markers in the vault's notes, grouped by marker in the
+/// order given. Markers are matched case-sensitively as whole words, so
+/// `TODO` doesn't match `TODOS`; `TODO!!: call` has priority 2. Frontmatter
+/// is skipped, and code (blocks and spans) too unless `include_code` is set.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn find_markers(
+    vault_path: &str,
+    markers: Option<Vec<String>>,
+    include_code: Option<bool>,
+) -> Result<Vec<MarkerGroup>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let markers: Vec<String> = match markers {
+        Some(markers) => markers
+            .into_iter()
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect(),
+        None => DEFAULT_MARKERS.iter().map(|m| m.to_string()).collect(),
+    };
+    if markers.is_empty() {
+        return Ok(Vec::new());
+    }
+    let include_code = include_code.unwrap_or(false);
+
+    // Longest first, so a marker isn't shadowed by one it starts with.
+    let mut alternatives: Vec<String> = markers.iter().map(|m| regex::escape(m)).collect();
+    alternatives.sort_by_key(|m| std::cmp::Reverse(m.len()));
+    let pattern = format!(r"(?:^|[^\w@])({})(!{{1,3}})?", alternatives.join("|"));
+    let regex = Regex::new(&pattern).map_err(|e| format!("Invalid marker: {}", e))?;
+
+    let mut groups: Vec<MarkerGroup> = markers
+        .iter()
+        .map(|marker| MarkerGroup {
+            marker: marker.clone(),
+            count: 0,
+            matches: Vec::new(),
+        })
+        .collect();
+    let settings = VaultSettings::load(root)?;
+    for file in vault::notes(root, &settings) {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let in_code = markdown::code_block_lines(&lines);
+        let body_start = frontmatter::line_count(&lines);
+
+        for (idx, line) in lines.iter().enumerate().skip(body_start) {
+            if in_code[idx] && !include_code {
+                continue;
+            }
+            let searched = if include_code {
+                line.to_string()
+            } else {
+                markdown::blank_code_spans(line)
+            };
+            for caps in regex.captures_iter(&searched) {
+                let marker = &caps[1];
+                let after = caps.get(2).unwrap_or_else(|| caps.get(1).unwrap()).end();
+                if searched[after..]
+                    .starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '!')
+                {
+                    continue;
+                }
+                let description = line[after..].trim_start_matches(':').trim();
+                let Some(group) = groups.iter_mut().find(|g| g.marker == marker) else {
+                    continue;
+                };
+                group.count += 1;
+                group.matches.push(MarkerMatch {
+                    path: file.to_string_lossy().to_string(),
+                    line_number: idx + 1,
+                    description: description.to_string(),
+                    priority: caps.get(2).map(|m| m.len() as u8),
+                });
+            }
+        }
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_count_utf16_code_units() {
+        let mut matches = Vec::new();
+        let regex = Regex::new("note").unwrap();
+        search_file(
+            Path::new("Note.md"),
+            "é 🎉 note and note\n",
+            &regex,
+            false,
+            10,
+            &mut matches,
+        );
+        let columns: Vec<(usize, usize)> =
+            matches.iter().map(|m| (m.column, m.end_column)).collect();
+        // `é` is one unit and `🎉` two, though each is one character.
+        assert_eq!(columns, [(6, 10), (15, 19)]);
+        assert_eq!(matches[0].match_start, "é 🎉 ".len());
+    }
+}
@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::vault::link_format;
+use crate::vault::settings::{LinkFormat, LinkPath, LinkStyle, VaultSettings};
+
+const OBSIDIAN_DIR: &str = ".obsidian";
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_vault_settings(vault_path: &str) -> Result<VaultSettings, String> {
+    VaultSettings::load(Path::new(vault_path))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn save_vault_settings(vault_path: &str, settings: VaultSettings) -> Result<(), String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    settings.save(root)
+}
+
+/// An example link from `from` to `to` (paths in the vault), in the vault's
+/// link format or in `format` when given, for previewing a change before it
+/// is saved.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn preview_link_format(
+    vault_path: &str,
+    from: &str,
+    to: &str,
+    format: Option<LinkFormat>,
+) -> Result<String, String> {
+    let root = Path::new(vault_path);
+    let mut settings = VaultSettings::load(root)?;
+    if let Some(format) = format {
+        settings.link_format = format;
+    }
+    let (from, to) = (root.join(from), root.join(to));
+    Ok(link_format::format_link(root, &settings, &from, &to, None))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedSetting {
+    /// Where the value came from, e.g. `app.json: newLinkFormat`.
+    pub source: String,
+    pub setting: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingConflict {
+    pub source: String,
+    pub setting: String,
+    pub current: Value,
+    pub obsidian: Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ObsidianImport {
+    pub imported: Vec<ImportedSetting>,
+    /// Settings already set to something else, left as they were.
+    pub conflicts: Vec<SettingConflict>,
+    /// Obsidian keys with no GraphNotes equivalent, or values that can't be
+    /// translated.
+    pub unrecognized: Vec<String>,
+    /// Config files that are missing or couldn't be parsed.
+    pub skipped: Vec<String>,
+}
+
+/// Carries over the settings GraphNotes shares with Obsidian from the
+/// vault's `.obsidian` folder: attachment folder, link format, excluded
+/// files, daily notes and the templates folder. Settings already set to a
+/// different value are reported as conflicts and kept unless `overwrite`
+/// is set; excluded files are added to the ignore patterns.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn import_obsidian_config(vault_path: &str, overwrite: bool) -> Result<ObsidianImport, String> {
+    let root = Path::new(vault_path);
+    let config_dir = root.join(OBSIDIAN_DIR);
+    if !config_dir.is_dir() {
+        return Err(format!("No {} folder in {}", OBSIDIAN_DIR, vault_path));
+    }
+    let mut settings = VaultSettings::load(root)?;
+    let mut import = ObsidianImporter {
+        report: ObsidianImport::default(),
+        overwrite,
+        changed: false,
+    };
+
+    if let Some(app) = import.read(&config_dir, "app.json") {
+        import.app(&mut settings, app);
+    }
+    if let Some(daily) = import.read(&config_dir, "daily-notes.json") {
+        import.daily_notes(&mut settings, daily);
+    }
+    if let Some(templates) = import.read(&config_dir, "templates.json") {
+        import.templates(&mut settings, templates);
+    }
+
+    if import.changed {
+        settings.save(root)?;
+    }
+    Ok(import.report)
+}
+
+struct ObsidianImporter {
+    report: ObsidianImport,
+    overwrite: bool,
+    changed: bool,
+}
+
+impl ObsidianImporter {
+    fn read(&mut self, config_dir: &Path, file: &str) -> Option<Map<String, Value>> {
+        let content = match fs::read_to_string(config_dir.join(file)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.report.skipped.push(format!("{}: not found", file));
+                return None;
+            }
+            Err(e) => {
+                self.report.skipped.push(format!("{}: {}", file, e));
+                return None;
+            }
+        };
+        match serde_json::from_str(&content) {
+            Ok(Value::Object(map)) => Some(map),
+            Ok(_) => {
+                self.report
+                    .skipped
+                    .push(format!("{}: not a JSON object", file));
+                None
+            }
+            Err(e) => {
+                self.report.skipped.push(format!("{}: {}", file, e));
+                None
+            }
+        }
+    }
+
+    fn app(&mut self, settings: &mut VaultSettings, app: Map<String, Value>) {
+        for (key, value) in app {
+            let source = format!("app.json: {}", key);
+            match (key.as_str(), &value) {
+                ("attachmentFolderPath", Value::String(folder)) => {
+                    let folder = if folder == "/" {
+                        String::new()
+                    } else {
+                        folder.clone()
+                    };
+                    let unset = settings.attachment_folder.is_none();
+                    self.set(
+                        source,
+                        "attachment_folder",
+                        &mut settings.attachment_folder,
+                        Some(folder),
+                        unset,
+                    );
+                }
+                ("newLinkFormat", Value::String(format)) => {
+                    let path = match format.as_str() {
+                        "shortest" => LinkPath::Shortest,
+                        // GraphNotes writes wikilinks from the vault root and
+                        // markdown links from the note, covering both.
+                        "relative" | "absolute" => LinkPath::Relative,
+                        _ => {
+                            self.unrecognized(source, &value);
+                            continue;
+                        }
+                    };
+                    let unset = settings.link_format.path == LinkPath::default();
+                    self.set(
+                        source,
+                        "link_format.path",
+                        &mut settings.link_format.path,
+                        path,
+                        unset,
+                    );
+                }
+                ("useMarkdownLinks", Value::Bool(markdown)) => {
+                    let style = if *markdown {
+                        LinkStyle::Markdown
+                    } else {
+                        LinkStyle::Wikilink
+                    };
+                    let unset = settings.link_format.style == LinkStyle::default();
+                    self.set(
+                        source,
+                        "link_format.style",
+                        &mut settings.link_format.style,
+                        style,
+                        unset,
+                    );
+                }
+                ("userIgnoreFilters", Value::Array(filters)) => {
+                    for filter in filters {
+                        match filter.as_str().and_then(ignore_pattern) {
+                            Some(pattern) => self.add_ignore_pattern(&source, settings, pattern),
+                            None => self.unrecognized(source.clone(), filter),
+                        }
+                    }
+                }
+                _ => self.unrecognized(source, &value),
+            }
+        }
+    }
+
+    fn daily_notes(&mut self, settings: &mut VaultSettings, daily: Map<String, Value>) {
+        let daily_notes = &mut settings.daily_notes;
+        for (key, value) in daily {
+            let source = format!("daily-notes.json: {}", key);
+            let Some(text) = value.as_str() else {
+                self.unrecognized(source, &value);
+                continue;
+            };
+            // Obsidian saves settings left at their default as "".
+            if text.is_empty() {
+                continue;
+            }
+            let (setting, current, imported) = match key.as_str() {
+                "folder" => (
+                    "daily_notes.folder",
+                    &mut daily_notes.folder,
+                    text.trim_matches('/').to_string(),
+                ),
+                "format" => (
+                    "daily_notes.date_format",
+                    &mut daily_notes.date_format,
+                    text.to_string(),
+                ),
+                "template" => (
+                    "daily_notes.template",
+                    &mut daily_notes.template,
+                    note_file(text),
+                ),
+                _ => {
+                    self.unrecognized(source, &value);
+                    continue;
+                }
+            };
+            let unset = current.is_none();
+            self.set(source, setting, current, Some(imported), unset);
+        }
+    }
+
+    fn templates(&mut self, settings: &mut VaultSettings, templates: Map<String, Value>) {
+        for (key, value) in templates {
+            let source = format!("templates.json: {}", key);
+            match (key.as_str(), value.as_str()) {
+                (_, Some("")) => {}
+                ("folder", Some(folder)) => {
+                    let folder = folder.trim_matches('/').to_string();
+                    let unset = settings.templates.folder.is_none();
+                    self.set(
+                        source,
+                        "templates.folder",
+                        &mut settings.templates.folder,
+                        Some(folder),
+                        unset,
+                    );
+                }
+                _ => self.unrecognized(source, &value),
+            }
+        }
+    }
+
+    /// Sets `current` to `value`, unless it is already set to something
+    /// else and overwriting wasn't asked for.
+    fn set<T: PartialEq + Serialize>(
+        &mut self,
+        source: String,
+        setting: &str,
+        current: &mut T,
+        value: T,
+        unset: bool,
+    ) {
+        let imported = serde_json::to_value(&value).unwrap_or_default();
+        if *current != value && !unset && !self.overwrite {
+            self.report.conflicts.push(SettingConflict {
+                source,
+                setting: setting.to_string(),
+                current: serde_json::to_value(&*current).unwrap_or_default(),
+                obsidian: imported,
+            });
+            return;
+        }
+        if *current != value {
+            *current = value;
+            self.changed = true;
+        }
+        self.report.imported.push(ImportedSetting {
+            source,
+            setting: setting.to_string(),
+            value: imported,
+        });
+    }
+
+    fn add_ignore_pattern(&mut self, source: &str, settings: &mut VaultSettings, pattern: String) {
+        if !settings.ignore_patterns.contains(&pattern) {
+            settings.ignore_patterns.push(pattern.clone());
+            self.changed = true;
+        }
+        self.report.imported.push(ImportedSetting {
+            source: source.to_string(),
+            setting: "ignore_patterns".to_string(),
+            value: Value::String(pattern),
+        });
+    }
+
+    fn unrecognized(&mut self, source: String, value: &Value) {
+        self.report
+            .unrecognized
+            .push(format!("{} = {}", source, value));
+    }
+}
+
+/// The ignore pattern for an Obsidian excluded-files filter, which matches
+/// paths starting with it. Regex filters (`/.../`) have no equivalent.
+fn ignore_pattern(filter: &str) -> Option<String> {
+    if filter.len() > 1 && filter.starts_with('/') && filter.ends_with('/') {
+        return None;
+    }
+    let path = filter.trim_matches('/');
+    (!path.is_empty()).then(|| glob::Pattern::escape(path))
+}
+
+/// Obsidian names notes without their extension.
+fn note_file(path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    if Path::new(path).extension().is_some_and(|ext| ext == "md") {
+        path.to_string()
+    } else {
+        format!("{}.md", path)
+    }
+}
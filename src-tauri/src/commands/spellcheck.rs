@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use spellbook::Dictionary;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use super::files::write_atomic;
+use crate::markdown::spellcheck;
+use crate::vault::{self, settings::VaultSettings};
+
+const DICTIONARY_FILE: &str = "dictionary.txt";
+const EN_US_AFF: &str = include_str!("../../dictionaries/en_US/en_US.aff");
+const EN_US_DIC: &str = include_str!("../../dictionaries/en_US/en_US.dic");
+/// Suggestions offered per misspelled word at most.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Dictionaries loaded so far, by language or file, kept in managed state
+/// since parsing one takes a while.
+#[derive(Default)]
+pub struct Dictionaries {
+    loaded: Mutex<HashMap<String, Arc<Dictionary>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Misspelling {
+    pub word: String,
+    /// 1-based line, and 1-based columns in characters; `end_column` is just
+    /// past the word.
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub suggestions: Vec<String>,
+}
+
+/// Misspelled words in `content`, checked in `language` (e.g. `en_US`).
+/// English is built in; other languages come from the dictionaries in the
+/// vault's `spellcheck` settings. With a vault, words in its custom
+/// dictionary are accepted. Code, frontmatter, links and URLs aren't
+/// checked.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn check_text(
+    dictionaries: State<'_, Dictionaries>,
+    content: &str,
+    language: &str,
+    vault_path: Option<String>,
+) -> Result<Vec<Misspelling>, String> {
+    let root = vault_path.as_deref().map(Path::new);
+    let settings = root
+        .map(VaultSettings::load)
+        .transpose()?
+        .unwrap_or_default();
+    let dictionary = dictionaries.get(language, &settings)?;
+    let custom: HashSet<String> = match root {
+        Some(root) => custom_words(root)?
+            .iter()
+            .map(|w| w.to_lowercase())
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    let mut suggestions: HashMap<String, Vec<String>> = HashMap::new();
+    let mut misspelled = Vec::new();
+    for word in spellcheck::words(content) {
+        if custom.contains(&word.text.to_lowercase()) || dictionary.check(&word.text) {
+            continue;
+        }
+        let suggested = suggestions.entry(word.text.clone()).or_insert_with(|| {
+            let mut out = Vec::new();
+            dictionary.suggest(&word.text, &mut out);
+            out.truncate(MAX_SUGGESTIONS);
+            out
+        });
+        misspelled.push(Misspelling {
+            suggestions: suggested.clone(),
+            word: word.text,
+            line: word.line,
+            column: word.column,
+            end_column: word.end_column,
+        });
+    }
+    Ok(misspelled)
+}
+
+/// Adds `word` to the vault's custom dictionary, `.graphnotes/dictionary.txt`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn add_to_dictionary(vault_path: &str, word: &str) -> Result<Vec<String>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let word = word.trim();
+    if word.is_empty() || word.chars().any(char::is_whitespace) {
+        return Err(format!("Not a single word: \"{}\"", word));
+    }
+    let mut words = custom_words(root)?;
+    if words
+        .iter()
+        .any(|w| w.to_lowercase() == word.to_lowercase())
+    {
+        return Ok(words.into_iter().collect());
+    }
+    words.insert(word.to_string());
+    let mut content = words.iter().cloned().collect::<Vec<_>>().join("\n");
+    content.push('\n');
+    let path = vault::state_dir(root).join(DICTIONARY_FILE);
+    fs::create_dir_all(vault::state_dir(root))
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+    write_atomic(&path, content)?;
+    Ok(words.into_iter().collect())
+}
+
+/// The words in the vault's custom dictionary, sorted.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_custom_dictionary(vault_path: &str) -> Result<Vec<String>, String> {
+    Ok(custom_words(Path::new(vault_path))?.into_iter().collect())
+}
+
+impl Dictionaries {
+    fn get(&self, language: &str, settings: &VaultSettings) -> Result<Arc<Dictionary>, String> {
+        let wanted = language_key(language);
+        let file = settings
+            .spellcheck
+            .dictionaries
+            .iter()
+            .map(PathBuf::from)
+            .find(|dic| {
+                dic.file_stem()
+                    .is_some_and(|stem| language_key(&stem.to_string_lossy()) == wanted)
+            });
+        let key = match &file {
+            Some(dic) => dic.to_string_lossy().to_string(),
+            None if wanted == "en" || wanted == "en_us" => "en_US".to_string(),
+            None => return Err(format!("No dictionary for {}", language)),
+        };
+
+        let mut loaded = self
+            .loaded
+            .lock()
+            .map_err(|_| "Spell check is unavailable".to_string())?;
+        if let Some(dictionary) = loaded.get(&key) {
+            return Ok(dictionary.clone());
+        }
+        let dictionary = match &file {
+            Some(dic) => load_dictionary(dic)?,
+            None => Dictionary::new(EN_US_AFF, EN_US_DIC)
+                .map_err(|e| format!("Failed to load dictionary: {}", e))?,
+        };
+        let dictionary = Arc::new(dictionary);
+        loaded.insert(key, dictionary.clone());
+        Ok(dictionary)
+    }
+}
+
+fn load_dictionary(dic: &Path) -> Result<Dictionary, String> {
+    let aff = dic.with_extension("aff");
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Failed to read {} (dictionaries must be UTF-8): {}",
+                path.display(),
+                e
+            )
+        })
+    };
+    Dictionary::new(&read(&aff)?, &read(dic)?)
+        .map_err(|e| format!("Failed to load {}: {}", dic.display(), e))
+}
+
+/// `en-US`, `en_us` and `EN_US` all name the same language.
+fn language_key(language: &str) -> String {
+    language.trim().replace('-', "_").to_lowercase()
+}
+
+fn custom_words(root: &Path) -> Result<BTreeSet<String>, String> {
+    let path = vault::state_dir(root).join(DICTIONARY_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
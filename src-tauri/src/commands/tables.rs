@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use super::files::{edit_note, FileError, TextSource};
+use crate::markdown::tables::{self, Alignment, Table};
+use crate::markdown::{protected_lines, LineBuffer};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParsedTable {
+    /// 1-based line of the header row.
+    pub start_line: usize,
+    /// 1-based line of the last row.
+    pub end_line: usize,
+    #[serde(flatten)]
+    pub table: Table,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TableOperation {
+    InsertRow {
+        index: usize,
+    },
+    DeleteRow {
+        index: usize,
+    },
+    InsertColumn {
+        index: usize,
+        header: Option<String>,
+    },
+    DeleteColumn {
+        index: usize,
+    },
+    SortByColumn {
+        column: usize,
+        descending: Option<bool>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CsvTableOptions {
+    pub has_header: bool,
+    /// Force a delimiter instead of detecting comma, semicolon or tab.
+    pub delimiter: Option<char>,
+    /// Longer cells are truncated with an ellipsis.
+    pub max_column_width: Option<usize>,
+    /// Further rows are summarised in a footer line.
+    pub max_rows: Option<usize>,
+}
+
+impl Default for CsvTableOptions {
+    fn default() -> Self {
+        Self {
+            has_header: true,
+            delimiter: None,
+            max_column_width: None,
+            max_rows: None,
+        }
+    }
+}
+
+struct LocatedTable {
+    buffer: LineBuffer,
+    start: usize,
+    end: usize,
+    table: Table,
+}
+
+fn locate_table(content: &str, line_number: usize) -> Result<LocatedTable, String> {
+    let buffer = LineBuffer::parse(content);
+    let lines = buffer.as_strs();
+    let excluded = protected_lines(&lines);
+
+    let (start, end) = line_number
+        .checked_sub(1)
+        .and_then(|idx| tables::table_at(&lines, &excluded, idx))
+        .ok_or_else(|| format!("No table found at line {}", line_number))?;
+    let table = tables::parse(&lines[start..end]);
+
+    Ok(LocatedTable {
+        buffer,
+        start,
+        end,
+        table,
+    })
+}
+
+/// Replaces the table containing `line_number` with what `edit` makes of
+/// it, holding the note's lock from the read to the write.
+fn edit_table(
+    path: &Path,
+    operation: &str,
+    line_number: usize,
+    edit: impl FnOnce(Table) -> Result<Table, String>,
+) -> Result<ParsedTable, FileError> {
+    let (mut start, mut end) = (0, 0);
+    let content = edit_note(path, operation, |content| {
+        let mut located = locate_table(content, line_number)?;
+        let table = edit(located.table)?;
+        if table.headers.is_empty() {
+            return Err("A table needs at least one column".to_string());
+        }
+        let rendered = tables::render(&table);
+        start = located.start;
+        end = located.start + rendered.len();
+        located
+            .buffer
+            .lines
+            .splice(located.start..located.end, rendered);
+        Ok(located.buffer.render())
+    })?;
+
+    Ok(ParsedTable {
+        start_line: start + 1,
+        end_line: end,
+        table: tables::parse(&LineBuffer::parse(&content).as_strs()[start..end]),
+    })
+}
+
+fn read_table(path: &Path, line_number: usize) -> Result<LocatedTable, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    locate_table(&content, line_number)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn parse_table(path: &str, line_number: usize) -> Result<ParsedTable, String> {
+    let located = read_table(Path::new(path), line_number)?;
+    Ok(ParsedTable {
+        start_line: located.start + 1,
+        end_line: located.end,
+        table: located.table,
+    })
+}
+
+/// Replaces the table containing `line_number` with `table`, re-rendered
+/// with aligned columns.
+#[tauri::command]
+pub fn update_table(
+    path: &str,
+    line_number: usize,
+    table: Table,
+) -> Result<ParsedTable, FileError> {
+    edit_table(Path::new(path), "update_table", line_number, |_| {
+        let mut table = table;
+        let columns = table.headers.len();
+        table.alignments.resize(columns, Alignment::None);
+        for row in &mut table.rows {
+            row.resize(columns, String::new());
+        }
+        Ok(table)
+    })
+}
+
+#[tauri::command]
+pub fn table_operation(
+    path: &str,
+    line_number: usize,
+    op: TableOperation,
+) -> Result<ParsedTable, FileError> {
+    edit_table(
+        Path::new(path),
+        "table_operation",
+        line_number,
+        |mut table| {
+            apply_operation(&mut table, op)?;
+            Ok(table)
+        },
+    )
+}
+
+fn apply_operation(table: &mut Table, op: TableOperation) -> Result<(), String> {
+    let columns = table.headers.len();
+    match op {
+        TableOperation::InsertRow { index } => {
+            let index = index.min(table.rows.len());
+            table.rows.insert(index, vec![String::new(); columns]);
+        }
+        TableOperation::DeleteRow { index } => {
+            if index >= table.rows.len() {
+                return Err(format!("Row {} does not exist", index));
+            }
+            table.rows.remove(index);
+        }
+        TableOperation::InsertColumn { index, header } => {
+            let index = index.min(columns);
+            table.headers.insert(index, header.unwrap_or_default());
+            table.alignments.insert(index, Alignment::None);
+            for row in &mut table.rows {
+                row.insert(index, String::new());
+            }
+        }
+        TableOperation::DeleteColumn { index } => {
+            if index >= columns {
+                return Err(format!("Column {} does not exist", index));
+            }
+            if columns == 1 {
+                return Err("Cannot delete the only column of a table".to_string());
+            }
+            table.headers.remove(index);
+            table.alignments.remove(index);
+            for row in &mut table.rows {
+                row.remove(index);
+            }
+        }
+        TableOperation::SortByColumn { column, descending } => {
+            if column >= columns {
+                return Err(format!("Column {} does not exist", column));
+            }
+            table
+                .rows
+                .sort_by(|a, b| compare_cells(&a[column], &b[column]));
+            if descending.unwrap_or(false) {
+                table.rows.reverse();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Numbers sort numerically, everything else case-insensitively.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn csv_to_markdown_table(
+    source: TextSource,
+    options: Option<CsvTableOptions>,
+) -> Result<String, String> {
+    let csv_content = source.load()?;
+    let options = options.unwrap_or_default();
+    let delimiter = match options.delimiter {
+        Some(ch) if ch.is_ascii() => ch as u8,
+        Some(ch) => return Err(format!("Unsupported delimiter: {}", ch)),
+        None => detect_delimiter(&csv_content),
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv_content.as_bytes());
+
+    let mut records: Vec<Vec<String>> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to parse CSV: {}", e))?;
+        records.push(
+            record
+                .iter()
+                .map(|cell| csv_cell(cell, options.max_column_width))
+                .collect(),
+        );
+    }
+    if records.is_empty() {
+        return Err("CSV contains no rows".to_string());
+    }
+
+    let columns = records.iter().map(Vec::len).max().unwrap_or(0);
+    let headers = if options.has_header {
+        records.remove(0)
+    } else {
+        (1..=columns).map(|i| format!("Column {}", i)).collect()
+    };
+
+    let hidden = match options.max_rows {
+        Some(max) if records.len() > max => {
+            let hidden = records.len() - max;
+            records.truncate(max);
+            hidden
+        }
+        _ => 0,
+    };
+
+    let mut headers = headers;
+    headers.resize(columns, String::new());
+    for row in &mut records {
+        row.resize(columns, String::new());
+    }
+
+    let table = Table {
+        headers,
+        alignments: vec![Alignment::None; columns],
+        rows: records,
+    };
+    let mut markdown = tables::render(&table).join("\n");
+    markdown.push('\n');
+    if hidden > 0 {
+        markdown.push_str(&format!("\n… {} more rows\n", hidden));
+    }
+
+    Ok(markdown)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn markdown_table_to_csv(path: &str, line_number: usize) -> Result<String, String> {
+    let located = read_table(Path::new(path), line_number)?;
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer
+        .write_record(&located.table.headers)
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    for row in &located.table.rows {
+        let cells: Vec<String> = row.iter().map(|cell| cell.replace("<br>", "\n")).collect();
+        writer
+            .write_record(&cells)
+            .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+/// Picks whichever of comma, semicolon or tab splits the first few records
+/// (outside quotes) most consistently.
+fn detect_delimiter(content: &str) -> u8 {
+    let sample = sample_records(content, 10);
+    let mut best = (b',', 0usize);
+
+    for candidate in [b',', b';', b'\t'] {
+        let counts: Vec<usize> = sample
+            .iter()
+            .map(|line| count_unquoted(line, candidate))
+            .collect();
+        let Some(&first) = counts.first() else { break };
+        let consistent = counts.iter().filter(|c| **c == first).count();
+        let score = if first == 0 {
+            0
+        } else {
+            consistent * 100 + first
+        };
+        if score > best.1 {
+            best = (candidate, score);
+        }
+    }
+
+    best.0
+}
+
+/// The first `limit` records that aren't blank. A line break inside quotes
+/// doesn't end a record.
+fn sample_records(content: &str, limit: usize) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (idx, byte) in content.bytes().enumerate() {
+        match byte {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => {
+                let record = &content[start..idx];
+                start = idx + 1;
+                if !record.trim().is_empty() {
+                    records.push(record);
+                    if records.len() == limit {
+                        return records;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !content[start..].trim().is_empty() {
+        records.push(&content[start..]);
+    }
+    records
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            in_quotes = !in_quotes;
+        } else if byte == delimiter && !in_quotes {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// A cell for the table, cut to `max_width` characters of its text before
+/// line breaks become `<br>`, so a cut never lands inside one.
+fn csv_cell(cell: &str, max_width: Option<usize>) -> String {
+    let cell = cell.trim().replace("\r\n", "\n");
+    let cell = match max_width {
+        Some(max) if max > 0 && cell.chars().count() > max => {
+            let mut truncated: String = cell.chars().take(max - 1).collect();
+            truncated.push('…');
+            truncated
+        }
+        _ => cell,
+    };
+    cell.replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_line_breaks_do_not_confuse_delimiter_detection() {
+        let csv = "name;note\n\"Ann\";\"one, two,\nthree, four\"\n\"Bob\";\"five, six\"\n";
+        assert_eq!(sample_records(csv, 10).len(), 3);
+        assert_eq!(detect_delimiter(csv), b';');
+        assert_eq!(detect_delimiter("a,b\n1,2\n"), b',');
+    }
+
+    #[test]
+    fn cells_are_cut_before_line_breaks_become_br() {
+        assert_eq!(csv_cell("ab\r\ncdef", Some(5)), "ab<br>c…");
+        assert_eq!(csv_cell(" ab\ncd ", Some(5)), "ab<br>cd");
+        assert_eq!(csv_cell("ab\ncd", None), "ab<br>cd");
+    }
+}
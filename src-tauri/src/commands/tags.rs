@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::files::{path_entry, FileEntry};
+use crate::markdown::{frontmatter, tags};
+use crate::vault::{self, settings::VaultSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagSource {
+    Frontmatter,
+    Body,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagOccurrence {
+    pub path: String,
+    /// 1-based; for frontmatter tags, the line the tag is listed on.
+    pub line_number: usize,
+    pub source: TagSource,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultTag {
+    /// Every occurrence, so a note can count more than once.
+    pub count: usize,
+    /// Notes with the tag.
+    pub note_count: usize,
+    pub occurrences: Vec<TagOccurrence>,
+}
+
+/// Every tag in the vault's notes, frontmatter `tags` and inline `#tags`,
+/// with where each one is used. Tags are matched ignoring case and keyed by
+/// their first spelling; nested tags like `#project/alpha` are their own
+/// entries. Inline tags in code and heading markers aren't tags.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_vault_tags(vault_path: String) -> Result<BTreeMap<String, VaultTag>, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    // Keyed by lowercased tag, with the spelling first seen.
+    let mut found: BTreeMap<String, (String, VaultTag)> = BTreeMap::new();
+    for note in vault::notes(root, &settings) {
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let path = note.to_string_lossy().to_string();
+        let mut tagged: Vec<String> = Vec::new();
+        for (tag, line_number, source) in note_tag_lines(&content) {
+            let key = tag.to_lowercase();
+            let (_, entry) = found
+                .entry(key.clone())
+                .or_insert_with(|| (tag, VaultTag::default()));
+            entry.count += 1;
+            entry.occurrences.push(TagOccurrence {
+                path: path.clone(),
+                line_number,
+                source,
+            });
+            if !tagged.contains(&key) {
+                entry.note_count += 1;
+                tagged.push(key);
+            }
+        }
+    }
+    Ok(found.into_values().collect())
+}
+
+/// The notes tagged `tag`, in frontmatter or inline, including those with
+/// a tag nested under it: `project` also finds `#project/alpha`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn get_notes_by_tag(vault_path: String, tag: String) -> Result<Vec<FileEntry>, String> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    Ok(vault::notes(root, &settings)
+        .into_iter()
+        .filter(|note| {
+            fs::read_to_string(note).is_ok_and(|content| {
+                let fm = frontmatter::parse_note(&content)
+                    .map(|(fm, _)| fm)
+                    .unwrap_or_default();
+                tags::note_tags(&fm, &content)
+                    .iter()
+                    .any(|t| tags::tag_matches(t, &tag))
+            })
+        })
+        .map(|note| path_entry(&note))
+        .collect())
+}
+
+/// A note's tags in file order, frontmatter ones first.
+fn note_tag_lines(content: &str) -> Vec<(String, usize, TagSource)> {
+    let fm = frontmatter::parse_note(content)
+        .map(|(fm, _)| fm)
+        .unwrap_or_default();
+    let yaml_lines: Vec<&str> = match frontmatter::split(content).yaml {
+        Some(yaml) => yaml.lines().collect(),
+        None => Vec::new(),
+    };
+    let tags_key = yaml_lines.iter().position(|line| {
+        line.split_once(':')
+            .is_some_and(|(key, _)| key.trim().eq_ignore_ascii_case("tags"))
+    });
+    let mut lines: Vec<(String, usize, TagSource)> = frontmatter::tags(&fm)
+        .into_iter()
+        .map(|tag| {
+            // The YAML starts on line 2, after the opening `---`.
+            let listed = tags_key.and_then(|start| {
+                yaml_lines[start..]
+                    .iter()
+                    .position(|line| line.contains(tag.as_str()))
+                    .map(|idx| start + idx)
+            });
+            let line_number = listed.or(tags_key).map_or(1, |idx| idx + 2);
+            (tag, line_number, TagSource::Frontmatter)
+        })
+        .collect();
+    lines.extend(
+        tags::inline_tag_lines(content)
+            .into_iter()
+            .map(|(tag, line)| (tag, line, TagSource::Body)),
+    );
+    lines
+}
@@ -0,0 +1,19 @@
+use tauri::State;
+
+use crate::tasks::{TaskInfo, TaskManager};
+
+#[tauri::command]
+pub fn list_tasks(tasks: State<'_, TaskManager>) -> Vec<TaskInfo> {
+    tasks.list()
+}
+
+/// Asks a running task to stop; it ends with a cancelled `task://failed`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn cancel_task(tasks: State<'_, TaskManager>, id: u64) -> Result<(), String> {
+    if tasks.cancel(id) {
+        Ok(())
+    } else {
+        Err(format!("No running task with id {}", id))
+    }
+}
@@ -0,0 +1,428 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+
+use super::backlinks::load_index;
+use super::caches::CacheKeys;
+use super::files::{write_atomic, FileError};
+use crate::markdown::lint::Diagnostic;
+use crate::markdown::templates::{self, TemplateContext};
+use crate::markdown::{frontmatter, links};
+use crate::vault::settings::{FolderTemplate, NewNoteLocation, VaultSettings};
+use crate::vault::{self, external};
+
+/// Characters of a template's body shown in the gallery.
+const PREVIEW_CHARS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateList {
+    /// The `templates.folder` setting, vault-relative; `None` when unset.
+    pub folder: Option<String>,
+    /// Whether the folder is set and exists. When it isn't, `templates` is
+    /// empty.
+    pub folder_found: bool,
+    pub templates: Vec<TemplateInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub path: String,
+    pub file_name: String,
+    /// The template's `title` frontmatter, or its file name without the
+    /// extension and with `-` and `_` as spaces.
+    pub name: String,
+    /// Names of the `{{...}}` tokens it uses, built in or not.
+    pub variables: Vec<String>,
+    /// The start of its body as a note made today would have it.
+    pub preview: String,
+    /// Its `description` frontmatter.
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateValidation {
+    pub path: String,
+    /// Whether the file exists; a missing one has no problems.
+    pub found: bool,
+    pub problems: Vec<Diagnostic>,
+}
+
+/// The templates in the vault's `templates.folder` and below it, by path,
+/// for picking one to make a note from.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn list_templates(vault_path: &str) -> Result<TemplateList, String> {
+    let root = Path::new(vault_path);
+    let folder = VaultSettings::load(root)?
+        .templates
+        .folder
+        .map(|folder| folder.trim_matches('/').to_string())
+        .filter(|folder| !folder.is_empty());
+    let dir = folder
+        .as_ref()
+        .map(|folder| root.join(folder))
+        .filter(|dir| dir.is_dir());
+    let templates = match &dir {
+        Some(dir) => vault::markdown_files(dir)
+            .iter()
+            .filter_map(|path| {
+                fs::read_to_string(path)
+                    .ok()
+                    .map(|content| template_info(path, &content))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(TemplateList {
+        folder,
+        folder_found: dir.is_some(),
+        templates,
+    })
+}
+
+/// Problems with the template at `path` that would show in notes made from
+/// it, such as misspelled or unknown tokens, by line.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn validate_template(path: &str) -> Result<TemplateValidation, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(TemplateValidation {
+                path: path.to_string(),
+                found: false,
+                problems: Vec::new(),
+            })
+        }
+        Err(e) => return Err(format!("Failed to read template {}: {}", path, e)),
+    };
+    Ok(TemplateValidation {
+        path: path.to_string(),
+        found: true,
+        problems: templates::check(&content),
+    })
+}
+
+fn template_info(path: &Path, content: &str) -> TemplateInfo {
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(['-', '_'], " ");
+    // Rendered first, as `{{date}}` in frontmatter isn't valid YAML.
+    let rendered = templates::render(content, &TemplateContext::new(stem.trim()));
+    let (fm, split) = frontmatter::parse_note(&rendered)
+        .unwrap_or_else(|_| (Default::default(), frontmatter::split(&rendered)));
+    let text = |key: &str| {
+        let value = fm.get(key).and_then(|value| value.as_str()).map(str::trim);
+        value.filter(|value| !value.is_empty()).map(str::to_string)
+    };
+    let body = split.body.trim();
+    let mut preview: String = body.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < body.len() {
+        preview.push('…');
+    }
+    TemplateInfo {
+        path: path.to_string_lossy().to_string(),
+        file_name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        name: text("title").unwrap_or_else(|| stem.trim().to_string()),
+        variables: templates::variables(content),
+        preview,
+        description: text("description"),
+    }
+}
+
+/// The most specific rule for `folder` (vault-relative): the one whose
+/// pattern has the most literal characters, earlier rules winning ties.
+fn folder_rule<'a>(rules: &'a [FolderTemplate], folder: &str) -> Option<&'a FolderTemplate> {
+    let mut prefixes: Vec<&str> = folder
+        .match_indices('/')
+        .map(|(idx, _)| &folder[..idx])
+        .collect();
+    prefixes.push(folder);
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| {
+            glob::Pattern::new(rule.pattern.trim_matches('/'))
+                .is_ok_and(|pattern| prefixes.iter().any(|prefix| pattern.matches(prefix)))
+        })
+        .max_by_key(|(idx, rule)| {
+            let literal = rule
+                .pattern
+                .chars()
+                .filter(|c| !matches!(c, '*' | '?' | '[' | ']'))
+                .count();
+            (literal, Reverse(*idx))
+        })
+        .map(|(_, rule)| rule)
+}
+
+/// Creates a note titled `title` in `folder` (relative to the vault) from
+/// the template configured for that folder, falling back to the vault's
+/// default template or an empty note. Returns the new note's path.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn create_note_in_folder(
+    vault_path: &str,
+    folder: &str,
+    title: &str,
+) -> Result<String, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    if Path::new(folder)
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(format!("Folder is outside the vault: {}", folder));
+    }
+    let settings = VaultSettings::load(root)?;
+    let dir = root.join(folder.trim_matches('/'));
+    if external::is_read_only(&dir) {
+        return Err(FileError::locked(&dir.to_string_lossy()).to_string());
+    }
+    let content = new_note_content(root, &settings, &dir, title)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let path = vault::unique_path(&dir, &vault::safe_file_name(title), "md");
+    write_atomic(&path, content)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Creates the note a link to a missing note asks for and returns its
+/// path. `link_text` is the link as written, e.g. `[[Idea|the idea]]`, or
+/// just its target. A path in the link, as in `[[projects/Idea]]`, is
+/// where the note goes; otherwise it goes beside `source_note` or in the
+/// inbox folder, per the vault's `new_notes` settings. The note is named
+/// and titled after the link's target, gets the template for its folder
+/// and keeps the link's alias in `aliases`. When the link already finds a
+/// note, e.g. because another window just created it, that note's path is
+/// returned instead.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn create_note_from_link(
+    keys: State<'_, CacheKeys>,
+    vault_path: &str,
+    source_note: &str,
+    link_text: &str,
+) -> Result<String, String> {
+    let (root, index) = load_index(&keys, vault_path)?;
+    let written = if link_text.contains("[[") {
+        link_text.trim().trim_start_matches('!').to_string()
+    } else {
+        format!("[[{}]]", link_text.trim())
+    };
+    let link = links::wikilinks(&written)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Not a link: {}", link_text))?;
+    let target = link.target.trim();
+    let target = target
+        .strip_suffix(".md")
+        .unwrap_or(target)
+        .trim_matches('/');
+    if target.is_empty() {
+        return Err(format!("Link has no target: {}", link_text));
+    }
+    if Path::new(target)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Link points outside the vault: {}", link_text));
+    }
+
+    let source = match Path::new(source_note) {
+        path if path.is_absolute() => path.to_path_buf(),
+        path => root.join(path),
+    };
+    if let Some(found) = index
+        .position(&source)
+        .and_then(|from| index.resolve_wikilink(from, target))
+    {
+        return Ok(index.notes[found].path.to_string_lossy().to_string());
+    }
+
+    let settings = VaultSettings::load(&root)?;
+    let (dir, title) = match target.rsplit_once('/') {
+        Some((folder, name)) => (root.join(folder), name),
+        None => {
+            let dir = match settings.new_notes.location {
+                NewNoteLocation::SameFolder => source
+                    .parent()
+                    .filter(|dir| dir.starts_with(&root))
+                    .unwrap_or(&root)
+                    .to_path_buf(),
+                NewNoteLocation::Inbox => root.join(
+                    settings
+                        .new_notes
+                        .inbox_folder
+                        .as_deref()
+                        .unwrap_or("")
+                        .trim_matches('/'),
+                ),
+            };
+            (dir, target)
+        }
+    };
+    let path = dir.join(format!("{}.md", vault::safe_file_name(title)));
+    if path.is_file() {
+        return Ok(path.to_string_lossy().to_string());
+    }
+
+    let mut content = new_note_content(&root, &settings, &dir, title)?;
+    if let Some(alias) = link
+        .alias
+        .as_deref()
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty() && *alias != title)
+    {
+        content = frontmatter::update(&content, |mapping| {
+            let mut aliases = match mapping.get("aliases") {
+                Some(serde_yaml::Value::Sequence(existing)) => existing.clone(),
+                _ => Vec::new(),
+            };
+            aliases.push(serde_yaml::Value::String(alias.to_string()));
+            mapping.insert("aliases".into(), serde_yaml::Value::Sequence(aliases));
+        })?;
+    }
+    if external::is_read_only(&path) {
+        return Err(FileError::locked(&path.to_string_lossy()).to_string());
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    // Never over a note another window created since the check above.
+    match File::options().write(true).create_new(true).open(&path) {
+        Ok(mut file) => file
+            .write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// A new note titled `title` in the folder `dir`, from the template
+/// configured for that folder with its default frontmatter, falling back to
+/// the vault's default template or an empty note.
+fn new_note_content(
+    root: &Path,
+    settings: &VaultSettings,
+    dir: &Path,
+    title: &str,
+) -> Result<String, String> {
+    let rule = folder_rule(
+        &settings.templates.folders,
+        &vault::relative_path(root, dir),
+    );
+
+    let template_path = rule
+        .and_then(|rule| rule.template.as_ref())
+        .or(settings.templates.default_template.as_ref());
+    let template = match template_path {
+        Some(rel) => fs::read_to_string(root.join(rel))
+            .map_err(|e| format!("Failed to read template {}: {}", rel, e))?,
+        None => String::new(),
+    };
+    let mut content = templates::render(&template, &TemplateContext::new(title));
+
+    if let Some(defaults) = rule
+        .map(|rule| &rule.frontmatter)
+        .filter(|fm| !fm.is_empty())
+    {
+        let defaults = serde_yaml::to_value(defaults)
+            .map_err(|e| format!("Invalid default frontmatter: {}", e))?;
+        content = frontmatter::update(&content, |mapping| {
+            if let serde_yaml::Value::Mapping(defaults) = defaults {
+                for (key, value) in defaults {
+                    if !mapping.contains_key(&key) {
+                        mapping.insert(key, value);
+                    }
+                }
+            }
+        })?;
+    }
+
+    Ok(content)
+}
+
+/// The daily note for `date`, today by default, named by the vault's
+/// `daily_notes` settings (`YYYY-MM-DD` in the vault root by default) and
+/// created from its template if it doesn't exist yet. Returns the path and
+/// whether it was created.
+pub(crate) fn daily_note(
+    root: &Path,
+    settings: &VaultSettings,
+    date: Option<NaiveDate>,
+) -> Result<(PathBuf, bool), String> {
+    let daily = &settings.daily_notes;
+    let mut context = TemplateContext::new("");
+    if let Some(date) = date {
+        let at = date
+            .and_time(context.now.time())
+            .and_local_timezone(Local)
+            .earliest();
+        context.now = at.ok_or_else(|| format!("Invalid local date: {}", date))?;
+    }
+    let name = context
+        .now
+        .format(&templates::chrono_format(daily_note_format(settings)))
+        .to_string();
+    let folder = daily_note_folder(settings);
+    let path = root.join(folder).join(format!("{}.md", name));
+    if Path::new(folder)
+        .join(&name)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!(
+            "Daily note is outside the vault: {}",
+            vault::relative_path(root, &path)
+        ));
+    }
+    if path.is_file() {
+        return Ok((path, false));
+    }
+
+    let template = match &daily.template {
+        Some(rel) => fs::read_to_string(root.join(rel))
+            .map_err(|e| format!("Failed to read template {}: {}", rel, e))?,
+        None => String::new(),
+    };
+    let title = Path::new(&name)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let content = templates::render(&template, &TemplateContext { title, ..context });
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    write_atomic(&path, content)?;
+    Ok((path, true))
+}
+
+/// The moment-style format daily notes are named with.
+pub(crate) fn daily_note_format(settings: &VaultSettings) -> &str {
+    let format = settings.daily_notes.date_format.as_deref();
+    format
+        .filter(|f| !f.trim().is_empty())
+        .unwrap_or("YYYY-MM-DD")
+}
+
+/// The vault-relative folder daily notes go in; empty for the vault root.
+pub(crate) fn daily_note_folder(settings: &VaultSettings) -> &str {
+    settings
+        .daily_notes
+        .folder
+        .as_deref()
+        .unwrap_or("")
+        .trim_matches('/')
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::files::{write_note, FileError};
+use super::rename::{rename_paths, LinkUpdates};
+use crate::markdown::{frontmatter, heading, protected_lines, LineBuffer};
+use crate::vault::journal::Journal;
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, portable, settings::VaultSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleSyncDirection {
+    /// Set the first H1 to the file name.
+    FilenameToHeading,
+    /// Rename the file after the first H1.
+    HeadingToFilename,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TitleSync {
+    /// Where the note is now.
+    pub path: String,
+    /// False when heading and file name already agreed.
+    pub changed: bool,
+    /// The H1 before and after; `None` before when the note had none.
+    pub old_heading: Option<String>,
+    pub heading: String,
+    /// The note's old path, when it was renamed.
+    pub renamed_from: Option<String>,
+    pub link_updates: LinkUpdates,
+    /// For `undo_operation`, when the note was renamed.
+    pub operation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TitleMismatch {
+    pub path: String,
+    pub heading: String,
+    /// The file name without its extension.
+    pub file_name: String,
+    /// The file name, extension included, `heading_to_filename` would give
+    /// it.
+    pub suggested_file_name: String,
+}
+
+/// Brings a note's first H1 and its file name into line, one way or the
+/// other. With `filename_to_heading` the H1 is rewritten, or added under the
+/// frontmatter when there is none. With `heading_to_filename` the note is
+/// renamed after its H1, made safe for every platform, and links to it are
+/// updated as `rename_note` does.
+#[tauri::command]
+pub fn sync_title(
+    vault_path: &str,
+    path: &str,
+    direction: TitleSyncDirection,
+) -> Result<TitleSync, FileError> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path).into());
+    }
+    let note = root.join(path);
+    if !note.starts_with(root)
+        || note
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(format!("Path is outside the vault: {}", note.display()).into());
+    }
+    // Held until the heading or the name is written, so an autosave can't
+    // land after the read and be lost.
+    let _lock = WriteLocks::global().lock(&note, "sync_title")?;
+    let content = fs::read_to_string(&note).map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut buffer = LineBuffer::parse(&content);
+    let found = first_h1(&buffer.as_strs());
+    let old_heading = found.as_ref().map(|(_, text)| text.clone());
+    let stem = note
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut sync = TitleSync {
+        path: note.to_string_lossy().to_string(),
+        changed: false,
+        old_heading: old_heading.clone(),
+        heading: old_heading.clone().unwrap_or_default(),
+        renamed_from: None,
+        link_updates: LinkUpdates::default(),
+        operation_id: None,
+    };
+
+    match direction {
+        TitleSyncDirection::FilenameToHeading => {
+            if old_heading.as_deref() == Some(stem.as_str()) {
+                return Ok(sync);
+            }
+            match found {
+                Some((idx, _)) => buffer.lines[idx] = format!("# {}", stem),
+                None => {
+                    let at = frontmatter::line_count(&buffer.as_strs());
+                    let mut heading = vec![format!("# {}", stem)];
+                    if buffer
+                        .lines
+                        .get(at)
+                        .is_some_and(|line| !line.trim().is_empty())
+                    {
+                        heading.push(String::new());
+                    }
+                    if at == buffer.lines.len() {
+                        buffer.set_trailing_newline(true);
+                    }
+                    buffer.lines.splice(at..at, heading);
+                }
+            }
+            write_note(&note, buffer.render())?;
+            sync.changed = true;
+            sync.heading = stem;
+        }
+        TitleSyncDirection::HeadingToFilename => {
+            let Some(heading) = old_heading else {
+                return Err(format!("Note has no H1 heading: {}", path).into());
+            };
+            let name = file_name_for(&heading, &note);
+            let renamed = note.with_file_name(&name);
+            if renamed == note {
+                return Ok(sync);
+            }
+            let mut journal = Journal::start(root, "sync_title");
+            let outcome = rename_paths(
+                root,
+                &[(note.clone(), renamed.clone())],
+                false,
+                Some(&mut journal),
+            );
+            if let Some(Some(error)) = outcome.errors.into_iter().next() {
+                return Err(error.into());
+            }
+            sync.changed = true;
+            sync.path = renamed.to_string_lossy().to_string();
+            sync.renamed_from = Some(note.to_string_lossy().to_string());
+            sync.link_updates = outcome.updates;
+            sync.operation_id = journal.finish(&VaultSettings::load(root)?.journal)?;
+        }
+    }
+    Ok(sync)
+}
+
+/// Notes whose first H1 doesn't match their file name, skipping ignored
+/// paths and notes without an H1.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn check_title_mismatches(vault_path: &str) -> Result<Vec<TitleMismatch>, String> {
+    let root = Path::new(vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let settings = VaultSettings::load(root)?;
+    let mut mismatches = Vec::new();
+    for note in vault::notes(root, &settings) {
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let Some((_, heading)) = first_h1(&lines) else {
+            continue;
+        };
+        let file_name = note
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let suggested_file_name = file_name_for(&heading, &note);
+        // A heading with characters file names can't hold agrees with the
+        // name it would be given.
+        if heading == file_name
+            || note
+                .file_name()
+                .is_some_and(|name| *name == *suggested_file_name)
+        {
+            continue;
+        }
+        mismatches.push(TitleMismatch {
+            path: note.to_string_lossy().to_string(),
+            heading,
+            file_name,
+            suggested_file_name,
+        });
+    }
+    Ok(mismatches)
+}
+
+/// The line index and text of the first `# ` heading, outside frontmatter
+/// and code blocks.
+fn first_h1(lines: &[&str]) -> Option<(usize, String)> {
+    let protected = protected_lines(lines);
+    lines
+        .iter()
+        .zip(protected)
+        .enumerate()
+        .filter(|(_, (_, protected))| !protected)
+        .find_map(|(idx, (line, _))| match heading(line) {
+            Some((1, text)) if !text.trim().is_empty() => Some((idx, text.trim().to_string())),
+            _ => None,
+        })
+}
+
+/// The file name a note titled `heading` gets, keeping `note`'s extension.
+/// Emphasis, code and link brackets are dropped rather than turned into
+/// `-`.
+fn file_name_for(heading: &str, note: &Path) -> String {
+    let plain = heading
+        .replace("[[", "")
+        .replace("]]", "")
+        .replace(['*', '`'], "");
+    let extension = note
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "md".to_string());
+    portable::safe_name(&format!("{}.{}", vault::safe_file_name(&plain), extension))
+}
@@ -0,0 +1,221 @@
+use notify_debouncer_full::notify::event::{ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{EventKind, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use super::autosave::AutosaveQueue;
+use super::monitor::HealthMonitor;
+use super::reminders::ReminderScheduler;
+use crate::vault::own_writes::OwnWrites;
+use crate::vault::{self, settings::VaultSettings, write_ledger};
+use crate::vaults::{Released, VaultStateRegistry, VaultWatcher};
+
+const CREATED_EVENT: &str = "vault://file-created";
+const MODIFIED_EVENT: &str = "vault://file-modified";
+const DELETED_EVENT: &str = "vault://file-deleted";
+const RENAMED_EVENT: &str = "vault://file-renamed";
+/// How long a path must be quiet before its changes are reported, so an
+/// editor's write-then-rename save arrives as one event.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    /// The path before a rename; only on `vault://file-renamed`.
+    pub old_path: Option<String>,
+    pub is_dir: bool,
+    /// For a file created, modified or renamed to: whether its content
+    /// differs from what GraphNotes last wrote to it. Only known in vaults
+    /// keeping a write ledger, for files saved since it was turned on.
+    pub external: Option<bool>,
+}
+
+/// Watches the vault at `path` for the calling window and reports changes
+/// to files and folders with `vault://file-created`, `-modified`,
+/// `-deleted` and `-renamed` events, sent to every window showing the
+/// vault. Hidden files and folders are ignored, as by `grep_search`, and so
+/// are the app's own writes and renames. A window watches one vault at a time; the
+/// vault it showed before stops being watched once no window shows it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn start_watching(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, VaultStateRegistry>,
+    path: String,
+) -> Result<(), String> {
+    let root = fs::canonicalize(&path).map_err(|_| format!("Vault does not exist: {}", path))?;
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", path));
+    }
+    attach_window(&app, &registry, &root, window.label())
+}
+
+/// Stops watching for the calling window; the vault stays watched while
+/// other windows show it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn stop_watching(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, VaultStateRegistry>,
+) -> Result<(), String> {
+    if let Some(released) = registry.detach(window.label())? {
+        release(&app, released)?;
+    }
+    Ok(())
+}
+
+/// Adds the window labelled `label` to those showing the vault at the
+/// canonical `root`, starting to watch it if it is the first.
+pub(crate) fn attach_window(
+    app: &AppHandle,
+    registry: &VaultStateRegistry,
+    root: &Path,
+    label: &str,
+) -> Result<(), String> {
+    let started = registry.attach(root, label, |root| {
+        let watcher = watch(app, root)?;
+        app.state::<ReminderScheduler>().schedule(app, root);
+        app.state::<HealthMonitor>().start(app, root);
+        Ok(watcher)
+    })?;
+    if let Some(released) = started {
+        release(app, released)?;
+    }
+    Ok(())
+}
+
+/// Tears down a vault no window shows any more: stops its watcher, its
+/// reminders and health checks and writes its queued autosaves. Must not
+/// be called holding the registry's lock, as stopping waits for the
+/// watcher's event handler.
+pub(crate) fn release(app: &AppHandle, released: Released) -> Result<(), String> {
+    released.watcher.stop();
+    app.state::<ReminderScheduler>().stop(&released.root);
+    app.state::<HealthMonitor>().stop(&released.root);
+    let autosaves: State<AutosaveQueue> = app.state();
+    autosaves.flush(Some(&released.root))?;
+    tracing::info!(vault = %released.root.display(), "vault closed");
+    Ok(())
+}
+
+fn watch(app: &AppHandle, root: &Path) -> Result<VaultWatcher, String> {
+    let handler = {
+        let app = app.clone();
+        let root = root.to_path_buf();
+        move |result: DebounceEventResult| {
+            if let Ok(events) = result {
+                report(&app, &root, events);
+            }
+        }
+    };
+    let mut debouncer = new_debouncer(DEBOUNCE, None, handler)
+        .map_err(|e| format!("Failed to watch vault: {}", e))?;
+    debouncer
+        .watcher()
+        .watch(root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch vault: {}", e))?;
+    debouncer.cache().add_root(root, RecursiveMode::Recursive);
+    Ok(debouncer)
+}
+
+fn report(app: &AppHandle, root: &Path, events: Vec<notify_debouncer_full::DebouncedEvent>) {
+    let windows = app.state::<VaultStateRegistry>().windows(root);
+    let ledger = match VaultSettings::load(root) {
+        Ok(settings) if settings.write_ledger => write_ledger::load(root).unwrap_or_default(),
+        _ => HashMap::new(),
+    };
+    // One event per change and path in a batch, in the order first seen.
+    let mut sent = HashSet::new();
+    let visible = |p: &Path| {
+        p.strip_prefix(root).is_ok_and(|rel| {
+            !rel.components()
+                .any(|c| vault::is_hidden(&c.as_os_str().to_string_lossy()))
+        })
+    };
+    // Notes, or folders of them, changed: reminders may have been edited,
+    // including by autosaves, which aren't reported.
+    let notes_changed = events
+        .iter()
+        .filter(|event| {
+            !matches!(
+                event.kind,
+                EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_)
+            )
+        })
+        .flat_map(|event| &event.paths)
+        .any(|p| visible(p) && (vault::is_markdown(p) || p.extension().is_none()));
+    if notes_changed {
+        app.state::<ReminderScheduler>().refresh(app, root);
+    }
+    let mut emit = |event: &'static str, path: &Path, old_path: Option<&Path>| {
+        if !visible(path) {
+            return;
+        }
+        // An atomic save can show up as the file being created.
+        if event != DELETED_EVENT && OwnWrites::global().is_own(path) {
+            return;
+        }
+        if !sent.insert((event, path.to_path_buf())) {
+            return;
+        }
+        let external = match event {
+            DELETED_EVENT => None,
+            _ => ledger
+                .get(&vault::relative_path(root, path))
+                .map(|entry| !vault::hash_file(path).is_ok_and(|hash| hash == entry.hash)),
+        };
+        let change = FileChange {
+            path: path.to_string_lossy().to_string(),
+            old_path: old_path.map(|p| p.to_string_lossy().to_string()),
+            is_dir: path.is_dir(),
+            external,
+        };
+        for window in &windows {
+            let _ = app.emit_to(window.as_str(), event, change.clone());
+        }
+    };
+
+    for event in events {
+        let paths = &event.paths;
+        match event.kind {
+            EventKind::Create(_) => paths.iter().for_each(|p| emit(CREATED_EVENT, p, None)),
+            EventKind::Remove(_) => paths.iter().for_each(|p| emit(DELETED_EVENT, p, None)),
+            // Saving through a hidden temporary file, as `write_atomic` and
+            // many editors do, is a modification.
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+                let (from, to) = (&paths[0], &paths[1]);
+                match (visible(from), visible(to)) {
+                    (true, true) => emit(RENAMED_EVENT, to, Some(from)),
+                    (false, true) => emit(MODIFIED_EVENT, to, None),
+                    (true, false) => emit(DELETED_EVENT, from, None),
+                    (false, false) => {}
+                }
+            }
+            // Half a rename, e.g. into or out of the vault: the path
+            // either appeared or went away.
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in paths {
+                    let event = if path.exists() {
+                        CREATED_EVENT
+                    } else {
+                        DELETED_EVENT
+                    };
+                    emit(event, path, None);
+                }
+            }
+            EventKind::Modify(ModifyKind::Metadata(_)) => {}
+            EventKind::Modify(_) => paths
+                .iter()
+                .filter(|p| p.is_file())
+                .for_each(|p| emit(MODIFIED_EVENT, p, None)),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use futures::stream::{self, StreamExt};
+use reqwest::{header, Client, StatusCode, Url};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::commands::files::{write_atomic, write_note, FileError};
+use crate::markdown::html::{self, HtmlConversion};
+use crate::markdown::{frontmatter, links};
+use crate::vault::write_locks::WriteLocks;
+use crate::vault::{self, link_format::LinkWriter, settings::VaultSettings};
+
+const ASSETS_DIR: &str = "assets";
+const IMAGE_DOWNLOADS: usize = 4;
+
+/// Errors from commands that fetch web pages, tagged by `kind` like
+/// `FileError`. `message` is always present for display.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WebError {
+    InvalidUrl {
+        url: String,
+        message: String,
+    },
+    /// The server answered with a non-success status.
+    Http {
+        url: String,
+        status: u16,
+        message: String,
+    },
+    /// No response arrived: DNS, connection, TLS or timeout failures.
+    Network {
+        url: String,
+        message: String,
+    },
+    /// The URL already has an archive note and `rearchive` wasn't set.
+    AlreadyArchived {
+        path: String,
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+    /// Writing the note failed. Serialized as the `FileError` itself, so a
+    /// locked or busy note reads the same as from the file commands.
+    #[serde(untagged)]
+    File(FileError),
+}
+
+impl WebError {
+    fn invalid_url(url: &str) -> Self {
+        WebError::InvalidUrl {
+            url: url.to_string(),
+            message: format!("Not an http(s) URL: {}", url),
+        }
+    }
+
+    fn http(url: &str, status: StatusCode) -> Self {
+        WebError::Http {
+            url: url.to_string(),
+            status: status.as_u16(),
+            message: format!("{} returned {}", url, status),
+        }
+    }
+
+    fn network(url: &str, error: reqwest::Error) -> Self {
+        let what = if error.is_timeout() {
+            "Timed out fetching"
+        } else {
+            "Failed to fetch"
+        };
+        WebError::Network {
+            url: url.to_string(),
+            message: format!("{} {}: {}", what, url, error),
+        }
+    }
+}
+
+impl From<String> for WebError {
+    fn from(message: String) -> Self {
+        WebError::Io { message }
+    }
+}
+
+impl From<FileError> for WebError {
+    fn from(error: FileError) -> Self {
+        WebError::File(error)
+    }
+}
+
+impl std::fmt::Display for WebError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebError::InvalidUrl { message, .. }
+            | WebError::Http { message, .. }
+            | WebError::Network { message, .. }
+            | WebError::AlreadyArchived { message, .. }
+            | WebError::Io { message } => f.write_str(message),
+            WebError::File(error) => error.fmt(f),
+        }
+    }
+}
+
+/// Saves a snapshot of a web page as a note in `destination_folder` and
+/// returns the note's path.
+///
+/// The page is converted to markdown and its images are downloaded to
+/// `assets/<note name>/` beside the note; images that fail to download keep
+/// their remote URL. The note's frontmatter records `source`,
+/// `archived_at` and `http_status`. With `rearchive`, an existing archive
+/// of the same URL is refreshed in place: other frontmatter is kept and
+/// `archived_at` becomes a list of every snapshot time.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn archive_url(
+    vault_path: String,
+    url: String,
+    destination_folder: String,
+    rearchive: Option<bool>,
+) -> Result<String, WebError> {
+    let root = Path::new(&vault_path);
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path).into());
+    }
+    let parsed = Url::parse(url.trim())
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .ok_or_else(|| WebError::invalid_url(&url))?;
+    let folder = root.join(destination_folder.trim_matches('/'));
+
+    let existing = find_archive(&folder, parsed.as_str());
+    if let Some(path) = &existing {
+        if !rearchive.unwrap_or(false) {
+            let path = path.to_string_lossy().to_string();
+            return Err(WebError::AlreadyArchived {
+                message: format!("{} is already archived at {}", url, path),
+                path,
+            });
+        }
+    }
+
+    let client = http_client()?;
+    let response = client
+        .get(parsed.clone())
+        .send()
+        .await
+        .map_err(|e| WebError::network(parsed.as_str(), e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(WebError::http(parsed.as_str(), status));
+    }
+    let base_url = response.url().clone();
+    let page = response
+        .text()
+        .await
+        .map_err(|e| WebError::network(parsed.as_str(), e))?;
+
+    // `Html` isn't `Send`, so it is parsed again after the downloads rather
+    // than held across them.
+    let (title, image_urls) = {
+        let document = Html::parse_document(&page);
+        let title = html::document_title(&document)
+            .unwrap_or_else(|| parsed.host_str().unwrap_or("Untitled").to_string());
+        (
+            title,
+            html::image_urls(html::main_content(&document), Some(&base_url)),
+        )
+    };
+
+    let note_path = match existing {
+        Some(path) => path,
+        None => vault::unique_path(&folder, &vault::safe_file_name(&title), "md"),
+    };
+    let stem = note_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let assets_dir = folder.join(ASSETS_DIR).join(&stem);
+
+    let downloaded = download_images(&client, &image_urls, &assets_dir).await;
+    let (image_targets, wiki_embeds) = local_image_targets(&note_path, &assets_dir, downloaded);
+
+    let markdown = {
+        let document = Html::parse_document(&page);
+        let conversion = HtmlConversion {
+            base_url: Some(base_url),
+            image_targets,
+            wiki_embeds,
+        };
+        html::to_markdown(html::main_content(&document), &conversion)
+    };
+
+    // Keep the previous frontmatter, if any, and swap in the new body. The
+    // note stays locked from this read until it's written.
+    fs::create_dir_all(&folder).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let _lock = WriteLocks::global()
+        .lock(&note_path, "archive_url")
+        .map_err(FileError::from)?;
+    let previous = fs::read_to_string(&note_path).unwrap_or_default();
+    let previous_yaml = frontmatter::split(&previous).yaml.unwrap_or_default();
+    let archived_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let content = frontmatter::update(&format!("---\n{}---\n{}", previous_yaml, markdown), |fm| {
+        use serde_yaml::Value;
+
+        if !fm.contains_key("title") {
+            fm.insert("title".into(), title.clone().into());
+        }
+        fm.insert("source".into(), parsed.as_str().into());
+        match fm.get_mut("archived_at") {
+            Some(Value::Sequence(times)) => times.push(archived_at.into()),
+            Some(previous) => {
+                *previous = Value::Sequence(vec![previous.clone(), archived_at.into()])
+            }
+            None => {
+                fm.insert("archived_at".into(), archived_at.into());
+            }
+        }
+        fm.insert("http_status".into(), status.as_u16().into());
+    })?;
+
+    write_note(&note_path, content)?;
+    Ok(note_path.to_string_lossy().to_string())
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HtmlToMarkdownOptions {
+    /// Page the HTML was copied from, for resolving relative URLs.
+    pub base_url: Option<String>,
+    /// Download remote images next to `note_path` and embed the local
+    /// copies instead.
+    pub download_images: bool,
+    /// The note being pasted into.
+    pub note_path: Option<String>,
+}
+
+/// Converts pasted HTML to markdown. Scripts and styles are dropped and
+/// unknown tags keep only their text.
+///
+/// With `download_images`, remote images are saved to `assets/<note name>/`
+/// beside the note, as `archive_url` does, and linked locally; images that
+/// fail to download keep their remote URL.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn html_to_markdown(
+    html: String,
+    options: Option<HtmlToMarkdownOptions>,
+) -> Result<String, WebError> {
+    let options = options.unwrap_or_default();
+    let base_url = match options
+        .base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+    {
+        Some(url) => Some(Url::parse(url).map_err(|_| WebError::invalid_url(url))?),
+        None => None,
+    };
+
+    let mut image_targets = HashMap::new();
+    let mut wiki_embeds = false;
+    if options.download_images {
+        let note = options
+            .note_path
+            .as_deref()
+            .map(Path::new)
+            .ok_or_else(|| "Downloading images needs the note's path".to_string())?;
+        let image_urls =
+            html::image_urls(Html::parse_fragment(&html).tree.root(), base_url.as_ref());
+        if !image_urls.is_empty() {
+            let stem = note
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let assets_dir = note
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(ASSETS_DIR)
+                .join(&stem);
+            let downloaded = download_images(&http_client()?, &image_urls, &assets_dir).await;
+            (image_targets, wiki_embeds) = local_image_targets(note, &assets_dir, downloaded);
+        }
+    }
+
+    let fragment = Html::parse_fragment(&html);
+    let conversion = HtmlConversion {
+        base_url,
+        image_targets,
+        wiki_embeds,
+    };
+    let markdown = html::to_markdown(fragment.tree.root(), &conversion);
+    Ok(markdown.trim_end().to_string())
+}
+
+/// Link targets for images downloaded to `assets_dir` for `note`, in the
+/// vault's link format, and whether they are wikilinks.
+fn local_image_targets(
+    note: &Path,
+    assets_dir: &Path,
+    downloaded: Vec<(String, String)>,
+) -> (HashMap<String, String>, bool) {
+    let note_dir = note.parent().unwrap_or(Path::new(""));
+    let root = vault::find_root(note).unwrap_or_else(|| note_dir.to_path_buf());
+    let format = VaultSettings::load(&root)
+        .map(|s| s.link_format)
+        .unwrap_or_default();
+    let writer = LinkWriter::new(&root, &format);
+    let targets = downloaded
+        .into_iter()
+        .map(|(url, file_name)| (url, writer.target(note_dir, &assets_dir.join(file_name))))
+        .collect();
+    (targets, writer.is_wiki())
+}
+
+fn http_client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("GraphNotes/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// An existing note in `folder` whose frontmatter `source` is `url`.
+fn find_archive(folder: &Path, url: &str) -> Option<PathBuf> {
+    vault::markdown_files(folder).into_iter().find(|note| {
+        fs::read_to_string(note)
+            .ok()
+            .and_then(|content| frontmatter::parse_note(&content).ok().map(|(fm, _)| fm))
+            .is_some_and(|fm| fm.get("source").and_then(|s| s.as_str()) == Some(url))
+    })
+}
+
+/// Downloads images into `dir`, returning each saved URL with its file
+/// name. Failed downloads are left out.
+async fn download_images(client: &Client, urls: &[String], dir: &Path) -> Vec<(String, String)> {
+    if urls.is_empty() || fs::create_dir_all(dir).is_err() {
+        return Vec::new();
+    }
+
+    let mut taken = Vec::new();
+    let planned: Vec<(String, String)> = urls
+        .iter()
+        .map(|url| {
+            let name = unique_name(image_file_name(url), &mut taken);
+            (url.clone(), name)
+        })
+        .collect();
+
+    stream::iter(planned)
+        .map(|(url, name)| async move {
+            let response = client.get(&url).send().await.ok()?;
+            if !response.status().is_success() {
+                return None;
+            }
+            let name = with_image_extension(name, response.headers().get(header::CONTENT_TYPE));
+            let bytes = response.bytes().await.ok()?;
+            write_atomic(&dir.join(&name), &bytes).ok()?;
+            Some((url, name))
+        })
+        .buffer_unordered(IMAGE_DOWNLOADS)
+        .filter_map(|saved| async move { saved })
+        .collect()
+        .await
+}
+
+fn image_file_name(url: &str) -> String {
+    let last = Url::parse(url)
+        .ok()
+        .and_then(|u| u.path_segments()?.next_back().map(links::decode_target))
+        .unwrap_or_default();
+    vault::safe_file_name(&last)
+}
+
+fn unique_name(name: String, taken: &mut Vec<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{}", ext)),
+        _ => (name.clone(), String::new()),
+    };
+    let mut candidate = name;
+    let mut n = 2;
+    while taken.iter().any(|t| t.eq_ignore_ascii_case(&candidate)) {
+        candidate = format!("{}-{}{}", stem, n, extension);
+        n += 1;
+    }
+    taken.push(candidate.clone());
+    candidate
+}
+
+/// Adds an extension from the response's content type when the URL's file
+/// name has none.
+fn with_image_extension(name: String, content_type: Option<&header::HeaderValue>) -> String {
+    if Path::new(&name).extension().is_some() {
+        return name;
+    }
+    let extension = match content_type
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+    {
+        t if t.starts_with("image/png") => "png",
+        t if t.starts_with("image/jpeg") => "jpg",
+        t if t.starts_with("image/gif") => "gif",
+        t if t.starts_with("image/webp") => "webp",
+        t if t.starts_with("image/svg") => "svg",
+        t if t.starts_with("image/avif") => "avif",
+        _ => return name,
+    };
+    format!("{}.{}", name, extension)
+}
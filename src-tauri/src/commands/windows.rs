@@ -0,0 +1,82 @@
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder};
+
+use super::watcher::{attach_window, release};
+use crate::vaults::VaultStateRegistry;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenVault {
+    pub path: String,
+    /// Labels of the windows showing it.
+    pub windows: Vec<String>,
+}
+
+/// Opens a new window on the vault at `vault_path` and returns its label.
+/// The window loads the app with `?vault=` set to the vault's path and is
+/// already watching it; events for the vault go to every window showing
+/// it. The same vault can be open in several windows.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub fn open_vault_window(
+    app: AppHandle,
+    registry: State<'_, VaultStateRegistry>,
+    vault_path: String,
+) -> Result<String, String> {
+    let root = fs::canonicalize(&vault_path)
+        .map_err(|_| format!("Vault does not exist: {}", vault_path))?;
+    if !root.is_dir() {
+        return Err(format!("Vault does not exist: {}", vault_path));
+    }
+    let label = registry.new_window_label();
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let url = format!(
+        "index.html?vault={}",
+        utf8_percent_encode(&root.to_string_lossy(), NON_ALPHANUMERIC)
+    );
+    attach_window(&app, &registry, &root, &label)?;
+    let built = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(url.into()))
+        .title(format!("{} - GraphNotes", name))
+        .inner_size(1200.0, 800.0)
+        .min_inner_size(800.0, 600.0)
+        .build();
+    if let Err(e) = built {
+        if let Some(released) = registry.detach(&label)? {
+            release(&app, released)?;
+        }
+        return Err(format!("Failed to open window: {}", e));
+    }
+    Ok(label)
+}
+
+/// The vaults open in any window, e.g. to switch to a window already
+/// showing a vault rather than opening another.
+#[tauri::command]
+pub fn get_open_vaults(registry: State<'_, VaultStateRegistry>) -> Vec<OpenVault> {
+    registry
+        .open_vaults()
+        .into_iter()
+        .map(|(root, windows)| OpenVault {
+            path: root.to_string_lossy().to_string(),
+            windows,
+        })
+        .collect()
+}
+
+/// Runs when a window is gone: it no longer shows its vault, and the vault
+/// is closed if no other window does.
+pub(crate) fn window_closed(app: &AppHandle, label: &str) {
+    let registry: State<VaultStateRegistry> = app.state();
+    let result = match registry.detach(label) {
+        Ok(Some(released)) => release(app, released),
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::warn!(window = label, error = %e, "vault not closed cleanly");
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::ipc::Invoke;
+use tauri::{Manager, Runtime};
+
+/// How many recent durations per command the p95 is taken over.
+const SAMPLES: usize = 200;
+/// Commands taking longer are logged at `info` rather than `debug`.
+const SLOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandStats {
+    pub command: String,
+    pub calls: u64,
+    pub last_ms: f64,
+    /// Over the most recent calls only.
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Call counts and timings per command, kept in managed state.
+///
+/// Every command is timed by the invoke handler, from when it is called to
+/// when it returns to Tauri, and runs in a `command` span so whatever it
+/// logs names it. Async commands return as soon as they are
+/// spawned, so the work of the long-running ones is timed again when their
+/// task finishes, as `task:<kind>`.
+#[derive(Default)]
+pub struct Diagnostics {
+    commands: Mutex<BTreeMap<String, Timings>>,
+}
+
+#[derive(Default)]
+struct Timings {
+    calls: u64,
+    last: Duration,
+    max: Duration,
+    recent: VecDeque<Duration>,
+}
+
+impl Diagnostics {
+    pub fn record(&self, command: &str, elapsed: Duration) {
+        let Ok(mut commands) = self.commands.lock() else {
+            return;
+        };
+        let timings = commands.entry(command.to_string()).or_default();
+        timings.calls += 1;
+        timings.last = elapsed;
+        timings.max = timings.max.max(elapsed);
+        if timings.recent.len() == SAMPLES {
+            timings.recent.pop_front();
+        }
+        timings.recent.push_back(elapsed);
+    }
+
+    /// Stats for every command called since start-up or the last reset, by
+    /// name.
+    pub fn commands(&self) -> Vec<CommandStats> {
+        let Ok(commands) = self.commands.lock() else {
+            return Vec::new();
+        };
+        commands
+            .iter()
+            .map(|(command, timings)| CommandStats {
+                command: command.clone(),
+                calls: timings.calls,
+                last_ms: millis(timings.last),
+                p95_ms: millis(p95(&timings.recent)),
+                max_ms: millis(timings.max),
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.clear();
+        }
+    }
+}
+
+/// Wraps the app's invoke handler so every command call is recorded.
+pub fn timed<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command().to_string();
+        let app = invoke.message.webview().app_handle().clone();
+        let span = tracing::info_span!("command", command = %command);
+        let _entered = span.enter();
+        let started = Instant::now();
+        let handled = handler(invoke);
+        let elapsed = started.elapsed();
+        if let Some(diagnostics) = app.try_state::<Diagnostics>() {
+            diagnostics.record(&command, elapsed);
+        }
+        if elapsed >= SLOW {
+            tracing::info!(duration_ms = millis(elapsed), "slow command");
+        } else {
+            tracing::debug!(duration_ms = millis(elapsed), "command returned");
+        }
+        handled
+    }
+}
+
+/// The nearest-rank 95th percentile.
+fn p95(samples: &VecDeque<Duration>) -> Duration {
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort();
+    let rank = (sorted.len() * 95).div_ceil(100);
+    sorted
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
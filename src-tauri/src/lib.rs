@@ -1,6 +1,22 @@
 mod commands;
+mod diagnostics;
+mod logging;
+mod markdown;
+mod tasks;
+#[cfg(test)]
+mod test_support;
+mod vault;
+mod vaults;
 
-use commands::files;
+use commands::{
+    actions, aliases, attachments, audit, autosave, backlinks, backup, caches, citations, compress,
+    dates, diff, encoding, export, external, files, folder_notes, format, frecency, glossary,
+    goals, graph_snapshots, health, import, journal, kanban, linkcheck, links, lint, local_api,
+    locks, math, metadata, monitor, properties, reading, references, reminders, rename, render,
+    review, rollover, schemas, search, settings, spellcheck, tables, tags, templates, titles,
+    watcher, web, windows,
+};
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -8,16 +24,184 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
+        .manage(autosave::AutosaveQueue::default())
+        .manage(backup::BackupScheduler::default())
+        .manage(caches::CacheKeys::default())
+        .manage(diagnostics::Diagnostics::default())
+        .manage(local_api::LocalApi::default())
+        .manage(monitor::HealthMonitor::default())
+        .manage(reminders::ReminderScheduler::default())
+        .manage(spellcheck::Dictionaries::default())
+        .manage(tasks::TaskManager::default())
+        .manage(vaults::VaultStateRegistry::<vaults::VaultWatcher>::default())
+        .setup(|app| {
+            app.manage(logging::Logging::init(app.handle()));
+            vault::external::init(&app.path().app_data_dir()?)?;
+            tracing::info!(version = app.package_info().version.to_string(), "started");
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                windows::window_closed(window.app_handle(), window.label());
+            }
+        })
+        .invoke_handler(diagnostics::timed(tauri::generate_handler![
+            actions::approve_note_action,
+            actions::list_note_actions,
+            actions::revoke_note_action,
+            actions::run_note_action,
+            actions::set_note_actions_allowed,
+            aliases::add_note_alias,
+            aliases::get_all_aliases,
+            aliases::remove_note_alias,
+            attachments::delete_note_with_attachments,
+            attachments::format_files_as_markdown,
+            attachments::rename_attachment,
+            attachments::repair_image_links,
+            audit::audit_external_changes,
+            audit::vault_audit,
+            autosave::autosave_file,
+            autosave::flush_autosaves,
+            autosave::is_own_write,
+            backlinks::backlinks_index_status,
+            backlinks::find_broken_links,
+            backlinks::find_orphan_notes,
+            backlinks::get_anchors,
+            backlinks::get_backlinks,
+            backlinks::get_outgoing_links,
+            backup::configure_auto_backup,
+            backup::run_backup_now,
+            backup::start_auto_backup,
+            caches::lock_vault_caches,
+            caches::unlock_vault_caches,
+            caches::vault_caches_locked,
+            citations::parse_citations,
+            citations::resolve_citations,
+            compress::compress_attachments,
+            commands::diagnostics::get_diagnostics,
+            commands::diagnostics::get_log_file_path,
+            commands::diagnostics::get_recent_logs,
+            commands::diagnostics::reset_diagnostics,
+            commands::diagnostics::set_log_level,
+            dates::infer_note_dates,
+            diff::diff_notes,
+            encoding::detect_mojibake,
+            encoding::fix_mojibake,
+            export::export_query,
+            export::export_vault_zip,
+            external::get_vault_mode,
+            external::open_external_folder,
             files::read_directory,
+            files::read_directory_recursive,
             files::read_file,
+            files::read_file_binary,
+            files::preview_file,
             files::write_file,
             files::create_file,
             files::delete_file,
             files::rename_file,
             files::file_exists,
             files::create_directory,
-        ])
+            folder_notes::create_folder_note,
+            folder_notes::get_folder_note,
+            format::change_section_level,
+            format::format_markdown,
+            format::format_note,
+            format::move_section,
+            format::number_headings,
+            format::renumber_footnotes,
+            format::sort_list,
+            frecency::frecency_score,
+            frecency::get_frequent_notes,
+            frecency::record_file_opened,
+            glossary::build_term_index,
+            glossary::get_terms_in_note,
+            goals::get_note_goal_progress,
+            goals::set_note_goal,
+            graph_snapshots::compare_graph_snapshots,
+            graph_snapshots::list_graph_snapshots,
+            graph_snapshots::note_link_trend,
+            graph_snapshots::save_graph_snapshot,
+            graph_snapshots::top_growing_notes,
+            health::check_vault,
+            import::import_dayone,
+            import::import_highlights_json,
+            journal::list_operations,
+            journal::undo_operation,
+            kanban::move_kanban_card,
+            kanban::parse_kanban,
+            linkcheck::check_external_links,
+            linkcheck::get_external_links,
+            links::build_link_index,
+            lint::lint_note,
+            lint::lint_vault,
+            local_api::get_local_api_status,
+            local_api::start_local_api,
+            local_api::stop_local_api,
+            locks::set_note_locked,
+            locks::is_note_locked,
+            math::extract_math,
+            math::validate_math,
+            metadata::get_inline_fields,
+            metadata::query_notes,
+            metadata::read_note_metadata,
+            metadata::read_vault_metadata,
+            metadata::refresh_query_blocks,
+            monitor::get_vault_health,
+            properties::get_note_properties,
+            properties::set_note_property,
+            reading::get_note_info,
+            reading::get_reading_position,
+            reading::set_reading_position,
+            references::format_note_reference,
+            references::format_note_references,
+            reminders::get_upcoming_reminders,
+            rename::apply_path_fixes,
+            rename::audit_cross_platform_paths,
+            rename::move_folder,
+            rename::normalize_filenames,
+            rename::rename_note,
+            rename::resolve_moved_path,
+            render::render_note_html,
+            review::find_stale_notes,
+            review::get_review_queue,
+            review::mark_reviewed,
+            rollover::rollover_tasks,
+            schemas::fix_schema_violations,
+            schemas::validate_note,
+            schemas::validate_vault,
+            search::find_markers,
+            search::fuzzy_find_files,
+            search::grep_search,
+            settings::get_vault_settings,
+            settings::import_obsidian_config,
+            settings::preview_link_format,
+            settings::save_vault_settings,
+            spellcheck::add_to_dictionary,
+            spellcheck::check_text,
+            spellcheck::get_custom_dictionary,
+            tables::parse_table,
+            tables::update_table,
+            tables::table_operation,
+            tables::csv_to_markdown_table,
+            tables::markdown_table_to_csv,
+            tags::get_notes_by_tag,
+            tags::get_vault_tags,
+            commands::tasks::cancel_task,
+            commands::tasks::list_tasks,
+            templates::create_note_from_link,
+            templates::create_note_in_folder,
+            templates::list_templates,
+            templates::validate_template,
+            titles::check_title_mismatches,
+            titles::sync_title,
+            watcher::start_watching,
+            watcher::stop_watching,
+            web::archive_url,
+            web::html_to_markdown,
+            windows::get_open_vaults,
+            windows::open_vault_window,
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
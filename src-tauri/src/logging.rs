@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{self, RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+const FILE_PREFIX: &str = "graphnotes";
+const FILE_SUFFIX: &str = "log";
+/// Daily files kept before the oldest is deleted.
+const MAX_FILES: usize = 7;
+
+/// The app's log, one JSON object per line in daily files under the app log
+/// directory, kept in managed state.
+///
+/// Log only what is needed to follow what happened: command names, paths,
+/// counts and errors. Never note content. A command failing with a `String`
+/// or `WebError` logs it through
+/// `#[tracing::instrument(skip_all, err(level = "warn"))]`; a `FileError`
+/// logs its kind as it is serialized.
+pub struct Logging {
+    dir: PathBuf,
+    level: Mutex<LevelFilter>,
+    reload: reload::Handle<LevelFilter, Registry>,
+    /// Flushes the file writer when the app exits.
+    _guard: WorkerGuard,
+}
+
+impl Logging {
+    /// Starts logging to the app log directory at `info`, or at the level in
+    /// `GRAPHNOTES_LOG` when set. When the log file can't be opened, logs
+    /// go to stderr instead, starting with a warning saying why, rather
+    /// than keeping the app from starting.
+    pub fn init(app: &AppHandle) -> Self {
+        let found = app
+            .path()
+            .app_log_dir()
+            .map_err(|e| format!("Failed to find log directory: {}", e));
+        let dir = found.clone().unwrap_or_default();
+        let level = std::env::var("GRAPHNOTES_LOG")
+            .ok()
+            .and_then(|level| parse_level(&level).ok())
+            .unwrap_or(LevelFilter::INFO);
+        let (filter, reload) = reload::Layer::new(level);
+
+        // Should a subscriber be set already, it is kept.
+        let (guard, unavailable) = match found.and_then(|dir| appender(&dir)) {
+            Ok(appender) => {
+                let (writer, guard) = tracing_appender::non_blocking(appender);
+                let file = fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_writer(writer);
+                let _ = tracing_subscriber::registry()
+                    .with(filter)
+                    .with(file)
+                    .try_init();
+                (guard, None)
+            }
+            Err(e) => {
+                let (writer, guard) = tracing_appender::non_blocking(std::io::stderr());
+                let _ = tracing_subscriber::registry()
+                    .with(filter)
+                    .with(fmt::layer().with_writer(writer))
+                    .try_init();
+                (guard, Some(e))
+            }
+        };
+        if let Some(error) = unavailable {
+            tracing::warn!(dir = %dir.display(), %error, "log file unavailable, logging to stderr");
+        }
+        Self {
+            dir,
+            level: Mutex::new(level),
+            reload,
+            _guard: guard,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn level(&self) -> LevelFilter {
+        self.level
+            .lock()
+            .map(|level| *level)
+            .unwrap_or(LevelFilter::INFO)
+    }
+
+    /// Changes what gets logged from now on.
+    pub fn set_level(&self, level: LevelFilter) -> Result<(), String> {
+        self.reload
+            .modify(|filter| *filter = level)
+            .map_err(|e| format!("Failed to change log level: {}", e))?;
+        if let Ok(mut current) = self.level.lock() {
+            *current = level;
+        }
+        Ok(())
+    }
+
+    /// The log files, oldest first. Their names end in the date, so that is
+    /// the order by name.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+}
+
+/// Daily log files in `dir`, which is created if need be.
+fn appender(dir: &Path) -> Result<RollingFileAppender, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    rolling::Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix(FILE_SUFFIX)
+        .max_log_files(MAX_FILES)
+        .build(dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))
+}
+
+/// `error`, `warn`, `info`, `debug`, `trace` or `off`, in any case.
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim()).map_err(|_| format!("Unknown log level: {}", level))
+}
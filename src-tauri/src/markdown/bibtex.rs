@@ -0,0 +1,655 @@
+use nom::branch::alt;
+use nom::bytes::complete::take_while1;
+use nom::character::complete::{char, digit1, multispace0};
+use nom::combinator::{map, opt};
+use nom::error::{Error, ErrorKind};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::IResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Person {
+    pub family: String,
+    pub given: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BibEntry {
+    pub key: String,
+    /// Lowercased entry type, e.g. `article`.
+    pub entry_type: String,
+    /// Field values with braces and common LaTeX escapes resolved, keyed by
+    /// lowercased field name.
+    pub fields: BTreeMap<String, String>,
+    pub authors: Vec<Person>,
+}
+
+#[derive(Debug, Default)]
+pub struct Bibliography {
+    pub entries: Vec<BibEntry>,
+    /// Entries that couldn't be parsed, as messages with line numbers.
+    pub errors: Vec<String>,
+}
+
+enum Piece<'a> {
+    Literal(&'a str),
+    Macro(&'a str),
+}
+
+/// Parses a `.bib` file. Malformed entries are skipped and reported rather
+/// than failing the whole file; `@comment` and `@preamble` are ignored and
+/// `@string` macros are expanded.
+pub fn parse(source: &str) -> Bibliography {
+    let mut bibliography = Bibliography::default();
+    let mut macros: HashMap<String, String> = HashMap::new();
+    let mut rest = source;
+
+    while let Some(at) = rest.find('@') {
+        let candidate = &rest[at..];
+        match entry(candidate) {
+            Ok((remaining, parsed)) => {
+                match parsed {
+                    Parsed::Entry {
+                        entry_type,
+                        key,
+                        fields,
+                    } => {
+                        let raw: Vec<(String, String)> = fields
+                            .into_iter()
+                            .map(|(name, value)| (name.to_lowercase(), expand(&value, &macros)))
+                            .collect();
+                        let authors = raw
+                            .iter()
+                            .find(|(name, _)| name == "author")
+                            .or_else(|| raw.iter().find(|(name, _)| name == "editor"))
+                            .map(|(_, value)| people(value))
+                            .unwrap_or_default();
+                        bibliography.entries.push(BibEntry {
+                            key: key.trim().to_string(),
+                            entry_type: entry_type.to_lowercase(),
+                            fields: raw
+                                .into_iter()
+                                .map(|(name, value)| (name, clean(&value)))
+                                .collect(),
+                            authors,
+                        });
+                    }
+                    Parsed::Macro { name, value } => {
+                        let value = expand(&value, &macros);
+                        macros.insert(name.to_lowercase(), value);
+                    }
+                    Parsed::Ignored => {}
+                }
+                rest = remaining;
+            }
+            Err(_) => {
+                let line = source[..source.len() - candidate.len()]
+                    .matches('\n')
+                    .count()
+                    + 1;
+                // `@` inside a comment line is common and not worth reporting.
+                if candidate[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    bibliography
+                        .errors
+                        .push(format!("Malformed entry on line {}", line));
+                }
+                rest = &candidate[1..];
+            }
+        }
+    }
+
+    bibliography
+}
+
+enum Parsed<'a> {
+    Entry {
+        entry_type: &'a str,
+        key: &'a str,
+        fields: Vec<(&'a str, Vec<Piece<'a>>)>,
+    },
+    Macro {
+        name: &'a str,
+        value: Vec<Piece<'a>>,
+    },
+    Ignored,
+}
+
+fn entry(input: &str) -> IResult<&str, Parsed<'_>> {
+    let (input, entry_type) = preceded(char('@'), ws(identifier))(input)?;
+    let close = match input.chars().next() {
+        Some('{') => '}',
+        Some('(') => ')',
+        _ => return Err(nom::Err::Error(Error::new(input, ErrorKind::Char))),
+    };
+
+    match entry_type.to_lowercase().as_str() {
+        "comment" | "preamble" => {
+            let open = if close == ')' { '(' } else { '{' };
+            let (input, _) = balanced(input, open, close)?;
+            Ok((input, Parsed::Ignored))
+        }
+        "string" => {
+            let (input, (name, value)) = delimited(ws(take_open), field, ws(char(close)))(input)?;
+            Ok((input, Parsed::Macro { name, value }))
+        }
+        _ => {
+            let key = take_while1(|c: char| c != ',' && c != close && !c.is_whitespace());
+            let fields = separated_list1(ws(char(',')), field);
+            let (input, (key, fields)) = delimited(
+                ws(take_open),
+                tuple((
+                    ws(key),
+                    map(opt(preceded(ws(char(',')), opt(fields))), |f| {
+                        f.flatten().unwrap_or_default()
+                    }),
+                )),
+                terminated(opt(ws(char(','))), ws(char(close))),
+            )(input)?;
+            Ok((
+                input,
+                Parsed::Entry {
+                    entry_type,
+                    key,
+                    fields,
+                },
+            ))
+        }
+    }
+}
+
+fn take_open(input: &str) -> IResult<&str, char> {
+    alt((char('{'), char('(')))(input)
+}
+
+fn field(input: &str) -> IResult<&str, (&str, Vec<Piece<'_>>)> {
+    let (input, (name, _, value)) = tuple((
+        ws(identifier),
+        ws(char('=')),
+        separated_list1(ws(char('#')), ws(piece)),
+    ))(input)?;
+    Ok((input, (name, value)))
+}
+
+fn piece(input: &str) -> IResult<&str, Piece<'_>> {
+    alt((
+        map(|i| balanced(i, '{', '}'), Piece::Literal),
+        map(quoted, Piece::Literal),
+        map(digit1, Piece::Literal),
+        map(identifier, Piece::Macro),
+    ))(input)
+}
+
+fn identifier(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| {
+        c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.' | '+' | '/' | '\'')
+    })(input)
+}
+
+fn ws<'a, O>(
+    inner: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, inner, multispace0)
+}
+
+/// Matches `open ... close` with nested delimiters, returning the inside.
+fn balanced(input: &str, open: char, close: char) -> IResult<&str, &str> {
+    if !input.starts_with(open) {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Char)));
+    }
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (idx, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((&input[idx + c.len_utf8()..], &input[open.len_utf8()..idx]));
+            }
+        }
+    }
+    Err(nom::Err::Error(Error::new(input, ErrorKind::Eof)))
+}
+
+/// A `"..."` value; quotes inside braces don't end it.
+fn quoted(input: &str) -> IResult<&str, &str> {
+    let (body, _) = char('"')(input)?;
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (idx, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '"' if depth == 0 => return Ok((&body[idx + 1..], &body[..idx])),
+            _ => {}
+        }
+    }
+    Err(nom::Err::Error(Error::new(input, ErrorKind::Eof)))
+}
+
+fn expand(pieces: &[Piece<'_>], macros: &HashMap<String, String>) -> String {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Literal(text) => text.to_string(),
+            Piece::Macro(name) => {
+                let name = name.to_lowercase();
+                macros
+                    .get(&name)
+                    .cloned()
+                    .or_else(|| month(&name).map(str::to_string))
+                    .unwrap_or(name)
+            }
+        })
+        .collect()
+}
+
+fn month(name: &str) -> Option<&'static str> {
+    const MONTHS: [(&str, &str); 12] = [
+        ("jan", "January"),
+        ("feb", "February"),
+        ("mar", "March"),
+        ("apr", "April"),
+        ("may", "May"),
+        ("jun", "June"),
+        ("jul", "July"),
+        ("aug", "August"),
+        ("sep", "September"),
+        ("oct", "October"),
+        ("nov", "November"),
+        ("dec", "December"),
+    ];
+    MONTHS
+        .iter()
+        .find(|(short, _)| *short == name)
+        .map(|(_, long)| *long)
+}
+
+/// Splits an `author` field on top-level `and`. A fully braced name such as
+/// `{World Health Organization}` is kept whole as a family name.
+fn people(raw: &str) -> Vec<Person> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut skip_to = 0;
+    for (idx, c) in raw.char_indices() {
+        if idx < skip_to {
+            continue;
+        }
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ' ' if depth == 0
+                && raw
+                    .get(idx..idx + 5)
+                    .is_some_and(|s| s.eq_ignore_ascii_case(" and ")) =>
+            {
+                names.push(&raw[start..idx]);
+                start = idx + 5;
+                skip_to = start;
+            }
+            _ => {}
+        }
+    }
+    names.push(&raw[start..]);
+
+    names
+        .into_iter()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let corporate = name.starts_with('{')
+                && name.ends_with('}')
+                && balanced(name, '{', '}').is_ok_and(|(rest, _)| rest.is_empty());
+            if corporate {
+                return Person {
+                    family: clean(name),
+                    given: String::new(),
+                };
+            }
+            let cleaned = clean(name);
+            match cleaned.split_once(',') {
+                Some((family, given)) => Person {
+                    family: family.trim().to_string(),
+                    given: given.trim().to_string(),
+                },
+                None => match cleaned.rsplit_once(' ') {
+                    Some((given, family)) => Person {
+                        family: family.to_string(),
+                        given: given.to_string(),
+                    },
+                    None => Person {
+                        family: cleaned.clone(),
+                        given: String::new(),
+                    },
+                },
+            }
+        })
+        .collect()
+}
+
+/// Resolves braces and common LaTeX escapes (`{\"o}`, `\'e`, `\&`, `--`)
+/// into plain text. Unknown commands are dropped, keeping their argument.
+fn clean(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => {}
+            '~' => out.push(' '),
+            '\\' => {
+                let Some(&next) = chars.peek() else {
+                    break;
+                };
+                if let Some(accent) = accent_mark(next) {
+                    chars.next();
+                    // The accented letter may be braced: `\"{o}`.
+                    while chars.peek() == Some(&'{') || chars.peek() == Some(&' ') {
+                        chars.next();
+                    }
+                    if let Some(base) = chars.next() {
+                        let base = if base == '\\' {
+                            chars.next().unwrap_or('i')
+                        } else {
+                            base
+                        };
+                        out.push_str(&compose(base, accent));
+                    }
+                } else if next.is_ascii_alphabetic() {
+                    let mut command = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        command.push(c);
+                        chars.next();
+                    }
+                    out.push_str(match command.as_str() {
+                        "ss" => "ß",
+                        "o" => "ø",
+                        "O" => "Ø",
+                        "ae" => "æ",
+                        "AE" => "Æ",
+                        "aa" => "å",
+                        "AA" => "Å",
+                        "l" => "ł",
+                        "L" => "Ł",
+                        "i" => "ı",
+                        _ => "",
+                    });
+                    if matches!(command.as_str(), "c" | "v" | "u" | "H" | "k") {
+                        while chars.peek() == Some(&'{') || chars.peek() == Some(&' ') {
+                            chars.next();
+                        }
+                        if let Some(base) = chars.next() {
+                            out.push(base);
+                            out.push(match command.as_str() {
+                                "c" => '\u{327}',
+                                "v" => '\u{30c}',
+                                "u" => '\u{306}',
+                                "H" => '\u{30b}',
+                                _ => '\u{328}',
+                            });
+                        }
+                    }
+                } else {
+                    chars.next();
+                    out.push(next);
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                if chars.peek() == Some(&'-') {
+                    chars.next();
+                    out.push('—');
+                } else {
+                    out.push('–');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn accent_mark(c: char) -> Option<char> {
+    match c {
+        '"' => Some('\u{308}'),
+        '\'' => Some('\u{301}'),
+        '`' => Some('\u{300}'),
+        '^' => Some('\u{302}'),
+        '~' => Some('\u{303}'),
+        '=' => Some('\u{304}'),
+        '.' => Some('\u{307}'),
+        _ => None,
+    }
+}
+
+/// Combines a letter with an accent, using the precomposed character for
+/// common Latin letters.
+fn compose(base: char, mark: char) -> String {
+    const COMPOSED: &[(char, &str, &str)] = &[
+        ('\u{308}', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+        ('\u{301}', "aeiouyAEIOUYcnsz", "áéíóúýÁÉÍÓÚÝćńśź"),
+        ('\u{300}', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+        ('\u{302}', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+        ('\u{303}', "anoANO", "ãñõÃÑÕ"),
+    ];
+    COMPOSED
+        .iter()
+        .find(|(m, _, _)| *m == mark)
+        .and_then(|(_, bases, composed)| {
+            let idx = bases.chars().position(|b| b == base)?;
+            composed.chars().nth(idx)
+        })
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| format!("{}{}", base, mark))
+}
+
+/// Formats an entry as a reference list item in a simple author-year
+/// style: `Smith, J., & Jones, A. (2020). Title. *Journal*.`
+pub fn format_reference(entry: &BibEntry) -> String {
+    let authors = match entry.authors.as_slice() {
+        [] => String::new(),
+        [only] => person_name(only),
+        people if people.len() > 6 => format!("{}, et al.", person_name(&people[0])),
+        people => {
+            let (last, rest) = people.split_last().unwrap();
+            format!(
+                "{}, & {}",
+                rest.iter().map(person_name).collect::<Vec<_>>().join(", "),
+                person_name(last)
+            )
+        }
+    };
+    let year = entry
+        .fields
+        .get("year")
+        .or_else(|| entry.fields.get("date"))
+        .map(|y| y.chars().take(4).collect::<String>())
+        .filter(|y| !y.is_empty())
+        .unwrap_or_else(|| "n.d.".to_string());
+
+    let mut parts = Vec::new();
+    let lead = if authors.is_empty() {
+        format!("({})", year)
+    } else {
+        format!("{} ({})", authors, year)
+    };
+    parts.push(format!("{}.", lead));
+    if let Some(title) = entry.fields.get("title") {
+        parts.push(format!("{}.", title.trim_end_matches('.')));
+    }
+    if let Some(container) = ["journal", "journaltitle", "booktitle", "publisher"]
+        .iter()
+        .find_map(|f| entry.fields.get(*f))
+    {
+        parts.push(format!("*{}*.", container.trim_end_matches('.')));
+    }
+    parts.join(" ")
+}
+
+/// `Family, G. H.`, or just the family name when there is no given name.
+fn person_name(person: &Person) -> String {
+    let initials: Vec<String> = person
+        .given
+        .split([' ', '-'])
+        .filter_map(|part| part.chars().next())
+        .filter(|c| c.is_alphabetic())
+        .map(|c| format!("{}.", c))
+        .collect();
+    if initials.is_empty() {
+        person.family.clone()
+    } else {
+        format!("{}, {}", person.family, initials.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(source: &str) -> BibEntry {
+        let bibliography = parse(source);
+        assert!(bibliography.errors.is_empty(), "{:?}", bibliography.errors);
+        assert_eq!(bibliography.entries.len(), 1);
+        bibliography.entries.into_iter().next().unwrap()
+    }
+
+    fn entry_with_authors(count: usize) -> BibEntry {
+        let authors: Vec<String> = (1..=count).map(|n| format!("Author{}, Given", n)).collect();
+        only(&format!(
+            "@article{{key, author = {{{}}}, title = {{On Things.}}, year = 2020}}",
+            authors.join(" and ")
+        ))
+    }
+
+    #[test]
+    fn nested_braces_are_kept_together_and_then_dropped() {
+        let entry =
+            only("@Article{Key1,\n  Title = {The {DNA} of {{Nested}} {Braces, and} more},\n}");
+        assert_eq!(entry.key, "Key1");
+        assert_eq!(entry.entry_type, "article");
+        assert_eq!(entry.fields["title"], "The DNA of Nested Braces, and more");
+    }
+
+    #[test]
+    fn strings_and_months_expand_inside_concatenations() {
+        let source = "@string{jn = \"Journal of {\"}Things{\"}\"}\n\
+                      @misc(k, journal = jn # \" Letters\", month = mar)";
+        let entry = only(source);
+        assert_eq!(entry.fields["journal"], "Journal of \"Things\" Letters");
+        assert_eq!(entry.fields["month"], "March");
+    }
+
+    #[test]
+    fn unicode_and_latex_accents_become_plain_text() {
+        let entry = only(
+            "@book{b, author = {G{\\\"o}del, Kurt and \\'Etienne Bézout and Ma\\v{c}ek, J. and \
+             {\\AA}ngstr\\\"{o}m, A.},\n\
+             title = {Caf\\'{e} \\& Stra\\ss{}e -- 1900---2000}}",
+        );
+        let families: Vec<&str> = entry.authors.iter().map(|p| p.family.as_str()).collect();
+        assert_eq!(families, ["Gödel", "Bézout", "Mac\u{30c}ek", "Ångström"]);
+        assert_eq!(entry.authors[1].given, "Étienne");
+        assert_eq!(entry.fields["title"], "Café & Straße – 1900—2000");
+    }
+
+    #[test]
+    fn authors_split_on_and_outside_braces_only() {
+        let entry = only(
+            "@report{r, author = {Smith, John and Jane Q. Doe AND {Barnes and Noble} and \
+             {World Health Organization}}}",
+        );
+        let names: Vec<(&str, &str)> = entry
+            .authors
+            .iter()
+            .map(|p| (p.family.as_str(), p.given.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Smith", "John"),
+                ("Doe", "Jane Q."),
+                ("Barnes and Noble", ""),
+                ("World Health Organization", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn editors_stand_in_for_missing_authors() {
+        let entry = only("@book{e, editor = {Ed Itor}}");
+        assert_eq!(entry.authors[0].family, "Itor");
+    }
+
+    #[test]
+    fn malformed_and_unterminated_entries_are_reported_and_skipped() {
+        let bibliography = parse(
+            "% reach me @ home\n@article{good1, title = {Fine}}\n@article{broken title = {x}}\n\
+             @article{good2, title = \"Also fine\"}\n@book{open, title = {never closed}\n",
+        );
+        let keys: Vec<&str> = bibliography
+            .entries
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(keys, ["good1", "good2"]);
+        assert_eq!(
+            bibliography.errors,
+            ["Malformed entry on line 3", "Malformed entry on line 5"]
+        );
+    }
+
+    #[test]
+    fn comments_and_preambles_are_ignored() {
+        let bibliography =
+            parse("@comment{an @article{x, title={no}} inside}\n@preamble{\"\\newcommand\"}\n");
+        assert!(bibliography.entries.is_empty());
+        assert!(bibliography.errors.is_empty());
+    }
+
+    #[test]
+    fn references_list_one_two_or_up_to_six_authors() {
+        assert_eq!(
+            format_reference(&entry_with_authors(1)),
+            "Author1, G. (2020). On Things."
+        );
+        assert_eq!(
+            format_reference(&entry_with_authors(2)),
+            "Author1, G., & Author2, G. (2020). On Things."
+        );
+        let six = format_reference(&entry_with_authors(6));
+        let listed =
+            "Author1, G., Author2, G., Author3, G., Author4, G., Author5, G., & Author6, G. (2020)";
+        assert!(six.starts_with(listed));
+    }
+
+    #[test]
+    fn references_shorten_more_than_six_authors() {
+        assert_eq!(
+            format_reference(&entry_with_authors(7)),
+            "Author1, G., et al. (2020). On Things."
+        );
+    }
+
+    #[test]
+    fn references_without_authors_or_year_lead_with_the_year() {
+        let entry = only(
+            "@inproceedings{p, title = {Talk}, booktitle = {Proceedings.}, date = {2019-05-01}}",
+        );
+        assert_eq!(format_reference(&entry), "(2019). Talk. *Proceedings*.");
+        let undated = only("@misc{m, author = {{ACME}}}");
+        assert_eq!(format_reference(&undated), "ACME (n.d.).");
+    }
+}
@@ -0,0 +1,113 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, heading, links, protected_lines, LineBuffer};
+
+// Pandoc citation keys: `@key` inside `[...]` or in running text, or
+// `@{key with odd characters}`. The preceding character rules out email
+// addresses.
+static CITATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^\p{L}\p{N}_.@\\])-?@(\{[^{}]+\}|[\p{L}\p{N}_][\p{L}\p{N}_:.#$%&+?<>~/\-]*)")
+        .unwrap()
+});
+
+const REFERENCE_HEADINGS: &[&str] = &["references", "bibliography"];
+
+/// A citation key used in a note. Line and column (in characters, pointing
+/// at the `@`) are 1-based.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub key: String,
+    pub line_number: usize,
+    pub column: usize,
+}
+
+/// Finds citation keys outside frontmatter, code, and link targets.
+pub fn extract(content: &str) -> Vec<Citation> {
+    let lines: Vec<&str> = content.lines().collect();
+    let protected = protected_lines(&lines);
+    let mut citations = Vec::new();
+
+    for (idx, (line, protected)) in lines.iter().zip(protected).enumerate() {
+        if protected || !line.contains('@') {
+            continue;
+        }
+        let scrubbed = links::blank_links(&blank_code_spans(line));
+        for caps in CITATION.captures_iter(&scrubbed) {
+            let key = caps.get(1).unwrap();
+            let raw = key.as_str();
+            let key_text = match raw.strip_prefix('{').and_then(|k| k.strip_suffix('}')) {
+                Some(braced) => braced.trim(),
+                // Trailing punctuation belongs to the sentence, not the key.
+                None => raw.trim_end_matches([
+                    ':', '.', '#', '$', '%', '&', '-', '+', '?', '<', '>', '~', '/',
+                ]),
+            };
+            if key_text.is_empty() {
+                continue;
+            }
+            let at = key.start() - 1;
+            citations.push(Citation {
+                key: key_text.to_string(),
+                line_number: idx + 1,
+                column: line[..at].chars().count() + 1,
+            });
+        }
+    }
+
+    citations
+}
+
+/// Replaces the body of the note's "References" (or "Bibliography")
+/// section with `list`, or appends a `## References` section when there is
+/// none. The section ends at the next heading of the same or higher level.
+pub fn set_references_section(content: &str, list: &str) -> String {
+    let mut buffer = LineBuffer::parse(content);
+    let section = {
+        let lines = buffer.as_strs();
+        let protected = protected_lines(&lines);
+        lines
+            .iter()
+            .enumerate()
+            .find_map(|(idx, line)| {
+                let (level, text) = heading(line).filter(|_| !protected[idx])?;
+                REFERENCE_HEADINGS
+                    .contains(&text.to_lowercase().as_str())
+                    .then_some((idx, level))
+            })
+            .map(|(start, level)| {
+                let end = (start + 1..lines.len()).find(|&idx| {
+                    !protected[idx] && heading(lines[idx]).is_some_and(|(l, _)| l <= level)
+                });
+                (start, end)
+            })
+    };
+    let list_lines = list.lines().map(str::to_string);
+
+    match section {
+        Some((start, end)) => {
+            let mut replacement = vec![String::new()];
+            replacement.extend(list_lines);
+            if end.is_some() {
+                replacement.push(String::new());
+            }
+            let end = end.unwrap_or(buffer.lines.len());
+            buffer.lines.splice(start + 1..end, replacement);
+        }
+        None => {
+            while buffer.lines.last().is_some_and(|l| l.trim().is_empty()) {
+                buffer.lines.pop();
+            }
+            if !buffer.lines.is_empty() {
+                buffer.lines.push(String::new());
+            }
+            buffer.lines.push("## References".to_string());
+            buffer.lines.push(String::new());
+            buffer.lines.extend(list_lines);
+        }
+    }
+
+    buffer.set_trailing_newline(true);
+    buffer.render()
+}
@@ -0,0 +1,227 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, protected_lines, LineBuffer};
+
+static DEFINITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s{0,3})\[\^([^\]\s]+)\]:").unwrap());
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\^([^\]\s]+)\]").unwrap());
+
+/// A footnote definition: its label and the lines it spans, continuation
+/// lines included.
+struct Definition {
+    label: String,
+    start: usize,
+    end: usize,
+}
+
+/// Renumbers numeric footnotes 1, 2, 3... in order of first reference,
+/// rewriting references and definitions alike. Numeric footnotes that are
+/// defined but never referenced are numbered after the rest. Named labels
+/// like `[^caveat]` keep their names.
+///
+/// With `relocate`, every definition moves to the end of the document,
+/// ordered by first reference. Running this again on its output changes
+/// nothing.
+pub fn renumber(content: &str, relocate: bool) -> String {
+    let mut buffer = LineBuffer::parse(content);
+    let lines = buffer.as_strs();
+    let protected = protected_lines(&lines);
+    let definitions = find_definitions(&lines, &protected);
+
+    // Labels in order of first reference, then any defined but unreferenced.
+    let mut order: Vec<String> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if protected[idx] {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        let skip = DEFINITION.find(&scrubbed).map(|m| m.end()).unwrap_or(0);
+        for caps in REFERENCE.captures_iter(&scrubbed[skip..]) {
+            if !order.contains(&caps[1].to_string()) {
+                order.push(caps[1].to_string());
+            }
+        }
+    }
+    for definition in &definitions {
+        if !order.contains(&definition.label) {
+            order.push(definition.label.clone());
+        }
+    }
+
+    let numeric = |label: &str| label.chars().all(|c| c.is_ascii_digit());
+    let renamed: HashMap<String, String> = order
+        .iter()
+        .filter(|label| numeric(label))
+        .enumerate()
+        .map(|(idx, label)| (label.clone(), (idx + 1).to_string()))
+        .collect();
+    let rename = |label: &str| {
+        renamed
+            .get(label)
+            .cloned()
+            .unwrap_or_else(|| label.to_string())
+    };
+
+    let mut rewritten: Vec<String> = Vec::with_capacity(lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        if protected[idx] || !line.contains("[^") {
+            rewritten.push(line.to_string());
+            continue;
+        }
+        // Match against a copy with code spans blanked so references inside
+        // them are left alone, but splice into the original line.
+        let scrubbed = blank_code_spans(line);
+        let mut out = String::with_capacity(line.len());
+        let mut last = 0;
+        for caps in REFERENCE.captures_iter(&scrubbed) {
+            let label = caps.get(1).unwrap();
+            out.push_str(&line[last..label.start()]);
+            out.push_str(&rename(label.as_str()));
+            last = label.end();
+        }
+        out.push_str(&line[last..]);
+        rewritten.push(out);
+    }
+
+    if relocate && !definitions.is_empty() {
+        let position = |label: &str| order.iter().position(|l| l == label).unwrap_or(usize::MAX);
+        let mut moved: Vec<(usize, Vec<String>)> = definitions
+            .iter()
+            .map(|d| (position(&d.label), rewritten[d.start..d.end].to_vec()))
+            .collect();
+        moved.sort_by_key(|(position, _)| *position);
+
+        let mut in_definition = vec![false; rewritten.len()];
+        for definition in &definitions {
+            in_definition[definition.start..definition.end].fill(true);
+        }
+        let mut kept: Vec<String> = Vec::with_capacity(rewritten.len());
+        let mut after_removed = false;
+        for (line, removed) in rewritten.into_iter().zip(in_definition) {
+            if removed {
+                after_removed = true;
+                continue;
+            }
+            // Don't leave a doubled blank line where a definition was.
+            let blank = line.trim().is_empty();
+            if blank && after_removed && kept.last().is_none_or(|l| l.trim().is_empty()) {
+                continue;
+            }
+            after_removed = false;
+            kept.push(line);
+        }
+        while kept.last().is_some_and(|l| l.trim().is_empty()) {
+            kept.pop();
+        }
+
+        let multi_line = moved.iter().any(|(_, block)| block.len() > 1);
+        if !kept.is_empty() {
+            kept.push(String::new());
+        }
+        for (idx, (_, block)) in moved.into_iter().enumerate() {
+            if idx > 0 && multi_line {
+                kept.push(String::new());
+            }
+            kept.extend(block);
+        }
+        rewritten = kept;
+        buffer.set_trailing_newline(true);
+    }
+
+    buffer.lines = rewritten;
+    buffer.render()
+}
+
+/// Footnote definitions outside code. A definition continues over lines
+/// indented by four spaces or a tab, including blank lines between them.
+fn find_definitions(lines: &[&str], protected: &[bool]) -> Vec<Definition> {
+    let is_continuation = |line: &str| line.starts_with("    ") || line.starts_with('\t');
+    let mut definitions = Vec::new();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let Some(caps) = DEFINITION.captures(lines[idx]).filter(|_| !protected[idx]) else {
+            idx += 1;
+            continue;
+        };
+        let start = idx;
+        let mut end = idx + 1;
+        let mut next = end;
+        while next < lines.len() && !protected[next] {
+            if is_continuation(lines[next]) {
+                next += 1;
+                end = next;
+            } else if lines[next].trim().is_empty() {
+                next += 1;
+            } else {
+                break;
+            }
+        }
+        definitions.push(Definition {
+            label: caps[2].to_string(),
+            start,
+            end,
+        });
+        idx = end;
+    }
+
+    definitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = "# Title\n\nSeven[^7], three[^3], seven again[^7] and a [^caveat].\n\n\
+        [^7]: Seven.\n[^caveat]: Named.\n\n\
+        ```md\nSample[^3] in a fence.\n[^3]: Fenced.\n```\n\n\
+        A `[^7]` span and [^3] again.\n\n\
+        [^3]: Three,\n    continued.\n\n[^12]: Never referenced.\n";
+
+    #[test]
+    fn renumbering_is_idempotent() {
+        for relocate in [false, true] {
+            let once = renumber(NOTES, relocate);
+            assert_eq!(renumber(&once, relocate), once);
+        }
+    }
+
+    #[test]
+    fn references_are_numbered_in_order_of_first_use() {
+        let renumbered = renumber(NOTES, false);
+        assert!(renumbered.contains("Seven[^1], three[^2], seven again[^1] and a [^caveat]."));
+        assert!(renumbered.contains("\n[^1]: Seven.\n"));
+        assert!(renumbered.contains("\n[^2]: Three,\n    continued.\n"));
+    }
+
+    #[test]
+    fn fenced_code_and_code_spans_are_untouched() {
+        let renumbered = renumber(NOTES, true);
+        assert!(renumbered.contains("```md\nSample[^3] in a fence.\n[^3]: Fenced.\n```\n"));
+        assert!(renumbered.contains("A `[^7]` span and [^2] again."));
+    }
+
+    #[test]
+    fn named_labels_keep_their_names() {
+        let renumbered = renumber(
+            "A[^note] and B[^1].\n\n[^1]: One.\n[^note]: Named.\n",
+            false,
+        );
+        assert_eq!(
+            renumbered,
+            "A[^note] and B[^1].\n\n[^1]: One.\n[^note]: Named.\n"
+        );
+    }
+
+    #[test]
+    fn unreferenced_numeric_definitions_are_numbered_after_the_rest() {
+        let renumbered = renumber(NOTES, true);
+        assert!(renumbered.ends_with(
+            "\n[^1]: Seven.\n\n[^2]: Three,\n    continued.\n\n[^caveat]: Named.\n\n\
+             [^3]: Never referenced.\n"
+        ));
+        assert!(!renumbered.contains("[^12]"));
+    }
+}
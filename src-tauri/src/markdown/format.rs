@@ -0,0 +1,219 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use super::{code_block_end, code_block_lines, fence_marker, frontmatter, tables};
+
+static BULLET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)[-*+](\s+)").unwrap());
+static THEMATIC_BREAK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}([-*_])(?:[ \t]*[-*_]){2,}[ \t]*$").unwrap());
+static ORDERED_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\d+[.)]\s").unwrap());
+static ATX_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}#{1,6}(\s|$)").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Marker every unordered list item is rewritten to use.
+    pub bullet_marker: char,
+    pub align_tables: bool,
+    /// Ensure a blank line before and after headings and code fences.
+    pub space_blocks: bool,
+    /// Runs of blank lines longer than this collapse down to it.
+    pub max_blank_lines: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            bullet_marker: '-',
+            align_tables: true,
+            space_blocks: true,
+            max_blank_lines: 1,
+        }
+    }
+}
+
+/// Normalizes markdown formatting. Frontmatter and fenced code blocks are
+/// copied through byte for byte, and formatting already formatted output
+/// yields the same text.
+pub fn format(content: &str, options: &FormatOptions) -> String {
+    let split = frontmatter::split(content);
+    let prefix = &content[..content.len() - split.body.len()];
+
+    let lines: Vec<&str> = split.body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let table_ranges = if options.align_tables {
+        tables::find_tables(&lines, &in_code)
+    } else {
+        Vec::new()
+    };
+
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut blank_run = 0;
+    let mut need_blank = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if in_code[i] {
+            // Opening fence: separate from preceding text, then copy the
+            // whole block verbatim.
+            if options.space_blocks {
+                push_separator(&mut out);
+            }
+            let start = i;
+            i = code_block_end(&lines, start);
+            out.extend(lines[start..i].iter().map(|l| l.to_string()));
+            blank_run = 0;
+            need_blank = options.space_blocks;
+            continue;
+        }
+
+        if let Some(&(start, end)) = table_ranges.iter().find(|(start, _)| *start == i) {
+            if need_blank {
+                push_separator(&mut out);
+                need_blank = false;
+            }
+            let table = tables::parse(&lines[start..end]);
+            out.extend(tables::render(&table));
+            blank_run = 0;
+            i = end;
+            continue;
+        }
+
+        let trimmed_line = trim_trailing(line, lines.get(i + 1).copied());
+        if trimmed_line.trim().is_empty() {
+            blank_run += 1;
+            if blank_run <= options.max_blank_lines && !out.is_empty() {
+                out.push(String::new());
+            }
+            need_blank = false;
+            i += 1;
+            continue;
+        }
+        blank_run = 0;
+
+        if ATX_HEADING.is_match(&trimmed_line) {
+            if options.space_blocks {
+                push_separator(&mut out);
+            }
+            out.push(trimmed_line);
+            need_blank = options.space_blocks;
+            i += 1;
+            continue;
+        }
+
+        if need_blank {
+            push_separator(&mut out);
+            need_blank = false;
+        }
+
+        let normalized = if THEMATIC_BREAK.is_match(&trimmed_line) {
+            trimmed_line
+        } else {
+            BULLET
+                .replace(&trimmed_line, |caps: &regex::Captures| {
+                    format!("{}{}{}", &caps[1], options.bullet_marker, &caps[2])
+                })
+                .to_string()
+        };
+        out.push(normalized);
+        i += 1;
+    }
+
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+
+    let mut result = prefix.to_string();
+    if !out.is_empty() {
+        result.push_str(&out.join("\n"));
+        result.push('\n');
+    }
+    result
+}
+
+/// Whether a line can continue the preceding paragraph rather than start a
+/// new block, which is the only case where a hard break means anything.
+fn continues_paragraph(line: &str) -> bool {
+    !line.trim().is_empty()
+        && !BULLET.is_match(line)
+        && !ORDERED_ITEM.is_match(line)
+        && !ATX_HEADING.is_match(line)
+        && !line.trim_start().starts_with(['>', '|'])
+        && fence_marker(line).is_none()
+}
+
+fn push_separator(out: &mut Vec<String>) {
+    if out.last().is_some_and(|l| !l.is_empty()) {
+        out.push(String::new());
+    }
+}
+
+/// Strips trailing whitespace, keeping a two-space hard line break when the
+/// next line continues the paragraph.
+fn trim_trailing(line: &str, next: Option<&str>) -> String {
+    let trimmed = line.trim_end();
+    let hard_break =
+        line.ends_with("  ") && !trimmed.is_empty() && next.is_some_and(continues_paragraph);
+    if hard_break {
+        format!("{}  ", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "---\ntitle:   Messy  \ntags: [a,   b]\n---\n# Title\nIntro text   \n\
+        * one\n+ two\n    * nested\n\n\n\n## Section\n\
+        | a | long header |\n|:-|--:|\n| x \\| y | 1 |\n| zz | 22 |\n\
+        ```js\nconst  x = 1;   \n\n\n\n* not a bullet\n```\n\
+        line with break  \ncontinues here\n* * *\n~~~\n# not a heading\n~~~\nend\n\n\n";
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let options = FormatOptions::default();
+        let once = format(MESSY, &options);
+        let twice = format(&once, &options);
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn frontmatter_and_code_blocks_are_untouched() {
+        let formatted = format(MESSY, &FormatOptions::default());
+        assert!(formatted.starts_with("---\ntitle:   Messy  \ntags: [a,   b]\n---\n"));
+        assert!(formatted.contains("```js\nconst  x = 1;   \n\n\n\n* not a bullet\n```\n"));
+        assert!(formatted.contains("~~~\n# not a heading\n~~~\n"));
+    }
+
+    #[test]
+    fn normalizes_bullets_headings_and_tables() {
+        let formatted = format(MESSY, &FormatOptions::default());
+        assert!(formatted.contains("Intro text\n- one\n- two\n    - nested\n\n## Section\n\n"));
+        assert!(formatted.contains(
+            "| a      | long header |\n| :----- | ----------: |\n| x \\| y |           1 |\n"
+        ));
+        assert!(formatted.contains("line with break  \ncontinues here\n* * *\n"));
+        assert!(formatted.ends_with("end\n"));
+    }
+
+    #[test]
+    fn idempotent_without_frontmatter_or_trailing_newline() {
+        let options = FormatOptions::default();
+        for input in [
+            "",
+            "text",
+            "# A\n# B",
+            "```\nunclosed\n\n\n",
+            "| a |\n| - |\n| b |",
+        ] {
+            let once = format(input, &options);
+            assert_eq!(once, format(&once, &options), "input: {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use serde_json::{Map, Value};
+
+/// A note split into its YAML frontmatter and markdown body.
+pub struct Split<'a> {
+    /// Raw YAML between the `---` delimiters, if the note has frontmatter.
+    pub yaml: Option<&'a str>,
+    pub body: &'a str,
+    /// Zero-based index of the first body line within the whole file.
+    pub body_start_line: usize,
+}
+
+pub fn split(content: &str) -> Split<'_> {
+    let no_frontmatter = Split {
+        yaml: None,
+        body: content,
+        body_start_line: 0,
+    };
+
+    let content_start = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let first_line_end = match content_start.find('\n') {
+        Some(idx) => idx,
+        None => return no_frontmatter,
+    };
+    if content_start[..first_line_end].trim_end() != "---" {
+        return no_frontmatter;
+    }
+
+    let yaml_start = first_line_end + 1;
+    let mut offset = yaml_start;
+
+    for (idx, line) in content_start[yaml_start..]
+        .split_inclusive('\n')
+        .enumerate()
+    {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let yaml = &content_start[yaml_start..offset];
+            let body = &content_start[offset + line.len()..];
+            return Split {
+                yaml: Some(yaml),
+                body,
+                body_start_line: idx + 2,
+            };
+        }
+        offset += line.len();
+    }
+
+    no_frontmatter
+}
+
+/// Number of leading lines occupied by frontmatter, delimiters included.
+pub fn line_count(lines: &[&str]) -> usize {
+    let first = match lines.first() {
+        Some(first) => first.trim_start_matches('\u{feff}').trim_end(),
+        None => return 0,
+    };
+    if first != "---" {
+        return 0;
+    }
+    lines
+        .iter()
+        .skip(1)
+        .position(|l| matches!(l.trim_end(), "---" | "..."))
+        .map(|idx| idx + 2)
+        .unwrap_or(0)
+}
+
+/// Parses frontmatter YAML into a JSON object. Empty frontmatter yields an
+/// empty map; anything other than a mapping at the top level is an error.
+pub fn parse(yaml: &str) -> Result<Map<String, Value>, String> {
+    if yaml.trim().is_empty() {
+        return Ok(Map::new());
+    }
+
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(yaml).map_err(|e| format!("Invalid frontmatter: {}", e))?;
+
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(Value::Null) => Ok(Map::new()),
+        Ok(_) => Err("Invalid frontmatter: expected a mapping".to_string()),
+        Err(e) => Err(format!("Invalid frontmatter: {}", e)),
+    }
+}
+
+/// Rewrites a note's frontmatter with `edit`, keeping existing key order
+/// and leaving the body untouched. A note without frontmatter gets a new
+/// block at the top.
+pub fn update(
+    content: &str,
+    edit: impl FnOnce(&mut serde_yaml::Mapping),
+) -> Result<String, String> {
+    let split = split(content);
+    let mut mapping = match split.yaml {
+        Some(yaml) if !yaml.trim().is_empty() => {
+            match serde_yaml::from_str(yaml).map_err(|e| format!("Invalid frontmatter: {}", e))? {
+                serde_yaml::Value::Mapping(mapping) => mapping,
+                serde_yaml::Value::Null => serde_yaml::Mapping::new(),
+                _ => return Err("Invalid frontmatter: expected a mapping".to_string()),
+            }
+        }
+        _ => serde_yaml::Mapping::new(),
+    };
+    edit(&mut mapping);
+
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let yaml = if mapping.is_empty() {
+        String::new()
+    } else {
+        serde_yaml::to_string(&mapping)
+            .map_err(|e| format!("Failed to serialize frontmatter: {}", e))?
+            .replace('\n', newline)
+    };
+    let bom = if content.starts_with('\u{feff}') {
+        "\u{feff}"
+    } else {
+        ""
+    };
+    Ok(format!(
+        "{}---{}{}---{}{}",
+        bom, newline, yaml, newline, split.body
+    ))
+}
+
+/// Adds `key: value` as the last line of a note's frontmatter without
+/// reformatting the rest of it, creating the block if there is none. The
+/// caller checks the key isn't already there; `value` is written as is.
+pub fn append_field(content: &str, key: &str, value: &str) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    match split(content).yaml {
+        Some(yaml) => {
+            let end = yaml.as_ptr() as usize - content.as_ptr() as usize + yaml.len();
+            format!(
+                "{}{}: {}{}{}",
+                &content[..end],
+                key,
+                value,
+                newline,
+                &content[end..]
+            )
+        }
+        None => {
+            let body = content.strip_prefix('\u{feff}');
+            let bom = if body.is_some() { "\u{feff}" } else { "" };
+            format!(
+                "{}---{newline}{}: {}{newline}---{newline}{}",
+                bom,
+                key,
+                value,
+                body.unwrap_or(content)
+            )
+        }
+    }
+}
+
+/// Splits and parses in one step, returning the parsed map and the body.
+pub fn parse_note(content: &str) -> Result<(Map<String, Value>, Split<'_>), String> {
+    let split = split(content);
+    let map = match split.yaml {
+        Some(yaml) => parse(yaml)?,
+        None => Map::new(),
+    };
+    Ok((map, split))
+}
+
+/// Reads the `tags` field, accepting a single string, a comma separated
+/// string or a list, with any leading `#` removed.
+pub fn tags(frontmatter: &Map<String, Value>) -> Vec<String> {
+    string_list(frontmatter.get("tags"))
+        .into_iter()
+        .map(|t| t.trim_start_matches('#').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Reads a field that may be a single string, a comma separated string, or a
+/// list of strings.
+pub fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => s
+            .split(',')
+            .map(|part| part.trim().to_string())
+            .filter(|part| !part.is_empty())
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.trim().to_string()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .filter(|item| !item.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Interprets a frontmatter date (`2024-06-12`, `2024-06-12 09:30`, RFC 3339)
+/// as seconds since the Unix epoch, treating dates without a zone as UTC.
+pub fn parse_date(value: &Value) -> Option<i64> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    let text = value.as_str()?.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.timestamp());
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(dt.and_utc().timestamp());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+}
@@ -0,0 +1,464 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use super::{heading, links, protected_lines, slug, LineBuffer};
+
+// Numbering we add (`1.`, `1.2`, `1.2.3`) or that looks typed by hand. A
+// bare number without a dot ("2024 Goals") is left alone.
+static NUMBER_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:\d+(?:\.\d+)+\.?|\d+\.)\s+").unwrap());
+// `Some text ^block-id`, or `^block-id` alone on the line after a list or table.
+static BLOCK_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap());
+
+/// An ATX heading outside frontmatter and code.
+#[derive(Debug, Clone)]
+pub struct Heading {
+    /// Zero-based line index.
+    pub line: usize,
+    pub level: usize,
+    pub text: String,
+}
+
+/// Every heading in the document, in order.
+pub fn outline(lines: &[&str]) -> Vec<Heading> {
+    let protected = protected_lines(lines);
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !protected[*idx])
+        .filter_map(|(idx, line)| {
+            let (level, text) = heading(line)?;
+            Some(Heading {
+                line: idx,
+                level,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorKind {
+    Heading,
+    Block,
+}
+
+/// Something a `#fragment` can point at within a note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anchor {
+    pub kind: AnchorKind,
+    /// What goes after `#`: the heading's slug, or `^id` for a block.
+    pub slug: String,
+    /// The heading text, or the block's text without its id.
+    pub text: String,
+    /// One-based line number.
+    pub line: usize,
+}
+
+/// Every heading and `^block` id in the document. Headings get the slugs a
+/// table of contents links to, repeats numbered `-1`, `-2`...
+pub fn anchors(content: &str) -> Vec<Anchor> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = outline(&lines);
+    let texts: Vec<String> = headings.iter().map(|h| h.text.clone()).collect();
+    let mut anchors: Vec<Anchor> = headings
+        .iter()
+        .zip(unique_slugs(&texts))
+        .map(|(h, slug)| Anchor {
+            kind: AnchorKind::Heading,
+            slug,
+            text: h.text.clone(),
+            line: h.line + 1,
+        })
+        .collect();
+
+    let protected = protected_lines(&lines);
+    for (idx, line) in lines.iter().enumerate().filter(|(idx, _)| !protected[*idx]) {
+        let Some(caps) = BLOCK_ID.captures(line) else {
+            continue;
+        };
+        let mut text = line[..caps.get(0).map_or(0, |m| m.start())].trim();
+        if text.is_empty() {
+            // An id on its own line names the block above it.
+            text = lines[..idx]
+                .iter()
+                .rev()
+                .map(|l| l.trim())
+                .find(|l| !l.is_empty())
+                .unwrap_or_default();
+        }
+        anchors.push(Anchor {
+            kind: AnchorKind::Block,
+            slug: format!("^{}", &caps[1]),
+            text: text.to_string(),
+            line: idx + 1,
+        });
+    }
+    anchors.sort_by_key(|a| a.line);
+    anchors
+}
+
+/// Whether a link `#fragment` (as written, possibly percent-encoded) names
+/// one of `anchors`.
+pub fn has_anchor(anchors: &[Anchor], fragment: &str) -> bool {
+    let wanted = percent_encoding::percent_decode_str(fragment)
+        .decode_utf8_lossy()
+        .to_lowercase();
+    anchors.iter().any(|a| a.slug.to_lowercase() == wanted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberingStyle {
+    /// `1.` for the top level in range, then `1.1`, `1.2.3`.
+    Dotted,
+    /// Strip numbering.
+    None,
+}
+
+/// Numbers (or un-numbers) headings from `min_level` to `max_level`.
+///
+/// Each heading is numbered under the nearest heading above it with a lower
+/// level, so a skipped level adds no `0` part: an H3 straight under an H1
+/// numbered `1.` is `1.1`, and an H2 after it `1.2`. A heading above the
+/// range restarts the numbering. Existing numbering is replaced rather than
+/// doubled.
+/// Links within the document to renamed headings, `[x](#slug)` and
+/// `[[#Heading]]` or `[[This note#Heading]]`, are updated to match.
+pub fn number(
+    content: &str,
+    path: &Path,
+    min_level: usize,
+    max_level: usize,
+    style: NumberingStyle,
+) -> String {
+    let mut buffer = LineBuffer::parse(content);
+    let lines = buffer.as_strs();
+    let headings = outline(&lines);
+
+    // Numbered headings enclosing the current one, as (level, number,
+    // children so far), and the count of those at the top.
+    let mut open: Vec<(usize, Vec<usize>, usize)> = Vec::new();
+    let mut top = 0;
+    let mut renamed: Vec<(usize, String)> = Vec::new();
+    for h in &headings {
+        if h.level < min_level {
+            open.clear();
+            top = 0;
+            continue;
+        }
+        if h.level > max_level {
+            continue;
+        }
+        while open.last().is_some_and(|(level, _, _)| *level >= h.level) {
+            open.pop();
+        }
+        let parts = match open.last_mut() {
+            Some((_, parent, children)) => {
+                *children += 1;
+                parent.iter().copied().chain([*children]).collect()
+            }
+            None => {
+                top += 1;
+                vec![top]
+            }
+        };
+        open.push((h.level, parts.clone(), 0));
+
+        let bare = NUMBER_PREFIX.replace(&h.text, "").to_string();
+        let text = match style {
+            NumberingStyle::None => bare,
+            NumberingStyle::Dotted => {
+                let parts: Vec<String> = parts.iter().map(|c| c.to_string()).collect();
+                let number = if parts.len() == 1 {
+                    format!("{}.", parts[0])
+                } else {
+                    parts.join(".")
+                };
+                format!("{} {}", number, bare)
+            }
+        };
+        if text != h.text {
+            renamed.push((h.line, text));
+        }
+    }
+    if renamed.is_empty() {
+        return content.to_string();
+    }
+
+    let new_texts: Vec<String> = headings
+        .iter()
+        .map(|h| {
+            renamed
+                .iter()
+                .find(|(line, _)| *line == h.line)
+                .map(|(_, text)| text.clone())
+                .unwrap_or_else(|| h.text.clone())
+        })
+        .collect();
+    let old_texts: Vec<String> = headings.iter().map(|h| h.text.clone()).collect();
+    let slug_map: HashMap<String, String> = unique_slugs(&old_texts)
+        .into_iter()
+        .zip(unique_slugs(&new_texts))
+        .collect();
+    let text_map: HashMap<String, String> = old_texts
+        .iter()
+        .zip(&new_texts)
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| (old.to_lowercase(), new.clone()))
+        .collect();
+
+    let protected = protected_lines(&lines);
+    let note_name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut rewritten: Vec<String> = Vec::with_capacity(lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        if let Some((_, text)) = renamed.iter().find(|(l, _)| *l == idx) {
+            let (_, old) = heading(line).unwrap_or_default();
+            // Splice the new text in place so indentation and closing `#`s
+            // survive.
+            let offset = old.as_ptr() as usize - line.as_ptr() as usize;
+            rewritten.push(format!(
+                "{}{}{}",
+                &line[..offset],
+                text,
+                &line[offset + old.len()..]
+            ));
+        } else if protected[idx] {
+            rewritten.push(line.to_string());
+        } else {
+            rewritten.push(update_links(line, &note_name, &slug_map, &text_map));
+        }
+    }
+
+    buffer.lines = rewritten;
+    buffer.render()
+}
+
+/// Slugs as GitHub assigns them, with `-1`, `-2`... for repeats.
+fn unique_slugs(texts: &[String]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    texts
+        .iter()
+        .map(|text| {
+            let base = slug(text);
+            let count = seen.entry(base.clone()).or_insert(0);
+            let unique = if *count == 0 {
+                base.clone()
+            } else {
+                format!("{}-{}", base, count)
+            };
+            *count += 1;
+            unique
+        })
+        .collect()
+}
+
+fn update_links(
+    line: &str,
+    note_name: &str,
+    slug_map: &HashMap<String, String>,
+    text_map: &HashMap<String, String>,
+) -> String {
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+
+    for link in links::markdown_links(line) {
+        let Some(anchor) = link.target.strip_prefix('#') else {
+            continue;
+        };
+        let decoded = percent_encoding::percent_decode_str(anchor)
+            .decode_utf8_lossy()
+            .to_lowercase();
+        if let Some(new) = slug_map.get(&decoded).filter(|new| **new != decoded) {
+            edits.push((link.target_start, link.target_end, format!("#{}", new)));
+        }
+    }
+
+    for mut link in links::wikilinks(line) {
+        let same_note = link.target.is_empty() || link.target.to_lowercase() == note_name;
+        let Some(new) = link
+            .anchor
+            .as_ref()
+            .filter(|_| same_note)
+            .and_then(|a| text_map.get(&a.to_lowercase()))
+        else {
+            continue;
+        };
+        link.anchor = Some(new.clone());
+        edits.push((link.start, link.end, link.render()));
+    }
+
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    for (start, end, replacement) in edits {
+        if start < last {
+            continue;
+        }
+        out.push_str(&line[last..start]);
+        out.push_str(&replacement);
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// The section starting at heading line `line` (zero-based): the heading up
+/// to the next heading of the same or a higher level.
+pub(super) fn section_at(
+    headings: &[Heading],
+    line: usize,
+    len: usize,
+) -> Result<(usize, std::ops::Range<usize>), String> {
+    let idx = headings
+        .iter()
+        .position(|h| h.line == line)
+        .ok_or_else(|| format!("Line {} is not a heading", line + 1))?;
+    let level = headings[idx].level;
+    let end = headings[idx + 1..]
+        .iter()
+        .find(|h| h.level <= level)
+        .map(|h| h.line)
+        .unwrap_or(len);
+    Ok((idx, line..end))
+}
+
+/// Swaps the section at `line` with its previous or next sibling, the
+/// neighbouring section of the same level under the same parent.
+pub fn move_section(content: &str, line: usize, direction: Direction) -> Result<String, String> {
+    let mut buffer = LineBuffer::parse(content);
+    let (first, second) = {
+        let lines = buffer.as_strs();
+        let headings = outline(&lines);
+        let (idx, range) = section_at(&headings, line, lines.len())?;
+        let level = headings[idx].level;
+        let sibling = match direction {
+            Direction::Up => headings[..idx].iter().rev().find(|h| h.level <= level),
+            Direction::Down => headings.get(
+                headings
+                    .iter()
+                    .position(|h| h.line == range.end)
+                    .unwrap_or(headings.len()),
+            ),
+        }
+        .filter(|h| h.level == level)
+        .ok_or_else(|| format!("No section to swap with {}", direction_name(direction)))?;
+        let (_, sibling_range) = section_at(&headings, sibling.line, lines.len())?;
+        match direction {
+            Direction::Up => (sibling_range, range),
+            Direction::Down => (range, sibling_range),
+        }
+    };
+
+    // `first` ends where `second` begins. A section coming off the end of
+    // the document needs a blank line after it, and one going there sheds
+    // its trailing blanks.
+    let at_end = second.end == buffer.lines.len();
+    let mut swapped = buffer.lines[second.clone()].to_vec();
+    if swapped.last().is_some_and(|l| !l.trim().is_empty()) {
+        swapped.push(String::new());
+    }
+    let mut moved = buffer.lines[first.clone()].to_vec();
+    while at_end && moved.last().is_some_and(|l| l.trim().is_empty()) {
+        moved.pop();
+    }
+    swapped.extend(moved);
+    buffer.lines.splice(first.start..second.end, swapped);
+    Ok(buffer.render())
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "above",
+        Direction::Down => "below",
+    }
+}
+
+/// Shifts the heading at `line` and every heading in its section by
+/// `delta` levels (negative promotes). Refuses changes that would take any
+/// of them above H1 or below H6.
+pub fn change_section_level(content: &str, line: usize, delta: i32) -> Result<String, String> {
+    let mut buffer = LineBuffer::parse(content);
+    let changes: Vec<(usize, usize)> = {
+        let lines = buffer.as_strs();
+        let headings = outline(&lines);
+        let (_, range) = section_at(&headings, line, lines.len())?;
+        let mut changes = Vec::new();
+        for h in headings.iter().filter(|h| range.contains(&h.line)) {
+            let level = h.level as i32 + delta;
+            if !(1..=6).contains(&level) {
+                return Err(format!(
+                    "Heading on line {} can't go to level {}",
+                    h.line + 1,
+                    level
+                ));
+            }
+            changes.push((h.line, level as usize));
+        }
+        changes
+    };
+
+    for (idx, level) in changes {
+        let line = &buffer.lines[idx];
+        let hashes = line.trim_start().len() - line.trim_start().trim_start_matches('#').len();
+        let start = line.len() - line.trim_start().len();
+        buffer.lines[idx] = format!(
+            "{}{}{}",
+            &line[..start],
+            "#".repeat(level),
+            &line[start + hashes..]
+        );
+    }
+    Ok(buffer.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(content: &str) -> String {
+        number(content, Path::new("Note.md"), 1, 6, NumberingStyle::Dotted)
+    }
+
+    #[test]
+    fn skipped_levels_number_from_the_nearest_numbered_heading() {
+        let content = "# Intro\n### Detail\n### More\n## Section\n#### Deep\n# Next\n### Again\n";
+        assert_eq!(
+            numbered(content),
+            "# 1. Intro\n### 1.1 Detail\n### 1.2 More\n## 1.3 Section\n#### 1.3.1 Deep\n\
+             # 2. Next\n### 2.1 Again\n"
+        );
+    }
+
+    #[test]
+    fn a_document_starting_below_the_top_level_numbers_from_one() {
+        assert_eq!(
+            numbered("### First\n### Second\n#### Inner\n"),
+            "### 1. First\n### 2. Second\n#### 2.1 Inner\n"
+        );
+    }
+
+    #[test]
+    fn renumbering_replaces_the_numbers_and_links() {
+        let once = numbered("# Intro\n### Detail\nSee [detail](#detail).\n");
+        assert_eq!(
+            once,
+            "# 1. Intro\n### 1.1 Detail\nSee [detail](#11-detail).\n"
+        );
+        assert_eq!(numbered(&once), once);
+    }
+}
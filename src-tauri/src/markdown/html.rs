@@ -0,0 +1,559 @@
+use ego_tree::NodeRef;
+use scraper::{Html, Node, Selector};
+use std::collections::HashMap;
+use url::Url;
+
+use super::tables::{self, Alignment, Table};
+
+/// Elements dropped along with everything inside them.
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "head", "title", "meta", "link", "iframe", "object",
+    "embed", "svg", "canvas", "button", "input", "select", "textarea",
+];
+
+/// Elements that start a new block. Anything else is treated as inline and
+/// unknown tags contribute only their text.
+const BLOCKS: &[&str] = &[
+    "html",
+    "body",
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "figure",
+    "figcaption",
+    "address",
+    "details",
+    "summary",
+    "center",
+    "form",
+    "fieldset",
+    "dl",
+    "dt",
+    "dd",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "table",
+    "hr",
+];
+
+// Stands in for `<br>` until whitespace has been collapsed.
+const BREAK: char = '\0';
+
+#[derive(Debug, Default)]
+pub struct HtmlConversion {
+    /// Base for resolving relative link and image URLs.
+    pub base_url: Option<Url>,
+    /// Replacement targets for image URLs (after resolving), e.g. paths of
+    /// downloaded copies.
+    pub image_targets: HashMap<String, String>,
+    /// Write replaced images as `![[target]]` embeds rather than markdown
+    /// images.
+    pub wiki_embeds: bool,
+}
+
+/// The page's `<title>`, falling back to its first `<h1>`.
+pub fn document_title(document: &Html) -> Option<String> {
+    ["title", "h1"].iter().find_map(|tag| {
+        let selector = Selector::parse(tag).ok()?;
+        let element = document.select(&selector).next()?;
+        let text = element
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+/// The part of a page worth keeping: its `<article>`, else `<main>`, else
+/// `<body>`.
+pub fn main_content(document: &Html) -> NodeRef<'_, Node> {
+    ["article", "main", "body"]
+        .iter()
+        .find_map(|tag| {
+            let selector = Selector::parse(tag).ok()?;
+            document.select(&selector).next().map(|e| *e)
+        })
+        .unwrap_or_else(|| document.tree.root())
+}
+
+/// Distinct http(s) image URLs under `root`, resolved against `base_url`.
+pub fn image_urls(root: NodeRef<'_, Node>, base_url: Option<&Url>) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for node in root.descendants() {
+        let Some(element) = node.value().as_element() else {
+            continue;
+        };
+        if element.name() != "img" || has_skipped_ancestor(node) {
+            continue;
+        }
+        let Some(src) = image_src(element) else {
+            continue;
+        };
+        let url = resolve(base_url, src);
+        if (url.starts_with("http://") || url.starts_with("https://")) && !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Converts the HTML under `root` to markdown.
+pub fn to_markdown(root: NodeRef<'_, Node>, conversion: &HtmlConversion) -> String {
+    let converter = Converter { conversion };
+    let blocks = if root.value().is_document() || root.value().is_fragment() {
+        converter.blocks(root)
+    } else {
+        converter.blocks_of(std::iter::once(root))
+    };
+    let mut markdown = blocks.join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+struct Converter<'a> {
+    conversion: &'a HtmlConversion,
+}
+
+impl Converter<'_> {
+    fn blocks(&self, node: NodeRef<'_, Node>) -> Vec<String> {
+        self.blocks_of(node.children())
+    }
+
+    fn blocks_of<'n>(&self, nodes: impl Iterator<Item = NodeRef<'n, Node>>) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for node in nodes {
+            let Some(element) = node.value().as_element() else {
+                inline.push_str(&self.inline(node));
+                continue;
+            };
+            let name = element.name();
+            if SKIPPED.contains(&name) {
+                continue;
+            }
+            if !BLOCKS.contains(&name) {
+                inline.push_str(&self.inline(node));
+                continue;
+            }
+
+            push_paragraph(&mut blocks, &mut inline);
+            match name {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level = name[1..].parse::<usize>().unwrap_or(1);
+                    let text = clean_inline(&self.inline_children(node), " ");
+                    if !text.is_empty() {
+                        blocks.push(format!("{} {}", "#".repeat(level), text));
+                    }
+                }
+                "ul" | "ol" => {
+                    let list = self.list(node, name == "ol");
+                    if !list.is_empty() {
+                        blocks.push(list);
+                    }
+                }
+                "pre" => blocks.push(code_block(node)),
+                "blockquote" => {
+                    let inner = self.blocks(node).join("\n\n");
+                    if !inner.is_empty() {
+                        blocks.push(prefix_lines(&inner, ">"));
+                    }
+                }
+                "table" => {
+                    if let Some(table) = self.table(node) {
+                        blocks.push(table);
+                    }
+                }
+                "hr" => blocks.push("---".to_string()),
+                "dt" => {
+                    let text = clean_inline(&self.inline_children(node), " ");
+                    if !text.is_empty() {
+                        blocks.push(format!("**{}**", text));
+                    }
+                }
+                _ => blocks.extend(self.blocks(node)),
+            }
+        }
+
+        push_paragraph(&mut blocks, &mut inline);
+        blocks
+    }
+
+    fn inline(&self, node: NodeRef<'_, Node>) -> String {
+        let element = match node.value() {
+            Node::Text(text) => return escape(&collapse_whitespace(text)),
+            Node::Element(element) => element,
+            _ => return String::new(),
+        };
+
+        match element.name() {
+            name if SKIPPED.contains(&name) => String::new(),
+            "br" => BREAK.to_string(),
+            "strong" | "b" => wrap(&self.inline_children(node), "**"),
+            "em" | "i" => wrap(&self.inline_children(node), "*"),
+            "del" | "s" | "strike" => wrap(&self.inline_children(node), "~~"),
+            "code" | "kbd" | "samp" => code_span(&node_text(node)),
+            "a" => {
+                let inner = self.inline_children(node);
+                let text = clean_inline(&inner, " ");
+                let href = element.attr("href").map(str::trim).unwrap_or_default();
+                if href.is_empty() || href.to_lowercase().starts_with("javascript:") {
+                    return inner;
+                }
+                let url = resolve(self.conversion.base_url.as_ref(), href);
+                let link = if text.is_empty() {
+                    format!("<{}>", url)
+                } else {
+                    format!("[{}]({})", text, link_target(&url))
+                };
+                let leading = if inner.starts_with(' ') { " " } else { "" };
+                let trailing = if inner.ends_with(' ') { " " } else { "" };
+                format!("{}{}{}", leading, link, trailing)
+            }
+            "img" => {
+                let Some(src) = image_src(element) else {
+                    return String::new();
+                };
+                if src.starts_with("data:") {
+                    return String::new();
+                }
+                let url = resolve(self.conversion.base_url.as_ref(), src);
+                let target = match self.conversion.image_targets.get(&url) {
+                    Some(local) if self.conversion.wiki_embeds => return format!("![[{}]]", local),
+                    Some(local) => local.clone(),
+                    None => link_target(&url),
+                };
+                let alt = collapse_whitespace(element.attr("alt").unwrap_or_default());
+                format!(
+                    "![{}]({})",
+                    alt.trim().replace('[', "\\[").replace(']', "\\]"),
+                    target
+                )
+            }
+            _ => self.inline_children(node),
+        }
+    }
+
+    fn inline_children(&self, node: NodeRef<'_, Node>) -> String {
+        node.children().map(|child| self.inline(child)).collect()
+    }
+
+    fn list(&self, node: NodeRef<'_, Node>, ordered: bool) -> String {
+        let start = node
+            .value()
+            .as_element()
+            .and_then(|e| e.attr("start"))
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(1);
+        let mut items = Vec::new();
+
+        for (number, child) in (start..).zip(node.children().filter(|c| is_element(*c, &["li"]))) {
+            let marker = if ordered {
+                format!("{}. ", number)
+            } else {
+                "- ".to_string()
+            };
+
+            // Nested lists stay tight against the item text; other blocks
+            // inside an item are separated by a blank line.
+            let mut content = String::new();
+            for block in self.blocks(child) {
+                if !content.is_empty() {
+                    content.push_str(if is_list(&block) { "\n" } else { "\n\n" });
+                }
+                content.push_str(&block);
+            }
+
+            let indent = " ".repeat(marker.len());
+            let mut lines = content.lines();
+            let mut item = format!("{}{}", marker, lines.next().unwrap_or_default());
+            for line in lines {
+                item.push('\n');
+                if !line.is_empty() {
+                    item.push_str(&indent);
+                    item.push_str(line);
+                }
+            }
+            items.push(item.trim_end().to_string());
+        }
+
+        items.join("\n")
+    }
+
+    fn table(&self, node: NodeRef<'_, Node>) -> Option<String> {
+        let mut rows: Vec<(bool, Vec<(String, Alignment)>)> = Vec::new();
+        for row in table_rows(node) {
+            let mut header = false;
+            let mut cells = Vec::new();
+            for cell in row.children().filter(|c| is_element(*c, &["th", "td"])) {
+                header |= is_element(cell, &["th"]);
+                cells.push((
+                    clean_inline(&self.inline_children(cell), "<br>"),
+                    cell_alignment(cell),
+                ));
+            }
+            if !cells.is_empty() {
+                rows.push((header || is_element(row.parent()?, &["thead"]), cells));
+            }
+        }
+        if rows.is_empty() {
+            return None;
+        }
+
+        let columns = rows.iter().map(|(_, cells)| cells.len()).max().unwrap_or(0);
+        let (_, header) = rows.remove(0);
+        let pad = |cells: Vec<String>| {
+            let mut cells = cells;
+            cells.resize(columns, String::new());
+            cells
+        };
+        let mut alignments: Vec<Alignment> = header.iter().map(|(_, a)| *a).collect();
+        alignments.resize(columns, Alignment::None);
+
+        let table = Table {
+            headers: pad(header.into_iter().map(|(text, _)| text).collect()),
+            alignments,
+            rows: rows
+                .into_iter()
+                .map(|(_, cells)| pad(cells.into_iter().map(|(text, _)| text).collect()))
+                .collect(),
+        };
+        Some(tables::render(&table).join("\n"))
+    }
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, inline: &mut String) {
+    let text = clean_inline(inline, "  \n");
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    inline.clear();
+}
+
+/// Collapses runs of spaces in converted inline text and turns `<br>`
+/// placeholders into `line_break`, dropping leading and trailing breaks.
+fn clean_inline(text: &str, line_break: &str) -> String {
+    let lines: Vec<String> = text
+        .split(BREAK)
+        .map(|line| {
+            line.split(' ')
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    let first = lines.iter().position(|l| !l.is_empty());
+    let last = lines.iter().rposition(|l| !l.is_empty());
+    match (first, last) {
+        (Some(first), Some(last)) => lines[first..=last].join(line_break),
+        _ => String::new(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for ch in text.chars().filter(|c| *c != BREAK) {
+        if ch.is_whitespace() {
+            if !space {
+                out.push(' ');
+            }
+            space = true;
+        } else {
+            out.push(ch);
+            space = false;
+        }
+    }
+    out
+}
+
+/// Escapes characters that would otherwise be read as markdown syntax.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Wraps inline text in an emphasis marker, keeping surrounding spaces
+/// outside it since `** bold **` isn't emphasis.
+fn wrap(inner: &str, marker: &str) -> String {
+    let trimmed = inner.trim_matches(' ');
+    if trimmed.is_empty() {
+        return inner.to_string();
+    }
+    let leading = if inner.starts_with(' ') { " " } else { "" };
+    let trailing = if inner.ends_with(' ') { " " } else { "" };
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn code_span(text: &str) -> String {
+    let text = collapse_whitespace(text);
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+    let fence = "`".repeat(longest_run(text, '`') + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+fn code_block(node: NodeRef<'_, Node>) -> String {
+    let code = node_text(node);
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    let language = std::iter::once(node)
+        .chain(node.children().filter(|c| is_element(*c, &["code"])))
+        .filter_map(|n| n.value().as_element()?.attr("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn prefix_lines(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                prefix.to_string()
+            } else {
+                format!("{} {}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_list(block: &str) -> bool {
+    block.starts_with("- ")
+        || block.split_once(". ").is_some_and(|(number, _)| {
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+fn table_rows<'a>(table: NodeRef<'a, Node>) -> Vec<NodeRef<'a, Node>> {
+    let mut rows = Vec::new();
+    for child in table.children() {
+        if is_element(child, &["tr"]) {
+            rows.push(child);
+        } else if is_element(child, &["thead", "tbody", "tfoot"]) {
+            rows.extend(child.children().filter(|c| is_element(*c, &["tr"])));
+        }
+    }
+    rows
+}
+
+fn cell_alignment(cell: NodeRef<'_, Node>) -> Alignment {
+    let Some(element) = cell.value().as_element() else {
+        return Alignment::None;
+    };
+    let style = element
+        .attr("style")
+        .unwrap_or_default()
+        .replace(' ', "")
+        .to_lowercase();
+    let align = element.attr("align").map(str::to_lowercase).or_else(|| {
+        style
+            .split("text-align:")
+            .nth(1)
+            .map(|s| s.split(';').next().unwrap_or_default().to_string())
+    });
+    match align.as_deref() {
+        Some("left") => Alignment::Left,
+        Some("center") => Alignment::Center,
+        Some("right") => Alignment::Right,
+        _ => Alignment::None,
+    }
+}
+
+fn image_src(element: &scraper::node::Element) -> Option<&str> {
+    ["src", "data-src"]
+        .iter()
+        .filter_map(|attr| element.attr(attr))
+        .map(str::trim)
+        .find(|src| !src.is_empty())
+}
+
+fn resolve(base_url: Option<&Url>, href: &str) -> String {
+    match base_url {
+        Some(base) if !href.starts_with('#') => base
+            .join(href)
+            .map(String::from)
+            .unwrap_or_else(|_| href.to_string()),
+        _ => href.to_string(),
+    }
+}
+
+/// Wraps targets that would break `(...)` link syntax in `<>`.
+fn link_target(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url)
+    } else {
+        url.to_string()
+    }
+}
+
+fn node_text(node: NodeRef<'_, Node>) -> String {
+    node.descendants()
+        .filter(|n| !has_skipped_ancestor(*n))
+        .filter_map(|n| match n.value() {
+            Node::Text(text) => Some(text.to_string()),
+            Node::Element(e) if e.name() == "br" => Some("\n".to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn has_skipped_ancestor(node: NodeRef<'_, Node>) -> bool {
+    node.ancestors().any(|a| is_element(a, SKIPPED))
+}
+
+fn is_element(node: NodeRef<'_, Node>, names: &[&str]) -> bool {
+    node.value()
+        .as_element()
+        .is_some_and(|e| names.contains(&e.name()))
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == ch {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
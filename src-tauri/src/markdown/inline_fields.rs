@@ -0,0 +1,144 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, code_block_lines, frontmatter, LineBuffer};
+
+// `[key:: value]` or `(key:: value)` anywhere in a line. Values may contain
+// wikilinks, whose brackets would otherwise end the field early.
+static BRACKETED_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\[(]([A-Za-z0-9_][\w \-/]*?)::[ \t]*((?:\[\[[^\]]*\]\]|[^\]\)\[])*)[\])]")
+        .unwrap()
+});
+
+// `key:: value` filling the whole line, optionally after a list or task marker.
+static LINE_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:(?:[-*+]|\d+[.)])\s+)?(?:\[.\]\s+)?([A-Za-z0-9_][\w \-/]*?)::[ \t]*(.*)$")
+        .unwrap()
+});
+
+static DATE_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2})?)?$").unwrap());
+
+static NUMBER_VALUE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^-?\d+(?:\.\d+)?$").unwrap());
+
+static LINK_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[\[([^\]|#]+)(?:#[^\]|]*)?(?:\|[^\]]*)?\]\]$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Number,
+    Date,
+    Link,
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InlineField {
+    /// Key as written in the note, for display.
+    pub key: String,
+    /// Lowercased key used for lookups and queries.
+    pub normalized_key: String,
+    pub raw_value: String,
+    pub value_type: FieldType,
+    /// Parsed value: a number, an ISO date string, the link target, or text.
+    pub value: Value,
+    pub line_number: usize,
+}
+
+pub fn normalize_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Extracts Dataview-style inline fields from a note, skipping frontmatter,
+/// fenced code blocks and inline code spans. Line numbers are 1-based and
+/// refer to the whole file.
+pub fn extract(content: &str) -> Vec<InlineField> {
+    let split = frontmatter::split(content);
+    let lines: Vec<&str> = split.body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut fields = Vec::new();
+
+    for (idx, (line, code)) in lines.iter().zip(in_code).enumerate() {
+        if code || !line.contains("::") {
+            continue;
+        }
+        let line_number = split.body_start_line + idx + 1;
+        let scrubbed = blank_code_spans(line);
+
+        let mut found_bracketed = false;
+        for caps in BRACKETED_FIELD.captures_iter(&scrubbed) {
+            found_bracketed = true;
+            fields.push(make_field(&caps[1], &caps[2], line_number));
+        }
+
+        if !found_bracketed {
+            if let Some(caps) = LINE_FIELD.captures(&scrubbed) {
+                fields.push(make_field(&caps[1], &caps[2], line_number));
+            }
+        }
+    }
+
+    fields
+}
+
+/// Replaces the value of inline field `key` on 1-based line `line_number`
+/// with `value`, leaving the rest of the line as written. `None` when the
+/// line has no such field.
+pub fn set_value(content: &str, line_number: usize, key: &str, value: &str) -> Option<String> {
+    let mut buffer = LineBuffer::parse(content);
+    let line = buffer.lines.get(line_number.checked_sub(1)?)?;
+    let scrubbed = blank_code_spans(line);
+    let wanted = normalize_key(key);
+    let caps = BRACKETED_FIELD
+        .captures_iter(&scrubbed)
+        .chain(LINE_FIELD.captures(&scrubbed))
+        .find(|caps| normalize_key(&caps[1]) == wanted)?;
+    let old = caps.get(2)?;
+    // Keep any space around the old value.
+    let start = old.start() + (old.len() - old.as_str().trim_start().len());
+    let end = start + old.as_str().trim().len();
+    let updated = format!("{}{}{}", &line[..start], value, &line[end..]);
+    buffer.lines[line_number - 1] = updated;
+    Some(buffer.render())
+}
+
+fn make_field(key: &str, raw_value: &str, line_number: usize) -> InlineField {
+    let key = key.trim().to_string();
+    let raw_value = raw_value.trim().to_string();
+    let (value_type, value) = classify(&raw_value);
+
+    InlineField {
+        normalized_key: normalize_key(&key),
+        key,
+        raw_value,
+        value_type,
+        value,
+        line_number,
+    }
+}
+
+pub fn classify(raw: &str) -> (FieldType, Value) {
+    if NUMBER_VALUE.is_match(raw) {
+        if let Some(number) = raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+        {
+            let value = match raw.parse::<i64>() {
+                Ok(int) => Value::from(int),
+                Err(_) => Value::Number(number),
+            };
+            return (FieldType::Number, value);
+        }
+    }
+    if DATE_VALUE.is_match(raw) {
+        return (FieldType::Date, Value::String(raw.to_string()));
+    }
+    if let Some(caps) = LINK_VALUE.captures(raw) {
+        return (FieldType::Link, Value::String(caps[1].trim().to_string()));
+    }
+    (FieldType::Text, Value::String(raw.to_string()))
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use super::lists::{indent_width, item_marker};
+use super::{heading, protected_lines, tags, LineBuffer};
+
+/// A board read from a note: each H2 heading is a column and the top-level
+/// list items under it are its cards. Line numbers are 1-based and ranges
+/// inclusive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KanbanBoard {
+    pub columns: Vec<KanbanColumn>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KanbanColumn {
+    pub title: String,
+    pub line_number: usize,
+    pub end_line: usize,
+    pub cards: Vec<KanbanCard>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KanbanCard {
+    pub text: String,
+    /// Whether the card is a task item.
+    pub task: bool,
+    pub completed: bool,
+    pub tags: Vec<String>,
+    pub line_number: usize,
+    /// Last line of the card, nested children included.
+    pub end_line: usize,
+}
+
+pub fn parse(content: &str) -> KanbanBoard {
+    let lines: Vec<&str> = content.lines().collect();
+    parse_lines(&lines)
+}
+
+fn parse_lines(lines: &[&str]) -> KanbanBoard {
+    let protected = protected_lines(lines);
+    let section_break =
+        |idx: usize| !protected[idx] && heading(lines[idx]).is_some_and(|(level, _)| level <= 2);
+
+    let mut columns = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let Some((2, title)) = heading(line).filter(|_| !protected[idx]) else {
+            continue;
+        };
+        let end = (idx + 1..lines.len())
+            .find(|&i| section_break(i))
+            .unwrap_or(lines.len());
+        let items: Vec<usize> = (idx + 1..end)
+            .filter(|&i| !protected[i] && item_marker(lines[i]).is_some())
+            .collect();
+        let top = items
+            .iter()
+            .filter_map(|&i| item_marker(lines[i]))
+            .map(|m| m.indent)
+            .min();
+
+        let mut cards = Vec::new();
+        for &start in &items {
+            let marker = item_marker(lines[start]).unwrap();
+            if Some(marker.indent) != top {
+                continue;
+            }
+            // A card runs until the next line indented no deeper than it.
+            let mut card_end = start;
+            for i in start + 1..end {
+                if lines[i].trim().is_empty() {
+                    continue;
+                }
+                let leading = &lines[i][..lines[i].len() - lines[i].trim_start().len()];
+                let indent = indent_width(leading);
+                if indent <= marker.indent && !protected[i] {
+                    break;
+                }
+                card_end = i;
+            }
+            let text = lines[start][marker.text_start..].trim();
+            cards.push(KanbanCard {
+                text: text.to_string(),
+                task: marker.checked.is_some(),
+                completed: marker.checked.unwrap_or(false),
+                tags: tags::inline_tags(text),
+                line_number: start + 1,
+                end_line: card_end + 1,
+            });
+        }
+        let last = (idx..end)
+            .rev()
+            .find(|&i| !lines[i].trim().is_empty())
+            .unwrap_or(idx);
+        columns.push(KanbanColumn {
+            title: title.to_string(),
+            line_number: idx + 1,
+            end_line: last + 1,
+            cards,
+        });
+    }
+
+    KanbanBoard { columns }
+}
+
+/// Moves the card starting on `card_line` (1-based), with its children, to
+/// index `position` of the column titled `target_column`. A position past the
+/// end appends.
+pub fn move_card(
+    content: &str,
+    card_line: usize,
+    target_column: &str,
+    position: usize,
+) -> Result<String, String> {
+    let mut buffer = LineBuffer::parse(content);
+    let board = parse_lines(&buffer.as_strs());
+    let card = board
+        .columns
+        .iter()
+        .flat_map(|c| &c.cards)
+        .find(|c| c.line_number == card_line)
+        .ok_or_else(|| format!("No card on line {}", card_line))?;
+    let source_indent = item_marker(&buffer.lines[card_line - 1])
+        .map(|m| m.indent)
+        .unwrap_or(0);
+    let mut moved: Vec<String> = buffer.lines.drain(card_line - 1..card.end_line).collect();
+    // Emptying a column shouldn't leave a doubled blank line behind.
+    let gap = card_line - 1;
+    let blank = |line: Option<&String>| line.is_some_and(|l| l.trim().is_empty());
+    if gap > 0 && blank(buffer.lines.get(gap - 1)) && blank(buffer.lines.get(gap)) {
+        buffer.lines.remove(gap);
+    }
+
+    let board = parse_lines(&buffer.as_strs());
+    let column = board
+        .columns
+        .iter()
+        .find(|c| c.title == target_column)
+        .or_else(|| {
+            board
+                .columns
+                .iter()
+                .find(|c| c.title.eq_ignore_ascii_case(target_column))
+        })
+        .ok_or_else(|| format!("No column named {}", target_column))?;
+
+    let insert_at = match column.cards.get(position) {
+        Some(card) => card.line_number - 1,
+        None => match column.cards.last() {
+            Some(card) => card.end_line,
+            None => {
+                // Below the heading, after the blank line that usually
+                // follows it.
+                let after_heading = column.line_number;
+                if buffer
+                    .lines
+                    .get(after_heading)
+                    .is_some_and(|l| l.trim().is_empty())
+                {
+                    after_heading + 1
+                } else {
+                    after_heading
+                }
+            }
+        },
+    };
+
+    let target_indent = column
+        .cards
+        .first()
+        .and_then(|card| item_marker(&buffer.lines[card.line_number - 1]))
+        .map(|m| m.indent)
+        .unwrap_or(0);
+    if target_indent != source_indent {
+        for line in moved.iter_mut().filter(|l| !l.trim().is_empty()) {
+            let stripped = strip_indent(line, source_indent);
+            *line = format!("{}{}", " ".repeat(target_indent), stripped);
+        }
+    }
+
+    if buffer
+        .lines
+        .get(insert_at)
+        .is_some_and(|l| heading(l).is_some())
+    {
+        moved.push(String::new());
+    }
+    buffer.lines.splice(insert_at..insert_at, moved);
+    Ok(buffer.render())
+}
+
+/// Removes up to `width` columns of leading whitespace.
+fn strip_indent(line: &str, width: usize) -> &str {
+    let mut removed = 0;
+    for (idx, c) in line.char_indices() {
+        if removed >= width || !matches!(c, ' ' | '\t') {
+            return &line[idx..];
+        }
+        removed += if c == '\t' { 4 } else { 1 };
+    }
+    ""
+}
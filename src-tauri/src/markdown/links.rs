@@ -0,0 +1,192 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+// `[[target]]`, `[[target#heading|alias]]`, `![[embed]]`.
+static WIKILINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(!?)\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]").unwrap()
+});
+
+// `[text](target "title")` and `![alt](<target with spaces>)`.
+static MARKDOWN_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(!?)\[([^\]]*)\]\((<[^>]+>|[^)\s]+)(?:\s+"[^"]*")?\)"#).unwrap()
+});
+
+// An `http(s)` URL outside of markdown link syntax.
+static BARE_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"https?://[^\s<>\[\]()`]+").unwrap());
+
+/// A `[[wikilink]]` found in a line.
+#[derive(Debug, Clone)]
+pub struct WikiLink {
+    pub embed: bool,
+    /// Note name or path, without any `#anchor` or `|alias`.
+    pub target: String,
+    /// Heading (or `^block` id) after `#`.
+    pub anchor: Option<String>,
+    pub alias: Option<String>,
+    /// Byte range of the whole link within the line.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl WikiLink {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.embed {
+            out.push('!');
+        }
+        out.push_str("[[");
+        out.push_str(&self.target);
+        if let Some(anchor) = &self.anchor {
+            out.push('#');
+            out.push_str(anchor);
+        }
+        if let Some(alias) = &self.alias {
+            out.push('|');
+            out.push_str(alias);
+        }
+        out.push_str("]]");
+        out
+    }
+}
+
+pub fn wikilinks(line: &str) -> Vec<WikiLink> {
+    WIKILINK
+        .captures_iter(line)
+        .map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let part = |idx: usize| {
+                caps.get(idx)
+                    .map(|m| m.as_str().trim().to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            WikiLink {
+                embed: &caps[1] == "!",
+                target: caps[2].trim().to_string(),
+                anchor: part(3),
+                alias: part(4),
+                start: whole.start(),
+                end: whole.end(),
+            }
+        })
+        .collect()
+}
+
+/// A standard markdown link or image found in a line.
+#[derive(Debug, Clone)]
+pub struct MarkdownLink {
+    /// Link text, or alt text for images.
+    pub text: String,
+    /// Target as written, without surrounding `<>`.
+    pub target: String,
+    /// Byte range of the target within the line (excluding `<>`).
+    pub target_start: usize,
+    pub target_end: usize,
+    /// Byte range of the whole link, including any leading `!`.
+    pub start: usize,
+    pub end: usize,
+}
+
+pub fn markdown_links(line: &str) -> Vec<MarkdownLink> {
+    MARKDOWN_LINK
+        .captures_iter(line)
+        .map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let target = caps.get(3).unwrap();
+            let (start, end) = if target.as_str().starts_with('<') {
+                (target.start() + 1, target.end() - 1)
+            } else {
+                (target.start(), target.end())
+            };
+            MarkdownLink {
+                text: caps[2].to_string(),
+                target: line[start..end].to_string(),
+                target_start: start,
+                target_end: end,
+                start: whole.start(),
+                end: whole.end(),
+            }
+        })
+        .collect()
+}
+
+/// An `http(s)` URL found in a line.
+#[derive(Debug, Clone)]
+pub struct HttpUrl {
+    pub url: String,
+    /// Link text when the URL is a markdown link target with non-empty text.
+    pub text: Option<String>,
+}
+
+/// Every `http(s)` URL in a line, whether a markdown link target, an
+/// autolink or bare text. Callers should blank code spans first.
+pub fn http_urls(line: &str) -> Vec<HttpUrl> {
+    let is_http = |t: &str| t.starts_with("http://") || t.starts_with("https://");
+    let mut urls = Vec::new();
+    let mut rest = line.to_string();
+    for link in markdown_links(line) {
+        if is_http(&link.target) {
+            let text = link.text.trim();
+            urls.push(HttpUrl {
+                url: link.target.clone(),
+                text: (!text.is_empty() && text != link.target).then(|| text.to_string()),
+            });
+        }
+        rest.replace_range(link.start..link.end, &" ".repeat(link.end - link.start));
+    }
+    for m in BARE_URL.find_iter(&rest) {
+        let url = m
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+        urls.push(HttpUrl {
+            url: url.to_string(),
+            text: None,
+        });
+    }
+    urls
+}
+
+/// Replaces wikilinks, markdown links and bare URLs with spaces, keeping
+/// byte offsets, so prose checks don't look inside them.
+pub fn blank_links(line: &str) -> String {
+    let mut out = line.to_string();
+    for regex in [&*WIKILINK, &*MARKDOWN_LINK, &*BARE_URL] {
+        let ranges: Vec<_> = regex.find_iter(&out).map(|m| m.range()).collect();
+        for range in ranges {
+            out.replace_range(range.clone(), &" ".repeat(range.len()));
+        }
+    }
+    out
+}
+
+/// Whether a link target points outside the vault (a URL or other scheme)
+/// or only at an anchor in the same note.
+pub fn is_external(target: &str) -> bool {
+    if target.starts_with('#') {
+        return true;
+    }
+    // A scheme is at least two characters so Windows drive letters (`C:`)
+    // aren't mistaken for one.
+    target.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Strips any `#anchor` or `?query` and percent-decodes a local target.
+pub fn decode_target(target: &str) -> String {
+    let path = target.split(['#', '?']).next().unwrap_or(target);
+    percent_encoding::percent_decode_str(path)
+        .decode_utf8_lossy()
+        .to_string()
+}
+
+/// Encodes a path for use as a markdown link target.
+pub fn encode_target(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
@@ -0,0 +1,218 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, code_block_end, fence_marker, frontmatter, heading};
+
+pub const RULES: &[&str] = &[
+    "broken-reference-link",
+    "duplicate-heading",
+    "heading-level-jump",
+    "unclosed-code-fence",
+    "bare-url",
+    "trailing-whitespace",
+];
+
+static REFERENCE_DEFINITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}\[([^\]^][^\]]*)\]:\s*\S").unwrap());
+static REFERENCE_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\[([^\]]*)\]").unwrap());
+static LINKED_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\]\([^)]*\)|<https?://[^>]*>|\[\[[^\]]*\]\]|\w+="[^"]*"|\w+='[^']*'"#).unwrap()
+});
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"https?://[^\s<>\[\]()]+").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub rule: String,
+    pub severity: Severity,
+    /// 1-based line and column (in characters).
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Replacement text for the whole line, when the fix is mechanical.
+    pub fix: Option<String>,
+}
+
+fn diagnostic(
+    rule: &str,
+    severity: Severity,
+    line: usize,
+    column: usize,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        rule: rule.to_string(),
+        severity,
+        line,
+        column,
+        message,
+        fix: None,
+    }
+}
+
+fn char_column(line: &str, byte_idx: usize) -> usize {
+    line[..byte_idx].chars().count() + 1
+}
+
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Lints a note with the given rules enabled. Frontmatter is skipped and
+/// code blocks are only inspected for being closed.
+pub fn lint(content: &str, enabled: &HashSet<String>) -> Vec<Diagnostic> {
+    let on = |rule: &str| enabled.contains(rule);
+    let all_lines: Vec<&str> = content.lines().collect();
+    let first_body_line = frontmatter::line_count(&all_lines);
+    let lines = &all_lines[first_body_line..];
+    let mut diagnostics = Vec::new();
+
+    let mut definitions = HashSet::new();
+    let mut prose: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if fence_marker(lines[i]).is_some() {
+            let end = code_block_end(lines, i);
+            let closed = end > i + 1
+                && fence_marker(lines[end - 1]).is_some_and(|(_, _, rest)| rest.is_empty());
+            if !closed && on("unclosed-code-fence") {
+                diagnostics.push(diagnostic(
+                    "unclosed-code-fence",
+                    Severity::Error,
+                    first_body_line + i + 1,
+                    1,
+                    "Code fence is never closed".to_string(),
+                ));
+            }
+            i = end;
+            continue;
+        }
+        if let Some(caps) = REFERENCE_DEFINITION.captures(lines[i]) {
+            definitions.insert(normalize_label(&caps[1]));
+        }
+        prose.push(i);
+        i += 1;
+    }
+
+    let mut seen_headings: HashMap<String, usize> = HashMap::new();
+    let mut previous_level = 0;
+
+    for idx in prose {
+        let line = lines[idx];
+        let line_number = first_body_line + idx + 1;
+
+        if let Some((level, text)) = heading(line) {
+            let text = text.to_string();
+
+            if on("heading-level-jump") && previous_level > 0 && level > previous_level + 1 {
+                let mut d = diagnostic(
+                    "heading-level-jump",
+                    Severity::Warning,
+                    line_number,
+                    1,
+                    format!("Heading jumps from H{} to H{}", previous_level, level),
+                );
+                d.fix = Some(format!("{} {}", "#".repeat(previous_level + 1), text));
+                diagnostics.push(d);
+            }
+            previous_level = level;
+
+            let key = text.to_lowercase();
+            if on("duplicate-heading") && !key.is_empty() {
+                if let Some(first) = seen_headings.get(&key) {
+                    diagnostics.push(diagnostic(
+                        "duplicate-heading",
+                        Severity::Warning,
+                        line_number,
+                        1,
+                        format!("Duplicate heading \"{}\" (first on line {})", text, first),
+                    ));
+                } else {
+                    seen_headings.insert(key, line_number);
+                }
+            }
+        }
+
+        let scrubbed = blank_code_spans(line);
+
+        if on("broken-reference-link") {
+            for caps in REFERENCE_LINK.captures_iter(&scrubbed) {
+                let whole = caps.get(0).unwrap();
+                let label = if caps[2].trim().is_empty() {
+                    &caps[1]
+                } else {
+                    &caps[2]
+                };
+                if label.starts_with('^') || definitions.contains(&normalize_label(label)) {
+                    continue;
+                }
+                diagnostics.push(diagnostic(
+                    "broken-reference-link",
+                    Severity::Error,
+                    line_number,
+                    char_column(line, whole.start()),
+                    format!("Reference \"{}\" has no definition", label),
+                ));
+            }
+        }
+
+        if on("bare-url") && !REFERENCE_DEFINITION.is_match(line) {
+            let without_links = LINKED_URL.replace_all(&scrubbed, |caps: &regex::Captures| {
+                " ".repeat(caps[0].len())
+            });
+            let bare: Vec<_> = URL.find_iter(&without_links).collect();
+            let mut fixed = line.to_string();
+            for m in bare.iter().rev() {
+                let url = m
+                    .as_str()
+                    .trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+                fixed.replace_range(m.start()..m.start() + url.len(), &format!("<{}>", url));
+            }
+            for m in &bare {
+                let mut d = diagnostic(
+                    "bare-url",
+                    Severity::Info,
+                    line_number,
+                    char_column(line, m.start()),
+                    "Bare URL should be wrapped in <> or a link".to_string(),
+                );
+                d.fix = Some(fixed.clone());
+                diagnostics.push(d);
+            }
+        }
+
+        if on("trailing-whitespace") {
+            let trimmed = line.trim_end();
+            let trailing = &line[trimmed.len()..];
+            // Exactly two spaces is a markdown hard line break.
+            if !trailing.is_empty() && trailing != "  " {
+                let mut d = diagnostic(
+                    "trailing-whitespace",
+                    Severity::Info,
+                    line_number,
+                    char_column(line, trimmed.len()),
+                    "Trailing whitespace".to_string(),
+                );
+                d.fix = Some(trimmed.to_string());
+                diagnostics.push(d);
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
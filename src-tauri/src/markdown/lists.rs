@@ -0,0 +1,276 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::LazyLock;
+
+use super::{fence_marker, frontmatter, heading, protected_lines, LineBuffer};
+
+static ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s*)(?:[-*+]|(\d{1,9})([.)]))(?:\s+|$)(?:\[([ xX])\](?:\s+|$))?").unwrap()
+});
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortListOptions {
+    pub descending: bool,
+    /// Sink completed tasks below open ones, sorting each group by text.
+    pub by_status: bool,
+    pub remove_duplicates: bool,
+}
+
+/// The marker at the start of a list item line.
+pub struct ItemMarker {
+    pub indent: usize,
+    /// `Some(done)` for task items.
+    pub checked: Option<bool>,
+    /// Byte offset where the item's text starts.
+    pub text_start: usize,
+}
+
+pub fn item_marker(line: &str) -> Option<ItemMarker> {
+    let caps = ITEM.captures(line)?;
+    Some(ItemMarker {
+        indent: indent_width(&caps[1]),
+        checked: caps.get(4).map(|c| c.as_str() != " "),
+        text_start: caps.get(0)?.end(),
+    })
+}
+
+/// A top-level item and everything nested under or continuing it.
+struct Item {
+    lines: Vec<String>,
+    text: String,
+    done: bool,
+}
+
+/// Sorts the top-level items of the list containing `line` (zero-based)
+/// with a natural, case-insensitive ordering. Nested lists and continuation
+/// lines move with their item, and ordered lists are renumbered from their
+/// original starting number.
+pub fn sort_list(content: &str, line: usize, options: &SortListOptions) -> Result<String, String> {
+    let mut buffer = LineBuffer::parse(content);
+    let lines = buffer.as_strs();
+    let protected = protected_lines(&lines);
+    let (start, end) = list_range(&lines, &protected, line)
+        .ok_or_else(|| format!("Line {} is not in a list", line + 1))?;
+
+    let top = (start..end)
+        .filter(|&idx| !protected[idx])
+        .filter_map(|idx| ITEM.captures(lines[idx]).map(|caps| indent_width(&caps[1])))
+        .min()
+        .unwrap_or(0);
+    let starts: Vec<usize> = (start..end)
+        .filter(|&idx| {
+            !protected[idx]
+                && ITEM
+                    .captures(lines[idx])
+                    .is_some_and(|caps| indent_width(&caps[1]) == top)
+        })
+        .collect();
+
+    let mut loose = false;
+    let mut items: Vec<Item> = Vec::with_capacity(starts.len());
+    for (n, &item_start) in starts.iter().enumerate() {
+        let item_end = starts.get(n + 1).copied().unwrap_or(end);
+        let mut chunk: Vec<String> = lines[item_start..item_end]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        while chunk.last().is_some_and(|l| l.trim().is_empty()) {
+            chunk.pop();
+            loose = true;
+        }
+        let caps = ITEM.captures(lines[item_start]).unwrap();
+        items.push(Item {
+            text: lines[item_start][caps.get(0).unwrap().end()..]
+                .trim()
+                .to_string(),
+            done: caps.get(4).is_some_and(|c| c.as_str() != " "),
+            lines: chunk,
+        });
+    }
+    // The lines before the first top-level item (an over-indented item, say)
+    // stay where they are.
+    let lead = starts
+        .first()
+        .map(|first| lines[start..*first].to_vec())
+        .unwrap_or_default();
+    let first_number = starts.first().and_then(|&idx| {
+        ITEM.captures(lines[idx])?
+            .get(2)?
+            .as_str()
+            .parse::<u64>()
+            .ok()
+    });
+
+    if options.remove_duplicates {
+        let mut seen: Vec<Vec<String>> = Vec::new();
+        items.retain(|item| {
+            let key = identity(item);
+            if seen.contains(&key) {
+                return false;
+            }
+            seen.push(key);
+            true
+        });
+    }
+
+    items.sort_by(|a, b| {
+        let status = if options.by_status {
+            a.done.cmp(&b.done)
+        } else {
+            Ordering::Equal
+        };
+        let text = natural_cmp(&a.text, &b.text);
+        status.then(if options.descending {
+            text.reverse()
+        } else {
+            text
+        })
+    });
+
+    let mut sorted: Vec<String> = lead.iter().map(|l| l.to_string()).collect();
+    for (n, item) in items.into_iter().enumerate() {
+        if n > 0 && loose {
+            sorted.push(String::new());
+        }
+        let mut item_lines = item.lines;
+        if let Some(number) = first_number {
+            item_lines[0] = renumber(&item_lines[0], number + n as u64);
+        }
+        sorted.extend(item_lines);
+    }
+
+    buffer.lines.splice(start..end, sorted);
+    Ok(buffer.render())
+}
+
+/// The contiguous block of list lines around `line`, ending at its last
+/// non-blank line: items, indented continuation and nested lines, lazy
+/// continuation lines, and blank lines between them.
+fn list_range(lines: &[&str], protected: &[bool], line: usize) -> Option<(usize, usize)> {
+    let is_item = |idx: usize| !protected[idx] && ITEM.is_match(lines[idx]);
+    let is_blank = |idx: usize| !protected[idx] && lines[idx].trim().is_empty();
+    let frontmatter = frontmatter::line_count(lines);
+    // A non-item line is part of the list when it is indented (fenced code
+    // inside an item included) or, unless it starts a new block, lazily
+    // continues the line above.
+    let continues = |idx: usize| {
+        if idx < frontmatter || is_blank(idx) || is_item(idx) {
+            return false;
+        }
+        let line = lines[idx];
+        if line.starts_with([' ', '\t']) {
+            return true;
+        }
+        if protected[idx] {
+            // The rest of a code block whose opening fence was indented.
+            return idx > 0 && protected[idx - 1];
+        }
+        heading(line).is_none() && fence_marker(line).is_none() && idx > 0 && !is_blank(idx - 1)
+    };
+    let in_list = |idx: usize| is_item(idx) || continues(idx);
+
+    if line >= lines.len() || !in_list(line) {
+        return None;
+    }
+    // Walk up to the first item of the list, then forward past any
+    // paragraph lines the walk picked up.
+    let mut start = line;
+    while start > frontmatter {
+        let mut prev = start - 1;
+        if is_blank(prev) {
+            if !is_item(start) && !lines[start].starts_with([' ', '\t']) {
+                break;
+            }
+            while prev > frontmatter && is_blank(prev) {
+                prev -= 1;
+            }
+        }
+        if !in_list(prev) {
+            break;
+        }
+        start = prev;
+    }
+    while start < line && !is_item(start) {
+        start += 1;
+    }
+    if !is_item(start) {
+        return None;
+    }
+
+    let mut end = start + 1;
+    let mut next = end;
+    while next < lines.len() {
+        if is_blank(next) {
+            next += 1;
+            continue;
+        }
+        if in_list(next) {
+            next += 1;
+            end = next;
+        } else {
+            break;
+        }
+    }
+    (line < end).then_some((start, end))
+}
+
+/// Width of leading whitespace, counting a tab as four columns.
+pub fn indent_width(indent: &str) -> usize {
+    indent.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// An item's lines without its marker, so `1. a` and `3. a` are duplicates.
+fn identity(item: &Item) -> Vec<String> {
+    let mut key: Vec<String> = item
+        .lines
+        .iter()
+        .map(|l| l.trim_end().to_string())
+        .collect();
+    key[0] = item.text.clone();
+    key
+}
+
+fn renumber(line: &str, number: u64) -> String {
+    match ITEM.captures(line).and_then(|caps| caps.get(2)) {
+        Some(digits) => format!(
+            "{}{}{}",
+            &line[..digits.start()],
+            number,
+            &line[digits.end()..]
+        ),
+        None => line.to_string(),
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    // Digits compare by magnitude: length without leading zeros, then value.
+    Number(usize, String),
+    Text(String),
+}
+
+fn natural_key(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let digit = c.is_ascii_digit();
+        let mut run = String::new();
+        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() == digit) {
+            run.push(c);
+            chars.next();
+        }
+        if digit {
+            let trimmed = run.trim_start_matches('0').to_string();
+            chunks.push(Chunk::Number(trimmed.len(), trimmed));
+        } else {
+            chunks.push(Chunk::Text(run.to_lowercase()));
+        }
+    }
+    chunks
+}
+
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_key(a).cmp(&natural_key(b)).then_with(|| a.cmp(b))
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use super::{blank_code_spans, protected_lines};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MathKind {
+    /// `$x^2$`
+    Inline,
+    /// `$$x^2$$`, on one line or several.
+    Display,
+}
+
+/// A math region, at 1-based lines and 1-based character columns counting
+/// its delimiters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MathRegion {
+    pub kind: MathKind,
+    /// The TeX between the delimiters, as written.
+    pub content: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    /// Column just past the closing delimiter.
+    pub end_column: usize,
+    /// Byte range of the whole region, delimiters included.
+    #[serde(skip)]
+    pub start: usize,
+    #[serde(skip)]
+    pub end: usize,
+}
+
+/// A delimiter with nothing to close it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MathProblem {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The math in a note and the delimiters left open.
+///
+/// `$...$` is inline math when the opening `$` isn't followed by a space
+/// and the closing one, on the same line, isn't preceded by a space or
+/// followed by a digit, so prices like `$5 and $10` stay text. `$$...$$`
+/// is display math and may span lines, but not a blank one. Frontmatter,
+/// code blocks, code spans and `\$` are skipped.
+pub fn scan(content: &str) -> (Vec<MathRegion>, Vec<MathProblem>) {
+    let text = masked(content);
+    let bytes = text.as_bytes();
+    let positions = Positions::new(content);
+    let mut regions = Vec::new();
+    let mut problems = Vec::new();
+    let mut i = 0;
+    let region = |kind, start: usize, end: usize, delimiter: usize| {
+        let (line, column) = positions.at(start);
+        let (end_line, end_column) = positions.at(end);
+        MathRegion {
+            kind,
+            content: content[start + delimiter..end - delimiter].to_string(),
+            line,
+            column,
+            end_line,
+            end_column,
+            start,
+            end,
+        }
+    };
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' if bytes.get(i + 1) == Some(&b'$') => match display_end(bytes, i + 2) {
+                Some(close) => {
+                    regions.push(region(MathKind::Display, i, close + 2, 2));
+                    i = close + 2;
+                }
+                None => {
+                    let (line, column) = positions.at(i);
+                    problems.push(MathProblem {
+                        message: "Display math is never closed with `$$`".to_string(),
+                        line,
+                        column,
+                    });
+                    i += 2;
+                }
+            },
+            b'$' => {
+                let next = bytes.get(i + 1).copied();
+                if next.is_none_or(|b| b.is_ascii_whitespace()) {
+                    i += 1;
+                    continue;
+                }
+                match inline_end(bytes, i + 1) {
+                    Some(close) => {
+                        regions.push(region(MathKind::Inline, i, close + 1, 1));
+                        i = close + 1;
+                    }
+                    None => {
+                        // `$5` is money, not math.
+                        if !next.is_some_and(|b| b.is_ascii_digit()) {
+                            let (line, column) = positions.at(i);
+                            problems.push(MathProblem {
+                                message: "`$` has no closing `$` on its line".to_string(),
+                                line,
+                                column,
+                            });
+                        }
+                        i += 1;
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    (regions, problems)
+}
+
+/// `content` with frontmatter, code blocks and code spans blanked out, at
+/// the same byte offsets.
+fn masked(content: &str) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let trimmed: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim_end_matches(['\n', '\r']))
+        .collect();
+    let protected = protected_lines(&trimmed);
+    let mut out = String::with_capacity(content.len());
+    for ((line, text), protected) in lines.iter().zip(&trimmed).zip(protected) {
+        if protected {
+            out.extend(text.chars().map(|c| if c.is_ascii() { ' ' } else { c }));
+        } else {
+            out.push_str(&blank_code_spans(text));
+        }
+        out.push_str(&line[text.len()..]);
+    }
+    out
+}
+
+/// Where the closing `$$` of display math opened just before `from` is.
+fn display_end(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' if bytes.get(i + 1) == Some(&b'$') => return Some(i),
+            b'\n' => {
+                let rest = &bytes[i + 1..];
+                let next_line =
+                    &rest[..rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len())];
+                if next_line.iter().all(u8::is_ascii_whitespace) {
+                    return None;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Where the `$` closing inline math opened just before `from` is.
+fn inline_end(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() && bytes[i] != b'\n' {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => {
+                let after = bytes.get(i + 1).copied();
+                if !bytes[i - 1].is_ascii_whitespace()
+                    && !after.is_some_and(|b| b.is_ascii_digit() || b == b'$')
+                {
+                    return Some(i);
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Turns byte offsets into 1-based lines and character columns.
+struct Positions<'a> {
+    content: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Positions<'a> {
+    fn new(content: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            content,
+            line_starts,
+        }
+    }
+
+    fn at(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let start = self.line_starts[line - 1];
+        (line, self.content[start..offset].chars().count() + 1)
+    }
+}
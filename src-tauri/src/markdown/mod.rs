@@ -0,0 +1,261 @@
+pub mod bibtex;
+pub mod citations;
+pub mod footnotes;
+pub mod format;
+pub mod frontmatter;
+pub mod headings;
+pub mod html;
+pub mod inline_fields;
+pub mod kanban;
+pub mod links;
+pub mod lint;
+pub mod lists;
+pub mod math;
+pub mod normalize;
+pub mod reading;
+pub mod reminders;
+pub mod render;
+pub mod spellcheck;
+pub mod tables;
+pub mod tags;
+pub mod templates;
+
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}(#{1,6})\s+(.*?)\s*#*\s*$").unwrap());
+
+/// A document as editable lines, remembering its line ending style and
+/// whether it ended with a newline so it can be written back unchanged
+/// apart from the edited lines.
+pub struct LineBuffer {
+    pub lines: Vec<String>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+impl LineBuffer {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(str::to_string).collect(),
+            newline: if content.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+            trailing_newline: content.ends_with('\n'),
+        }
+    }
+
+    pub fn as_strs(&self) -> Vec<&str> {
+        self.lines.iter().map(String::as_str).collect()
+    }
+
+    pub fn set_trailing_newline(&mut self, trailing: bool) {
+        self.trailing_newline = trailing;
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = self.lines.join(self.newline);
+        if self.trailing_newline && !self.lines.is_empty() {
+            out.push_str(self.newline);
+        }
+        out
+    }
+}
+
+/// Parses an ATX heading into its level and text, without closing `#`s.
+pub fn heading(line: &str) -> Option<(usize, &str)> {
+    let caps = HEADING.captures(line)?;
+    Some((caps.get(1)?.len(), caps.get(2)?.as_str()))
+}
+
+/// GitHub-style heading anchor: lowercased, punctuation dropped, spaces as
+/// hyphens.
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Marks lines that structural edits must leave alone: frontmatter and
+/// fenced code blocks.
+pub fn protected_lines(lines: &[&str]) -> Vec<bool> {
+    let mut mask = code_block_lines(lines);
+    let frontmatter = frontmatter::line_count(lines);
+    for flag in mask.iter_mut().take(frontmatter) {
+        *flag = true;
+    }
+    mask
+}
+
+/// Marks which lines belong to fenced code blocks (fence lines included).
+///
+/// A fence is three or more backticks or tildes indented by at most three
+/// spaces; it is closed by a fence of the same character that is at least as
+/// long. An unclosed fence runs to the end of the document.
+pub fn code_block_lines(lines: &[&str]) -> Vec<bool> {
+    let mut mask = Vec::with_capacity(lines.len());
+    let mut open: Option<(char, usize)> = None;
+
+    for line in lines {
+        let fence = fence_marker(line);
+        match (open, fence) {
+            (None, Some((ch, len, _))) => {
+                open = Some((ch, len));
+                mask.push(true);
+            }
+            (Some((ch, len)), Some((fch, flen, rest)))
+                if fch == ch && flen >= len && rest.is_empty() =>
+            {
+                open = None;
+                mask.push(true);
+            }
+            (Some(_), _) => mask.push(true),
+            (None, None) => mask.push(false),
+        }
+    }
+
+    mask
+}
+
+/// Given the index of an opening fence line, returns the index just past its
+/// closing fence (or the end of the document when the fence is unclosed).
+pub fn code_block_end(lines: &[&str], start: usize) -> usize {
+    let (ch, len) = match fence_marker(lines[start]) {
+        Some((ch, len, _)) => (ch, len),
+        None => return start + 1,
+    };
+    for (offset, line) in lines[start + 1..].iter().enumerate() {
+        if let Some((close_ch, close_len, rest)) = fence_marker(line) {
+            if close_ch == ch && close_len >= len && rest.is_empty() {
+                return start + offset + 2;
+            }
+        }
+    }
+    lines.len()
+}
+
+/// Returns the fence character, run length and trailing info string when the
+/// line opens or closes a fenced code block.
+pub fn fence_marker(line: &str) -> Option<(char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let trimmed = &line[indent..];
+    let ch = trimmed.chars().next()?;
+    if ch != '`' && ch != '~' {
+        return None;
+    }
+    let len = trimmed.chars().take_while(|c| *c == ch).count();
+    if len < 3 {
+        return None;
+    }
+    let rest = trimmed[len..].trim();
+    if ch == '`' && rest.contains('`') {
+        return None;
+    }
+    Some((ch, len, rest))
+}
+
+/// Replaces inline code spans with spaces so byte offsets stay valid while
+/// their contents are ignored by pattern matching.
+pub fn blank_code_spans(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut out = line.as_bytes().to_vec();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+        let search_from = i + run;
+        let mut close = None;
+        let mut j = search_from;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let close_run = bytes[j..].iter().take_while(|b| **b == b'`').count();
+                if close_run == run {
+                    close = Some(j);
+                    break;
+                }
+                j += close_run;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                for b in &mut out[i..end + run] {
+                    *b = b' ';
+                }
+                i = end + run;
+            }
+            None => i = search_from,
+        }
+    }
+
+    // Only ASCII bytes were replaced, and whole code spans at that, so the
+    // result is still valid UTF-8.
+    String::from_utf8(out).unwrap_or_else(|_| line.to_string())
+}
+
+/// Derives a note title: frontmatter `title`, then the first H1, then the
+/// filename without its extension.
+pub fn note_title(
+    path: &Path,
+    frontmatter: &serde_json::Map<String, serde_json::Value>,
+    body: &str,
+) -> String {
+    if let Some(title) = frontmatter.get("title").and_then(|t| t.as_str()) {
+        if !title.trim().is_empty() {
+            return title.trim().to_string();
+        }
+    }
+
+    let lines: Vec<&str> = body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    for (line, code) in lines.iter().zip(in_code) {
+        if code {
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("# ") {
+            let heading = heading.trim();
+            if !heading.is_empty() {
+                return heading.to_string();
+            }
+        }
+    }
+
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Counts words in the note body, leaving out frontmatter and fenced code.
+pub fn word_count(content: &str) -> usize {
+    let body = frontmatter::split(content).body;
+    let lines: Vec<&str> = body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    lines
+        .iter()
+        .zip(in_code)
+        .filter(|(_, code)| !code)
+        .map(|(line, _)| {
+            line.split_whitespace()
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .count()
+        })
+        .sum()
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use super::{code_block_lines, LineBuffer};
+
+/// Whitespace rules applied to note content before it is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriteNormalization {
+    /// End the file with exactly one newline.
+    pub final_newline: bool,
+    /// Strip trailing spaces and tabs, keeping a two-space hard line break.
+    pub trim_trailing_whitespace: bool,
+    /// Expand leading tabs to this many spaces. Off by default.
+    pub tabs_to_spaces: Option<usize>,
+}
+
+impl Default for WriteNormalization {
+    fn default() -> Self {
+        Self {
+            final_newline: true,
+            trim_trailing_whitespace: true,
+            tabs_to_spaces: None,
+        }
+    }
+}
+
+/// Applies the normalization rules. Lines inside fenced code blocks are left
+/// exactly as they are.
+pub fn normalize(content: &str, rules: &WriteNormalization) -> String {
+    if content.is_empty() {
+        return String::new();
+    }
+
+    let mut buffer = LineBuffer::parse(content);
+    let in_code = code_block_lines(&buffer.as_strs());
+
+    for (line, code) in buffer.lines.iter_mut().zip(&in_code) {
+        if *code {
+            continue;
+        }
+        if let Some(width) = rules.tabs_to_spaces {
+            let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+            let indent: String = line[..indent_len]
+                .chars()
+                .map(|c| {
+                    if c == '\t' {
+                        " ".repeat(width)
+                    } else {
+                        c.to_string()
+                    }
+                })
+                .collect();
+            line.replace_range(..indent_len, &indent);
+        }
+        if rules.trim_trailing_whitespace {
+            let trimmed_len = line.trim_end_matches([' ', '\t']).len();
+            let hard_break = trimmed_len > 0 && &line[trimmed_len..] == "  ";
+            if !hard_break {
+                line.truncate(trimmed_len);
+            }
+        }
+    }
+
+    if rules.final_newline {
+        while buffer.lines.len() > 1
+            && buffer.lines.last().is_some_and(|l| l.trim().is_empty())
+            && !in_code
+                .get(buffer.lines.len() - 1)
+                .copied()
+                .unwrap_or(false)
+        {
+            buffer.lines.pop();
+        }
+        buffer.set_trailing_newline(true);
+    }
+
+    buffer.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_two_space_hard_break() {
+        let rules = WriteNormalization::default();
+        let out = normalize("first line  \nsecond line   \nthird\t\n", &rules);
+        assert_eq!(out, "first line  \nsecond line\nthird\n");
+    }
+
+    #[test]
+    fn preserves_fenced_code_exactly() {
+        let rules = WriteNormalization {
+            tabs_to_spaces: Some(4),
+            ..WriteNormalization::default()
+        };
+        let input = "text   \n```py\n\tdef f():   \n\t\treturn 1  \n```\n\tindented\n";
+        let out = normalize(input, &rules);
+        assert_eq!(
+            out,
+            "text\n```py\n\tdef f():   \n\t\treturn 1  \n```\n    indented\n"
+        );
+    }
+
+    #[test]
+    fn ends_with_exactly_one_newline() {
+        let rules = WriteNormalization::default();
+        assert_eq!(normalize("a", &rules), "a\n");
+        assert_eq!(normalize("a\n\n\n", &rules), "a\n");
+        assert_eq!(normalize("a\r\nb  \r\n\r\n", &rules), "a\r\nb  \r\n");
+        assert_eq!(normalize("", &rules), "");
+    }
+}
@@ -0,0 +1,109 @@
+use super::headings::{anchors, AnchorKind};
+use super::{code_block_lines, fence_marker, frontmatter};
+
+/// Words inside fenced code blocks, leaving out the fences.
+pub fn code_word_count(content: &str) -> usize {
+    let body = frontmatter::split(content).body;
+    let lines: Vec<&str> = body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    lines
+        .iter()
+        .zip(in_code)
+        .filter(|(line, code)| *code && fence_marker(line).is_none())
+        .map(|(line, _)| line.split_whitespace().count())
+        .sum()
+}
+
+/// Whole minutes to read `words` of prose and `code_words` of code at the
+/// given speeds, rounded up; zero only for a note with no words at all.
+pub fn reading_minutes(
+    words: usize,
+    code_words: usize,
+    words_per_minute: u32,
+    code_words_per_minute: u32,
+) -> u64 {
+    let minutes = words as f64 / f64::from(words_per_minute.max(1))
+        + code_words as f64 / f64::from(code_words_per_minute.max(1));
+    minutes.ceil() as u64
+}
+
+/// The heading above the 1-based `line`, by slug, and how many paragraphs
+/// below it the line is; no heading when the line comes before the first.
+/// A fenced code block counts as one paragraph, blank lines and all.
+pub fn anchor_at(content: &str, line: usize) -> (Option<String>, usize) {
+    let sections = sections(content);
+    let Some(section) = sections
+        .iter()
+        .rev()
+        .find(|section| section.line <= line.max(1))
+    else {
+        return (None, 0);
+    };
+    let paragraph = section
+        .paragraphs
+        .iter()
+        .filter(|start| **start <= line)
+        .count();
+    (section.slug.clone(), paragraph)
+}
+
+/// The 1-based line an anchor from `anchor_at` points at now: the start of
+/// its paragraph, or of the section's last when there are fewer than
+/// there were. `None` when the note no longer has the heading.
+pub fn anchor_line(content: &str, heading: Option<&str>, paragraph: usize) -> Option<usize> {
+    let sections = sections(content);
+    let section = sections
+        .iter()
+        .find(|section| section.slug.as_deref() == heading)?;
+    Some(match paragraph {
+        0 => section.line,
+        n => section
+            .paragraphs
+            .get(n - 1)
+            .or(section.paragraphs.last())
+            .copied()
+            .unwrap_or(section.line),
+    })
+}
+
+/// The part of a note under one heading, or before the first.
+struct Section {
+    slug: Option<String>,
+    /// 1-based: the heading's line, or the note's first line.
+    line: usize,
+    /// The 1-based lines paragraphs start on.
+    paragraphs: Vec<usize>,
+}
+
+fn sections(content: &str) -> Vec<Section> {
+    let lines: Vec<&str> = content.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let body_start = frontmatter::line_count(&lines);
+    let is_text = |idx: usize| in_code[idx] || !lines[idx].trim().is_empty();
+
+    let mut starts = vec![(None, 1, body_start)];
+    starts.extend(
+        anchors(content)
+            .into_iter()
+            .filter(|anchor| anchor.kind == AnchorKind::Heading)
+            .map(|anchor| (Some(anchor.slug), anchor.line, anchor.line)),
+    );
+    let ends: Vec<usize> = starts
+        .iter()
+        .skip(1)
+        .map(|(_, line, _)| line - 1)
+        .chain([lines.len()])
+        .collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|((slug, line, first), end)| Section {
+            slug,
+            line,
+            paragraphs: (first..end)
+                .filter(|&idx| is_text(idx) && (idx == first || !is_text(idx - 1)))
+                .map(|idx| idx + 1)
+                .collect(),
+        })
+        .collect()
+}
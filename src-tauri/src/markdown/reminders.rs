@@ -0,0 +1,96 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, code_block_lines, frontmatter, lists};
+
+// `(remind:: 2024-07-01 09:00)` or `[reminder:: 2024-07-01]`.
+static REMINDER_FIELD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)[\[(]\s*remind(?:er)?::[ \t]*([^\])]*)[\])]").unwrap());
+
+// `⏰ 2024-07-01 09:00`, as the Tasks and Reminder plugins write it.
+static ALARM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"⏰\u{FE0F}?[ \t]*(\d{4}-\d{2}-\d{2}(?:[ T]\d{1,2}:\d{2}(?::\d{2})?)?)").unwrap()
+});
+
+/// When a reminder given only a date goes off.
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+
+/// A reminder on a task line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskReminder {
+    /// 1-based, in the whole file.
+    pub line_number: usize,
+    /// The task's text without its reminder.
+    pub text: String,
+    /// Local time.
+    pub due: NaiveDateTime,
+    pub done: bool,
+}
+
+/// The reminders set on tasks in a note, in order, skipping frontmatter,
+/// code blocks and code spans. A task can have more than one.
+pub fn task_reminders(content: &str) -> Vec<TaskReminder> {
+    let split = frontmatter::split(content);
+    let lines: Vec<&str> = split.body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut reminders = Vec::new();
+
+    for (idx, (line, code)) in lines.iter().zip(in_code).enumerate() {
+        if code || !(line.contains("::") || line.contains('⏰')) {
+            continue;
+        }
+        let Some((marker, done)) =
+            lists::item_marker(line).and_then(|m| Some((m.text_start, m.checked?)))
+        else {
+            continue;
+        };
+        let scrubbed = blank_code_spans(line);
+        let mut found: Vec<(usize, usize, NaiveDateTime)> = REMINDER_FIELD
+            .captures_iter(&scrubbed)
+            .chain(ALARM.captures_iter(&scrubbed))
+            .filter_map(|caps| {
+                let whole = caps.get(0)?;
+                Some((whole.start(), whole.end(), parse_due(&caps[1])?))
+            })
+            .collect();
+        if found.is_empty() {
+            continue;
+        }
+        found.sort_by_key(|(start, _, _)| *start);
+        let mut text = String::new();
+        let mut at = marker.min(line.len());
+        for (start, end, _) in &found {
+            text.push_str(&line[at..*start]);
+            at = *end;
+        }
+        text.push_str(&line[at..]);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        for (_, _, due) in found {
+            reminders.push(TaskReminder {
+                line_number: split.body_start_line + idx + 1,
+                text: text.clone(),
+                due,
+                done,
+            });
+        }
+    }
+    reminders
+}
+
+/// `2024-07-01 09:00`, `2024-07-01T09:00:30` or a bare `2024-07-01`.
+pub fn parse_due(raw: &str) -> Option<NaiveDateTime> {
+    let raw = raw.trim();
+    for format in [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ] {
+        if let Ok(due) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(due);
+        }
+    }
+    let day = NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()?;
+    Some(day.and_time(NaiveTime::from_hms_opt(DEFAULT_TIME.0, DEFAULT_TIME.1, 0)?))
+}
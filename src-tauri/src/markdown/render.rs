@@ -0,0 +1,307 @@
+use pulldown_cmark::{html, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{
+    css_for_theme_with_class_style, highlighted_html_for_string, ClassStyle, ClassedHTMLGenerator,
+};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use super::headings::{outline, section_at};
+use super::{math, slug};
+
+static BLOCK_ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+\^[A-Za-z0-9-]+\s*$").unwrap());
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+// Stand-ins for math while the Markdown is parsed, from the private use
+// area so they can't clash with a note's text.
+const MATH_OPEN: char = '\u{E000}';
+const MATH_CLOSE: char = '\u{E001}';
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Highlighting {
+    /// `hl-` prefixed classes, styled by `syntax_css`.
+    #[default]
+    Classes,
+    /// Colours from the theme as inline styles.
+    Inline,
+}
+
+/// Resolves the parts of a note that depend on the vault around it.
+pub trait Resolver {
+    /// `href` for a `[[wikilink]]` target (anchor included), or `None` when
+    /// it points at nothing.
+    fn link(&mut self, target: &str) -> Option<String>;
+    /// HTML for a `![[embed]]` of a note, or `None` when the target is an
+    /// attachment.
+    fn embed(&mut self, target: &str) -> Option<String>;
+    /// `src` for an image; `wiki` for `![[image.png]]` targets.
+    fn image(&mut self, target: &str, wiki: bool) -> String;
+}
+
+/// Renders a note body (without frontmatter) to HTML. Math is left as
+/// written, delimiters and all, for the frontend to typeset; it is swapped
+/// for placeholders while the Markdown is parsed so `_` and `*` in it
+/// aren't read as emphasis.
+pub fn to_html(
+    markdown: &str,
+    highlighting: Highlighting,
+    theme: &str,
+    resolver: &mut dyn Resolver,
+) -> Result<String, String> {
+    let theme = find_theme(theme)?;
+    let (regions, _) = math::scan(markdown);
+    let mut protected = String::with_capacity(markdown.len());
+    let mut at = 0;
+    for (idx, region) in regions.iter().enumerate() {
+        protected.push_str(&markdown[at..region.start]);
+        protected.push_str(&format!("{}{}{}", MATH_OPEN, idx, MATH_CLOSE));
+        at = region.end;
+    }
+    protected.push_str(&markdown[at..]);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM
+        | Options::ENABLE_WIKILINKS;
+    let mut parser = Parser::new_ext(&protected, options);
+    let mut events = Vec::new();
+
+    while let Some(event) = parser.next() {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                let mut code = String::new();
+                for event in parser.by_ref() {
+                    match event {
+                        Event::Text(text) => code.push_str(&text),
+                        Event::End(TagEnd::CodeBlock) => break,
+                        _ => {}
+                    }
+                }
+                events.push(Event::Html(
+                    highlight(&code, &language, highlighting, theme)?.into(),
+                ));
+            }
+            Event::Start(Tag::Link {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                ..
+            }) => {
+                let open = match resolver.link(&dest_url) {
+                    Some(href) => format!(
+                        "<a href=\"{}\" class=\"internal-link\" data-target=\"{}\">",
+                        escape(&href),
+                        escape(&dest_url)
+                    ),
+                    None => format!(
+                        "<a class=\"internal-link is-unresolved\" data-target=\"{}\">",
+                        escape(&dest_url)
+                    ),
+                };
+                events.push(Event::InlineHtml(open.into()));
+            }
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let wiki = matches!(link_type, LinkType::WikiLink { .. });
+                if let Some(embedded) = wiki.then(|| resolver.embed(&dest_url)).flatten() {
+                    // The alt text isn't shown for an embedded note.
+                    let mut depth = 1;
+                    for event in parser.by_ref() {
+                        match event {
+                            Event::Start(Tag::Image { .. }) => depth += 1,
+                            Event::End(TagEnd::Image) => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    events.push(Event::Html(embedded.into()));
+                    continue;
+                }
+                events.push(Event::Start(Tag::Image {
+                    link_type,
+                    dest_url: resolver.image(&dest_url, wiki).into(),
+                    title,
+                    id,
+                }));
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut out = String::new();
+    html::push_html(&mut out, tidy(events).into_iter());
+    if regions.is_empty() {
+        return Ok(out);
+    }
+    let mut pieces = out.split(MATH_OPEN);
+    let mut restored = pieces.next().unwrap_or_default().to_string();
+    for piece in pieces {
+        let region = piece
+            .split_once(MATH_CLOSE)
+            .and_then(|(idx, rest)| Some((regions.get(idx.parse::<usize>().ok()?)?, rest)));
+        match region {
+            Some((region, rest)) => {
+                restored.push_str(&escape(&markdown[region.start..region.end]));
+                restored.push_str(rest);
+            }
+            None => restored.push_str(piece),
+        }
+    }
+    Ok(restored)
+}
+
+/// Unwraps paragraphs holding nothing but an embed, which is block HTML,
+/// and hides `^block` ids at the end of paragraphs and list items.
+fn tidy(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut out: Vec<Event> = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::Paragraph) => {
+                let mut inner = Vec::new();
+                for event in events.by_ref() {
+                    if event == Event::End(TagEnd::Paragraph) {
+                        break;
+                    }
+                    inner.push(event);
+                }
+                if let Some(Event::Text(text)) = inner.last_mut() {
+                    *text = BLOCK_ID.replace(text, "").into_owned().into();
+                }
+                if let [Event::Html(_)] = inner.as_slice() {
+                    out.extend(inner);
+                } else {
+                    out.push(Event::Start(Tag::Paragraph));
+                    out.extend(inner);
+                    out.push(Event::End(TagEnd::Paragraph));
+                }
+            }
+            Event::Text(text) if matches!(events.peek(), Some(Event::End(TagEnd::Item))) => {
+                out.push(Event::Text(BLOCK_ID.replace(&text, "").into_owned().into()));
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// The stylesheet for `Highlighting::Classes` output in `theme`.
+pub fn syntax_css(theme: &str) -> Result<String, String> {
+    css_for_theme_with_class_style(find_theme(theme)?, CLASS_STYLE)
+        .map_err(|e| format!("Failed to build syntax CSS: {}", e))
+}
+
+/// The part of a note an embed anchor points at: the section under a
+/// matching heading, or the block ending in `^id`. `None` if nothing matches.
+pub fn embedded_part(content: &str, anchor: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let range = match anchor.strip_prefix('^') {
+        Some(id) => {
+            let marker = format!("^{}", id);
+            let line = lines.iter().position(|l| l.trim_end().ends_with(&marker))?;
+            let start = lines[..line]
+                .iter()
+                .rposition(|l| l.trim().is_empty())
+                .map_or(0, |i| i + 1);
+            let end = lines[line..]
+                .iter()
+                .position(|l| l.trim().is_empty())
+                .map_or(lines.len(), |i| line + i);
+            start..end
+        }
+        None => {
+            let headings = outline(&lines);
+            let wanted = slug(anchor);
+            let heading = headings
+                .iter()
+                .find(|h| h.text.eq_ignore_ascii_case(anchor.trim()) || slug(&h.text) == wanted)?;
+            section_at(&headings, heading.line, lines.len()).ok()?.1
+        }
+    };
+    Some(lines[range].join("\n"))
+}
+
+fn find_theme(name: &str) -> Result<&'static Theme, String> {
+    THEMES
+        .themes
+        .get(name)
+        .ok_or_else(|| format!("Unknown highlighting theme: {}", name))
+}
+
+fn highlight(
+    code: &str,
+    language: &str,
+    highlighting: Highlighting,
+    theme: &Theme,
+) -> Result<String, String> {
+    let class = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape(language))
+    };
+    let syntax = Some(language)
+        .filter(|l| !l.is_empty())
+        .and_then(|l| SYNTAXES.find_syntax_by_token(l));
+    let Some(syntax) = syntax else {
+        return Ok(format!(
+            "<pre><code{}>{}</code></pre>\n",
+            class,
+            escape(code)
+        ));
+    };
+
+    match highlighting {
+        Highlighting::Classes => {
+            let mut generator =
+                ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+            for line in LinesWithEndings::from(code) {
+                generator
+                    .parse_html_for_line_which_includes_newline(line)
+                    .map_err(|e| format!("Failed to highlight code: {}", e))?;
+            }
+            Ok(format!(
+                "<pre class=\"hl-code\"><code{}>{}</code></pre>\n",
+                class,
+                generator.finalize()
+            ))
+        }
+        Highlighting::Inline => highlighted_html_for_string(code, &SYNTAXES, syntax, theme)
+            .map_err(|e| format!("Failed to highlight code: {}", e)),
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,59 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, links, protected_lines};
+
+// Letters, with apostrophes allowed inside (`don't`, `l’homme`). Digits and
+// underscores are matched too so identifiers like `v2_final` can be skipped
+// whole rather than checked in pieces.
+static WORD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\p{L}\p{M}\p{N}_]+(?:['’][\p{L}\p{M}]+)*").unwrap());
+
+// Text that isn't prose: HTML tags, `#tags`, email addresses and `^block` ids.
+static NOT_PROSE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<[^<>\n]+>|(?:^|\s)#[^\s#]+|[\w.+\-]+@[\w\-]+(?:\.[\w\-]+)+|\s\^[\w\-]+\s*$")
+        .unwrap()
+});
+
+/// A word to spell check, at a 1-based line and 1-based character columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+    /// Column just past the word.
+    pub end_column: usize,
+}
+
+/// The prose words in `content`. Frontmatter, code blocks and spans, links
+/// and URLs, HTML tags, `#tags` and email addresses are left out, as are
+/// single letters and words with digits or underscores.
+pub fn words(content: &str) -> Vec<Word> {
+    let lines: Vec<&str> = content.lines().collect();
+    let protected = protected_lines(&lines);
+    let mut words = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if protected[idx] {
+            continue;
+        }
+        let mut text = links::blank_links(&blank_code_spans(line));
+        let ranges: Vec<_> = NOT_PROSE.find_iter(&text).map(|m| m.range()).collect();
+        for range in ranges {
+            text.replace_range(range.clone(), &" ".repeat(range.len()));
+        }
+        for m in WORD.find_iter(&text) {
+            let word = m.as_str();
+            if word.chars().nth(1).is_none() || word.chars().any(|c| c.is_numeric() || c == '_') {
+                continue;
+            }
+            let column = line[..m.start()].chars().count() + 1;
+            words.push(Word {
+                text: word.to_string(),
+                line: idx + 1,
+                column,
+                end_column: column + word.chars().count(),
+            });
+        }
+    }
+    words
+}
@@ -0,0 +1,222 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+static DELIMITER_CELL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^:?-+:?$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// A GFM pipe table. Cell text is stored unescaped: an escaped `\|` in the
+/// source becomes `|` here and is escaped again when rendered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub alignments: Vec<Alignment>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Splits a table row into unescaped, trimmed cells. Leading and trailing
+/// pipes are optional.
+pub fn split_row(line: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.trim().chars().peekable();
+    let mut leading = true;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                current.push('|');
+                chars.next();
+            }
+            '|' => {
+                if !(leading && current.trim().is_empty()) {
+                    cells.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            _ => current.push(ch),
+        }
+        leading = false;
+    }
+
+    if !current.trim().is_empty() {
+        cells.push(current.trim().to_string());
+    }
+
+    cells
+}
+
+pub fn has_unescaped_pipe(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .any(|(i, b)| *b == b'|' && (i == 0 || bytes[i - 1] != b'\\'))
+}
+
+pub fn is_delimiter_row(line: &str) -> bool {
+    if !line.contains('-') {
+        return false;
+    }
+    let cells = split_row(line);
+    !cells.is_empty() && cells.iter().all(|c| DELIMITER_CELL.is_match(c))
+}
+
+fn parse_alignment(cell: &str) -> Alignment {
+    match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    }
+}
+
+/// Finds pipe tables outside code blocks, returning half-open line ranges
+/// (header row inclusive, first non-table line exclusive).
+pub fn find_tables(lines: &[&str], in_code: &[bool]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < lines.len() {
+        let is_header = !in_code[i]
+            && !in_code[i + 1]
+            && has_unescaped_pipe(lines[i])
+            && is_delimiter_row(lines[i + 1]);
+        if !is_header {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i + 2;
+        while end < lines.len()
+            && !in_code[end]
+            && !lines[end].trim().is_empty()
+            && has_unescaped_pipe(lines[end])
+        {
+            end += 1;
+        }
+        ranges.push((start, end));
+        i = end;
+    }
+
+    ranges
+}
+
+/// Returns the table range containing `line_idx`, if any.
+pub fn table_at(lines: &[&str], excluded: &[bool], line_idx: usize) -> Option<(usize, usize)> {
+    find_tables(lines, excluded)
+        .into_iter()
+        .find(|(start, end)| line_idx >= *start && line_idx < *end)
+}
+
+/// Parses the lines of a table found by `find_tables`. Rows are padded so
+/// every row has as many cells as the widest row.
+pub fn parse(lines: &[&str]) -> Table {
+    let mut headers = split_row(lines[0]);
+    let mut alignments: Vec<Alignment> = split_row(lines[1])
+        .iter()
+        .map(|c| parse_alignment(c))
+        .collect();
+    let mut rows: Vec<Vec<String>> = lines[2..].iter().map(|l| split_row(l)).collect();
+
+    let columns = rows
+        .iter()
+        .map(|r| r.len())
+        .chain([headers.len(), alignments.len()])
+        .max()
+        .unwrap_or(0);
+
+    headers.resize(columns, String::new());
+    alignments.resize(columns, Alignment::None);
+    for row in &mut rows {
+        row.resize(columns, String::new());
+    }
+
+    Table {
+        headers,
+        alignments,
+        rows,
+    }
+}
+
+fn escape_cell(cell: &str) -> String {
+    cell.replace('\n', " ").replace('|', "\\|")
+}
+
+fn pad(text: &str, width: usize, alignment: Alignment) -> String {
+    let len = text.chars().count();
+    let gap = width.saturating_sub(len);
+    match alignment {
+        Alignment::Right => format!("{}{}", " ".repeat(gap), text),
+        Alignment::Center => {
+            let left = gap / 2;
+            format!("{}{}{}", " ".repeat(left), text, " ".repeat(gap - left))
+        }
+        _ => format!("{}{}", text, " ".repeat(gap)),
+    }
+}
+
+/// Renders a table with every column padded to its widest cell.
+pub fn render(table: &Table) -> Vec<String> {
+    let columns = table.headers.len();
+    let header: Vec<String> = table.headers.iter().map(|c| escape_cell(c)).collect();
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            (0..columns)
+                .map(|i| escape_cell(row.get(i).map(String::as_str).unwrap_or("")))
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain([header[i].chars().count(), 3])
+                .max()
+                .unwrap_or(3)
+        })
+        .collect();
+
+    let alignment = |i: usize| table.alignments.get(i).copied().unwrap_or_default();
+    let render_row = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| pad(c, widths[i], alignment(i)))
+            .collect();
+        format!("| {} |", padded.join(" | "))
+    };
+
+    let delimiter: Vec<String> = (0..columns)
+        .map(|i| {
+            let width = widths[i];
+            match alignment(i) {
+                Alignment::None => "-".repeat(width),
+                Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+            }
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(rows.len() + 2);
+    out.push(render_row(&header));
+    out.push(format!("| {} |", delimiter.join(" | ")));
+    for row in &rows {
+        out.push(render_row(row));
+    }
+    out
+}
@@ -0,0 +1,68 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use super::{blank_code_spans, code_block_lines, frontmatter};
+
+// A tag starts after whitespace or at the line start, must contain a
+// non-digit, and may be nested with `/` (`#project/alpha`).
+static INLINE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[\s(,;])#([\p{L}\p{N}_\-/]*[\p{L}_\-][\p{L}\p{N}_\-/]*)").unwrap()
+});
+
+/// Finds inline tags, ignoring frontmatter, code blocks, code spans and
+/// headings' leading `#` markers.
+pub fn inline_tags(content: &str) -> Vec<String> {
+    inline_tag_lines(content)
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .collect()
+}
+
+/// Like `inline_tags`, with the 1-based line number of each tag within the
+/// whole file.
+pub fn inline_tag_lines(content: &str) -> Vec<(String, usize)> {
+    let split = frontmatter::split(content);
+    let lines: Vec<&str> = split.body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut tags = Vec::new();
+
+    for (idx, (line, code)) in lines.iter().zip(in_code).enumerate() {
+        if code || !line.contains('#') {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        for caps in INLINE_TAG.captures_iter(&scrubbed) {
+            let tag = caps[1].trim_end_matches('/');
+            if tag.is_empty() {
+                continue;
+            }
+            tags.push((tag.to_string(), split.body_start_line + idx + 1));
+        }
+    }
+
+    tags
+}
+
+/// Every tag on a note, frontmatter and inline, deduplicated
+/// case-insensitively.
+pub fn note_tags(
+    frontmatter: &serde_json::Map<String, serde_json::Value>,
+    content: &str,
+) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let inline = inline_tags(content);
+    for tag in frontmatter::tags(frontmatter).into_iter().chain(inline) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Case-insensitive tag match where `#project` also matches nested tags
+/// like `#project/alpha`.
+pub fn tag_matches(tag: &str, wanted: &str) -> bool {
+    let tag = tag.trim_start_matches('#').to_lowercase();
+    let wanted = wanted.trim_start_matches('#').to_lowercase();
+    tag == wanted || tag.starts_with(&format!("{}/", wanted))
+}
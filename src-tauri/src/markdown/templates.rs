@@ -0,0 +1,262 @@
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::sync::LazyLock;
+
+use super::lint::{Diagnostic, Severity};
+
+static TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_]+)(?::([^}]*))?\s*\}\}").unwrap());
+// Anything between braces, including what `TOKEN` won't fill in.
+static BRACED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{([^{}]*)\}\}").unwrap());
+
+/// The tokens `render` fills in.
+pub const BUILT_IN_TOKENS: &[&str] = &["title", "date", "time"];
+
+// Moment.js-style date tokens, as used by Obsidian templates, longest first.
+const DATE_TOKENS: &[(&str, &str)] = &[
+    ("YYYY", "%Y"),
+    ("YY", "%y"),
+    ("MMMM", "%B"),
+    ("MMM", "%b"),
+    ("MM", "%m"),
+    ("M", "%-m"),
+    ("dddd", "%A"),
+    ("ddd", "%a"),
+    ("DD", "%d"),
+    ("D", "%-d"),
+    ("HH", "%H"),
+    ("H", "%-H"),
+    ("hh", "%I"),
+    ("h", "%-I"),
+    ("mm", "%M"),
+    ("ss", "%S"),
+    ("A", "%p"),
+    ("a", "%P"),
+];
+
+/// Values available to a template.
+pub struct TemplateContext {
+    pub title: String,
+    pub now: DateTime<Local>,
+}
+
+impl TemplateContext {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            now: Local::now(),
+        }
+    }
+}
+
+/// Fills in `{{title}}`, `{{date}}`, `{{time}}`, and formatted variants like
+/// `{{date:dddd, MMMM D}}`. Unknown tokens are left as they are.
+pub fn render(template: &str, context: &TemplateContext) -> String {
+    TOKEN
+        .replace_all(template, |caps: &regex::Captures| {
+            let format = caps.get(2).map(|m| m.as_str().trim());
+            match (caps[1].to_lowercase().as_str(), format) {
+                ("title", None) => context.title.clone(),
+                ("date", None) => context.now.format("%Y-%m-%d").to_string(),
+                ("time", None) => context.now.format("%H:%M").to_string(),
+                ("date" | "time", Some(format)) => {
+                    context.now.format(&chrono_format(format)).to_string()
+                }
+                _ => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Translates a Moment.js format into a chrono one. Text in `[brackets]` is
+/// copied literally.
+pub fn chrono_format(format: &str) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    'outer: while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            if let Some(close) = rest.find(']') {
+                out.push_str(&rest[1..close].replace('%', "%%"));
+                rest = &rest[close + 1..];
+                continue;
+            }
+        }
+        for (token, replacement) in DATE_TOKENS {
+            if let Some(after) = rest.strip_prefix(token) {
+                out.push_str(replacement);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        if c == '%' {
+            out.push('%');
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// The names of the `{{...}}` tokens in a template, built in or not, each
+/// once, in the order they first appear.
+pub fn variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in TOKEN.captures_iter(template) {
+        let name = caps[1].to_lowercase();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Tokens `render` will leave as they are, or fill in other than meant: a
+/// misspelled built-in like `{{tittle}}`, a format on `{{title}}`, `mm`
+/// (minutes) where a date probably wants `MM`, lowercase `yyyy` or `dd`,
+/// braces that don't make a token, and `{{` never closed. Other names are
+/// reported too, as they stay in every note made from the template.
+pub fn check(template: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (idx, line) in template.lines().enumerate() {
+        let mut closed_to = 0;
+        for braced in BRACED.find_iter(line) {
+            closed_to = braced.end();
+            let column = line[..braced.start()].chars().count() + 1;
+            let mut report =
+                |rule: &str, severity, message: String, replacement: Option<String>| {
+                    diagnostics.push(Diagnostic {
+                        rule: rule.to_string(),
+                        severity,
+                        line: idx + 1,
+                        column,
+                        message,
+                        fix: replacement.map(|with| {
+                            format!(
+                                "{}{}{}",
+                                &line[..braced.start()],
+                                with,
+                                &line[braced.end()..]
+                            )
+                        }),
+                    });
+                };
+            let Some(caps) = TOKEN
+                .captures(braced.as_str())
+                .filter(|caps| caps[0].len() == braced.len())
+            else {
+                report(
+                    "invalid-token",
+                    Severity::Warning,
+                    format!("`{}` isn't a token and is left as written", braced.as_str()),
+                    None,
+                );
+                continue;
+            };
+            let name = caps[1].to_lowercase();
+            let format = caps.get(2).map(|m| m.as_str().trim());
+            if !BUILT_IN_TOKENS.contains(&name.as_str()) {
+                match BUILT_IN_TOKENS
+                    .iter()
+                    .find(|built_in| distance(&name, built_in) == 1)
+                {
+                    Some(built_in) => {
+                        let fixed = match format {
+                            Some(format) => format!("{{{{{}:{}}}}}", built_in, format),
+                            None => format!("{{{{{}}}}}", built_in),
+                        };
+                        report(
+                            "misspelled-token",
+                            Severity::Warning,
+                            format!(
+                                "Unknown token `{}`; did you mean `{}`?",
+                                braced.as_str(),
+                                fixed
+                            ),
+                            Some(fixed),
+                        );
+                    }
+                    None => report(
+                        "unknown-token",
+                        Severity::Info,
+                        format!(
+                            "`{}` isn't built in and is left as written",
+                            braced.as_str()
+                        ),
+                        None,
+                    ),
+                }
+                continue;
+            }
+            match (name.as_str(), format) {
+                ("title", Some(_)) => report(
+                    "token-format",
+                    Severity::Error,
+                    "`{{title}}` doesn't take a format".to_string(),
+                    Some("{{title}}".to_string()),
+                ),
+                ("date" | "time", Some(format)) => {
+                    let months =
+                        name == "date" && format.contains("mm") && !format.contains(['H', 'h']);
+                    let years = format.contains("yy");
+                    let days = format.contains("dd") && !format.contains("ddd");
+                    let mut notes = Vec::new();
+                    let mut fixed = format.to_string();
+                    if months {
+                        notes.push("`mm` is minutes, months are `MM`");
+                        fixed = fixed.replace("mm", "MM");
+                    }
+                    if years {
+                        notes.push("`yy` is copied as written, years are `YYYY`");
+                        fixed = fixed.replace("yy", "YY");
+                    }
+                    if days {
+                        notes.push("`dd` is copied as written, days are `DD`");
+                        fixed = fixed.replace("dd", "DD");
+                    }
+                    if !notes.is_empty() {
+                        report(
+                            "token-format",
+                            Severity::Warning,
+                            format!("In `{}`: {}", braced.as_str(), notes.join("; ")),
+                            Some(format!("{{{{{}:{}}}}}", name, fixed)),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(open) = line[closed_to..].find("{{") {
+            diagnostics.push(Diagnostic {
+                rule: "unclosed-token".to_string(),
+                severity: Severity::Warning,
+                line: idx + 1,
+                column: line[..closed_to + open].chars().count() + 1,
+                message: "`{{` is never closed with `}}`".to_string(),
+                fix: None,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Character insertions, deletions, substitutions and swaps of neighbours
+/// that turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let above = &rows[i - 1];
+            row[j] = (above[j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(above[j] + 1)
+                .min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::diagnostics::Diagnostics;
+
+const PROGRESS_EVENT: &str = "task://progress";
+const DONE_EVENT: &str = "task://done";
+const FAILED_EVENT: &str = "task://failed";
+
+/// Progress without a payload is sent at most this often.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    ZipExport,
+    Grep,
+    CompressAttachments,
+    QueryExport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub kind: TaskKind,
+    pub message: String,
+    /// Between 0 and 1, when the task knows how far along it is.
+    pub fraction: Option<f64>,
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    pub cancelled: bool,
+}
+
+/// Payload of every `task://` event. `data` carries command-specific
+/// results: partial results on progress, the final result on done.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub id: u64,
+    pub kind: TaskKind,
+    pub message: String,
+    pub fraction: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    /// Set on `task://failed` when the task stopped because it was cancelled.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+/// Long-running commands, kept in managed state.
+///
+/// A command opts in by taking `AppHandle` and `State<TaskManager>`, calling
+/// `start` before the work begins, then `Task::progress` as it goes and
+/// `Task::check_cancelled` wherever stopping early is safe. It ends with
+/// `Task::finish`, which emits `task://done` or `task://failed` and hands the
+/// result back. Starting emits a first `task://progress`, which is how the
+/// frontend learns the id to pass to `cancel_task`. The work itself should run
+/// off the main thread, in an async command using `spawn_blocking`.
+#[derive(Default)]
+pub struct TaskManager {
+    next_id: AtomicU64,
+    running: Mutex<BTreeMap<u64, Arc<TaskState>>>,
+}
+
+struct TaskState {
+    info: Mutex<TaskInfo>,
+    cancelled: AtomicBool,
+    last_emit: Mutex<Option<Instant>>,
+    started: Instant,
+}
+
+impl TaskManager {
+    pub fn start(&self, app: &AppHandle, kind: TaskKind, message: impl Into<String>) -> Task {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let state = Arc::new(TaskState {
+            info: Mutex::new(TaskInfo {
+                id,
+                kind,
+                message: message.into(),
+                fraction: Some(0.0),
+                started_at: crate::vault::now_secs(),
+                cancelled: false,
+            }),
+            cancelled: AtomicBool::new(false),
+            last_emit: Mutex::new(None),
+            started: Instant::now(),
+        });
+        if let Ok(mut running) = self.running.lock() {
+            running.insert(id, state.clone());
+        }
+        let task = Task {
+            id,
+            app: app.clone(),
+            state,
+        };
+        task.emit(PROGRESS_EVENT, None, false);
+        task
+    }
+
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let Ok(running) = self.running.lock() else {
+            return Vec::new();
+        };
+        running
+            .values()
+            .filter_map(|state| state.info.lock().ok().map(|info| info.clone()))
+            .collect()
+    }
+
+    /// Asks a task to stop. Returns false when no such task is running.
+    pub fn cancel(&self, id: u64) -> bool {
+        let Some(state) = self
+            .running
+            .lock()
+            .ok()
+            .and_then(|running| running.get(&id).cloned())
+        else {
+            return false;
+        };
+        state.cancelled.store(true, Ordering::Relaxed);
+        if let Ok(mut info) = state.info.lock() {
+            info.cancelled = true;
+        }
+        true
+    }
+
+    fn remove(&self, id: u64) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(&id);
+        }
+    }
+}
+
+/// A running task, handed to the command doing the work.
+pub struct Task {
+    id: u64,
+    app: AppHandle,
+    state: Arc<TaskState>,
+}
+
+impl Task {
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fails with a "cancelled" error once `cancel_task` has been called.
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err("Cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reports progress. Without `data` the event is throttled, so this is
+    /// cheap to call for every item.
+    pub fn progress(&self, message: impl Into<String>, fraction: Option<f64>, data: Option<Value>) {
+        if let Ok(mut info) = self.state.info.lock() {
+            info.message = message.into();
+            info.fraction = fraction.map(|f| f.clamp(0.0, 1.0));
+        }
+        if data.is_none() {
+            let Ok(mut last) = self.state.last_emit.lock() else {
+                return;
+            };
+            if last.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        self.emit(PROGRESS_EVENT, data, false);
+    }
+
+    /// Ends the task with the command's result: `task://done` carrying the
+    /// serialized value, or `task://failed` carrying the error.
+    pub fn finish<T: Serialize>(self, result: Result<T, String>) -> Result<T, String> {
+        let name = self.record_duration();
+        let duration_ms = self.state.started.elapsed().as_secs_f64() * 1000.0;
+        match &result {
+            Ok(value) => {
+                tracing::info!(task = %name, duration_ms, "task done");
+                self.set_message("Done", Some(1.0));
+                self.emit(DONE_EVENT, serde_json::to_value(value).ok(), false);
+            }
+            Err(message) => {
+                let error_kind = if self.is_cancelled() {
+                    "cancelled"
+                } else {
+                    "failed"
+                };
+                tracing::warn!(
+                    task = %name,
+                    duration_ms,
+                    error_kind,
+                    error = %message,
+                    "task failed"
+                );
+                self.set_message(message.clone(), None);
+                self.emit(FAILED_EVENT, None, self.is_cancelled());
+            }
+        }
+        result
+    }
+
+    /// Times the task in diagnostics as `task:<kind>`, returning that name.
+    fn record_duration(&self) -> String {
+        let kind = self
+            .state
+            .info
+            .lock()
+            .ok()
+            .and_then(|info| serde_json::to_value(info.kind).ok());
+        let name = format!(
+            "task:{}",
+            kind.as_ref().and_then(Value::as_str).unwrap_or_default()
+        );
+        self.app
+            .state::<Diagnostics>()
+            .record(&name, self.state.started.elapsed());
+        name
+    }
+
+    fn set_message(&self, message: impl Into<String>, fraction: Option<f64>) {
+        if let Ok(mut info) = self.state.info.lock() {
+            info.message = message.into();
+            info.fraction = fraction;
+        }
+    }
+
+    fn emit(&self, event: &str, data: Option<Value>, cancelled: bool) {
+        let Ok(info) = self.state.info.lock() else {
+            return;
+        };
+        let payload = TaskEvent {
+            id: info.id,
+            kind: info.kind,
+            message: info.message.clone(),
+            fraction: info.fraction,
+            data,
+            cancelled,
+        };
+        drop(info);
+        let _ = self.app.emit(event, payload);
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.app.state::<TaskManager>().remove(self.id);
+    }
+}
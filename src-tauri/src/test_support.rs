@@ -0,0 +1,50 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A vault folder under the temp dir for one test, removed when dropped,
+/// so a failing test doesn't leave it behind. Each gets its own folder, so
+/// tests running at once never share one even under the same name.
+pub struct TempVault {
+    root: PathBuf,
+}
+
+impl TempVault {
+    /// A vault holding `files`, as vault-relative paths and their contents.
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let root =
+            std::env::temp_dir().join(format!("graphnotes-{}-{}-{}", name, std::process::id(), n));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for (rel, content) in files {
+            let path = root.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        TempVault { root }
+    }
+}
+
+impl Deref for TempVault {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AsRef<Path> for TempVault {
+    fn as_ref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for TempVault {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
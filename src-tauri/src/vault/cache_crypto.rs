@@ -0,0 +1,201 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{read_json, state_dir, write_json};
+use crate::commands::files::write_atomic;
+
+const KEY_FILE: &str = "cache-key.json";
+/// Starts every encrypted cache file, before the nonce.
+const MAGIC: &[u8] = b"GNCACHE1";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+/// The vault's cache key, wrapped with a key derived from its passphrase,
+/// in `.graphnotes/cache-key.json`. Both are hex.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct KeyFile {
+    salt: String,
+    wrapped_key: String,
+}
+
+/// A vault's cache key, once unlocked with its passphrase.
+pub struct CacheKey {
+    cipher: XChaCha20Poly1305,
+}
+
+/// How a vault's caches are stored, given its `encrypt_caches` setting and
+/// whether its key is unlocked.
+#[derive(Clone)]
+pub enum CacheMode {
+    Plain,
+    Encrypted(Arc<CacheKey>),
+    /// Encrypted, but the key isn't unlocked: caches are neither read nor
+    /// written.
+    Locked,
+}
+
+impl CacheKey {
+    /// Unwraps the vault's cache key with `passphrase`. The first unlock
+    /// makes a new random key, so the passphrase given then is the one.
+    pub fn unlock(vault_path: &Path, passphrase: &str) -> Result<Self, String> {
+        if passphrase.is_empty() {
+            return Err("Passphrase is empty".to_string());
+        }
+        let path = state_dir(vault_path).join(KEY_FILE);
+        let stored: KeyFile = read_json(&path)?;
+        if stored.wrapped_key.is_empty() {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+            let wrapping = Self::from_passphrase(passphrase, &salt)?;
+            write_json(
+                &path,
+                &KeyFile {
+                    salt: to_hex(&salt),
+                    wrapped_key: to_hex(&wrapping.seal(&key)?),
+                },
+            )?;
+            return Ok(Self::new(&key));
+        }
+
+        let salt = from_hex(&stored.salt)
+            .ok_or_else(|| format!("Corrupt key file: {}", path.display()))?;
+        let wrapped = from_hex(&stored.wrapped_key)
+            .ok_or_else(|| format!("Corrupt key file: {}", path.display()))?;
+        let key = Self::from_passphrase(passphrase, &salt)?
+            .open(&wrapped)
+            .map_err(|_| "Wrong passphrase".to_string())?;
+        if key.len() != 32 {
+            return Err(format!("Corrupt key file: {}", path.display()));
+        }
+        Ok(Self::new(Key::from_slice(&key)))
+    }
+
+    fn new(key: &Key) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(key),
+        }
+    }
+
+    fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("Failed to derive key: {}", e))?;
+        Ok(Self::new(Key::from_slice(&key)))
+    }
+
+    /// `MAGIC`, a random nonce, then the ciphertext.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Failed to encrypt".to_string())?;
+        Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        let body = sealed
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| "Not encrypted".to_string())?;
+        if body.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt".to_string())
+    }
+
+    /// Encrypts a plain cache file in place; encrypted ones are left alone.
+    pub fn encrypt_file(&self, path: &Path) -> Result<(), String> {
+        if !path.is_file() || is_encrypted(path) {
+            return Ok(());
+        }
+        let plain =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        write_atomic(path, self.seal(&plain)?)
+    }
+}
+
+impl CacheMode {
+    pub fn is_locked(&self) -> bool {
+        matches!(self, CacheMode::Locked)
+    }
+
+    /// Whether `path` can be read in this mode rather than counting as
+    /// missing.
+    pub fn can_read(&self, path: &Path) -> bool {
+        match self {
+            CacheMode::Plain => !is_encrypted(path),
+            CacheMode::Encrypted(_) => true,
+            CacheMode::Locked => false,
+        }
+    }
+
+    /// Reads a JSON cache file like `vault::read_json`. Files in the other
+    /// form count as missing, except that plain files are still read when
+    /// encrypted, until they are next written.
+    pub fn read_json<T: DeserializeOwned + Default>(&self, path: &Path) -> Result<T, String> {
+        let key = match self {
+            CacheMode::Locked => return Ok(T::default()),
+            CacheMode::Plain if is_encrypted(path) => return Ok(T::default()),
+            CacheMode::Encrypted(key) if is_encrypted(path) => key,
+            _ => return read_json(path),
+        };
+        let sealed =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let plain = key
+            .open(&sealed)
+            .map_err(|e| format!("{}: {}", e, path.display()))?;
+        serde_json::from_slice(&plain)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Writes a JSON cache file like `vault::write_json`, encrypted if the
+    /// vault's caches are. Fails while they are locked.
+    pub fn write_json<T: Serialize + ?Sized>(&self, path: &Path, value: &T) -> Result<(), String> {
+        let key = match self {
+            CacheMode::Plain => return write_json(path, value),
+            CacheMode::Locked => return Err("Vault caches are locked".to_string()),
+            CacheMode::Encrypted(key) => key,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        let json = serde_json::to_vec(value)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        write_atomic(path, key.seal(&json)?)
+    }
+}
+
+fn is_encrypted(path: &Path) -> bool {
+    let mut start = [0u8; MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok()
+        && start == MAGIC
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
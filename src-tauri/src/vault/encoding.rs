@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How much of a file without a byte order mark is looked at to tell
+/// UTF-16 from other text.
+const SNIFF_BYTES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+}
+
+/// Decodes a text file's bytes, going by the byte order mark when there is
+/// one. UTF-16 without one is recognised by its zero bytes, which ASCII
+/// text in UTF-16 is full of. The mark itself is dropped.
+pub fn decode(bytes: &[u8]) -> Result<(String, TextEncoding), String> {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return utf8(rest).map(|text| (text, TextEncoding::Utf8Bom));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return utf16(rest, u16::from_le_bytes).map(|text| (text, TextEncoding::Utf16Le));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return utf16(rest, u16::from_be_bytes).map(|text| (text, TextEncoding::Utf16Be));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        // Valid UTF-8 can still be UTF-16 whose every other byte is zero.
+        if !bytes.contains(&0) {
+            return Ok((text.to_string(), TextEncoding::Utf8));
+        }
+    }
+    match utf16_without_bom(bytes) {
+        Some(TextEncoding::Utf16Le) => {
+            utf16(bytes, u16::from_le_bytes).map(|text| (text, TextEncoding::Utf16Le))
+        }
+        Some(TextEncoding::Utf16Be) => {
+            utf16(bytes, u16::from_be_bytes).map(|text| (text, TextEncoding::Utf16Be))
+        }
+        _ => utf8(bytes).map(|text| (text, TextEncoding::Utf8)),
+    }
+}
+
+/// The result of undoing double-encoded UTF-8 in some text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MojibakeRepair {
+    pub text: String,
+    /// Characters restored, e.g. one for each `â€™` turned back into `’`.
+    pub replacements: usize,
+    /// Characters of the original text that were part of a repaired
+    /// sequence.
+    pub repaired_chars: usize,
+    /// Sequences that look double-encoded but don't decode, e.g. `â€`
+    /// with its last byte lost.
+    pub broken: usize,
+}
+
+/// Undoes UTF-8 that was read as Windows-1252 (or Latin-1) and saved
+/// again, which turns `’` into `â€™` and `é` into `Ã©`. Only sequences
+/// that decode to a character are replaced, so text that is partly fine
+/// keeps its good parts. Text encoded twice over is repaired in further
+/// passes.
+pub fn repair_mojibake(text: &str) -> MojibakeRepair {
+    let mut repair = repair_pass(text);
+    for _ in 0..2 {
+        let again = repair_pass(&repair.text);
+        if again.replacements == 0 {
+            break;
+        }
+        repair.text = again.text;
+        repair.replacements += again.replacements;
+    }
+    repair
+}
+
+/// The share of words, 0 to 1, made only of letters, digits, apostrophes
+/// and hyphens, which mojibake like `cafÃ©` is not. Text without
+/// words scores 1.
+pub fn readable_word_share(text: &str) -> f64 {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| c.is_ascii_punctuation()))
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .collect();
+    if words.is_empty() {
+        return 1.0;
+    }
+    let readable = words
+        .iter()
+        .filter(|w| {
+            w.chars().all(|c| {
+                c.is_ascii_alphanumeric()
+                    || matches!(c, '\'' | '’' | '-')
+                    || (c.is_alphabetic() && !('\u{80}'..='\u{bf}').contains(&c))
+            })
+        })
+        .count();
+    readable as f64 / words.len() as f64
+}
+
+fn repair_pass(text: &str) -> MojibakeRepair {
+    let chars: Vec<char> = text.chars().collect();
+    let mut repair = MojibakeRepair {
+        text: String::with_capacity(text.len()),
+        replacements: 0,
+        repaired_chars: 0,
+        broken: 0,
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let length = match windows_1252_byte(chars[i]) {
+            Some(0xC2..=0xDF) => 2,
+            Some(0xE0..=0xEF) => 3,
+            Some(0xF0..=0xF4) => 4,
+            _ => 0,
+        };
+        if length > 0 {
+            let bytes: Vec<u8> = chars[i..]
+                .iter()
+                .take(length)
+                .map_while(|&c| windows_1252_byte(c))
+                .enumerate()
+                .take_while(|&(n, b)| n == 0 || (0x80..=0xBF).contains(&b))
+                .map(|(_, b)| b)
+                .collect();
+            match std::str::from_utf8(&bytes) {
+                Ok(decoded) if bytes.len() == length => {
+                    repair.text.push_str(decoded);
+                    repair.replacements += 1;
+                    repair.repaired_chars += length;
+                    i += length;
+                    continue;
+                }
+                // A long sequence cut short right after a Windows-1252
+                // symbol like `€` is almost surely mojibake; anything else
+                // is as likely to be real text.
+                _ if length > 2 && bytes.get(1).is_some_and(|b| (0x80..=0x9F).contains(b)) => {
+                    repair.broken += 1
+                }
+                _ => {}
+            }
+        }
+        repair.text.push(chars[i]);
+        i += 1;
+    }
+    repair
+}
+
+/// The byte a character comes from when bytes are read as Windows-1252,
+/// falling back to Latin-1 for the bytes Windows-1252 leaves undefined.
+fn windows_1252_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        '\u{0}'..='\u{ff}' => c as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryKind {
+    Image,
+    Archive,
+    Database,
+    Unknown,
+}
+
+/// What a binary file is, with a short name for its format like `png` or
+/// `sqlite`, going by its first bytes; at least 265 are needed to spot a
+/// tar archive. `None` when nothing known matches, which doesn't make the
+/// file text.
+pub fn binary_format(bytes: &[u8]) -> Option<(BinaryKind, &'static str)> {
+    const SIGNATURES: &[(&[u8], BinaryKind, &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", BinaryKind::Image, "png"),
+        (b"\xFF\xD8\xFF", BinaryKind::Image, "jpeg"),
+        (b"GIF87a", BinaryKind::Image, "gif"),
+        (b"GIF89a", BinaryKind::Image, "gif"),
+        (b"II*\0", BinaryKind::Image, "tiff"),
+        (b"MM\0*", BinaryKind::Image, "tiff"),
+        (b"\0\0\x01\0", BinaryKind::Image, "ico"),
+        (b"PK\x03\x04", BinaryKind::Archive, "zip"),
+        (b"PK\x05\x06", BinaryKind::Archive, "zip"),
+        (b"\x1F\x8B", BinaryKind::Archive, "gzip"),
+        (b"7z\xBC\xAF\x27\x1C", BinaryKind::Archive, "7z"),
+        (b"Rar!\x1A\x07", BinaryKind::Archive, "rar"),
+        (b"\xFD7zXZ\0", BinaryKind::Archive, "xz"),
+        (b"\x28\xB5\x2F\xFD", BinaryKind::Archive, "zstd"),
+        (b"SQLite format 3\0", BinaryKind::Database, "sqlite"),
+        (b"%PDF-", BinaryKind::Unknown, "pdf"),
+        (b"\x7FELF", BinaryKind::Unknown, "elf"),
+        (b"ID3", BinaryKind::Unknown, "mp3"),
+        (b"OggS", BinaryKind::Unknown, "ogg"),
+        (b"\0asm", BinaryKind::Unknown, "wasm"),
+    ];
+    if let Some((_, kind, format)) = SIGNATURES
+        .iter()
+        .find(|(magic, _, _)| bytes.starts_with(magic))
+    {
+        return Some((*kind, format));
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" {
+        return Some(match &bytes[8..12] {
+            b"WEBP" => (BinaryKind::Image, "webp"),
+            b"WAVE" => (BinaryKind::Unknown, "wav"),
+            _ => (BinaryKind::Unknown, "riff"),
+        });
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return Some(match &bytes[8..12] {
+            b"avif" | b"avis" => (BinaryKind::Image, "avif"),
+            b"heic" | b"heix" | b"mif1" => (BinaryKind::Image, "heic"),
+            b"qt  " => (BinaryKind::Unknown, "mov"),
+            _ => (BinaryKind::Unknown, "mp4"),
+        });
+    }
+    // `BZh9` alone could start a line of text; the block magic after it
+    // can't.
+    if bytes.len() >= 10
+        && bytes.starts_with(b"BZh")
+        && &bytes[4..10] == b"\x31\x41\x59\x26\x53\x59"
+    {
+        return Some((BinaryKind::Archive, "bzip2"));
+    }
+    if bytes.get(257..262) == Some(b"ustar") {
+        return Some((BinaryKind::Archive, "tar"));
+    }
+    None
+}
+
+/// A MIME type for an attachment, from its extension or, failing that, from
+/// the first bytes of the file.
+pub fn mime_type(path: &Path, bytes: &[u8]) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let by_extension = match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        "avif" => Some("image/avif"),
+        "bmp" => Some("image/bmp"),
+        "ico" => Some("image/x-icon"),
+        "pdf" => Some("application/pdf"),
+        "mp3" => Some("audio/mpeg"),
+        "wav" => Some("audio/wav"),
+        "ogg" => Some("audio/ogg"),
+        "m4a" => Some("audio/mp4"),
+        "mp4" => Some("video/mp4"),
+        "webm" => Some("video/webm"),
+        "mov" => Some("video/quicktime"),
+        "md" | "markdown" => Some("text/markdown"),
+        "txt" => Some("text/plain"),
+        "csv" => Some("text/csv"),
+        "json" => Some("application/json"),
+        "html" | "htm" => Some("text/html"),
+        "zip" => Some("application/zip"),
+        _ => None,
+    };
+    if let Some(mime) = by_extension {
+        return mime;
+    }
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"ID3", "audio/mpeg"),
+    ];
+    if let Some((_, mime)) = SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+    {
+        return mime;
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    "application/octet-stream"
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| "File is not UTF-8 or UTF-16 text".to_string())
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err("File is not valid UTF-16: odd number of bytes".to_string());
+    }
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| "File is not valid UTF-16".to_string())
+}
+
+/// Which half of each byte pair is mostly zero, if either clearly is.
+fn utf16_without_bom(bytes: &[u8]) -> Option<TextEncoding> {
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES) & !1];
+    if sample.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros_at = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd * 10 >= pairs * 4 && even * 10 < pairs {
+        Some(TextEncoding::Utf16Le)
+    } else if even * 10 >= pairs * 4 && odd * 10 < pairs {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
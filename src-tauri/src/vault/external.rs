@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+use super::{now_secs, read_json, write_json};
+
+const REGISTRY_FILE: &str = "external_vaults.json";
+/// Below the app data directory, one folder per external vault.
+const STATE_ROOT: &str = "external";
+
+/// A folder opened as a vault without being made one: nothing of
+/// GraphNotes' is written into it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalFolder {
+    pub path: String,
+    /// Whether its notes may be changed; on unless turned off.
+    pub read_only: bool,
+    /// Whether `[[wikilinks]]` count as links. Off, so a docs repository's
+    /// graph is built from its relative markdown links alone.
+    pub wikilinks: bool,
+    /// Seconds since the Unix epoch.
+    pub opened_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RegistryFile {
+    folders: Vec<ExternalFolder>,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// The app data directory; until it is known no folder is external.
+    data_dir: Option<PathBuf>,
+    folders: HashMap<PathBuf, ExternalFolder>,
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(RwLock::default);
+
+/// Loads the folders opened as external vaults, kept in `data_dir`.
+pub fn init(data_dir: &Path) -> Result<(), String> {
+    let file: RegistryFile = read_json(&data_dir.join(REGISTRY_FILE))?;
+    let mut registry = REGISTRY
+        .write()
+        .map_err(|_| "External vaults are unavailable".to_string())?;
+    registry.data_dir = Some(data_dir.to_path_buf());
+    registry.folders = file
+        .folders
+        .into_iter()
+        .map(|folder| (PathBuf::from(&folder.path), folder))
+        .collect();
+    Ok(())
+}
+
+/// Registers the folder at the canonical `root` as an external vault, or
+/// updates how it is opened, and makes its state directory.
+pub fn open(root: &Path, read_only: bool, wikilinks: bool) -> Result<ExternalFolder, String> {
+    let mut registry = REGISTRY
+        .write()
+        .map_err(|_| "External vaults are unavailable".to_string())?;
+    let Some(data_dir) = registry.data_dir.clone() else {
+        return Err("External vaults are unavailable".to_string());
+    };
+    let folder = ExternalFolder {
+        path: root.to_string_lossy().to_string(),
+        read_only,
+        wikilinks,
+        opened_at: now_secs(),
+    };
+    let state = state_path(&data_dir, root);
+    fs::create_dir_all(&state).map_err(|e| format!("Failed to create directory: {}", e))?;
+    registry.folders.insert(root.to_path_buf(), folder.clone());
+    let mut folders: Vec<ExternalFolder> = registry.folders.values().cloned().collect();
+    folders.sort_by(|a, b| a.path.cmp(&b.path));
+    write_json(&data_dir.join(REGISTRY_FILE), &RegistryFile { folders })?;
+    Ok(folder)
+}
+
+/// How the folder at `root` was opened, if it is an external vault.
+pub fn get(root: &Path) -> Option<ExternalFolder> {
+    REGISTRY.read().ok()?.folders.get(root).cloned()
+}
+
+/// Where an external vault's state is kept instead of `.graphnotes`:
+/// `<app data>/external/<hash of its path>`. `None` for other folders.
+pub fn state_dir(root: &Path) -> Option<PathBuf> {
+    let registry = REGISTRY.read().ok()?;
+    let data_dir = registry
+        .data_dir
+        .as_deref()
+        .filter(|_| registry.folders.contains_key(root))?;
+    Some(state_path(data_dir, root))
+}
+
+/// Whether `path` is in, or is, a folder opened read-only.
+pub fn is_read_only(path: &Path) -> bool {
+    REGISTRY.read().is_ok_and(|registry| {
+        registry
+            .folders
+            .iter()
+            .any(|(root, folder)| folder.read_only && path.starts_with(root))
+    })
+}
+
+/// Whether links written `[[like this]]` count in the vault at `root`:
+/// always, unless it is an external vault that hasn't turned them on.
+pub fn wikilinks_enabled(root: &Path) -> bool {
+    get(root).is_none_or(|folder| folder.wikilinks)
+}
+
+fn state_path(data_dir: &Path, root: &Path) -> PathBuf {
+    let hash = Sha256::digest(root.to_string_lossy().as_bytes());
+    let name: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    data_dir.join(STATE_ROOT).join(name)
+}
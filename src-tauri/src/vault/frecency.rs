@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{is_within, now_secs, read_json, relative_path, state_dir, write_json};
+
+const FRECENCY_FILE: &str = "frecency.json";
+/// Notes tracked at most; the lowest scoring are forgotten first.
+const MAX_ENTRIES: usize = 500;
+/// An open counts half as much after this long.
+const HALF_LIFE_SECS: f64 = 14.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Visits {
+    pub opens: u32,
+    /// Seconds since the Unix epoch.
+    pub last_opened: u64,
+    /// Decayed open count as of `last_opened`.
+    score: f64,
+}
+
+impl Visits {
+    /// The decayed open count at `now`.
+    pub fn score_at(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_opened) as f64;
+        self.score * 0.5f64.powf(age / HALF_LIFE_SECS)
+    }
+}
+
+/// Open counts per note, keyed by vault-relative path, in
+/// `.graphnotes/frecency.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Frecency {
+    pub notes: BTreeMap<String, Visits>,
+}
+
+impl Frecency {
+    pub fn load(vault_path: &Path) -> Result<Self, String> {
+        read_json(&state_dir(vault_path).join(FRECENCY_FILE))
+    }
+
+    fn save(&self, vault_path: &Path) -> Result<(), String> {
+        write_json(&state_dir(vault_path).join(FRECENCY_FILE), self)
+    }
+
+    /// Current score of a note, or zero when it has never been opened.
+    pub fn score(&self, vault_path: &Path, path: &Path, now: u64) -> f64 {
+        self.notes
+            .get(&relative_path(vault_path, path))
+            .map(|visits| visits.score_at(now))
+            .unwrap_or(0.0)
+    }
+}
+
+pub fn record_open(vault_path: &Path, path: &Path) -> Result<(), String> {
+    let mut store = Frecency::load(vault_path)?;
+    let now = now_secs();
+    let visits = store
+        .notes
+        .entry(relative_path(vault_path, path))
+        .or_default();
+    visits.score = visits.score_at(now) + 1.0;
+    visits.opens += 1;
+    visits.last_opened = now;
+
+    if store.notes.len() > MAX_ENTRIES {
+        let mut ranked: Vec<(String, f64)> = store
+            .notes
+            .iter()
+            .map(|(k, v)| (k.clone(), v.score_at(now)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for (key, _) in ranked.into_iter().take(store.notes.len() - MAX_ENTRIES) {
+            store.notes.remove(&key);
+        }
+    }
+    store.save(vault_path)
+}
+
+/// Moves entries at or below `old` (a note or a folder) to `new`.
+pub fn rename(vault_path: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    let mut store = Frecency::load(vault_path)?;
+    let old_rel = relative_path(vault_path, old);
+    let new_rel = relative_path(vault_path, new);
+    let moved: Vec<String> = store
+        .notes
+        .keys()
+        .filter(|k| is_within(k, &old_rel))
+        .cloned()
+        .collect();
+    if moved.is_empty() {
+        return Ok(());
+    }
+    for key in moved {
+        let visits = store.notes.remove(&key).unwrap_or_default();
+        store
+            .notes
+            .insert(format!("{}{}", new_rel, &key[old_rel.len()..]), visits);
+    }
+    store.save(vault_path)
+}
+
+/// Forgets entries at or below `path`.
+pub fn remove(vault_path: &Path, path: &Path) -> Result<(), String> {
+    let mut store = Frecency::load(vault_path)?;
+    let rel = relative_path(vault_path, path);
+    let before = store.notes.len();
+    store.notes.retain(|k, _| !is_within(k, &rel));
+    if store.notes.len() == before {
+        return Ok(());
+    }
+    store.save(vault_path)
+}
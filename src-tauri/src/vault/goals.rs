@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{is_within, read_json, relative_path, state_dir, write_json};
+
+const GOALS_FILE: &str = "goals.json";
+/// Days of history kept per note.
+const HISTORY_LIMIT: usize = 365;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyCount {
+    /// Local date, `YYYY-MM-DD`.
+    pub date: String,
+    pub words: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Goal {
+    pub target_words: usize,
+    /// The last word count saved on each day, oldest first.
+    pub history: Vec<DailyCount>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct GoalList {
+    /// Keyed by vault-relative path.
+    goals: BTreeMap<String, Goal>,
+}
+
+fn load(vault_path: &Path) -> Result<GoalList, String> {
+    read_json(&state_dir(vault_path).join(GOALS_FILE))
+}
+
+fn save(vault_path: &Path, list: &GoalList) -> Result<(), String> {
+    write_json(&state_dir(vault_path).join(GOALS_FILE), list)
+}
+
+pub fn get(vault_path: &Path, path: &Path) -> Result<Option<Goal>, String> {
+    Ok(load(vault_path)?
+        .goals
+        .remove(&relative_path(vault_path, path)))
+}
+
+/// Sets a note's word goal, keeping its history. A target of zero removes
+/// the goal.
+pub fn set(vault_path: &Path, path: &Path, target_words: usize) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let rel = relative_path(vault_path, path);
+    if target_words == 0 {
+        list.goals.remove(&rel);
+    } else {
+        list.goals.entry(rel).or_default().target_words = target_words;
+    }
+    save(vault_path, &list)
+}
+
+/// Records today's word count for a note with a goal; notes without one
+/// are left alone and nothing is written.
+pub fn record(vault_path: &Path, path: &Path, words: usize) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let Some(goal) = list.goals.get_mut(&relative_path(vault_path, path)) else {
+        return Ok(());
+    };
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    match goal.history.last_mut() {
+        Some(last) if last.date == today => last.words = words,
+        _ => goal.history.push(DailyCount { date: today, words }),
+    }
+    if goal.history.len() > HISTORY_LIMIT {
+        let excess = goal.history.len() - HISTORY_LIMIT;
+        goal.history.drain(..excess);
+    }
+    save(vault_path, &list)
+}
+
+/// Moves goals at or below `old` (a note or a folder) to `new`.
+pub fn rename(vault_path: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let old_rel = relative_path(vault_path, old);
+    let new_rel = relative_path(vault_path, new);
+    let moved: Vec<String> = list
+        .goals
+        .keys()
+        .filter(|k| is_within(k, &old_rel))
+        .cloned()
+        .collect();
+    if moved.is_empty() {
+        return Ok(());
+    }
+    for key in moved {
+        let goal = list.goals.remove(&key).unwrap_or_default();
+        list.goals
+            .insert(format!("{}{}", new_rel, &key[old_rel.len()..]), goal);
+    }
+    save(vault_path, &list)
+}
+
+/// Drops goals at or below `path`.
+pub fn remove(vault_path: &Path, path: &Path) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let rel = relative_path(vault_path, path);
+    let before = list.goals.len();
+    list.goals.retain(|k, _| !is_within(k, &rel));
+    if list.goals.len() == before {
+        return Ok(());
+    }
+    save(vault_path, &list)
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+use super::{external, find_root, human_size, now_secs, settings::VaultSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCondition {
+    /// Less free space than the vault's `health.low_disk_megabytes`.
+    LowDisk,
+    /// The filesystem is mounted read-only, or the vault's folder can't be
+    /// written by this user.
+    ReadOnlyFs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultHealth {
+    pub path: String,
+    /// Free space this user may fill on the vault's filesystem; `None` when
+    /// it couldn't be read.
+    pub available_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub low_disk_threshold_bytes: u64,
+    pub conditions: Vec<HealthCondition>,
+    /// What to tell the user, when anything is wrong.
+    pub message: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub checked_at: u64,
+}
+
+struct Probe {
+    available: u64,
+    total: u64,
+    read_only_fs: bool,
+    writable: bool,
+}
+
+/// Looks at the free space on the filesystem the vault at `root` is on and
+/// whether its folder can be written. A folder opened read-only as an
+/// external vault is never written, so it can't be a read-only problem.
+pub fn check(root: &Path) -> VaultHealth {
+    let settings = VaultSettings::load(root).unwrap_or_default().health;
+    let threshold = settings.low_disk_megabytes.saturating_mul(1024 * 1024);
+    let probe = probe(root);
+    if let Err(e) = &probe {
+        tracing::warn!(vault = %root.display(), error = %e, "vault health not checked");
+    }
+    let mut conditions = Vec::new();
+    let mut messages = Vec::new();
+    if let Ok(probe) = &probe {
+        if probe.available < threshold {
+            conditions.push(HealthCondition::LowDisk);
+            messages.push(if probe.available == 0 {
+                "The vault's disk is full.".to_string()
+            } else {
+                format!(
+                    "The vault's disk is almost full: {} left.",
+                    human_size(probe.available)
+                )
+            });
+        }
+        if !(probe.writable || external::is_read_only(root)) {
+            conditions.push(HealthCondition::ReadOnlyFs);
+            messages.push(if probe.read_only_fs {
+                "The vault's disk is read-only.".to_string()
+            } else {
+                "The vault's folder can't be written: you don't have permission.".to_string()
+            });
+        }
+    }
+    VaultHealth {
+        path: root.to_string_lossy().to_string(),
+        available_bytes: probe.as_ref().ok().map(|probe| probe.available),
+        total_bytes: probe.as_ref().ok().map(|probe| probe.total),
+        low_disk_threshold_bytes: threshold,
+        conditions,
+        message: (!messages.is_empty()).then(|| messages.join(" ")),
+        checked_at: now_secs(),
+    }
+}
+
+/// The health of wherever `path` is being written, when something is
+/// wrong with it: the vault it is in or, outside any vault, the nearest
+/// folder that exists.
+pub fn problem(path: &Path) -> Option<VaultHealth> {
+    let root = find_root(path).or_else(|| {
+        path.ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .map(Path::to_path_buf)
+    })?;
+    Some(check(&root)).filter(|health| !health.conditions.is_empty())
+}
+
+/// `error`, from writing `path`, with what is wrong with its disk in
+/// front, if anything, so "os error 28" reads as a full disk.
+pub fn explain(path: &Path, error: String) -> String {
+    match problem(path).and_then(|health| health.message) {
+        Some(message) => format!("{} {}", message, error),
+        None => error,
+    }
+}
+
+// The statvfs fields are u32 on some platforms and u64 on others.
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)]
+fn probe(path: &Path) -> io::Result<Probe> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `path` is NUL-terminated and `stat` is a statvfs for the
+    // call to fill in; both outlive the calls.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let writable = unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0;
+    let block = u64::from(stat.f_frsize);
+    Ok(Probe {
+        available: u64::from(stat.f_bavail).saturating_mul(block),
+        total: u64::from(stat.f_blocks).saturating_mul(block),
+        read_only_fs: stat.f_flag & libc::ST_RDONLY != 0,
+        writable,
+    })
+}
+
+#[cfg(windows)]
+fn probe(path: &Path) -> io::Result<Probe> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNameW,
+    };
+    use windows_sys::Win32::System::SystemServices::FILE_READ_ONLY_VOLUME;
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut available, mut total) = (0u64, 0u64);
+    // SAFETY: `path` is NUL-terminated and every buffer is as long as the
+    // calls are told; all outlive the calls.
+    if unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut available, &mut total, null_mut()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut volume = [0u16; 261];
+    let mut flags = 0u32;
+    let read_only_fs =
+        unsafe { GetVolumePathNameW(path.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) } != 0
+            && unsafe {
+                GetVolumeInformationW(
+                    volume.as_ptr(),
+                    null_mut(),
+                    0,
+                    null_mut(),
+                    null_mut(),
+                    &mut flags,
+                    null_mut(),
+                    0,
+                )
+            } != 0
+            && flags & FILE_READ_ONLY_VOLUME != 0;
+    Ok(Probe {
+        available,
+        total,
+        read_only_fs,
+        writable: !read_only_fs,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn probe(_path: &Path) -> io::Result<Probe> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Disk space can't be read on this platform",
+    ))
+}
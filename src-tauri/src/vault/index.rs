@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::markdown::{self, blank_code_spans, code_block_lines, frontmatter, links, tags};
+
+/// A note as seen by vault-wide reports: its metadata plus the links it
+/// makes to other notes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedNote {
+    /// Not stored: the link index keys notes by vault-relative path instead.
+    #[serde(skip)]
+    pub path: PathBuf,
+    pub title: String,
+    pub aliases: Vec<String>,
+    pub frontmatter: Map<String, Value>,
+    pub tags: Vec<String>,
+    pub word_count: usize,
+    /// File mtime in seconds since the Unix epoch.
+    pub modified: Option<u64>,
+    /// Wikilinks, and markdown links pointing at `.md` files, outside code.
+    pub links: Vec<NoteLink>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Wiki,
+    Markdown,
+}
+
+/// A link to a note, as written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub kind: LinkKind,
+    /// The wikilink target without anchor or alias, or the markdown link
+    /// target still encoded.
+    pub target: String,
+    /// The whole link, e.g. `[[Note#Heading|alias]]`.
+    pub text: String,
+    pub embed: bool,
+    /// One-based line number within the file.
+    pub line: usize,
+    /// The line the link is on, trimmed.
+    pub line_content: String,
+}
+
+/// Every note in a set of files, with links resolved between them.
+pub struct VaultIndex {
+    root: PathBuf,
+    pub notes: Vec<IndexedNote>,
+    by_stem: HashMap<String, Vec<usize>>,
+    by_path: HashMap<PathBuf, usize>,
+    /// Lowercased vault-relative paths without extension, e.g. `area/sub/note`.
+    by_rel: HashMap<String, usize>,
+    /// The same paths by every trailing part after a `/`, e.g. `sub/note`
+    /// and `note`.
+    by_rel_suffix: HashMap<String, Vec<usize>>,
+    /// Lowercased titles and aliases, each to the first note with it.
+    by_title: HashMap<String, usize>,
+    by_alias: HashMap<String, usize>,
+}
+
+impl VaultIndex {
+    /// Reads and indexes `files`. Unreadable files are left out.
+    pub fn build(root: &Path, files: &[PathBuf]) -> Self {
+        Self::from_notes(root, files.iter().filter_map(|f| index_note(f)).collect())
+    }
+
+    /// Indexes notes that have already been read. Wikilinks are dropped in
+    /// an external vault that doesn't use them.
+    pub fn from_notes(root: &Path, mut notes: Vec<IndexedNote>) -> Self {
+        if !super::external::wikilinks_enabled(root) {
+            for note in &mut notes {
+                note.links.retain(|link| link.kind == LinkKind::Markdown);
+            }
+        }
+        let mut by_stem: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_path = HashMap::new();
+        let mut by_rel = HashMap::new();
+        let mut by_rel_suffix: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_title = HashMap::new();
+        let mut by_alias = HashMap::new();
+        for (idx, note) in notes.iter().enumerate() {
+            if let Some(stem) = note.path.file_stem() {
+                by_stem
+                    .entry(stem.to_string_lossy().to_lowercase())
+                    .or_default()
+                    .push(idx);
+            }
+            by_path.insert(note.path.clone(), idx);
+
+            let rel = super::relative_path(root, &note.path).to_lowercase();
+            let rel = rel
+                .rsplit_once('.')
+                .map(|(stem, _)| stem.to_string())
+                .unwrap_or(rel);
+            for (slash, _) in rel.match_indices('/') {
+                by_rel_suffix
+                    .entry(rel[slash + 1..].to_string())
+                    .or_default()
+                    .push(idx);
+            }
+            by_rel.entry(rel).or_insert(idx);
+
+            by_title.entry(note.title.to_lowercase()).or_insert(idx);
+            for alias in &note.aliases {
+                by_alias.entry(alias.to_lowercase()).or_insert(idx);
+            }
+        }
+
+        Self {
+            root: root.to_path_buf(),
+            notes,
+            by_stem,
+            by_path,
+            by_rel,
+            by_rel_suffix,
+            by_title,
+            by_alias,
+        }
+    }
+
+    /// Index of the note at `path`.
+    pub fn position(&self, path: &Path) -> Option<usize> {
+        self.by_path.get(path).copied()
+    }
+
+    /// Resolves a wikilink target from the note at `from`. Tries the file
+    /// name (preferring the linking note's folder, then the shortest path),
+    /// then a vault-relative or trailing partial path, then titles and
+    /// aliases, mirroring the editor's link parser. Matching ignores case.
+    pub fn resolve_wikilink(&self, from: usize, target: &str) -> Option<usize> {
+        let target = target.trim();
+        let target = target
+            .strip_suffix(".md")
+            .or_else(|| target.strip_suffix(".markdown"))
+            .unwrap_or(target);
+        if target.is_empty() {
+            return None;
+        }
+        let lowered = target.to_lowercase();
+
+        if !lowered.contains('/') {
+            if let Some(candidates) = self.by_stem.get(&lowered) {
+                let from_dir = self.notes[from].path.parent();
+                return candidates.iter().copied().min_by_key(|&idx| {
+                    let path = &self.notes[idx].path;
+                    (
+                        path.parent() != from_dir,
+                        path.components().count(),
+                        path.clone(),
+                    )
+                });
+            }
+        } else {
+            let wanted = lowered.trim_start_matches('/');
+            // A full vault-relative path first, then the shortest path ending
+            // in the partial one, e.g. `[[sub/Note]]` for `area/sub/Note.md`.
+            let by_rel = self.by_rel.get(wanted).copied().or_else(|| {
+                self.by_rel_suffix
+                    .get(wanted)?
+                    .iter()
+                    .copied()
+                    .min_by_key(|&idx| {
+                        let path = &self.notes[idx].path;
+                        (path.components().count(), path.clone())
+                    })
+            });
+            if by_rel.is_some() {
+                return by_rel;
+            }
+        }
+
+        // The first note with the title or the alias, as read.
+        let by_title = self.by_title.get(&lowered).copied();
+        let by_alias = self.by_alias.get(&lowered).copied();
+        by_title.into_iter().chain(by_alias).min()
+    }
+
+    /// Resolves a relative (or vault-rooted `/`) markdown link target.
+    pub fn resolve_markdown_link(&self, from: usize, target: &str) -> Option<usize> {
+        let decoded = links::decode_target(target);
+        let note = &self.notes[from].path;
+        let path = match decoded.strip_prefix('/') {
+            Some(rooted) => self.root.join(rooted),
+            None => note.parent().unwrap_or(&self.root).join(decoded),
+        };
+        self.by_path.get(&normalize_path(&path)).copied()
+    }
+
+    /// The note a link in `from` points at.
+    pub fn resolve(&self, from: usize, link: &NoteLink) -> Option<usize> {
+        match link.kind {
+            LinkKind::Wiki => self.resolve_wikilink(from, &link.target),
+            LinkKind::Markdown => self.resolve_markdown_link(from, &link.target),
+        }
+    }
+
+    /// Notes that `from` links to, deduplicated, excluding itself.
+    pub fn outgoing(&self, from: usize) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        self.notes[from]
+            .links
+            .iter()
+            .filter_map(|link| self.resolve(from, link))
+            .filter(|&to| to != from && seen.insert(to))
+            .collect()
+    }
+
+    /// Number of distinct notes linking to each note, by index.
+    pub fn backlink_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.notes.len()];
+        for from in 0..self.notes.len() {
+            for to in self.outgoing(from) {
+                counts[to] += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Reads a note and extracts what the index needs from it.
+pub fn index_note(path: &Path) -> Option<IndexedNote> {
+    let content = fs::read_to_string(path).ok()?;
+    let modified = fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Some(index_content(path, &content, modified))
+}
+
+/// What the index needs from a note at `path` holding `content`, which
+/// needn't be what is on disk, e.g. the note as it was in an old commit.
+pub fn index_content(path: &Path, content: &str, modified: Option<u64>) -> IndexedNote {
+    let (fm, split) = frontmatter::parse_note(content)
+        .unwrap_or_else(|_| (Map::new(), frontmatter::split(content)));
+
+    let lines: Vec<&str> = split.body.lines().collect();
+    let in_code = code_block_lines(&lines);
+    let mut note_links = Vec::new();
+    for (idx, (line, code)) in lines.iter().zip(in_code).enumerate() {
+        if code || !(line.contains("[[") || line.contains("](")) {
+            continue;
+        }
+        let scrubbed = blank_code_spans(line);
+        let note_link = |kind, target, start, end, embed| NoteLink {
+            kind,
+            target,
+            text: line[start..end].to_string(),
+            embed,
+            line: split.body_start_line + idx + 1,
+            line_content: line.trim().to_string(),
+        };
+        for link in links::wikilinks(&scrubbed) {
+            note_links.push(note_link(
+                LinkKind::Wiki,
+                link.target,
+                link.start,
+                link.end,
+                link.embed,
+            ));
+        }
+        for link in links::markdown_links(&scrubbed) {
+            if !links::is_external(&link.target)
+                && super::is_markdown(Path::new(&links::decode_target(&link.target)))
+            {
+                let embed = line[link.start..].starts_with('!');
+                note_links.push(note_link(
+                    LinkKind::Markdown,
+                    link.target,
+                    link.start,
+                    link.end,
+                    embed,
+                ));
+            }
+        }
+    }
+
+    IndexedNote {
+        path: path.to_path_buf(),
+        title: markdown::note_title(path, &fm, split.body),
+        aliases: frontmatter::string_list(fm.get("aliases").or_else(|| fm.get("alias"))),
+        tags: tags::note_tags(&fm, content),
+        word_count: markdown::word_count(content),
+        frontmatter: fm,
+        modified,
+        links: note_links,
+    }
+}
+
+/// Collapses `.` and `..` components without touching the filesystem, so
+/// link targets compare equal to the paths found by the vault walk.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(notes: &[(&str, &str)]) -> VaultIndex {
+        let root = Path::new("/vault");
+        let notes = notes
+            .iter()
+            .map(|(path, content)| index_content(&root.join(path), content, None))
+            .collect();
+        VaultIndex::from_notes(root, notes)
+    }
+
+    fn resolved(index: &VaultIndex, target: &str) -> Option<PathBuf> {
+        index
+            .resolve_wikilink(0, target)
+            .map(|idx| index.notes[idx].path.clone())
+    }
+
+    #[test]
+    fn path_style_links_match_the_full_path_then_the_shortest_ending() {
+        let index = index(&[
+            ("Home.md", ""),
+            ("area/sub/Note.md", ""),
+            ("sub/Note.md", ""),
+            ("deep/area/sub/Note.md", ""),
+        ]);
+        assert_eq!(
+            resolved(&index, "Area/Sub/Note"),
+            Some("/vault/area/sub/Note.md".into())
+        );
+        assert_eq!(
+            resolved(&index, "sub/note.md"),
+            Some("/vault/sub/Note.md".into())
+        );
+        assert_eq!(
+            resolved(&index, "/area/sub/Note"),
+            Some("/vault/area/sub/Note.md".into())
+        );
+        assert_eq!(resolved(&index, "other/Note"), None);
+    }
+
+    #[test]
+    fn titles_and_aliases_resolve_to_the_first_note_with_them() {
+        let index = index(&[
+            ("Home.md", ""),
+            ("a.md", "---\naliases: [Shared, Only Alias]\n---\n"),
+            ("b.md", "---\ntitle: Shared\naliases: [Other]\n---\n"),
+            ("c.md", "---\ntitle: Only Title\n---\n"),
+        ]);
+        assert_eq!(resolved(&index, "shared"), Some("/vault/a.md".into()));
+        assert_eq!(resolved(&index, "ONLY TITLE"), Some("/vault/c.md".into()));
+        assert_eq!(resolved(&index, "Other"), Some("/vault/b.md".into()));
+        assert_eq!(resolved(&index, "Nobody"), None);
+    }
+}
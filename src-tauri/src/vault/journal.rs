@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::settings::JournalSettings;
+use super::{hash_file, now_secs, read_json, relative_path, state_dir, write_json};
+
+const JOURNAL_DIR: &str = "journal";
+const OPERATION_FILE: &str = "operation.json";
+
+/// One change made by an operation, with what undoing it needs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    /// A file's content was replaced. `original` names the copy of the old
+    /// content in the operation's folder, or is `None` if the file was new.
+    Write {
+        path: String,
+        original: Option<String>,
+        /// Hash of the content written, to tell whether it changed since.
+        hash: String,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+}
+
+/// A destructive bulk operation, kept in `.graphnotes/journal/<id>/` so it
+/// can be undone. Paths are vault-relative.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Operation {
+    pub id: String,
+    /// The command that made it, e.g. `normalize_filenames`.
+    pub kind: String,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    /// In the order they were made.
+    pub changes: Vec<Change>,
+}
+
+/// Records an operation's changes as they are made. Nothing is stored until
+/// the first change, and `finish` only keeps the operation if there was one.
+pub struct Journal {
+    root: PathBuf,
+    operation: Operation,
+    dir: PathBuf,
+}
+
+impl Journal {
+    pub fn start(vault_path: &Path, kind: &str) -> Self {
+        let journal_dir = journal_dir(vault_path);
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut id = stamp.clone();
+        let mut n = 2;
+        while journal_dir.join(&id).exists() {
+            id = format!("{}-{}", stamp, n);
+            n += 1;
+        }
+        Self {
+            root: vault_path.to_path_buf(),
+            dir: journal_dir.join(&id),
+            operation: Operation {
+                id,
+                kind: kind.to_string(),
+                at: now_secs(),
+                changes: Vec::new(),
+            },
+        }
+    }
+
+    /// The command making the operation.
+    pub fn kind(&self) -> &str {
+        &self.operation.kind
+    }
+
+    /// Runs `write`, which replaces the content of `path`, keeping a copy of
+    /// what was there first.
+    pub fn write<E: From<String>>(
+        &mut self,
+        path: &Path,
+        write: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        let original = if path.is_file() {
+            let name = format!("{}.orig", self.operation.changes.len());
+            fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            fs::copy(path, self.dir.join(&name))
+                .map_err(|e| format!("Failed to keep original of {}: {}", path.display(), e))?;
+            Some(name)
+        } else {
+            None
+        };
+        if let Err(e) = write() {
+            if let Some(name) = &original {
+                let _ = fs::remove_file(self.dir.join(name));
+            }
+            return Err(e);
+        }
+        self.operation.changes.push(Change::Write {
+            path: relative_path(&self.root, path),
+            original,
+            hash: hash_file(path)?,
+        });
+        Ok(())
+    }
+
+    pub fn renamed(&mut self, from: &Path, to: &Path) {
+        self.operation.changes.push(Change::Rename {
+            from: relative_path(&self.root, from),
+            to: relative_path(&self.root, to),
+        });
+    }
+
+    /// Saves the operation and prunes old ones by the vault's `journal`
+    /// settings. Returns its id, or `None` when nothing changed.
+    pub fn finish(self, settings: &JournalSettings) -> Result<Option<String>, String> {
+        if self.operation.changes.is_empty() {
+            return Ok(None);
+        }
+        write_json(&self.dir.join(OPERATION_FILE), &self.operation)?;
+        prune(&self.root, settings, &self.operation.id);
+        Ok(Some(self.operation.id))
+    }
+}
+
+/// The vault's recorded operations, newest first. Unreadable ones are left
+/// out.
+pub fn operations(vault_path: &Path) -> Vec<Operation> {
+    let Ok(entries) = fs::read_dir(journal_dir(vault_path)) else {
+        return Vec::new();
+    };
+    let mut operations: Vec<Operation> = entries
+        .flatten()
+        .filter_map(|e| read_json::<Operation>(&e.path().join(OPERATION_FILE)).ok())
+        .filter(|op| !op.id.is_empty())
+        .collect();
+    operations.sort_by(|a, b| (b.at, &b.id).cmp(&(a.at, &a.id)));
+    operations
+}
+
+pub fn load(vault_path: &Path, id: &str) -> Result<Operation, String> {
+    let path = operation_dir(vault_path, id)?.join(OPERATION_FILE);
+    if !path.is_file() {
+        return Err(format!("No such operation: {}", id));
+    }
+    read_json(&path)
+}
+
+/// The folder holding operation `id`, refusing ids that aren't a plain name.
+pub fn operation_dir(vault_path: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err(format!("No such operation: {}", id));
+    }
+    Ok(journal_dir(vault_path).join(id))
+}
+
+pub fn remove(vault_path: &Path, id: &str) -> Result<(), String> {
+    let dir = operation_dir(vault_path, id)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))
+}
+
+fn journal_dir(vault_path: &Path) -> PathBuf {
+    state_dir(vault_path).join(JOURNAL_DIR)
+}
+
+/// Deletes operations older than `max_age_days`, then the oldest until the
+/// journal fits in `max_megabytes`. The operation just made is kept either
+/// way; zero turns a limit off.
+fn prune(vault_path: &Path, settings: &JournalSettings, keep: &str) {
+    let mut operations = operations(vault_path);
+    operations.retain(|op| op.id != keep);
+    let oldest_kept = now_secs().saturating_sub(settings.max_age_days * 24 * 60 * 60);
+    let dir_size = |id: &str| {
+        super::files_where(&journal_dir(vault_path).join(id), |_| true)
+            .iter()
+            .filter_map(|p| fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum::<u64>()
+    };
+    let mut total: u64 = dir_size(keep) + operations.iter().map(|op| dir_size(&op.id)).sum::<u64>();
+    let max_bytes = settings.max_megabytes * 1024 * 1024;
+    // Oldest first.
+    for op in operations.iter().rev() {
+        let too_old = settings.max_age_days > 0 && op.at < oldest_kept;
+        let too_big = settings.max_megabytes > 0 && total > max_bytes;
+        if !too_old && !too_big {
+            continue;
+        }
+        total = total.saturating_sub(dir_size(&op.id));
+        let _ = remove(vault_path, &op.id);
+    }
+}
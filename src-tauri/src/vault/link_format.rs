@@ -0,0 +1,138 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::settings::{LinkFormat, LinkPath, LinkStyle, VaultSettings};
+use super::{files_where, is_markdown, relative_link, relative_path};
+use crate::markdown::links;
+
+/// Writes links from notes in the vault in its configured format.
+///
+/// Wikilinks use the bare note or file name with `LinkPath::Shortest` when
+/// no other file in the vault shares it, and the path from the vault root
+/// otherwise. Markdown links use the path relative to the linking note; with
+/// `Shortest` a vault-rooted `/path` is used instead when that is shorter.
+pub struct LinkWriter<'a> {
+    root: &'a Path,
+    format: LinkFormat,
+    /// How many files have each lowercased name (note stem or file name).
+    names: OnceCell<HashMap<String, usize>>,
+}
+
+impl<'a> LinkWriter<'a> {
+    pub fn new(root: &'a Path, format: &LinkFormat) -> Self {
+        Self {
+            root,
+            format: format.clone(),
+            names: OnceCell::new(),
+        }
+    }
+
+    pub fn is_wiki(&self) -> bool {
+        self.format.style == LinkStyle::Wikilink
+    }
+
+    /// A link from a note in `from_dir` to `to`, showing `alias` when given.
+    pub fn link(&self, from_dir: &Path, to: &Path, alias: Option<&str>) -> String {
+        match self.format.style {
+            LinkStyle::Wikilink => match alias {
+                Some(alias) if alias != display_name(to) => {
+                    format!("[[{}|{}]]", self.wiki_target(to), alias)
+                }
+                _ => format!("[[{}]]", self.wiki_target(to)),
+            },
+            LinkStyle::Markdown => {
+                let text = alias
+                    .map(str::to_string)
+                    .unwrap_or_else(|| display_name(to));
+                format!(
+                    "[{}]({})",
+                    text.replace('[', "\\[").replace(']', "\\]"),
+                    self.markdown_target(from_dir, to)
+                )
+            }
+        }
+    }
+
+    /// An embed of `to`, e.g. an image; `alt` is only used by markdown links.
+    pub fn embed(&self, from_dir: &Path, to: &Path, alt: Option<&str>) -> String {
+        match self.format.style {
+            LinkStyle::Wikilink => format!("![[{}]]", self.wiki_target(to)),
+            LinkStyle::Markdown => format!(
+                "!{}",
+                self.link(from_dir, to, Some(alt.unwrap_or_default()))
+            ),
+        }
+    }
+
+    /// The target part of a link to `to` alone, for callers that write the
+    /// surrounding syntax themselves.
+    pub fn target(&self, from_dir: &Path, to: &Path) -> String {
+        match self.format.style {
+            LinkStyle::Wikilink => self.wiki_target(to),
+            LinkStyle::Markdown => self.markdown_target(from_dir, to),
+        }
+    }
+
+    /// What goes inside `[[...]]` for `to`: the name for a note is its stem.
+    pub fn wiki_target(&self, to: &Path) -> String {
+        let rel = relative_path(self.root, to);
+        let name = display_name(to);
+        let unique = self
+            .names()
+            .get(&name.to_lowercase())
+            .is_none_or(|count| *count <= 1);
+        if self.format.path == LinkPath::Shortest && unique {
+            return name;
+        }
+        match rel.rsplit_once('.') {
+            Some((stem, _)) if is_markdown(to) => stem.to_string(),
+            _ => rel,
+        }
+    }
+
+    /// The encoded target of a markdown link from `from_dir` to `to`.
+    pub fn markdown_target(&self, from_dir: &Path, to: &Path) -> String {
+        let relative = relative_link(from_dir, to);
+        let rooted = format!("/{}", relative_path(self.root, to));
+        let target = if self.format.path == LinkPath::Shortest && rooted.len() < relative.len() {
+            rooted
+        } else {
+            relative
+        };
+        links::encode_target(&target)
+    }
+
+    fn names(&self) -> &HashMap<String, usize> {
+        self.names.get_or_init(|| {
+            let mut names = HashMap::new();
+            for file in files_where(self.root, |_| true) {
+                *names.entry(display_name(&file).to_lowercase()).or_insert(0) += 1;
+            }
+            names
+        })
+    }
+}
+
+/// A link from `from_note` to `to` in the vault's link format.
+pub fn format_link(
+    root: &Path,
+    settings: &VaultSettings,
+    from_note: &Path,
+    to: &Path,
+    alias: Option<&str>,
+) -> String {
+    let from_dir = from_note.parent().unwrap_or(root);
+    LinkWriter::new(root, &settings.link_format).link(from_dir, to, alias)
+}
+
+/// The text a link shows by default: a note's stem or a file's name.
+fn display_name(path: &Path) -> String {
+    let name = if is_markdown(path) {
+        path.file_stem()
+    } else {
+        path.file_name()
+    };
+    name.map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::cache_crypto::CacheMode;
+use super::index::{self, IndexedNote, VaultIndex};
+use super::{now_secs, relative_path, state_dir};
+
+const LINK_INDEX_FILE: &str = "link-index.json";
+/// Bumped whenever `IndexedNote` changes shape, so old indexes are rebuilt.
+const VERSION: u32 = 1;
+
+/// Notes `refreshed` took from the stored index, and notes it had to read,
+/// across every vault since start-up.
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// What a note's file looked like when it was indexed. A note whose mtime
+/// or size differs has changed since and is read again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    /// Milliseconds since the Unix epoch.
+    modified_ms: u64,
+    size: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        let modified = meta
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        Some(Self {
+            modified_ms: modified.as_millis() as u64,
+            size: meta.len(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    stamp: Stamp,
+    note: IndexedNote,
+}
+
+/// Indexed notes keyed by vault-relative path, in
+/// `.graphnotes/link-index.json`. Queries go through `refreshed`, which
+/// reads only the notes that changed since they were last indexed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LinkIndex {
+    version: u32,
+    /// Seconds since the Unix epoch; zero until first built.
+    updated_at: u64,
+    files: BTreeMap<String, Entry>,
+}
+
+impl LinkIndex {
+    fn load(vault_path: &Path, mode: &CacheMode) -> Result<Self, String> {
+        let index: Self = mode.read_json(&index_path(vault_path))?;
+        Ok(if index.version == VERSION {
+            index
+        } else {
+            Self::default()
+        })
+    }
+
+    fn save(&self, vault_path: &Path, mode: &CacheMode) -> Result<(), String> {
+        mode.write_json(&index_path(vault_path), self)
+    }
+
+    fn is_fresh(&self, rel: &str, stamp: Option<Stamp>) -> bool {
+        stamp.is_some() && self.files.get(rel).map(|entry| entry.stamp) == stamp
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexStatus {
+    /// Notes in the vault.
+    pub notes: usize,
+    /// Notes whose index entry matches the file.
+    pub fresh: usize,
+    /// Notes that are new or changed since they were indexed.
+    pub stale: Vec<String>,
+    /// Indexed notes that no longer exist.
+    pub removed: Vec<String>,
+    /// When the index was last written, in seconds since the Unix epoch.
+    pub updated_at: Option<u64>,
+    pub up_to_date: bool,
+}
+
+pub fn index_path(vault_path: &Path) -> PathBuf {
+    state_dir(vault_path).join(LINK_INDEX_FILE)
+}
+
+/// How far the stored index is behind `files`, without reading any notes.
+/// While the vault's caches are locked every note counts as stale.
+pub fn status(
+    vault_path: &Path,
+    files: &[PathBuf],
+    mode: &CacheMode,
+) -> Result<IndexStatus, String> {
+    let index = LinkIndex::load(vault_path, mode)?;
+    let mut seen = HashSet::new();
+    let mut stale = Vec::new();
+    for file in files {
+        let rel = relative_path(vault_path, file);
+        if !index.is_fresh(&rel, Stamp::of(file)) {
+            stale.push(rel.clone());
+        }
+        seen.insert(rel);
+    }
+    let removed: Vec<String> = index
+        .files
+        .keys()
+        .filter(|rel| !seen.contains(*rel))
+        .cloned()
+        .collect();
+    Ok(IndexStatus {
+        notes: files.len(),
+        fresh: files.len() - stale.len(),
+        up_to_date: stale.is_empty() && removed.is_empty(),
+        stale,
+        removed,
+        updated_at: Some(index.updated_at).filter(|at| *at > 0),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Notes in the stored index.
+    pub entries: usize,
+    /// Size of the stored index file.
+    pub bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of notes served from the index, once any were asked for.
+    pub hit_rate: Option<f64>,
+}
+
+/// The size of the stored index and how often it saved reading a note.
+pub fn cache_stats(vault_path: &Path, mode: &CacheMode) -> Result<CacheStats, String> {
+    let index = LinkIndex::load(vault_path, mode)?;
+    let bytes = fs::metadata(index_path(vault_path))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let hits = HITS.load(Ordering::Relaxed);
+    let misses = MISSES.load(Ordering::Relaxed);
+    Ok(CacheStats {
+        entries: index.files.len(),
+        bytes,
+        hits,
+        misses,
+        hit_rate: Some(hits + misses)
+            .filter(|n| *n > 0)
+            .map(|n| hits as f64 / n as f64),
+    })
+}
+
+pub fn reset_cache_stats() {
+    HITS.store(0, Ordering::Relaxed);
+    MISSES.store(0, Ordering::Relaxed);
+}
+
+/// The index of `files`, bringing the stored one up to date first: new and
+/// changed notes are read again and deleted ones dropped. While the vault's
+/// caches are locked every note is read and nothing is stored.
+pub fn refreshed(
+    vault_path: &Path,
+    files: &[PathBuf],
+    mode: &CacheMode,
+) -> Result<VaultIndex, String> {
+    if mode.is_locked() {
+        MISSES.fetch_add(files.len() as u64, Ordering::Relaxed);
+        return Ok(VaultIndex::build(vault_path, files));
+    }
+    let mut index = LinkIndex::load(vault_path, mode)?;
+    let mut seen = HashSet::new();
+    let mut changed = false;
+    for file in files {
+        let rel = relative_path(vault_path, file);
+        let stamp = Stamp::of(file);
+        if index.is_fresh(&rel, stamp) {
+            HITS.fetch_add(1, Ordering::Relaxed);
+        } else {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            changed = true;
+            match stamp.zip(index::index_note(file)) {
+                Some((stamp, note)) => {
+                    index.files.insert(rel.clone(), Entry { stamp, note });
+                }
+                None => {
+                    index.files.remove(&rel);
+                }
+            }
+        }
+        seen.insert(rel);
+    }
+    let before = index.files.len();
+    index.files.retain(|rel, _| seen.contains(rel));
+    changed |= index.files.len() != before;
+
+    if changed || index.version != VERSION {
+        index.version = VERSION;
+        index.updated_at = now_secs();
+        // The index is only a cache, so a vault that can't be written to
+        // still gets answers, just without the saved work.
+        let _ = index.save(vault_path, mode);
+    }
+
+    let notes = files
+        .iter()
+        .filter_map(|file| {
+            let entry = index.files.remove(&relative_path(vault_path, file))?;
+            Some(IndexedNote {
+                path: file.clone(),
+                ..entry.note
+            })
+        })
+        .collect();
+    Ok(VaultIndex::from_notes(vault_path, notes))
+}
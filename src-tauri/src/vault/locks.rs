@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::{external, read_json, relative_path, state_dir, write_json};
+
+const LOCKS_FILE: &str = "locks.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LockList {
+    /// Vault-relative paths of locked notes.
+    locked: Vec<String>,
+}
+
+fn load(vault_path: &Path) -> LockList {
+    read_json(&state_dir(vault_path).join(LOCKS_FILE)).unwrap_or_default()
+}
+
+fn is_read_only(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().readonly())
+}
+
+/// A note is locked when it is listed in `.graphnotes/locks.json`, its
+/// read-only bit is set or it is in a folder opened read-only; any alone is
+/// enough.
+pub fn is_locked(vault_path: Option<&Path>, path: &Path) -> bool {
+    if is_read_only(path) || external::is_read_only(path) {
+        return true;
+    }
+    match vault_path {
+        Some(root) => {
+            let rel = relative_path(root, path);
+            load(root).locked.contains(&rel)
+        }
+        None => false,
+    }
+}
+
+/// Locked notes at or below `path`, for operations on whole folders.
+pub fn locked_within(vault_path: Option<&Path>, path: &Path) -> Vec<String> {
+    if path.is_file() || external::is_read_only(path) {
+        return if is_locked(vault_path, path) {
+            vec![path.to_string_lossy().to_string()]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut locked: Vec<String> = super::files_where(path, is_read_only)
+        .into_iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    if let Some(root) = vault_path {
+        for rel in load(root).locked {
+            let full = root.join(&rel);
+            let full_display = full.to_string_lossy().to_string();
+            if full.starts_with(path) && !locked.contains(&full_display) {
+                locked.push(full_display);
+            }
+        }
+    }
+    locked
+}
+
+/// Records the lock in the lock list and mirrors it in the file's read-only
+/// bit, except in a folder opened read-only, which is left as it is.
+/// Failing to change the permission bit is tolerated because the lock
+/// list alone is enforced.
+pub fn set_locked(vault_path: &Path, path: &Path, locked: bool) -> Result<(), String> {
+    let rel = relative_path(vault_path, path);
+    let mut list = load(vault_path);
+    list.locked.retain(|p| *p != rel);
+    if locked {
+        list.locked.push(rel);
+        list.locked.sort();
+    }
+    write_json(&state_dir(vault_path).join(LOCKS_FILE), &list)?;
+
+    if external::is_read_only(path) {
+        return Ok(());
+    }
+    if let Ok(metadata) = fs::metadata(path) {
+        let mut permissions = metadata.permissions();
+        set_writable(&mut permissions, !locked);
+        let _ = fs::set_permissions(path, permissions);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_writable(permissions: &mut fs::Permissions, writable: bool) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.mode();
+    permissions.set_mode(if writable {
+        mode | 0o200
+    } else {
+        mode & !0o222
+    });
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::permissions_set_readonly_false)]
+fn set_writable(permissions: &mut fs::Permissions, writable: bool) {
+    permissions.set_readonly(!writable);
+}
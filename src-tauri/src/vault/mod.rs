@@ -0,0 +1,334 @@
+pub mod cache_crypto;
+pub mod encoding;
+pub mod external;
+pub mod frecency;
+pub mod goals;
+pub mod health;
+pub mod index;
+pub mod journal;
+pub mod link_format;
+pub mod link_index;
+pub mod locks;
+pub mod own_writes;
+pub mod portable;
+pub mod positions;
+pub mod reminders;
+pub mod renames;
+pub mod schemas;
+pub mod settings;
+pub mod write_ledger;
+pub mod write_locks;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::files::write_atomic;
+use settings::VaultSettings;
+
+/// Directory inside a vault holding GraphNotes' own state.
+pub const STATE_DIR: &str = ".graphnotes";
+
+/// Where the vault's state is kept: `.graphnotes` inside it, or for a
+/// folder opened as an external vault a directory in the app's data.
+pub fn state_dir(vault_path: &Path) -> PathBuf {
+    external::state_dir(vault_path).unwrap_or_else(|| vault_path.join(STATE_DIR))
+}
+
+/// Finds the vault containing `path` by looking for the nearest ancestor
+/// with a state directory.
+pub fn find_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| state_dir(dir).is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Path relative to the vault root with `/` separators, as used by ignore
+/// patterns and in stored state.
+pub fn relative_path(vault_path: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(vault_path).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether the relative path `key` is `prefix` itself or lies below it.
+pub fn is_within(key: &str, prefix: &str) -> bool {
+    key == prefix
+        || key
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Seconds since the Unix epoch, as stored in state files.
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reads a JSON state file, returning the default value when it is missing.
+pub fn read_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T, String> {
+    match fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(T::default()),
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Writes a JSON state file atomically, creating its directory if needed.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    write_atomic(path, json)
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref(),
+        Some("md") | Some("markdown")
+    )
+}
+
+pub fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// Recursively collects markdown files under `root`, skipping hidden files
+/// and directories. Symlinked directories are not followed so link cycles
+/// can't hang the walk. Results are sorted by path.
+pub fn markdown_files(root: &Path) -> Vec<PathBuf> {
+    files_where(root, is_markdown)
+}
+
+/// Like `markdown_files`, but collects every non-hidden file accepted by
+/// `accept`.
+pub fn files_where(root: &Path, accept: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(root, &accept, &mut files);
+    files.sort();
+    files
+}
+
+fn collect_files(dir: &Path, accept: &dyn Fn(&Path) -> bool, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_hidden(&name) {
+            continue;
+        }
+        let file_type = match entry.file_type() {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_files(&path, accept, files);
+        } else if accept(&path) && path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+/// SHA-256 of a file's contents as lowercase hex, read in chunks so large
+/// attachments aren't loaded into memory.
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `2.4 MB`, in powers of 1024 as file managers show sizes.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Path of `target` relative to the directory `from_dir`, with `/`
+/// separators, suitable for a markdown link.
+pub fn relative_link(from_dir: &Path, target: &Path) -> String {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = target.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> =
+        std::iter::repeat_n("..".to_string(), from.len() - common).collect();
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+/// Turns a title into a file name that is valid on every platform and
+/// won't break wikilinks: path separators and `:*?"<>|#^[]` become `-`.
+pub fn safe_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned: String = cleaned
+        .trim_matches(['.', ' ', '-'])
+        .chars()
+        .take(120)
+        .collect();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Where new attachments for `note` go, by the `attachment_folder` setting:
+/// the vault root when it is unset or empty, a folder beside the note when
+/// it starts with `./`, and otherwise a folder from the vault root.
+pub fn attachment_dir(vault_path: &Path, settings: &VaultSettings, note: &Path) -> PathBuf {
+    let folder = settings
+        .attachment_folder
+        .as_deref()
+        .unwrap_or_default()
+        .trim();
+    let (base, folder) = match folder.strip_prefix("./").or((folder == ".").then_some("")) {
+        Some(beside) => (note.parent().unwrap_or(vault_path), beside),
+        None => (vault_path, folder),
+    };
+    match folder.trim_matches('/') {
+        "" => base.to_path_buf(),
+        folder => base.join(folder),
+    }
+}
+
+/// `dir/stem.extension`, or `dir/stem 2.extension` and so on when that name
+/// is taken.
+pub fn unique_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let with_extension = |name: String| {
+        if extension.is_empty() {
+            name
+        } else {
+            format!("{}.{}", name, extension)
+        }
+    };
+    let mut candidate = dir.join(with_extension(stem.to_string()));
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = dir.join(with_extension(format!("{} {}", stem, n)));
+        n += 1;
+    }
+    candidate
+}
+
+/// Decides which notes vault-wide commands should see, based on the vault's
+/// ignore patterns and private folders.
+pub struct NoteFilter {
+    root: PathBuf,
+    ignore: Vec<glob::Pattern>,
+    private: Vec<glob::Pattern>,
+}
+
+impl NoteFilter {
+    pub fn new(vault_path: &Path, settings: &VaultSettings) -> Self {
+        let patterns = |list: &[String]| {
+            list.iter()
+                .filter_map(|p| glob::Pattern::new(p.trim_matches('/')).ok())
+                .collect()
+        };
+        Self {
+            root: vault_path.to_path_buf(),
+            ignore: patterns(&settings.ignore_patterns),
+            private: patterns(&settings.private_folders),
+        }
+    }
+
+    /// A path is ignored when a pattern matches it or any of its parent
+    /// folders, so `drafts` excludes everything below `drafts/`. Notes in
+    /// private folders are ignored too.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.matches(&self.ignore, path) || self.is_private(path)
+    }
+
+    pub fn is_private(&self, path: &Path) -> bool {
+        self.matches(&self.private, path)
+    }
+
+    fn matches(&self, patterns: &[glob::Pattern], path: &Path) -> bool {
+        if patterns.is_empty() {
+            return false;
+        }
+        let rel = relative_path(&self.root, path);
+        let mut prefixes = rel
+            .match_indices('/')
+            .map(|(idx, _)| &rel[..idx])
+            .chain([rel.as_str()]);
+        prefixes.any(|prefix| patterns.iter().any(|p| p.matches(prefix)))
+    }
+}
+
+/// Markdown files in the vault that aren't excluded by its settings, either
+/// by an ignore pattern or by being in a private folder.
+pub fn notes(vault_path: &Path, settings: &VaultSettings) -> Vec<PathBuf> {
+    let filter = NoteFilter::new(vault_path, settings);
+    markdown_files(vault_path)
+        .into_iter()
+        .filter(|p| !filter.is_ignored(p))
+        .collect()
+}
+
+/// Every non-hidden file under `dir` accepted by `accept`, minus those in
+/// the vault's private folders unless `include_private` is set.
+pub fn visible_files(
+    vault_path: &Path,
+    dir: &Path,
+    include_private: bool,
+    accept: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>, String> {
+    let filter = NoteFilter::new(vault_path, &VaultSettings::load(vault_path)?);
+    Ok(files_where(dir, |p| {
+        accept(p) && (include_private || !filter.is_private(p))
+    }))
+}
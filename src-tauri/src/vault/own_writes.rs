@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a write is remembered; the watcher reports well within it.
+const FORGET_AFTER: Duration = Duration::from_secs(30);
+
+/// One for the whole app, like the write locks: files are written from
+/// helpers that have no app handle to reach managed state through.
+static OWN_WRITES: LazyLock<OwnWrites> = LazyLock::new(OwnWrites::default);
+
+/// What the app's own writes and renames left on disk, by canonical path,
+/// so the watcher can tell their echoes from changes made elsewhere.
+#[derive(Default)]
+pub struct OwnWrites {
+    written: Mutex<HashMap<PathBuf, Mark>>,
+}
+
+struct Mark {
+    modified: SystemTime,
+    size: u64,
+    at: Instant,
+}
+
+impl OwnWrites {
+    pub fn global() -> &'static OwnWrites {
+        &OWN_WRITES
+    }
+
+    /// Remembers the file or folder at `path` as the app just left it.
+    pub fn record(&self, path: &Path) {
+        let (Ok(key), Some((modified, size))) = (fs::canonicalize(path), stamp(path)) else {
+            return;
+        };
+        let mut written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        written.retain(|_, mark| mark.at.elapsed() < FORGET_AFTER);
+        written.insert(
+            key,
+            Mark {
+                modified,
+                size,
+                at: Instant::now(),
+            },
+        );
+    }
+
+    /// Whether `path` is still exactly as an app write recently left it.
+    /// The first change made elsewhere makes it forget the write.
+    pub fn is_own(&self, path: &Path) -> bool {
+        let Ok(key) = fs::canonicalize(path) else {
+            return false;
+        };
+        let mut written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(mark) = written.get(&key) else {
+            return false;
+        };
+        let own =
+            mark.at.elapsed() < FORGET_AFTER && stamp(path) == Some((mark.modified, mark.size));
+        if !own {
+            written.remove(&key);
+        }
+        own
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempVault;
+
+    #[test]
+    fn a_write_is_own_until_changed_elsewhere() {
+        let root = TempVault::new("own-writes", &[("Note.md", "ours\n")]);
+        let note = root.join("Note.md");
+        let own_writes = OwnWrites::default();
+        assert!(!own_writes.is_own(&note));
+
+        own_writes.record(&note);
+        assert!(own_writes.is_own(&note));
+        // The watcher reports canonical paths; callers may not.
+        assert!(own_writes.is_own(&root.join(".").join("Note.md")));
+
+        fs::write(&note, "edited elsewhere\n").unwrap();
+        assert!(!own_writes.is_own(&note));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_write_through_a_symlinked_vault_matches_its_real_path() {
+        let root = TempVault::new("own-writes-real", &[("Note.md", "ours\n")]);
+        let link = TempVault::new("own-writes-link", &[]);
+        let vault = link.join("vault");
+        std::os::unix::fs::symlink(&*root, &vault).unwrap();
+        let own_writes = OwnWrites::default();
+
+        own_writes.record(&vault.join("Note.md"));
+        let real = fs::canonicalize(root.join("Note.md")).unwrap();
+        assert!(own_writes.is_own(&real));
+    }
+}
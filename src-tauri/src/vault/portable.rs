@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+const WINDOWS_RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+const WINDOWS_FORBIDDEN: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Longest file or folder name most filesystems store, in bytes.
+pub const MAX_NAME_BYTES: usize = 255;
+/// Longest vault-relative path that stays under Windows' 260-character
+/// limit, leaving room for where the vault itself lives.
+pub const MAX_RELATIVE_PATH: usize = 200;
+
+/// A rule a path breaks on at least one of Windows, macOS and Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathRule {
+    ForbiddenCharacter,
+    TrailingDotOrSpace,
+    ReservedName,
+    NameTooLong,
+    PathTooLong,
+    CaseCollision,
+}
+
+/// The rules a single file or folder name breaks, with a message for each.
+pub fn name_problems(name: &str) -> Vec<(PathRule, String)> {
+    let mut problems = Vec::new();
+    if let Some(c) = name
+        .chars()
+        .find(|c| WINDOWS_FORBIDDEN.contains(c) || c.is_control())
+    {
+        problems.push((
+            PathRule::ForbiddenCharacter,
+            format!("\"{}\" contains {:?}, which Windows doesn't allow", name, c),
+        ));
+    }
+    if name.ends_with(['.', ' ']) {
+        problems.push((
+            PathRule::TrailingDotOrSpace,
+            format!("\"{}\" ends with a dot or space, which Windows drops", name),
+        ));
+    }
+    if is_reserved(name) {
+        problems.push((
+            PathRule::ReservedName,
+            format!("\"{}\" is a reserved device name on Windows", name),
+        ));
+    }
+    if name.len() > MAX_NAME_BYTES {
+        problems.push((
+            PathRule::NameTooLong,
+            format!(
+                "Name is {} bytes, over the {} most filesystems allow",
+                name.len(),
+                MAX_NAME_BYTES
+            ),
+        ));
+    }
+    problems
+}
+
+/// `name` changed just enough to be stored everywhere: forbidden characters
+/// become `-`, trailing dots and spaces go, reserved device names get a `_`
+/// and overlong names are shortened, keeping the extension.
+pub fn safe_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if WINDOWS_FORBIDDEN.contains(&c) || c.is_control() {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let mut safe = replaced.trim_end_matches(['.', ' ']).to_string();
+    if is_reserved(&safe) {
+        let end = safe.find('.').unwrap_or(safe.len());
+        safe.insert(end, '_');
+    }
+    if safe.len() > MAX_NAME_BYTES {
+        safe = shorten(&safe, safe.len() - MAX_NAME_BYTES);
+    }
+    if safe.is_empty() {
+        "Untitled".to_string()
+    } else {
+        safe
+    }
+}
+
+/// `name` with at least `bytes` bytes cut from the end of its stem, keeping
+/// some of the stem and the extension.
+pub fn shorten(name: &str, bytes: usize) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
+    };
+    let mut keep = stem.len().saturating_sub(bytes).max(stem.len().min(8));
+    while !stem.is_char_boundary(keep) {
+        keep -= 1;
+    }
+    let stem = stem[..keep].trim_end_matches(['.', ' ']);
+    match extension {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => stem.to_string(),
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    let stem = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_lowercase();
+    WINDOWS_RESERVED.contains(&stem.as_str())
+}
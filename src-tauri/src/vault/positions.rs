@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{is_within, now_secs, read_json, relative_path, state_dir, write_json};
+
+const POSITIONS_FILE: &str = "positions.json";
+/// Notes remembered at most; the longest unread are forgotten first.
+const MAX_ENTRIES: usize = 1000;
+
+/// Where reading a note stopped: a paragraph counted from a heading rather
+/// than a scroll offset, so it still points at the same text after edits
+/// elsewhere in the note.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadingAnchor {
+    /// The heading's slug, as `get_anchors` gives it; the start of the note
+    /// when unset.
+    pub heading: Option<String>,
+    /// Paragraphs below the heading; zero is the heading itself.
+    pub paragraph: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedPosition {
+    #[serde(flatten)]
+    anchor: ReadingAnchor,
+    /// Seconds since the Unix epoch.
+    saved_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PositionList {
+    /// Keyed by vault-relative path.
+    positions: BTreeMap<String, SavedPosition>,
+}
+
+fn load(vault_path: &Path) -> Result<PositionList, String> {
+    read_json(&state_dir(vault_path).join(POSITIONS_FILE))
+}
+
+fn save(vault_path: &Path, list: &PositionList) -> Result<(), String> {
+    write_json(&state_dir(vault_path).join(POSITIONS_FILE), list)
+}
+
+/// The note's saved anchor and when it was saved.
+pub fn get(vault_path: &Path, path: &Path) -> Result<Option<(ReadingAnchor, u64)>, String> {
+    Ok(load(vault_path)?
+        .positions
+        .remove(&relative_path(vault_path, path))
+        .map(|saved| (saved.anchor, saved.saved_at)))
+}
+
+pub fn set(vault_path: &Path, path: &Path, anchor: ReadingAnchor) -> Result<u64, String> {
+    let mut list = load(vault_path)?;
+    let saved_at = now_secs();
+    list.positions.insert(
+        relative_path(vault_path, path),
+        SavedPosition { anchor, saved_at },
+    );
+    if list.positions.len() > MAX_ENTRIES {
+        let mut by_age: Vec<(u64, String)> = list
+            .positions
+            .iter()
+            .map(|(key, saved)| (saved.saved_at, key.clone()))
+            .collect();
+        by_age.sort();
+        for (_, key) in by_age.into_iter().take(list.positions.len() - MAX_ENTRIES) {
+            list.positions.remove(&key);
+        }
+    }
+    save(vault_path, &list)?;
+    Ok(saved_at)
+}
+
+/// Moves positions at or below `old` (a note or a folder) to `new`.
+pub fn rename(vault_path: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let old_rel = relative_path(vault_path, old);
+    let new_rel = relative_path(vault_path, new);
+    let moved: Vec<String> = list
+        .positions
+        .keys()
+        .filter(|k| is_within(k, &old_rel))
+        .cloned()
+        .collect();
+    if moved.is_empty() {
+        return Ok(());
+    }
+    for key in moved {
+        let saved = list.positions.remove(&key).unwrap_or_default();
+        list.positions
+            .insert(format!("{}{}", new_rel, &key[old_rel.len()..]), saved);
+    }
+    save(vault_path, &list)
+}
+
+/// Drops positions at or below `path`.
+pub fn remove(vault_path: &Path, path: &Path) -> Result<(), String> {
+    let mut list = load(vault_path)?;
+    let rel = relative_path(vault_path, path);
+    let before = list.positions.len();
+    list.positions.retain(|k, _| !is_within(k, &rel));
+    if list.positions.len() == before {
+        return Ok(());
+    }
+    save(vault_path, &list)
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{read_json, state_dir, write_json};
+
+const REMINDERS_FILE: &str = "reminders.json";
+/// Entries for reminders due longer ago than this are forgotten.
+const KEEP_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// Which reminders have gone off, in `.graphnotes/reminders.json`, so a
+/// reminder fires once even across restarts. Keyed by vault-relative path
+/// and task text; each holds the due time, in seconds since the Unix
+/// epoch, of the last time that reminder fired. Moving a reminder later
+/// makes it due again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FiredReminders {
+    pub fired: BTreeMap<String, u64>,
+}
+
+impl FiredReminders {
+    pub fn load(vault_path: &Path) -> Result<Self, String> {
+        read_json(&state_dir(vault_path).join(REMINDERS_FILE))
+    }
+
+    pub fn save(&mut self, vault_path: &Path, now: u64) -> Result<(), String> {
+        self.fired.retain(|_, due| *due + KEEP_SECS > now);
+        write_json(&state_dir(vault_path).join(REMINDERS_FILE), self)
+    }
+
+    pub fn key(relative_path: &str, text: &str) -> String {
+        format!("{}#{}", relative_path, text)
+    }
+
+    /// Whether the reminder `key` due at `due` has already gone off.
+    pub fn has_fired(&self, key: &str, due: u64) -> bool {
+        self.fired.get(key).is_some_and(|fired| *fired >= due)
+    }
+
+    pub fn record(&mut self, key: String, due: u64) {
+        let fired = self.fired.entry(key).or_default();
+        *fired = (*fired).max(due);
+    }
+}
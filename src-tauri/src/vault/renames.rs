@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::{is_within, now_secs, read_json, relative_path, state_dir, write_json};
+
+const RENAMES_FILE: &str = "renames.json";
+/// Renames kept at most; the oldest are forgotten first.
+const MAX_ENTRIES: usize = 10_000;
+/// Renames followed at most when resolving one path.
+const MAX_CHAIN: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rename {
+    /// Vault-relative paths of the note or folder.
+    pub from: String,
+    pub to: String,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+}
+
+/// Every rename and move made through GraphNotes, oldest first, in
+/// `.graphnotes/renames.json`, so paths saved before a rename can still be
+/// found.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenameLog {
+    pub renames: Vec<Rename>,
+}
+
+impl RenameLog {
+    pub fn load(vault_path: &Path) -> Result<Self, String> {
+        read_json(&state_dir(vault_path).join(RENAMES_FILE))
+    }
+
+    fn save(&self, vault_path: &Path) -> Result<(), String> {
+        write_json(&state_dir(vault_path).join(RENAMES_FILE), self)
+    }
+
+    /// Where the vault-relative `path` ended up after the renames made
+    /// between `since` and `until`, in seconds since the Unix epoch. Renames
+    /// apply in the order they were made and each at most once, so a note
+    /// renamed back and forth ends where it was last put rather than looping.
+    pub fn follow(&self, path: &str, since: u64, until: u64) -> String {
+        let mut current = path.to_string();
+        let mut steps = 0;
+        for rename in self
+            .renames
+            .iter()
+            .filter(|rename| (since..=until).contains(&rename.at))
+        {
+            if steps == MAX_CHAIN {
+                break;
+            }
+            if is_within(&current, &rename.from) {
+                current = format!("{}{}", rename.to, &current[rename.from.len()..]);
+                steps += 1;
+            }
+        }
+        current
+    }
+}
+
+/// Records that the note or folder at `old` is now at `new`.
+pub fn record(vault_path: &Path, old: &Path, new: &Path) -> Result<(), String> {
+    let mut log = RenameLog::load(vault_path)?;
+    log.renames.push(Rename {
+        from: relative_path(vault_path, old),
+        to: relative_path(vault_path, new),
+        at: now_secs(),
+    });
+    if log.renames.len() > MAX_ENTRIES {
+        let excess = log.renames.len() - MAX_ENTRIES;
+        log.renames.drain(..excess);
+    }
+    log.save(vault_path)
+}
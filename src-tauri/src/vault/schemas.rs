@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{read_json, state_dir};
+use crate::markdown::frontmatter;
+
+const SCHEMAS_FILE: &str = "schemas.json";
+
+/// Note type schemas from `.graphnotes/schemas.json`, keyed by the value of
+/// a note's `type` frontmatter field.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schemas(pub BTreeMap<String, NoteSchema>);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteSchema {
+    pub fields: BTreeMap<String, FieldSchema>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldSchema {
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    /// Allowed values for `enum` fields.
+    #[serde(default)]
+    pub values: Vec<String>,
+    /// Used when fixing a missing required field.
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Number,
+    Boolean,
+    Date,
+    List,
+    Enum,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    MissingField,
+    WrongType,
+    UnknownEnumValue,
+    InvalidFrontmatter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub note_type: String,
+    /// Empty for `invalid_frontmatter`.
+    pub field: String,
+    pub kind: ViolationKind,
+    pub message: String,
+}
+
+impl Schemas {
+    pub fn load(vault_path: &Path) -> Result<Self, String> {
+        read_json(&state_dir(vault_path).join(SCHEMAS_FILE))
+    }
+
+    /// Checks a note against the schema for its `type`. Notes without a
+    /// type, or with one that has no schema, always pass.
+    pub fn validate(&self, path: &Path, content: &str) -> Vec<SchemaViolation> {
+        let path = path.to_string_lossy().to_string();
+        let fm = match frontmatter::parse_note(content) {
+            Ok((fm, _)) => fm,
+            Err(message) => {
+                return vec![SchemaViolation {
+                    path,
+                    note_type: String::new(),
+                    field: String::new(),
+                    kind: ViolationKind::InvalidFrontmatter,
+                    message,
+                }]
+            }
+        };
+        let Some((note_type, schema)) = self.schema_for(&fm) else {
+            return Vec::new();
+        };
+
+        let mut violations = Vec::new();
+        for (field, spec) in &schema.fields {
+            let violation = |kind, message| SchemaViolation {
+                path: path.clone(),
+                note_type: note_type.to_string(),
+                field: field.clone(),
+                kind,
+                message,
+            };
+            match fm.get(field).filter(|v| !v.is_null()) {
+                None if spec.required => violations.push(violation(
+                    ViolationKind::MissingField,
+                    format!("Missing required field: {}", field),
+                )),
+                None => {}
+                Some(value) if !spec.field_type.accepts(value) => violations.push(violation(
+                    ViolationKind::WrongType,
+                    format!("Field {} should be a {}", field, spec.field_type.name()),
+                )),
+                Some(value) if spec.field_type == FieldType::Enum => {
+                    let text = scalar_text(value);
+                    if !spec.values.iter().any(|v| v.eq_ignore_ascii_case(&text)) {
+                        violations.push(violation(
+                            ViolationKind::UnknownEnumValue,
+                            format!(
+                                "Field {} has unknown value {} (expected one of {})",
+                                field,
+                                text,
+                                spec.values.join(", ")
+                            ),
+                        ));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        violations
+    }
+
+    pub fn schema_for<'a>(&'a self, fm: &Map<String, Value>) -> Option<(&'a str, &'a NoteSchema)> {
+        let note_type = fm.get("type").and_then(Value::as_str)?.trim();
+        self.0
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(note_type))
+            .map(|(name, schema)| (name.as_str(), schema))
+    }
+}
+
+impl FieldType {
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Number => value.is_number(),
+            FieldType::Boolean => value.is_boolean(),
+            FieldType::Date => frontmatter::parse_date(value).is_some(),
+            FieldType::List => value.is_array(),
+            FieldType::Enum => value.is_string() || value.is_number() || value.is_boolean(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Number => "number",
+            FieldType::Boolean => "boolean",
+            FieldType::Date => "date",
+            FieldType::List => "list",
+            FieldType::Enum => "enum",
+        }
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
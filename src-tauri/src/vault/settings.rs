@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+use super::{read_json, state_dir, write_json};
+use crate::markdown::normalize::WriteNormalization;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Backend vault settings, stored in `.graphnotes/settings.json`. Keys this
+/// version doesn't know about are kept so saving never drops them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    /// Glob patterns, relative to the vault root, for notes vault-wide
+    /// commands skip (e.g. `templates/**`).
+    pub ignore_patterns: Vec<String>,
+    /// Folders (relative to the vault root, globs allowed) kept out of
+    /// search, exports, indexes and reports. Their notes still open normally.
+    pub private_folders: Vec<String>,
+    /// Encrypt the link index and graph snapshots, which repeat note content,
+    /// with a key unlocked by `unlock_vault_caches`.
+    pub encrypt_caches: bool,
+    /// Where new attachments go: a vault-relative folder, empty for the
+    /// vault root, or starting with `./` for a folder beside the note.
+    pub attachment_folder: Option<String>,
+    pub backup: BackupSettings,
+    pub daily_notes: DailyNoteSettings,
+    pub graph_snapshots: GraphSnapshotSettings,
+    /// When the vault's disk counts as nearly full.
+    pub health: HealthSettings,
+    pub journal: JournalSettings,
+    /// How a folder's index note is named; folder notes are off when unset.
+    pub folder_notes: Option<FolderNoteStyle>,
+    pub lint: LintSettings,
+    /// How commands write new links.
+    pub link_format: LinkFormat,
+    pub link_check: LinkCheckSettings,
+    /// Where notes created from links to missing notes go.
+    pub new_notes: NewNoteSettings,
+    /// Shell-free commands that can be run on a note. Untrusted: none runs
+    /// until the user allows actions for the vault and approves it, which
+    /// is kept outside the vault.
+    pub note_actions: NoteActionSettings,
+    /// How fast notes are read, for reading-time estimates.
+    pub reading: ReadingSettings,
+    pub spellcheck: SpellcheckSettings,
+    pub templates: TemplateSettings,
+    /// Default whitespace normalization for `write_file`; none when unset.
+    pub write_normalization: Option<WriteNormalization>,
+    /// Record what every save leaves on disk, so `audit_external_changes`
+    /// can tell which files something else changed.
+    pub write_ledger: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Automatic zip backups while the app runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Hours between backups; zero turns them off.
+    pub interval_hours: u32,
+    pub destination_dir: Option<String>,
+    /// How many backups to keep; older ones are deleted.
+    pub keep_count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyNoteSettings {
+    pub folder: Option<String>,
+    /// Moment-style date format for the note's name, e.g. `YYYY-MM-DD`.
+    pub date_format: Option<String>,
+    /// Vault-relative template for new daily notes.
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphSnapshotSettings {
+    /// How many snapshots to keep; older ones are deleted. Zero keeps all.
+    pub keep_count: usize,
+}
+
+/// What the health monitor started with the watcher looks out for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    /// Free space below which `health://low_disk` is sent.
+    pub low_disk_megabytes: u64,
+    pub check_interval_minutes: u64,
+}
+
+/// How long undo information for bulk operations is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalSettings {
+    /// Operations older than this can no longer be undone. Zero keeps them.
+    pub max_age_days: u64,
+    /// Space the kept originals may take; the oldest go first. Zero is no
+    /// limit.
+    pub max_megabytes: u64,
+}
+
+/// The note that stands for a folder: `Alpha/Alpha.md` or `Alpha/_index.md`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderNoteStyle {
+    SameName,
+    Index,
+}
+
+impl FolderNoteStyle {
+    pub fn note_path(self, folder: &Path) -> Option<PathBuf> {
+        match self {
+            FolderNoteStyle::SameName => {
+                let name = folder.file_name()?.to_string_lossy();
+                Some(folder.join(format!("{}.md", name)))
+            }
+            FolderNoteStyle::Index => Some(folder.join("_index.md")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkFormat {
+    pub style: LinkStyle,
+    pub path: LinkPath,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStyle {
+    /// `[[Note]]` and `![[image.png]]`.
+    #[default]
+    Wikilink,
+    /// `[Note](Note.md)` and `![](image.png)`.
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPath {
+    /// As little of the path as still finds the target.
+    #[default]
+    Shortest,
+    /// The full path: from the vault root for wikilinks, from the linking
+    /// note for markdown links.
+    Relative,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintSettings {
+    pub disabled_rules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCheckSettings {
+    /// Hosts never requested, e.g. intranet servers. A domain also covers
+    /// its subdomains.
+    pub skip_domains: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewNoteSettings {
+    pub location: NewNoteLocation,
+    /// Vault-relative folder for `inbox`; the vault root when unset.
+    pub inbox_folder: Option<String>,
+}
+
+/// Where a note created from a link goes, unless the link gives a path
+/// like `[[projects/Idea]]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NewNoteLocation {
+    /// Beside the note with the link.
+    #[default]
+    SameFolder,
+    /// In `inbox_folder`.
+    Inbox,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteActionSettings {
+    /// Declared by the vault, which may have come from anywhere, so each
+    /// only runs once approved in the app; see `approve_note_action`.
+    pub actions: Vec<NoteAction>,
+}
+
+/// A named command run on a note, e.g. `typora {{path}}`. The command is
+/// split into arguments like a shell would, honouring quotes and `\`, but
+/// never run through one; `{{path}}` (the note's absolute path) and
+/// `{{vault}}` (the vault root's) are filled in within each argument.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteAction {
+    pub name: String,
+    pub command: String,
+    /// Absolute or vault-relative; the vault root when unset.
+    pub working_dir: Option<String>,
+    /// The command is killed if it runs longer.
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadingSettings {
+    pub words_per_minute: u32,
+    /// Code is read more slowly than prose.
+    pub code_words_per_minute: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellcheckSettings {
+    /// Hunspell `.dic` files, each with its `.aff` beside it. The file name
+    /// is the language it is used for, e.g. `de_DE.dic`.
+    pub dictionaries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateSettings {
+    /// Vault-relative folder the templates live in.
+    pub folder: Option<String>,
+    /// Vault-relative template used when no folder rule matches.
+    pub default_template: Option<String>,
+    pub folders: Vec<FolderTemplate>,
+}
+
+/// Template and default frontmatter for new notes in folders matching
+/// `pattern`, a glob relative to the vault root such as `meetings` or
+/// `projects/*/notes`. A rule also covers the folders below the ones it
+/// matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderTemplate {
+    pub pattern: String,
+    pub template: Option<String>,
+    /// Added to the new note unless its template already sets the key.
+    pub frontmatter: Map<String, Value>,
+}
+
+impl Default for GraphSnapshotSettings {
+    fn default() -> Self {
+        Self { keep_count: 50 }
+    }
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            low_disk_megabytes: 500,
+            check_interval_minutes: 5,
+        }
+    }
+}
+
+impl Default for JournalSettings {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            max_megabytes: 200,
+        }
+    }
+}
+
+impl Default for LinkCheckSettings {
+    fn default() -> Self {
+        Self {
+            skip_domains: Vec::new(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl Default for NoteAction {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            command: String::new(),
+            working_dir: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl Default for ReadingSettings {
+    fn default() -> Self {
+        Self {
+            words_per_minute: 230,
+            code_words_per_minute: 80,
+        }
+    }
+}
+
+impl VaultSettings {
+    pub fn load(vault_path: &Path) -> Result<Self, String> {
+        read_json(&state_dir(vault_path).join(SETTINGS_FILE))
+    }
+
+    pub fn save(&self, vault_path: &Path) -> Result<(), String> {
+        write_json(&state_dir(vault_path).join(SETTINGS_FILE), self)
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{hash_file, now_secs, relative_path, state_dir};
+use crate::commands::files::write_atomic;
+
+const LEDGER_FILE: &str = "write_ledger.jsonl";
+/// Files remembered at most; the least recently written are forgotten.
+const MAX_ENTRIES: usize = 5000;
+/// Size the file may grow to before it is rewritten with one line per
+/// file, about twice what `MAX_ENTRIES` lines take.
+const MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Held while the ledger is appended to or rewritten, so a rewrite can't
+/// drop a line written meanwhile.
+static LEDGER: Mutex<()> = Mutex::new(());
+
+/// What GraphNotes left on disk the last time it wrote a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Vault-relative.
+    pub path: String,
+    /// Modification time after the write, in seconds since the Unix epoch.
+    pub modified: Option<u64>,
+    pub size: u64,
+    /// SHA-256 of the content, as from `hash_file`.
+    pub hash: String,
+    /// Seconds since the Unix epoch.
+    pub written_at: u64,
+}
+
+/// Records the file at `path` as just written by the app, in
+/// `.graphnotes/write_ledger.jsonl`: one JSON line per write, the last for
+/// a path winning.
+pub fn record(vault_path: &Path, path: &Path) -> Result<(), String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let entry = LedgerEntry {
+        path: relative_path(vault_path, path),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        size: metadata.len(),
+        hash: hash_file(path)?,
+        written_at: now_secs(),
+    };
+    let mut line =
+        serde_json::to_string(&entry).map_err(|e| format!("Failed to record write: {}", e))?;
+    line.push('\n');
+
+    let _guard = LEDGER
+        .lock()
+        .map_err(|_| "Write ledger is unavailable".to_string())?;
+    let file = state_dir(vault_path).join(LEDGER_FILE);
+    fs::create_dir_all(state_dir(vault_path))
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+    let size = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .and_then(|mut ledger| {
+            ledger.write_all(line.as_bytes())?;
+            ledger.metadata()
+        })
+        .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?
+        .len();
+    if size > MAX_BYTES {
+        compact(&file, read(&file)?)?;
+    }
+    Ok(())
+}
+
+/// The last write the app made to each file, keyed by vault-relative path.
+pub fn load(vault_path: &Path) -> Result<HashMap<String, LedgerEntry>, String> {
+    let _guard = LEDGER
+        .lock()
+        .map_err(|_| "Write ledger is unavailable".to_string())?;
+    read(&state_dir(vault_path).join(LEDGER_FILE))
+}
+
+/// Lines that don't parse, e.g. one cut short by a crash, are skipped.
+fn read(file: &Path) -> Result<HashMap<String, LedgerEntry>, String> {
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", file.display(), e)),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LedgerEntry>(line).ok())
+        .map(|entry| (entry.path.clone(), entry))
+        .collect())
+}
+
+/// Rewrites the ledger with one line per file, keeping the most recently
+/// written.
+fn compact(file: &Path, entries: HashMap<String, LedgerEntry>) -> Result<(), String> {
+    let mut entries: Vec<LedgerEntry> = entries.into_values().collect();
+    entries.sort_by(|a, b| (a.written_at, &a.path).cmp(&(b.written_at, &b.path)));
+    let skip = entries.len().saturating_sub(MAX_ENTRIES);
+    let mut out = String::new();
+    for entry in &entries[skip..] {
+        out.push_str(
+            &serde_json::to_string(entry).map_err(|e| format!("Failed to record write: {}", e))?,
+        );
+        out.push('\n');
+    }
+    write_atomic(file, out)
+}
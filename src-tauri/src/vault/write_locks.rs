@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, LazyLock, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// How long a write waits for another operation to let go of its file.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One for the whole app, like the write ledger's: notes are written from
+/// helpers deep inside commands that have no app handle to reach managed
+/// state through.
+static WRITE_LOCKS: LazyLock<WriteLocks> = LazyLock::new(WriteLocks::default);
+
+/// Which files are being written, and by what. A command holds a file's
+/// lock from reading it to writing it back, so nothing else can write it in
+/// between and have that write overwritten.
+#[derive(Default)]
+pub struct WriteLocks {
+    held: Mutex<HashMap<PathBuf, Holder>>,
+    released: Condvar,
+}
+
+struct Holder {
+    /// A thread may take a lock it holds again, e.g. a bulk operation
+    /// writing through `write_note`.
+    thread: ThreadId,
+    depth: usize,
+    operation: String,
+}
+
+/// A file stayed locked by another operation for longer than a write would
+/// wait.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Busy {
+    pub path: String,
+    /// The command holding the lock, e.g. `move_folder`.
+    pub operation: String,
+}
+
+impl std::fmt::Display for Busy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is being written by {}; try again when it finishes",
+            self.path, self.operation
+        )
+    }
+}
+
+/// Holds the locks it was returned with until dropped.
+pub struct WriteGuard<'a> {
+    locks: &'a WriteLocks,
+    paths: Vec<PathBuf>,
+}
+
+impl WriteLocks {
+    pub fn global() -> &'static WriteLocks {
+        &WRITE_LOCKS
+    }
+
+    /// Locks one file for `operation`, waiting up to `LOCK_TIMEOUT` for
+    /// whatever holds it.
+    pub fn lock(&self, path: &Path, operation: &str) -> Result<WriteGuard<'_>, Busy> {
+        self.lock_all([path], operation, LOCK_TIMEOUT)
+    }
+
+    /// Locks every file in `paths` before any is touched, in path order, so
+    /// two operations over overlapping files can't each hold what the
+    /// other waits for. If one stays busy past `timeout`, those already
+    /// taken are let go again.
+    pub fn lock_all<P: AsRef<Path>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+        operation: &str,
+        timeout: Duration,
+    ) -> Result<WriteGuard<'_>, Busy> {
+        let mut keys: Vec<PathBuf> = paths.into_iter().map(|path| key(path.as_ref())).collect();
+        keys.sort();
+        keys.dedup();
+        let deadline = Instant::now() + timeout;
+        let me = thread::current().id();
+        let mut guard = WriteGuard {
+            locks: self,
+            paths: Vec::with_capacity(keys.len()),
+        };
+        let mut held = self.held();
+        for key in keys {
+            loop {
+                match held.get_mut(&key) {
+                    None => {
+                        let holder = Holder {
+                            thread: me,
+                            depth: 1,
+                            operation: operation.to_string(),
+                        };
+                        held.insert(key.clone(), holder);
+                        break;
+                    }
+                    Some(holder) if holder.thread == me => {
+                        holder.depth += 1;
+                        break;
+                    }
+                    Some(holder) => {
+                        let left = deadline.saturating_duration_since(Instant::now());
+                        if left.is_zero() {
+                            let busy = Busy {
+                                path: key.to_string_lossy().to_string(),
+                                operation: holder.operation.clone(),
+                            };
+                            // The guard's drop takes the map again.
+                            drop(held);
+                            return Err(busy);
+                        }
+                        held = self
+                            .released
+                            .wait_timeout(held, left)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0;
+                    }
+                }
+            }
+            guard.paths.push(key);
+        }
+        Ok(guard)
+    }
+
+    /// Whether another thread holds the lock on `path`.
+    pub fn is_busy(&self, path: &Path) -> bool {
+        let me = thread::current().id();
+        self.held()
+            .get(&key(path))
+            .is_some_and(|holder| holder.thread != me)
+    }
+
+    fn held(&self) -> MutexGuard<'_, HashMap<PathBuf, Holder>> {
+        // Nothing panics while the map is held, so a poisoned one is whole.
+        self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        if self.paths.is_empty() {
+            return;
+        }
+        let mut held = self.locks.held();
+        for path in &self.paths {
+            if let Some(holder) = held.get_mut(path) {
+                holder.depth -= 1;
+                if holder.depth == 0 {
+                    held.remove(path);
+                }
+            }
+        }
+        drop(held);
+        self.locks.released.notify_all();
+    }
+}
+
+/// The same file reached as `vault/Note.md` and `vault/./Note.md`, or
+/// through a symlinked vault folder, takes the same lock. A file not
+/// written yet is keyed by its folder's real path.
+fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|parent| parent.join(name))
+            .unwrap_or(path.into()),
+        _ => path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| PathBuf::from(format!("/graphnotes-write-locks/{}", name)))
+            .collect()
+    }
+
+    #[test]
+    fn a_held_file_is_busy_until_its_guard_drops() {
+        let locks = Arc::new(WriteLocks::default());
+        let guard = locks
+            .lock_all(paths(&["a.md", "b.md"]), "move_folder", LOCK_TIMEOUT)
+            .unwrap();
+        let other = Arc::clone(&locks);
+        let busy = thread::spawn(move || {
+            other
+                .lock_all(paths(&["b.md"]), "autosave", Duration::from_millis(20))
+                .err()
+        })
+        .join()
+        .unwrap()
+        .expect("b.md was free");
+        assert_eq!(busy.operation, "move_folder");
+        assert!(busy.path.ends_with("b.md"));
+
+        drop(guard);
+        let other = Arc::clone(&locks);
+        let relocked = thread::spawn(move || {
+            other
+                .lock_all(paths(&["b.md"]), "autosave", Duration::ZERO)
+                .is_ok()
+        });
+        assert!(relocked.join().unwrap());
+    }
+
+    #[test]
+    fn a_thread_can_take_a_lock_it_holds_again() {
+        let locks = WriteLocks::default();
+        let outer = locks
+            .lock_all(paths(&["a.md", "b.md"]), "move_folder", LOCK_TIMEOUT)
+            .unwrap();
+        let inner = locks.lock(&paths(&["a.md"])[0], "write_note").unwrap();
+        drop(inner);
+        assert_eq!(locks.held().len(), 2);
+        drop(outer);
+        assert!(locks.held().is_empty());
+    }
+
+    #[test]
+    fn a_timed_out_lock_lets_go_of_what_it_took() {
+        let locks = Arc::new(WriteLocks::default());
+        let _held = locks
+            .lock_all(paths(&["c.md"]), "infer_note_dates", LOCK_TIMEOUT)
+            .unwrap();
+        let other = Arc::clone(&locks);
+        thread::spawn(move || {
+            assert!(other
+                .lock_all(paths(&["a.md", "c.md"]), "undo", Duration::ZERO)
+                .is_err())
+        })
+        .join()
+        .unwrap();
+        assert!(!locks.is_busy(&paths(&["a.md"])[0]));
+    }
+
+    #[test]
+    fn overlapping_bulk_operations_in_any_order_finish() {
+        let locks = Arc::new(WriteLocks::default());
+        let inside = Arc::new(AtomicUsize::new(0));
+        let workers: Vec<_> = (0..8)
+            .map(|n| {
+                let locks = Arc::clone(&locks);
+                let inside = Arc::clone(&inside);
+                thread::spawn(move || {
+                    let mut names = ["a.md", "b.md", "c.md", "d.md"];
+                    names.rotate_left(n % 4);
+                    if n % 2 == 1 {
+                        names.reverse();
+                    }
+                    for _ in 0..50 {
+                        let _guard = locks
+                            .lock_all(paths(&names[..3]), "bulk", LOCK_TIMEOUT)
+                            .unwrap();
+                        assert_eq!(
+                            inside.fetch_add(1, Ordering::SeqCst),
+                            0,
+                            "a file locked twice"
+                        );
+                        thread::yield_now();
+                        inside.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(locks.held().is_empty());
+    }
+}
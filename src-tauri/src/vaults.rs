@@ -0,0 +1,316 @@
+use notify_debouncer_full::notify::RecommendedWatcher;
+use notify_debouncer_full::{Debouncer, FileIdMap};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+pub type VaultWatcher = Debouncer<RecommendedWatcher, FileIdMap>;
+
+/// What each open vault shares between the windows showing it, keyed by
+/// the vault's canonical path. Kept in managed state.
+///
+/// A window joins a vault with `attach`, which starts the vault's watcher
+/// if it is the first, and leaves with `detach` or by attaching to another
+/// vault. When the last window leaves, the vault is handed back as a
+/// `Released` so the caller can stop its watcher and flush its autosaves.
+/// That happens outside the lock: stopping a watcher waits for its event
+/// thread, which may itself be waiting to look up the vault's windows.
+///
+/// Everything else vault-scoped is already shared app-wide and keyed by
+/// vault: the link index lives in the vault's state directory, cache keys
+/// are held per vault by `CacheKeys`, and task ids are unique across
+/// windows.
+pub struct VaultStateRegistry<W = VaultWatcher> {
+    vaults: Mutex<HashMap<PathBuf, VaultState<W>>>,
+    next_window: AtomicU64,
+}
+
+struct VaultState<W> {
+    windows: BTreeSet<String>,
+    watcher: W,
+}
+
+/// A vault whose last window has left, with its watcher.
+pub struct Released<W = VaultWatcher> {
+    pub root: PathBuf,
+    pub watcher: W,
+}
+
+impl<W> Default for VaultStateRegistry<W> {
+    fn default() -> Self {
+        Self {
+            vaults: Mutex::new(HashMap::new()),
+            next_window: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<W> VaultStateRegistry<W> {
+    /// Adds `window` to the windows showing the vault at `root`, which must
+    /// be canonical, calling `start` for its watcher if no other window has
+    /// it open. A window shows one vault at a time, so it leaves any other;
+    /// that vault is returned if it has no windows left. If `start` fails
+    /// the window stays where it was.
+    pub fn attach(
+        &self,
+        root: &Path,
+        window: &str,
+        start: impl FnOnce(&Path) -> Result<W, String>,
+    ) -> Result<Option<Released<W>>, String> {
+        let mut vaults = self.lock()?;
+        if vaults
+            .get(root)
+            .is_some_and(|vault| vault.windows.contains(window))
+        {
+            return Ok(None);
+        }
+        if !vaults.contains_key(root) {
+            let watcher = start(root)?;
+            vaults.insert(
+                root.to_path_buf(),
+                VaultState {
+                    windows: BTreeSet::new(),
+                    watcher,
+                },
+            );
+        }
+        let released = leave(&mut vaults, window);
+        if let Some(vault) = vaults.get_mut(root) {
+            vault.windows.insert(window.to_string());
+        }
+        Ok(released)
+    }
+
+    /// Takes `window` off the vault it shows, returning the vault if that
+    /// was its last window.
+    pub fn detach(&self, window: &str) -> Result<Option<Released<W>>, String> {
+        Ok(leave(&mut *self.lock()?, window))
+    }
+
+    /// The windows showing the vault at `root`, by label.
+    pub fn windows(&self, root: &Path) -> Vec<String> {
+        let Ok(vaults) = self.vaults.lock() else {
+            return Vec::new();
+        };
+        vaults
+            .get(root)
+            .map(|vault| vault.windows.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Open vaults, by path, with the windows showing each.
+    pub fn open_vaults(&self) -> Vec<(PathBuf, Vec<String>)> {
+        let Ok(vaults) = self.vaults.lock() else {
+            return Vec::new();
+        };
+        let mut open: Vec<(PathBuf, Vec<String>)> = vaults
+            .iter()
+            .map(|(root, vault)| (root.clone(), vault.windows.iter().cloned().collect()))
+            .collect();
+        open.sort();
+        open
+    }
+
+    /// A label no window has had yet, e.g. `vault-3`.
+    pub fn new_window_label(&self) -> String {
+        format!(
+            "vault-{}",
+            self.next_window.fetch_add(1, Ordering::Relaxed) + 1
+        )
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<PathBuf, VaultState<W>>>, String> {
+        self.vaults
+            .lock()
+            .map_err(|_| "Vault state is unavailable".to_string())
+    }
+}
+
+fn leave<W>(vaults: &mut HashMap<PathBuf, VaultState<W>>, window: &str) -> Option<Released<W>> {
+    let root = vaults
+        .iter_mut()
+        .find_map(|(root, vault)| vault.windows.remove(window).then(|| root.clone()))?;
+    if !vaults.get(&root)?.windows.is_empty() {
+        return None;
+    }
+    let vault = vaults.remove(&root)?;
+    Some(Released {
+        root,
+        watcher: vault.watcher,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    /// Stands in for a watcher, counting how many are running.
+    struct Probe(Arc<AtomicUsize>);
+
+    impl Probe {
+        fn start(running: &Arc<AtomicUsize>) -> Result<Probe, String> {
+            running.fetch_add(1, Ordering::SeqCst);
+            Ok(Probe(running.clone()))
+        }
+    }
+
+    impl Drop for Probe {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn vault(n: usize) -> PathBuf {
+        PathBuf::from(format!("/vaults/{}", n))
+    }
+
+    fn vault_of<W>(registry: &VaultStateRegistry<W>, window: &str) -> Option<PathBuf> {
+        let open = registry.open_vaults();
+        open.into_iter()
+            .find(|(_, windows)| windows.iter().any(|w| w == window))
+            .map(|(root, _)| root)
+    }
+
+    #[test]
+    fn last_window_to_leave_releases_the_vault() {
+        let registry = VaultStateRegistry::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        assert!(registry
+            .attach(&vault(1), "main", |_| Probe::start(&running))
+            .unwrap()
+            .is_none());
+        assert!(registry
+            .attach(&vault(1), "vault-1", |_| Probe::start(&running))
+            .unwrap()
+            .is_none());
+        assert_eq!(running.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            registry.windows(&vault(1)),
+            vec!["main".to_string(), "vault-1".to_string()]
+        );
+
+        assert!(registry.detach("main").unwrap().is_none());
+        let released = registry.detach("vault-1").unwrap().expect("vault released");
+        assert_eq!(released.root, vault(1));
+        drop(released);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+        assert!(registry.open_vaults().is_empty());
+    }
+
+    #[test]
+    fn attaching_to_another_vault_leaves_the_first() {
+        let registry = VaultStateRegistry::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        registry
+            .attach(&vault(1), "main", |_| Probe::start(&running))
+            .unwrap();
+        let released = registry
+            .attach(&vault(2), "main", |_| Probe::start(&running))
+            .unwrap();
+        assert_eq!(released.map(|r| r.root), Some(vault(1)));
+        assert_eq!(vault_of(&registry, "main"), Some(vault(2)));
+        assert_eq!(running.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn failed_start_leaves_the_window_where_it_was() {
+        let registry = VaultStateRegistry::default();
+        let running = Arc::new(AtomicUsize::new(0));
+        registry
+            .attach(&vault(1), "main", |_| Probe::start(&running))
+            .unwrap();
+        let failed = registry.attach(&vault(2), "main", |_| {
+            Err::<Probe, _>("no watcher".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(vault_of(&registry, "main"), Some(vault(1)));
+        assert_eq!(
+            registry.open_vaults(),
+            vec![(vault(1), vec!["main".to_string()])]
+        );
+    }
+
+    #[test]
+    fn two_windows_switching_vaults_concurrently_keep_one_watcher_per_open_vault() {
+        let registry = Arc::new(VaultStateRegistry::default());
+        let running = Arc::new(AtomicUsize::new(0));
+        let released = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = ["main", "vault-1"]
+            .into_iter()
+            .enumerate()
+            .map(|(offset, window)| {
+                let (registry, running, released, barrier) = (
+                    registry.clone(),
+                    running.clone(),
+                    released.clone(),
+                    barrier.clone(),
+                );
+                thread::spawn(move || {
+                    barrier.wait();
+                    for i in 0..2000 {
+                        let outcome = if i % 7 == 6 {
+                            registry.detach(window).unwrap()
+                        } else {
+                            registry
+                                .attach(&vault((i + offset) % 3), window, |_| {
+                                    Probe::start(&running)
+                                })
+                                .unwrap()
+                        };
+                        if outcome.is_some() {
+                            released.fetch_add(1, Ordering::SeqCst);
+                        }
+                        // Every vault still open has a window and a watcher.
+                        let open = registry.open_vaults();
+                        assert!(open.iter().all(|(_, windows)| !windows.is_empty()));
+                    }
+                    drop(registry.detach(window).unwrap());
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(registry.open_vaults().is_empty());
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+        assert!(released.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn two_windows_on_one_vault_never_release_it_while_either_remains() {
+        let registry = Arc::new(VaultStateRegistry::default());
+        let running = Arc::new(AtomicUsize::new(0));
+        registry
+            .attach(&vault(0), "main", |_| Probe::start(&running))
+            .unwrap();
+        let barrier = Arc::new(Barrier::new(2));
+        let flapping = {
+            let (registry, running, barrier) = (registry.clone(), running.clone(), barrier.clone());
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..5000 {
+                    registry
+                        .attach(&vault(0), "vault-1", |_| Probe::start(&running))
+                        .unwrap();
+                    assert!(registry.detach("vault-1").unwrap().is_none());
+                }
+            })
+        };
+        barrier.wait();
+        for _ in 0..5000 {
+            assert_eq!(vault_of(&registry, "main"), Some(vault(0)));
+            assert!(!registry.windows(&vault(0)).is_empty());
+        }
+        flapping.join().unwrap();
+        assert_eq!(running.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            registry.open_vaults(),
+            vec![(vault(0), vec!["main".to_string()])]
+        );
+    }
+}